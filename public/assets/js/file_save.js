@@ -1,5 +1,5 @@
 // File saving utility using File System Access API
-export async function save_stream_to_disk(stream) {
+export async function save_stream_to_disk(stream, suggestedName) {
     try {
         // Check if File System Access API is supported
         if (!window.showSaveFilePicker) {
@@ -7,9 +7,17 @@ export async function save_stream_to_disk(stream) {
             return;
         }
 
+        const isBinary = suggestedName.endsWith('.bin');
+        const isCast = suggestedName.endsWith('.cast');
         const handle = await window.showSaveFilePicker({
-            suggestedName: 'serial_log.txt',
-            types: [{
+            suggestedName,
+            types: [isBinary ? {
+                description: 'Binary Files',
+                accept: { 'application/octet-stream': ['.bin'] }
+            } : isCast ? {
+                description: 'Asciicast Recordings',
+                accept: { 'application/x-asciicast': ['.cast'] }
+            } : {
                 description: 'Text Files',
                 accept: { 'text/plain': ['.txt'] }
             }],
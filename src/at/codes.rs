@@ -0,0 +1,87 @@
+//! Meanings of the numeric error codes of 3GPP TS 27.007 (`+CME ERROR`) and
+//! TS 27.005 (`+CMS ERROR`)
+
+pub fn cme_error(code: u16) -> Option<&'static str> {
+    Some(match code {
+        0 => "Phone failure",
+        1 => "No connection to phone",
+        2 => "Phone adaptor link reserved",
+        3 => "Operation not allowed",
+        4 => "Operation not supported",
+        5 => "PH-SIM PIN required",
+        6 => "PH-FSIM PIN required",
+        7 => "PH-FSIM PUK required",
+        10 => "SIM not inserted",
+        11 => "SIM PIN required",
+        12 => "SIM PUK required",
+        13 => "SIM failure",
+        14 => "SIM busy",
+        15 => "SIM wrong",
+        16 => "Incorrect password",
+        17 => "SIM PIN2 required",
+        18 => "SIM PUK2 required",
+        20 => "Memory full",
+        21 => "Invalid index",
+        22 => "Not found",
+        23 => "Memory failure",
+        24 => "Text string too long",
+        25 => "Invalid characters in text string",
+        26 => "Dial string too long",
+        27 => "Invalid characters in dial string",
+        30 => "No network service",
+        31 => "Network timeout",
+        32 => "Network not allowed, emergency calls only",
+        40 => "Network personalization PIN required",
+        41 => "Network personalization PUK required",
+        42 => "Network subset personalization PIN required",
+        43 => "Network subset personalization PUK required",
+        44 => "Service provider personalization PIN required",
+        45 => "Service provider personalization PUK required",
+        46 => "Corporate personalization PIN required",
+        47 => "Corporate personalization PUK required",
+        50 => "Incorrect parameters",
+        100 => "Unknown error",
+        103 => "Illegal MS",
+        106 => "Illegal ME",
+        107 => "GPRS services not allowed",
+        111 => "PLMN not allowed",
+        112 => "Location area not allowed",
+        113 => "Roaming not allowed in this location area",
+        132 => "Service option not supported",
+        133 => "Requested service option not subscribed",
+        134 => "Service option temporarily out of order",
+        148 => "Unspecified GPRS error",
+        149 => "PDP authentication failure",
+        150 => "Invalid mobile class",
+        _ => return None,
+    })
+}
+
+pub fn cms_error(code: u16) -> Option<&'static str> {
+    Some(match code {
+        300 => "ME failure",
+        301 => "SMS service of ME reserved",
+        302 => "Operation not allowed",
+        303 => "Operation not supported",
+        304 => "Invalid PDU mode parameter",
+        305 => "Invalid text mode parameter",
+        310 => "SIM not inserted",
+        311 => "SIM PIN required",
+        312 => "PH-SIM PIN required",
+        313 => "SIM failure",
+        314 => "SIM busy",
+        315 => "SIM wrong",
+        316 => "SIM PUK required",
+        317 => "SIM PIN2 required",
+        318 => "SIM PUK2 required",
+        320 => "Memory failure",
+        321 => "Invalid memory index",
+        322 => "Memory full",
+        330 => "SMSC address unknown",
+        331 => "No network service",
+        332 => "Network timeout",
+        340 => "No +CNMA acknowledgement expected",
+        500 => "Unknown error",
+        _ => return None,
+    })
+}
@@ -0,0 +1,237 @@
+//! Common AT commands of cellular modems and Wi-Fi/BLE modules. Text in
+//! angle brackets is a placeholder to fill in before sending.
+
+use super::DEFAULT_TIMEOUT_MS;
+
+#[derive(PartialEq)]
+pub struct LibraryCommand {
+    pub command: &'static str,
+    pub description: &'static str,
+    pub group: &'static str,
+    /// Slow commands, e.g. network scans, wait longer for their result
+    pub timeout_ms: Option<u32>,
+}
+
+impl LibraryCommand {
+    pub fn has_placeholder(&self) -> bool {
+        self.command.contains('<')
+    }
+
+    /// The command up to its first placeholder
+    fn stem(&self) -> &'static str {
+        self.command.split('<').next().unwrap_or(self.command)
+    }
+}
+
+const fn cmd(
+    command: &'static str,
+    description: &'static str,
+    group: &'static str,
+) -> LibraryCommand {
+    LibraryCommand {
+        command,
+        description,
+        group,
+        timeout_ms: None,
+    }
+}
+
+const fn slow(
+    command: &'static str,
+    description: &'static str,
+    group: &'static str,
+    timeout_ms: u32,
+) -> LibraryCommand {
+    LibraryCommand {
+        command,
+        description,
+        group,
+        timeout_ms: Some(timeout_ms),
+    }
+}
+
+pub const COMMANDS: &[LibraryCommand] = &[
+    cmd("AT", "Check that the modem answers", "General"),
+    cmd("ATI", "Product identification", "General"),
+    cmd("ATE0", "Turn command echo off", "General"),
+    cmd("ATE1", "Turn command echo on", "General"),
+    cmd("AT+CMEE=2", "Report errors as text", "General"),
+    cmd("AT+CMEE=1", "Report errors as numeric codes", "General"),
+    cmd("AT&V", "Show the current configuration", "General"),
+    cmd("AT&F", "Restore factory settings", "General"),
+    cmd("AT&W", "Save settings to the profile", "General"),
+    cmd("AT+CGMI", "Manufacturer", "General"),
+    cmd("AT+CGMM", "Model", "General"),
+    cmd("AT+CGMR", "Firmware revision", "General"),
+    cmd("AT+CGSN", "IMEI", "General"),
+    cmd("AT+IPR?", "Current baud rate", "General"),
+    cmd("AT+IPR=<rate>", "Set the baud rate", "General"),
+    cmd("AT+CCLK?", "Real-time clock", "General"),
+    cmd("AT+CPIN?", "SIM PIN status", "SIM"),
+    cmd("AT+CPIN=\"<pin>\"", "Enter the SIM PIN", "SIM"),
+    cmd("AT+CIMI", "IMSI of the SIM", "SIM"),
+    cmd("AT+CCID", "ICCID of the SIM", "SIM"),
+    cmd("AT+CNUM", "Subscriber number", "SIM"),
+    cmd("AT+CLCK=\"SC\",2", "Is the SIM PIN lock on", "SIM"),
+    cmd("AT+CSQ", "Signal quality (RSSI, BER)", "Network"),
+    cmd("AT+CESQ", "Extended signal quality (RSRP, RSRQ)", "Network"),
+    cmd("AT+CREG?", "Circuit-switched registration", "Network"),
+    cmd("AT+CGREG?", "GPRS registration", "Network"),
+    cmd("AT+CEREG?", "LTE registration", "Network"),
+    cmd(
+        "AT+CREG=2",
+        "Report registration changes with cell info",
+        "Network",
+    ),
+    cmd("AT+COPS?", "Current operator", "Network"),
+    slow("AT+COPS=?", "Scan for operators", "Network", 180_000),
+    slow(
+        "AT+COPS=0",
+        "Select the operator automatically",
+        "Network",
+        180_000,
+    ),
+    slow(
+        "AT+COPS=1,2,\"<mccmnc>\"",
+        "Select an operator manually",
+        "Network",
+        180_000,
+    ),
+    cmd("AT+CFUN?", "Functionality level", "Network"),
+    slow("AT+CFUN=1", "Full functionality", "Network", 15_000),
+    slow("AT+CFUN=0", "Minimum functionality", "Network", 15_000),
+    slow("AT+CFUN=4", "Flight mode", "Network", 15_000),
+    cmd("AT+CGDCONT?", "PDP contexts", "Packet data"),
+    cmd(
+        "AT+CGDCONT=1,\"IP\",\"<apn>\"",
+        "Set the APN of context 1",
+        "Packet data",
+    ),
+    cmd("AT+CGATT?", "Packet domain attach state", "Packet data"),
+    slow(
+        "AT+CGATT=1",
+        "Attach to the packet domain",
+        "Packet data",
+        75_000,
+    ),
+    slow("AT+CGACT=1,1", "Activate context 1", "Packet data", 150_000),
+    cmd("AT+CGACT?", "Context activation state", "Packet data"),
+    cmd(
+        "AT+CGPADDR",
+        "Addresses of the active contexts",
+        "Packet data",
+    ),
+    cmd("AT+CMGF=1", "SMS text mode", "SMS"),
+    cmd("AT+CMGF=0", "SMS PDU mode", "SMS"),
+    cmd("AT+CSCA?", "SMS service centre", "SMS"),
+    slow(
+        "AT+CMGL=\"ALL\"",
+        "List all messages (text mode)",
+        "SMS",
+        20_000,
+    ),
+    cmd("AT+CMGR=<index>", "Read a message", "SMS"),
+    cmd("AT+CMGD=<index>", "Delete a message", "SMS"),
+    cmd("AT+CNMI=2,1,0,0,0", "Report new messages with +CMTI", "SMS"),
+    cmd("AT+CPMS?", "Message storage use", "SMS"),
+    slow("ATD<number>;", "Dial a voice call", "Calls", 60_000),
+    slow("ATA", "Answer a call", "Calls", 60_000),
+    cmd("ATH", "Hang up", "Calls"),
+    cmd("AT+CLIP=1", "Show the caller number on RING", "Calls"),
+    cmd("AT+CLCC", "List current calls", "Calls"),
+    cmd("AT+QENG=\"servingcell\"", "Serving cell details", "Quectel"),
+    cmd("AT+QCSQ", "Signal by radio technology", "Quectel"),
+    cmd("AT+QGMR", "Full firmware version", "Quectel"),
+    cmd("AT+QNWINFO", "Network type, operator and band", "Quectel"),
+    cmd("AT+QCCID", "ICCID of the SIM", "Quectel"),
+    slow("AT+QPOWD", "Power down", "Quectel", 65_000),
+    cmd("AT+CPSI?", "Serving cell details", "SIMCom"),
+    cmd("AT+CSUB", "Module and firmware version", "SIMCom"),
+    cmd("AT+CNMP?", "Preferred network mode", "SIMCom"),
+    cmd("AT+CPOF", "Power down", "SIMCom"),
+    cmd("ATI9", "Firmware and profile version", "u-blox"),
+    cmd("AT+UPSV?", "Power saving mode", "u-blox"),
+    cmd("AT+URAT?", "Selected radio technologies", "u-blox"),
+    cmd("AT+GMR", "AT and SDK versions", "ESP-AT"),
+    slow("AT+RST", "Restart the module", "ESP-AT", 10_000),
+    cmd("AT+CWMODE=1", "Station mode", "ESP-AT"),
+    slow("AT+CWLAP", "Scan for access points", "ESP-AT", 15_000),
+    slow(
+        "AT+CWJAP=\"<ssid>\",\"<password>\"",
+        "Join an access point",
+        "ESP-AT",
+        20_000,
+    ),
+    cmd("AT+CWJAP?", "Access point joined", "ESP-AT"),
+    cmd("AT+CWQAP", "Leave the access point", "ESP-AT"),
+    cmd("AT+CIFSR", "Local IP and MAC addresses", "ESP-AT"),
+    cmd("AT+CIPSTATUS", "Connection status", "ESP-AT"),
+    slow(
+        "AT+CIPSTART=\"TCP\",\"<host>\",<port>",
+        "Open a TCP connection",
+        "ESP-AT",
+        15_000,
+    ),
+    cmd(
+        "AT+CIPSEND=<length>",
+        "Send data on the connection",
+        "ESP-AT",
+    ),
+    cmd("AT+CIPCLOSE", "Close the connection", "ESP-AT"),
+    cmd("AT+BLEINIT=2", "Start BLE as server", "ESP-AT BLE"),
+    cmd("AT+BLEINIT=1", "Start BLE as client", "ESP-AT BLE"),
+    cmd("AT+BLEADDR?", "BLE address", "ESP-AT BLE"),
+    cmd("AT+BLENAME=\"<name>\"", "Set the device name", "ESP-AT BLE"),
+    slow(
+        "AT+BLESCAN=1,<seconds>",
+        "Scan for devices",
+        "ESP-AT BLE",
+        60_000,
+    ),
+    cmd("AT+BLEADVSTART", "Start advertising", "ESP-AT BLE"),
+    cmd("AT+BLEADVSTOP", "Stop advertising", "ESP-AT BLE"),
+];
+
+/// Commands matching every word of `query` in their command, description or group
+pub fn search(query: &str) -> Vec<&'static LibraryCommand> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    COMMANDS
+        .iter()
+        .filter(|c| {
+            let text = format!("{} {} {}", c.command, c.description, c.group).to_lowercase();
+            words.iter().all(|w| text.contains(w.as_str()))
+        })
+        .collect()
+}
+
+/// Timeout for a typed command, from the library entry it was written from
+pub fn timeout_for(command: &str) -> u32 {
+    let command = command.trim().to_ascii_uppercase();
+    COMMANDS
+        .iter()
+        .filter(|c| {
+            if c.has_placeholder() {
+                command.starts_with(&c.stem().to_ascii_uppercase())
+            } else {
+                command == c.command.to_ascii_uppercase()
+            }
+        })
+        .max_by_key(|c| c.stem().len())
+        .and_then(|c| c.timeout_ms)
+        .unwrap_or(DEFAULT_TIMEOUT_MS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_and_timeouts() {
+        let found: Vec<_> = search("signal quectel").iter().map(|c| c.command).collect();
+        assert_eq!(found, ["AT+QCSQ"]);
+        assert!(search("").len() == COMMANDS.len());
+        assert_eq!(timeout_for("at+cops=?"), 180_000);
+        assert_eq!(timeout_for("AT+CWJAP=\"home\",\"secret\""), 20_000);
+        assert_eq!(timeout_for("AT+CSQ"), DEFAULT_TIMEOUT_MS);
+    }
+}
@@ -0,0 +1,388 @@
+//! AT command console: pairs each command with the modem's response and
+//! final result code, and picks out unsolicited result codes (URCs)
+
+pub mod codes;
+pub mod library;
+
+use std::fmt;
+
+/// How long a command waits for its final result unless the library knows better
+pub const DEFAULT_TIMEOUT_MS: u32 = 5000;
+
+/// Blocks kept in the console
+const BLOCKS_KEPT: usize = 500;
+
+/// Result codes that come unasked, matched at the start of a line
+const URC_PREFIXES: &[&str] = &[
+    "RING",
+    "+CRING",
+    "+CLIP",
+    "+CCWA",
+    "+CMTI",
+    "+CMT",
+    "+CDS",
+    "+CBM",
+    "+CREG",
+    "+CGREG",
+    "+CEREG",
+    "+C5GREG",
+    "+CGEV",
+    "+CUSD",
+    "+CIEV",
+    "+CTZV",
+    "+CTZE",
+    "+CPIN",
+    "+QIURC",
+    "+QIND",
+    "+QUSIM",
+    "+UUSORD",
+    "+UUSOCL",
+    "+UUPSDD",
+    "+CMQTTRXSTART",
+    "+IPD",
+    "RDY",
+    "SMS Ready",
+    "Call Ready",
+    "PB DONE",
+    "POWERED DOWN",
+    "NORMAL POWER DOWN",
+    "WIFI CONNECTED",
+    "WIFI GOT IP",
+    "WIFI DISCONNECT",
+    "+BLECONN",
+    "+BLEDISCONN",
+    "+BLESCAN",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FinalResult {
+    Ok,
+    Error,
+    /// Equipment error, a number or text depending on `AT+CMEE`
+    CmeError(String),
+    /// SMS error
+    CmsError(String),
+    /// Data mode started, with the rate or protocol if given
+    Connect(String),
+    NoCarrier,
+    Busy,
+    NoAnswer,
+    NoDialtone,
+    /// No final result arrived in time
+    Timeout,
+}
+
+impl FinalResult {
+    pub fn parse(line: &str) -> Option<Self> {
+        let line = line.trim();
+        if let Some(code) = line.strip_prefix("+CME ERROR:") {
+            return Some(Self::CmeError(code.trim().to_string()));
+        }
+        if let Some(code) = line.strip_prefix("+CMS ERROR:") {
+            return Some(Self::CmsError(code.trim().to_string()));
+        }
+        if let Some(rest) = line.strip_prefix("CONNECT") {
+            if rest.is_empty() || rest.starts_with(' ') {
+                return Some(Self::Connect(rest.trim().to_string()));
+            }
+        }
+        match line {
+            "OK" => Some(Self::Ok),
+            "ERROR" => Some(Self::Error),
+            "NO CARRIER" => Some(Self::NoCarrier),
+            "BUSY" => Some(Self::Busy),
+            "NO ANSWER" => Some(Self::NoAnswer),
+            "NO DIALTONE" | "NO DIAL TONE" => Some(Self::NoDialtone),
+            _ => None,
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Ok | Self::Connect(_))
+    }
+
+    /// What a numeric error code means
+    pub fn description(&self) -> Option<&'static str> {
+        match self {
+            Self::CmeError(code) => codes::cme_error(code.parse().ok()?),
+            Self::CmsError(code) => codes::cms_error(code.parse().ok()?),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FinalResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ok => write!(f, "OK"),
+            Self::Error => write!(f, "ERROR"),
+            Self::CmeError(code) => write!(f, "+CME ERROR: {}", code),
+            Self::CmsError(code) => write!(f, "+CMS ERROR: {}", code),
+            Self::Connect(rate) if rate.is_empty() => write!(f, "CONNECT"),
+            Self::Connect(rate) => write!(f, "CONNECT {}", rate),
+            Self::NoCarrier => write!(f, "NO CARRIER"),
+            Self::Busy => write!(f, "BUSY"),
+            Self::NoAnswer => write!(f, "NO ANSWER"),
+            Self::NoDialtone => write!(f, "NO DIALTONE"),
+            Self::Timeout => write!(f, "TIMEOUT"),
+        }
+    }
+}
+
+/// The command name responses are prefixed with, e.g. `+CREG` for `AT+CREG?`
+pub fn command_name(command: &str) -> Option<String> {
+    let command = command.trim();
+    let body = command
+        .get(..2)
+        .filter(|at| at.eq_ignore_ascii_case("AT"))
+        .map(|_| &command[2..])?;
+    let name: String = body
+        .chars()
+        .take_while(|c| !matches!(c, '=' | '?'))
+        .collect::<String>()
+        .to_ascii_uppercase();
+    (!name.is_empty()).then_some(name)
+}
+
+/// A line with a known unsolicited result code
+pub fn is_urc(line: &str) -> bool {
+    URC_PREFIXES.iter().any(|prefix| {
+        line.strip_prefix(prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with([':', ' ']))
+    })
+}
+
+/// The part of a URC to highlight, e.g. `+CREG:` of `+CREG: 1,"00C1"`
+pub fn urc_prefix(line: &str) -> &str {
+    match line.find(':') {
+        Some(i) => &line[..=i],
+        None => line,
+    }
+}
+
+/// A command with everything the modem answered to it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Exchange {
+    pub command: String,
+    pub response: Vec<String>,
+    /// `None` while waiting
+    pub result: Option<FinalResult>,
+    pub sent_ms: f64,
+    pub elapsed_ms: f64,
+    timeout_ms: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Exchange(Exchange),
+    /// A line that came without a command waiting for it, or a known URC
+    /// while one was
+    Unsolicited(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AtLog {
+    blocks: Vec<Block>,
+    /// Index of the exchange waiting for its final result
+    pending: Option<usize>,
+}
+
+impl AtLog {
+    pub fn blocks(&self) -> &[Block] {
+        &self.blocks
+    }
+
+    pub fn is_waiting(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn begin(&mut self, command: String, timeout_ms: u32, now_ms: f64) {
+        self.check_timeout(f64::INFINITY);
+        self.push(Block::Exchange(Exchange {
+            command,
+            response: Vec::new(),
+            result: None,
+            sent_ms: now_ms,
+            elapsed_ms: 0.0,
+            timeout_ms,
+        }));
+        self.pending = Some(self.blocks.len() - 1);
+    }
+
+    /// Adds a block, dropping the oldest ones beyond the limit
+    fn push(&mut self, block: Block) {
+        self.blocks.push(block);
+        if self.blocks.len() > BLOCKS_KEPT {
+            let dropped = self.blocks.len() - BLOCKS_KEPT;
+            self.blocks.drain(..dropped);
+            self.pending = self.pending.and_then(|i| i.checked_sub(dropped));
+        }
+    }
+
+    /// Drops the waiting command, e.g. when it could not be written
+    pub fn abandon(&mut self) {
+        if let Some(i) = self.pending.take() {
+            self.blocks.remove(i);
+        }
+    }
+
+    fn pending_mut(&mut self) -> Option<&mut Exchange> {
+        match self.blocks.get_mut(self.pending?) {
+            Some(Block::Exchange(exchange)) => Some(exchange),
+            _ => None,
+        }
+    }
+
+    /// Files a received line under the waiting command or as unsolicited
+    pub fn feed(&mut self, line: &str, now_ms: f64) {
+        let line = line.trim_end();
+        if line.is_empty() {
+            return;
+        }
+        let Some(exchange) = self.pending_mut() else {
+            self.push(Block::Unsolicited(line.to_string()));
+            return;
+        };
+        // The modem echoes the command unless echo is off (ATE0)
+        if exchange.response.is_empty() && line.eq_ignore_ascii_case(exchange.command.trim()) {
+            return;
+        }
+        if let Some(result) = FinalResult::parse(line) {
+            exchange.result = Some(result);
+            exchange.elapsed_ms = now_ms - exchange.sent_ms;
+            self.pending = None;
+            return;
+        }
+        let answers = command_name(&exchange.command)
+            .is_some_and(|name| line.to_ascii_uppercase().starts_with(&name));
+        if is_urc(line) && !answers {
+            self.push(Block::Unsolicited(line.to_string()));
+        } else {
+            exchange.response.push(line.to_string());
+        }
+    }
+
+    /// The waiting command has run out of time
+    pub fn is_overdue(&self, now_ms: f64) -> bool {
+        match self.pending.and_then(|i| self.blocks.get(i)) {
+            Some(Block::Exchange(exchange)) => {
+                now_ms - exchange.sent_ms >= exchange.timeout_ms as f64
+            }
+            _ => false,
+        }
+    }
+
+    /// Ends the waiting command if its time is up
+    pub fn check_timeout(&mut self, now_ms: f64) {
+        let Some(exchange) = self.pending_mut() else {
+            return;
+        };
+        if now_ms - exchange.sent_ms >= exchange.timeout_ms as f64 {
+            exchange.result = Some(FinalResult::Timeout);
+            exchange.elapsed_ms = exchange.timeout_ms as f64;
+            self.pending = None;
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.pending = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(log: &AtLog, i: usize) -> &Exchange {
+        match &log.blocks()[i] {
+            Block::Exchange(exchange) => exchange,
+            other => panic!("not an exchange: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_final_results() {
+        assert_eq!(FinalResult::parse("OK"), Some(FinalResult::Ok));
+        assert_eq!(
+            FinalResult::parse("+CME ERROR: 10"),
+            Some(FinalResult::CmeError("10".into()))
+        );
+        assert_eq!(
+            FinalResult::parse("CONNECT 115200"),
+            Some(FinalResult::Connect("115200".into()))
+        );
+        assert_eq!(FinalResult::parse("CONNECTED"), None);
+        assert_eq!(FinalResult::parse("+CSQ: 20,99"), None);
+        assert_eq!(
+            FinalResult::CmeError("10".into()).description(),
+            Some("SIM not inserted")
+        );
+        assert_eq!(
+            FinalResult::CmsError("500".into()).description(),
+            Some("Unknown error")
+        );
+        assert_eq!(
+            FinalResult::CmeError("SIM not inserted".into()).description(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_command_names() {
+        assert_eq!(command_name("AT+CREG?").as_deref(), Some("+CREG"));
+        assert_eq!(
+            command_name("at+cgdcont=1,\"IP\",\"apn\"").as_deref(),
+            Some("+CGDCONT")
+        );
+        assert_eq!(command_name("ATI"), Some("I".to_string()));
+        assert_eq!(command_name("AT"), None);
+        assert_eq!(command_name("hello"), None);
+    }
+
+    #[test]
+    fn test_groups_response_and_urcs() {
+        let mut log = AtLog::default();
+        log.feed("RDY", 0.0);
+        log.begin("AT+CREG?".into(), 1000, 10.0);
+        for line in ["AT+CREG?", "+CREG: 0,1", "+CMTI: \"SM\",3", "", "OK"] {
+            log.feed(line, 60.0);
+        }
+        assert!(!log.is_waiting());
+        assert_eq!(log.blocks()[0], Block::Unsolicited("RDY".into()));
+        let first = exchange(&log, 1);
+        assert_eq!(first.response, ["+CREG: 0,1"]);
+        assert_eq!(first.result, Some(FinalResult::Ok));
+        assert_eq!(first.elapsed_ms, 50.0);
+        assert_eq!(
+            log.blocks()[2],
+            Block::Unsolicited("+CMTI: \"SM\",3".into())
+        );
+
+        log.begin("AT+CPIN?".into(), 1000, 100.0);
+        log.feed("+CME ERROR: 10", 120.0);
+        assert_eq!(
+            exchange(&log, 3).result,
+            Some(FinalResult::CmeError("10".into()))
+        );
+
+        log.begin("AT+COPS=?".into(), 1000, 200.0);
+        log.check_timeout(500.0);
+        assert!(log.is_waiting());
+        assert!(log.is_overdue(1200.0));
+        log.check_timeout(1200.0);
+        assert_eq!(exchange(&log, 4).result, Some(FinalResult::Timeout));
+        log.feed("OK", 1300.0);
+        assert_eq!(log.blocks()[5], Block::Unsolicited("OK".into()));
+    }
+
+    #[test]
+    fn test_urc_matching() {
+        assert!(is_urc("RING"));
+        assert!(is_urc("+CMT: \"+123\",,\"24/01/01\""));
+        assert!(!is_urc("+CMTX: 1"));
+        assert!(!is_urc("RINGING"));
+        assert_eq!(urc_prefix("+CREG: 1,\"00C1\""), "+CREG:");
+        assert_eq!(urc_prefix("WIFI GOT IP"), "WIFI GOT IP");
+    }
+}
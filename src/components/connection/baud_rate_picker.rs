@@ -29,7 +29,7 @@ pub fn BaudRatePicker() -> Element {
                     }
                 },
                 class: "w-full",
-                disabled: state.conn.is_connected(),
+                disabled: (state.conn.is_busy)(),
             }
         }
     }
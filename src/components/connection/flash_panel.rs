@@ -0,0 +1,352 @@
+use crate::components::connection::TransferProgress;
+use crate::components::ui::{PanelHeader, ToggleSwitch};
+use crate::hooks::transfer::use_transfer_controller;
+use crate::state::AppState;
+use crate::transfer::esp::EspOptions;
+use crate::transfer::image::{combine, image_size, parse_image};
+use crate::transfer::stm32::FlashOptions;
+use crate::utils::format::format_bytes;
+use dioxus::prelude::*;
+
+/// Where raw binaries go when no address is given
+const DEFAULT_STM32_BASE: &str = "0x08000000";
+/// Baud rates the ESP loader may switch to; `None` keeps the current one
+const ESP_BAUD_RATES: [Option<u32>; 4] = [None, Some(230_400), Some(460_800), Some(921_600)];
+
+#[derive(Clone, Copy, PartialEq)]
+enum FlashTarget {
+    Stm32,
+    Esp,
+}
+
+/// An image picked for the ESP flasher with the offset it goes to
+#[derive(Clone, PartialEq)]
+struct EspImage {
+    name: String,
+    data: Vec<u8>,
+    offset: String,
+}
+
+/// Offset from esptool's usual file names; merged images start at 0
+fn default_esp_offset(name: &str) -> &'static str {
+    let name = name.to_lowercase();
+    if name.contains("merged") || name.contains("factory") {
+        "0x0"
+    } else if name.contains("bootloader") {
+        "0x1000"
+    } else if name.contains("partition") {
+        "0x8000"
+    } else if name.contains("boot_app0") || name.contains("ota_data") {
+        "0xE000"
+    } else {
+        "0x10000"
+    }
+}
+
+#[component]
+pub fn FlashButton(is_open: Signal<bool>) -> Element {
+    let state = use_context::<AppState>();
+    let target = use_signal(|| FlashTarget::Stm32);
+    let stm32_options = use_signal(FlashOptions::default);
+    let stm32_base = use_signal(|| DEFAULT_STM32_BASE.to_string());
+    let esp_options = use_signal(EspOptions::default);
+    let esp_images = use_signal(Vec::<EspImage>::new);
+    let running = state.transfer.is_running();
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-9 w-9",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#16181a] border border-[#2a2e33] hover:border-primary/50",
+                class: if is_open() || running { "border-primary text-primary" } else { "text-gray-500 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "Flash Firmware",
+                span { class: "material-symbols-outlined text-[20px]", "memory" }
+            }
+
+            if is_open() {
+                FlashPanel {
+                    target,
+                    stm32_options,
+                    stm32_base,
+                    esp_options,
+                    esp_images,
+                }
+            }
+        }
+    }
+}
+
+/// Flashes an image through the STM32 or Espressif serial bootloader
+#[component]
+fn FlashPanel(
+    target: Signal<FlashTarget>,
+    stm32_options: Signal<FlashOptions>,
+    stm32_base: Signal<String>,
+    esp_options: Signal<EspOptions>,
+    esp_images: Signal<Vec<EspImage>>,
+) -> Element {
+    let state = use_context::<AppState>();
+    let status = (state.transfer.status)();
+    let idle = state.conn.is_connected() && !state.transfer.is_running();
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-80 z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right text-left",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                PanelHeader { title: "Flash Firmware", subtitle: None }
+
+                div { class: "flex gap-1",
+                    for (t , label) in [(FlashTarget::Stm32, "STM32"), (FlashTarget::Esp, "ESP32 / ESP8266")] {
+                        button {
+                            key: "{label}",
+                            class: "flex-1 py-1 rounded text-[10px] font-bold border transition-colors",
+                            class: if target() == t { "border-primary text-primary bg-primary/10" } else { "border-[#2a2e33] text-gray-500 hover:text-white" },
+                            onclick: move |_| target.set(t),
+                            "{label}"
+                        }
+                    }
+                }
+
+                match target() {
+                    FlashTarget::Stm32 => rsx! {
+                        Stm32Form { options: stm32_options, base: stm32_base, idle }
+                    },
+                    FlashTarget::Esp => rsx! {
+                        EspForm { options: esp_options, images: esp_images, idle }
+                    },
+                }
+
+                if !state.conn.is_connected() {
+                    span { class: "text-xs text-gray-600 italic", "Connect to a port to flash" }
+                }
+
+                if let Some(status) = status {
+                    TransferProgress { status }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn Stm32Form(options: Signal<FlashOptions>, base: Signal<String>, idle: bool) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let current = options();
+
+    rsx! {
+        span { class: "text-xs text-gray-500",
+            "Start the chip with BOOT0 high. The port runs at 8E1 while flashing, and the whole flash is erased."
+        }
+
+        div { class: "flex flex-col gap-1",
+            span { class: "text-[10px] uppercase text-gray-500 font-bold", "Address for .bin files" }
+            input {
+                class: "bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                value: "{base}",
+                oninput: move |e| base.set(e.value()),
+            }
+        }
+
+        div { class: "flex gap-4",
+            ToggleSwitch {
+                label: "Verify",
+                active: current.verify,
+                onclick: move |_| options.write().verify = !current.verify,
+            }
+            ToggleSwitch {
+                label: "Run after",
+                active: current.go,
+                onclick: move |_| options.write().go = !current.go,
+            }
+        }
+
+        label {
+            class: "flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+            class: if idle { "text-gray-400 hover:text-white hover:border-gray-500 cursor-pointer" } else { "text-gray-700 pointer-events-none" },
+            span { class: "material-symbols-outlined text-[16px]", "upload" }
+            "Flash Image"
+            input {
+                class: "hidden",
+                r#type: "file",
+                accept: ".hex,.ihex,.ihx,.s19,.s28,.s37,.srec,.mot,.bin",
+                disabled: !idle,
+                onchange: move |evt: FormEvent| async move {
+                    let Some(file) = evt.files().into_iter().next() else {
+                        return;
+                    };
+                    let Some(address) = parse_address(&base.peek()) else {
+                        state.error("Invalid address for .bin files");
+                        return;
+                    };
+                    let bytes = match file.read_bytes().await {
+                        Ok(bytes) => bytes,
+                        Err(e) => {
+                            state.error(&format!("Failed to read file: {}", e));
+                            return;
+                        }
+                    };
+                    match parse_image(&file.name(), &bytes, address) {
+                        Ok(segments) => {
+                            state.info(&format!(
+                                "{}: {} in {} segments",
+                                file.name(),
+                                format_bytes(image_size(&segments)),
+                                segments.len()
+                            ));
+                            controller.flash_stm32(file.name(), segments, *options.peek());
+                        }
+                        Err(e) => state.error(&format!("Invalid image: {}", e)),
+                    }
+                },
+            }
+        }
+    }
+}
+
+#[component]
+fn EspForm(options: Signal<EspOptions>, images: Signal<Vec<EspImage>>, idle: bool) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let current = options();
+    let can_flash = idle && !images.read().is_empty();
+
+    let flash = move |_| {
+        let mut parsed = Vec::new();
+        for image in images.peek().iter() {
+            let Some(offset) = parse_address(&image.offset) else {
+                state.error(&format!("Invalid offset for {}", image.name));
+                return;
+            };
+            match parse_image(&image.name, &image.data, offset) {
+                Ok(segments) => parsed.push(segments),
+                Err(e) => {
+                    state.error(&format!("Invalid image {}: {}", image.name, e));
+                    return;
+                }
+            }
+        }
+        let name = match images.peek().as_slice() {
+            [image] => image.name.clone(),
+            all => format!("{} images", all.len()),
+        };
+        match combine(parsed) {
+            Ok(segments) => controller.flash_esp(name, segments, *options.peek()),
+            Err(e) => state.error(&e),
+        }
+    };
+
+    rsx! {
+        span { class: "text-xs text-gray-500",
+            "Hold BOOT (GPIO0) low during reset. Bootloaders go to 0x0 on ESP32-S3/C-series, 0x1000 on ESP32/S2."
+        }
+
+        div { class: "flex flex-col gap-1",
+            for (i , image) in images().into_iter().enumerate() {
+                div { key: "{i}-{image.name}", class: "flex items-center gap-2",
+                    span {
+                        class: "flex-1 truncate text-xs font-mono text-gray-300",
+                        title: "{image.name}",
+                        "{image.name}"
+                    }
+                    input {
+                        class: "w-24 bg-[#0d0f10] text-white px-2 py-1 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                        value: "{image.offset}",
+                        oninput: move |e| {
+                            if let Some(image) = images.write().get_mut(i) {
+                                image.offset = e.value();
+                            }
+                        },
+                    }
+                    button {
+                        class: "material-symbols-outlined text-[16px] text-gray-500 hover:text-red-400",
+                        onclick: move |_| {
+                            images.write().remove(i);
+                        },
+                        "close"
+                    }
+                }
+            }
+            label { class: "flex items-center justify-center gap-1 py-1 rounded border border-dashed border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold text-gray-500 hover:text-white hover:border-gray-500 cursor-pointer",
+                span { class: "material-symbols-outlined text-[16px]", "add" }
+                "Add .bin"
+                input {
+                    class: "hidden",
+                    r#type: "file",
+                    multiple: true,
+                    accept: ".bin,.hex",
+                    onchange: move |evt: FormEvent| async move {
+                        for file in evt.files() {
+                            match file.read_bytes().await {
+                                Ok(bytes) => images.write().push(EspImage {
+                                    offset: default_esp_offset(&file.name()).to_string(),
+                                    name: file.name(),
+                                    data: bytes.to_vec(),
+                                }),
+                                Err(e) => state.error(&format!("Failed to read file: {}", e)),
+                            }
+                        }
+                    },
+                }
+            }
+        }
+
+        div { class: "flex flex-col gap-1",
+            span { class: "text-[10px] uppercase text-gray-500 font-bold", "Flash baud rate" }
+            div { class: "flex gap-1",
+                for baud in ESP_BAUD_RATES {
+                    button {
+                        key: "{baud:?}",
+                        class: "flex-1 py-1 rounded text-[10px] font-bold border transition-colors",
+                        class: if current.baud_rate == baud { "border-primary text-primary bg-primary/10" } else { "border-[#2a2e33] text-gray-500 hover:text-white" },
+                        onclick: move |_| options.write().baud_rate = baud,
+                        match baud {
+                            Some(rate) => rate.to_string(),
+                            None => "Keep".to_string(),
+                        }
+                    }
+                }
+            }
+        }
+
+        div { class: "flex gap-4",
+            ToggleSwitch {
+                label: "Verify MD5",
+                active: current.verify,
+                onclick: move |_| options.write().verify = !current.verify,
+            }
+            ToggleSwitch {
+                label: "Reboot after",
+                active: current.reboot,
+                onclick: move |_| options.write().reboot = !current.reboot,
+            }
+        }
+
+        button {
+            class: "flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+            class: if can_flash { "text-gray-400 hover:text-white hover:border-gray-500" } else { "text-gray-700" },
+            disabled: !can_flash,
+            onclick: flash,
+            span { class: "material-symbols-outlined text-[16px]", "bolt" }
+            "Flash"
+        }
+    }
+}
+
+/// Accepts hex with a `0x` prefix or decimal
+fn parse_address(text: &str) -> Option<u32> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
@@ -0,0 +1,110 @@
+use crate::components::ui::CustomSelect;
+use crate::state::{AppState, CustomLevelRule, LevelPreset, LogLevel};
+use dioxus::prelude::*;
+
+#[component]
+pub fn LevelDetectionSettings() -> Element {
+    let state = use_context::<AppState>();
+    let config = (state.log.level_detection)();
+    let mut pattern = use_signal(String::new);
+    let mut level = use_signal(|| LogLevel::Error);
+    let mut error = use_signal(|| None::<String>);
+
+    let mut add_rule = move || {
+        let text = pattern().trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        if let Err(e) = regex::Regex::new(&text) {
+            error.set(Some(e.to_string()));
+            return;
+        }
+        error.set(None);
+        let mut config = state.log.level_detection.peek().clone();
+        config.custom.push(CustomLevelRule {
+            pattern: text,
+            level: level(),
+        });
+        state.log.set_level_detection(config);
+        pattern.set(String::new());
+    };
+
+    rsx! {
+        div { class: "flex flex-col gap-1.5 col-span-2",
+            label { class: "text-[10px] font-bold text-gray-500 uppercase tracking-widest px-1",
+                "Level Detection"
+            }
+            div { class: "flex flex-wrap gap-1",
+                for preset in LevelPreset::ALL {
+                    {
+                        let enabled = config.presets.contains(&preset);
+                        rsx! {
+                            button {
+                                class: "px-2 py-1 rounded border text-[10px] transition-colors",
+                                class: if enabled { "border-primary/40 text-primary bg-primary/10" } else { "border-[#2a2e33] text-gray-500 hover:text-gray-300" },
+                                onclick: move |_| {
+                                    let mut config = state.log.level_detection.peek().clone();
+                                    if enabled {
+                                        config.presets.retain(|p| *p != preset);
+                                    } else {
+                                        config.presets.push(preset);
+                                    }
+                                    state.log.set_level_detection(config);
+                                },
+                                "{preset.label()}"
+                            }
+                        }
+                    }
+                }
+            }
+            for (i , rule) in config.custom.iter().enumerate() {
+                div { class: "flex items-center gap-2 px-1 text-[10px] font-mono",
+                    span { class: "text-gray-400 w-7", "{rule.level.label()}" }
+                    span { class: "flex-1 text-gray-300 truncate", title: "{rule.pattern}", "{rule.pattern}" }
+                    button {
+                        class: "material-symbols-outlined text-[14px] text-gray-500 hover:text-red-400",
+                        title: "Remove rule",
+                        onclick: move |_| {
+                            let mut config = state.log.level_detection.peek().clone();
+                            config.custom.remove(i);
+                            state.log.set_level_detection(config);
+                        },
+                        "close"
+                    }
+                }
+            }
+            div { class: "flex items-center gap-1",
+                CustomSelect {
+                    options: LogLevel::ALL.iter().map(|l| l.label()).collect::<Vec<_>>(),
+                    selected: level().label().to_string(),
+                    onchange: move |val: String| {
+                        if let Some(l) = LogLevel::ALL.iter().find(|l| l.label() == val) {
+                            level.set(*l);
+                        }
+                    },
+                    class: "w-20",
+                }
+                input {
+                    class: "flex-1 min-w-0 bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                    placeholder: "Custom regex, e.g. panic|abort",
+                    value: "{pattern}",
+                    oninput: move |e| pattern.set(e.value()),
+                    onkeydown: move |evt| {
+                        if evt.key() == Key::Enter {
+                            add_rule();
+                        }
+                    },
+                }
+                button {
+                    class: "material-symbols-outlined text-[18px] text-gray-400 hover:text-white",
+                    title: "Add rule",
+                    onclick: move |_| add_rule(),
+                    "add"
+                }
+            }
+            if let Some(msg) = error() {
+                span { class: "text-[10px] text-red-400 px-1", "{msg}" }
+            }
+        }
+    }
+}
@@ -0,0 +1,293 @@
+use crate::components::connection::TransferProgress;
+use crate::components::ui::PanelHeader;
+use crate::hooks::transfer::use_transfer_controller;
+use crate::state::AppState;
+use crate::transfer::micropython::{join_path, Entry};
+use crate::utils::format::format_bytes;
+use dioxus::prelude::*;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MicroPythonTab {
+    Script,
+    Files,
+}
+
+#[component]
+pub fn MicroPythonButton(is_open: Signal<bool>) -> Element {
+    let tab = use_signal(|| MicroPythonTab::Script);
+    let script = use_signal(|| "print('hello from MicroPython')\n".to_string());
+    let dir = use_signal(|| "/".to_string());
+    let files = use_signal(Vec::<Entry>::new);
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-9 w-9",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#16181a] border border-[#2a2e33] hover:border-primary/50",
+                class: if is_open() { "border-primary text-primary" } else { "text-gray-500 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "MicroPython",
+                span { class: "material-symbols-outlined text-[20px]", "terminal" }
+            }
+
+            if is_open() {
+                MicroPythonPanel { tab, script, dir, files }
+            }
+        }
+    }
+}
+
+/// Runs scripts and manages files on a MicroPython board through its raw REPL
+#[component]
+fn MicroPythonPanel(
+    tab: Signal<MicroPythonTab>,
+    script: Signal<String>,
+    dir: Signal<String>,
+    files: Signal<Vec<Entry>>,
+) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let status = (state.transfer.status)();
+    let idle = state.conn.is_connected() && !state.transfer.is_running();
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-96 z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right text-left",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                PanelHeader {
+                    title: "MicroPython",
+                    subtitle: Some("Raw REPL, output shows in the monitor".to_string()),
+                }
+
+                div { class: "flex gap-1",
+                    for (t , label) in [(MicroPythonTab::Script, "Script"), (MicroPythonTab::Files, "Files")] {
+                        button {
+                            key: "{label}",
+                            class: "flex-1 py-1 rounded text-[10px] font-bold border transition-colors",
+                            class: if tab() == t { "border-primary text-primary bg-primary/10" } else { "border-[#2a2e33] text-gray-500 hover:text-white" },
+                            onclick: move |_| {
+                                tab.set(t);
+                                if t == MicroPythonTab::Files && idle && files.peek().is_empty() {
+                                    controller.list_micropython(dir.peek().clone(), files);
+                                }
+                            },
+                            "{label}"
+                        }
+                    }
+                }
+
+                match tab() {
+                    MicroPythonTab::Script => rsx! {
+                        ScriptForm { script, idle }
+                    },
+                    MicroPythonTab::Files => rsx! {
+                        FilesForm { dir, files, idle }
+                    },
+                }
+
+                if !state.conn.is_connected() {
+                    span { class: "text-xs text-gray-600 italic", "Connect to a port to use the REPL" }
+                }
+
+                if let Some(status) = status {
+                    TransferProgress { status }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ScriptForm(script: Signal<String>, idle: bool) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let can_run = idle && !script.read().trim().is_empty();
+
+    rsx! {
+        textarea {
+            class: "h-48 bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono resize-none",
+            spellcheck: false,
+            value: "{script}",
+            oninput: move |e| script.set(e.value()),
+            onkeydown: move |e| {
+                if e.key() == Key::Enter && e.modifiers().ctrl() {
+                    e.prevent_default();
+                    if can_run {
+                        controller.run_micropython(script.peek().clone());
+                    }
+                }
+            },
+        }
+
+        div { class: "flex gap-2",
+            button {
+                class: "flex-1 flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+                class: if can_run { "text-gray-400 hover:text-white hover:border-gray-500" } else { "text-gray-700" },
+                disabled: !can_run,
+                title: "Run (Ctrl+Enter)",
+                onclick: move |_| controller.run_micropython(script.peek().clone()),
+                span { class: "material-symbols-outlined text-[16px]", "play_arrow" }
+                "Run"
+            }
+            label { class: "flex items-center justify-center gap-1 px-3 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold text-gray-400 hover:text-white hover:border-gray-500 cursor-pointer transition-all",
+                span { class: "material-symbols-outlined text-[16px]", "folder_open" }
+                "Open"
+                input {
+                    class: "hidden",
+                    r#type: "file",
+                    accept: ".py,.txt",
+                    onchange: move |evt: FormEvent| async move {
+                        let Some(file) = evt.files().into_iter().next() else {
+                            return;
+                        };
+                        match file.read_bytes().await {
+                            Ok(bytes) => script.set(String::from_utf8_lossy(&bytes).into_owned()),
+                            Err(e) => state.error(&format!("Failed to read file: {}", e)),
+                        }
+                    },
+                }
+            }
+            button {
+                class: "flex items-center justify-center gap-1 px-3 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+                class: if idle { "text-gray-400 hover:text-white hover:border-gray-500" } else { "text-gray-700" },
+                disabled: !idle,
+                title: "Soft reset (Ctrl+D)",
+                onclick: move |_| controller.soft_reset_micropython(),
+                span { class: "material-symbols-outlined text-[16px]", "restart_alt" }
+                "Reset"
+            }
+        }
+    }
+}
+
+#[component]
+fn FilesForm(dir: Signal<String>, files: Signal<Vec<Entry>>, idle: bool) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+
+    let mut open_dir = move |path: String| {
+        dir.set(path.clone());
+        files.set(Vec::new());
+        controller.list_micropython(path, files);
+    };
+    let parent = {
+        let current = dir();
+        let trimmed = current.trim_end_matches('/');
+        (!trimmed.is_empty()).then(|| match trimmed.rsplit_once('/') {
+            Some(("", _)) | None => "/".to_string(),
+            Some((parent, _)) => parent.to_string(),
+        })
+    };
+
+    rsx! {
+        div { class: "flex gap-2",
+            input {
+                class: "flex-1 bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                value: "{dir}",
+                oninput: move |e| dir.set(e.value()),
+                onkeydown: move |e| {
+                    if e.key() == Key::Enter && idle {
+                        open_dir(dir.peek().clone());
+                    }
+                },
+            }
+            button {
+                class: "material-symbols-outlined text-[18px] px-2 rounded border border-[#2a2e33]",
+                class: if idle { "text-gray-400 hover:text-white" } else { "text-gray-700" },
+                disabled: !idle,
+                title: "Refresh",
+                onclick: move |_| open_dir(dir.peek().clone()),
+                "refresh"
+            }
+        }
+
+        div { class: "flex flex-col max-h-56 overflow-y-auto custom-scrollbar rounded border border-[#2a2e33] bg-[#0d0f10]",
+            if let Some(parent) = parent {
+                button {
+                    class: "flex items-center gap-2 px-2 py-1 text-xs font-mono text-gray-400 hover:bg-white/5 text-left",
+                    disabled: !idle,
+                    onclick: move |_| open_dir(parent.clone()),
+                    span { class: "material-symbols-outlined text-[16px]", "arrow_upward" }
+                    ".."
+                }
+            }
+            for entry in files() {
+                div {
+                    key: "{entry.name}",
+                    class: "group flex items-center gap-2 px-2 py-1 text-xs font-mono text-gray-300 hover:bg-white/5",
+                    if entry.is_dir {
+                        button {
+                            class: "flex-1 flex items-center gap-2 truncate text-left",
+                            disabled: !idle,
+                            onclick: {
+                                let path = join_path(&dir.peek(), &entry.name);
+                                move |_| open_dir(path.clone())
+                            },
+                            span { class: "material-symbols-outlined text-[16px] text-primary", "folder" }
+                            "{entry.name}"
+                        }
+                    } else {
+                        span { class: "material-symbols-outlined text-[16px] text-gray-500", "description" }
+                        span { class: "flex-1 truncate", title: "{entry.name}", "{entry.name}" }
+                        span { class: "text-[10px] text-gray-600", "{format_bytes(entry.size)}" }
+                        button {
+                            class: "material-symbols-outlined text-[16px] text-gray-500 hover:text-white disabled:opacity-30",
+                            disabled: !idle,
+                            title: "Download",
+                            onclick: {
+                                let path = join_path(&dir.peek(), &entry.name);
+                                move |_| controller.download_micropython(path.clone())
+                            },
+                            "download"
+                        }
+                    }
+                    button {
+                        class: "material-symbols-outlined text-[16px] text-gray-500 hover:text-red-400 disabled:opacity-30",
+                        disabled: !idle,
+                        title: "Delete",
+                        onclick: {
+                            let entry = entry.clone();
+                            move |_| controller.remove_micropython(dir.peek().clone(), entry.clone(), files)
+                        },
+                        "delete"
+                    }
+                }
+            }
+            if files.read().is_empty() {
+                span { class: "px-2 py-3 text-xs text-gray-600 italic text-center", "No files listed" }
+            }
+        }
+
+        label {
+            class: "flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+            class: if idle { "text-gray-400 hover:text-white hover:border-gray-500 cursor-pointer" } else { "text-gray-700 pointer-events-none" },
+            span { class: "material-symbols-outlined text-[16px]", "upload" }
+            "Upload to {dir}"
+            input {
+                class: "hidden",
+                r#type: "file",
+                disabled: !idle,
+                onchange: move |evt: FormEvent| async move {
+                    let Some(file) = evt.files().into_iter().next() else {
+                        return;
+                    };
+                    match file.read_bytes().await {
+                        Ok(bytes) => {
+                            controller
+                                .upload_micropython(dir.peek().clone(), file.name(), bytes.to_vec(), files)
+                        }
+                        Err(e) => state.error(&format!("Failed to read file: {}", e)),
+                    }
+                },
+            }
+        }
+    }
+}
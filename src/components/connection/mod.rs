@@ -1,7 +1,17 @@
 pub mod baud_rate_picker;
+pub mod flash_panel;
+pub mod level_detection;
+pub mod micropython_panel;
+pub mod rx_framing;
 pub mod settings_dropdown;
 pub mod status;
+pub mod transfer_panel;
 
 pub use baud_rate_picker::BaudRatePicker;
+pub use flash_panel::FlashButton;
+pub use level_detection::LevelDetectionSettings;
+pub use micropython_panel::MicroPythonButton;
+pub use rx_framing::RxFramingSettings;
 pub use settings_dropdown::SettingsDropdown;
 pub use status::PortStatus;
+pub use transfer_panel::{TransferButton, TransferProgress};
@@ -0,0 +1,183 @@
+use crate::components::ui::CustomSelect;
+use crate::config::LINE_BYTES_HARD_LIMIT;
+use crate::state::{AppState, CrMode, RxDelimiter, RxFraming};
+use crate::utils::{format_delimiter_list, parse_delimiter_list};
+use dioxus::prelude::*;
+
+const MODES: [&str; 4] = ["Newline", "Delimiter", "Regex", "Fixed Length"];
+const CR_MODES: [&str; 3] = ["CR Ends Line", "CR Overwrites", "CR Overwrites + History"];
+
+fn cr_mode_name(mode: CrMode) -> &'static str {
+    match mode {
+        CrMode::LineEnd => CR_MODES[0],
+        CrMode::Overwrite => CR_MODES[1],
+        CrMode::OverwriteKeepHistory => CR_MODES[2],
+    }
+}
+
+fn cr_mode_from_name(name: &str) -> CrMode {
+    match name {
+        "CR Overwrites" => CrMode::Overwrite,
+        "CR Overwrites + History" => CrMode::OverwriteKeepHistory,
+        _ => CrMode::LineEnd,
+    }
+}
+
+fn mode_name(delimiter: &RxDelimiter) -> &'static str {
+    match delimiter {
+        RxDelimiter::Newline => MODES[0],
+        RxDelimiter::Sequences(_) => MODES[1],
+        RxDelimiter::Regex(_) => MODES[2],
+        RxDelimiter::FixedLength(_) => MODES[3],
+    }
+}
+
+/// Builds the framing from the form fields, or an error message to show under them
+fn build_framing(
+    mode: &str,
+    pattern: &str,
+    idle: &str,
+    max_line: &str,
+    cr_mode: CrMode,
+) -> Result<RxFraming, String> {
+    let delimiter = match mode {
+        "Delimiter" => RxDelimiter::Sequences(parse_delimiter_list(pattern)?),
+        "Regex" => {
+            regex::bytes::Regex::new(pattern).map_err(|e| e.to_string())?;
+            RxDelimiter::Regex(pattern.to_string())
+        }
+        "Fixed Length" => match pattern.trim().parse::<usize>() {
+            Ok(n) if n > 0 => RxDelimiter::FixedLength(n),
+            _ => return Err("Length must be a positive number".to_string()),
+        },
+        _ => RxDelimiter::Newline,
+    };
+
+    let idle = idle.trim();
+    let idle_flush_ms = if idle.is_empty() {
+        None
+    } else {
+        Some(
+            idle.parse::<u32>()
+                .map_err(|_| "Idle timeout must be in milliseconds".to_string())?,
+        )
+    };
+
+    let max_line_bytes = match max_line.trim().parse::<usize>() {
+        Ok(n) if (1..=LINE_BYTES_HARD_LIMIT).contains(&n) => n,
+        _ => {
+            return Err(format!(
+                "Max line length must be 1-{}",
+                LINE_BYTES_HARD_LIMIT
+            ))
+        }
+    };
+
+    Ok(RxFraming {
+        delimiter,
+        idle_flush_ms,
+        max_line_bytes,
+        cr_mode,
+    })
+}
+
+#[component]
+pub fn RxFramingSettings() -> Element {
+    let state = use_context::<AppState>();
+    let initial = state.serial.rx_framing.peek().clone();
+
+    let mut mode = use_signal(|| mode_name(&initial.delimiter).to_string());
+    let mut pattern = use_signal(|| match &initial.delimiter {
+        RxDelimiter::Sequences(list) => format_delimiter_list(list),
+        RxDelimiter::Regex(p) => p.clone(),
+        RxDelimiter::FixedLength(n) => n.to_string(),
+        _ => String::new(),
+    });
+    let mut idle = use_signal(|| {
+        initial
+            .idle_flush_ms
+            .map(|ms| ms.to_string())
+            .unwrap_or_default()
+    });
+    let mut max_line = use_signal(|| initial.max_line_bytes.to_string());
+    let mut cr_mode = use_signal(|| initial.cr_mode);
+    let mut error = use_signal(|| None::<String>);
+
+    let mut apply =
+        move || match build_framing(&mode(), &pattern(), &idle(), &max_line(), cr_mode()) {
+            Ok(framing) => {
+                error.set(None);
+                if *state.serial.rx_framing.peek() != framing {
+                    state.serial.set_rx_framing(framing);
+                }
+            }
+            Err(e) => error.set(Some(e)),
+        };
+
+    let placeholder = match mode().as_str() {
+        "Delimiter" => "e.g. \\x03|\\0",
+        "Regex" => "e.g. \\r?\\n> ",
+        "Fixed Length" => "e.g. 16",
+        _ => "",
+    };
+
+    rsx! {
+        div { class: "flex flex-col gap-1.5 col-span-2",
+            label { class: "text-[10px] font-bold text-gray-500 uppercase tracking-widest px-1",
+                "RX Line Split"
+            }
+            CustomSelect {
+                options: MODES.to_vec(),
+                selected: mode(),
+                onchange: move |val: String| {
+                    mode.set(val);
+                    pattern.set(String::new());
+                    apply();
+                },
+            }
+            if mode() == MODES[0] {
+                CustomSelect {
+                    options: CR_MODES.to_vec(),
+                    selected: cr_mode_name(cr_mode()).to_string(),
+                    onchange: move |val: String| {
+                        cr_mode.set(cr_mode_from_name(&val));
+                        apply();
+                    },
+                }
+            } else {
+                input {
+                    class: "w-full bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                    placeholder: "{placeholder}",
+                    value: "{pattern}",
+                    oninput: move |e| {
+                        pattern.set(e.value());
+                        apply();
+                    },
+                }
+            }
+            input {
+                class: "w-full bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                placeholder: "Idle flush (ms, optional)",
+                value: "{idle}",
+                oninput: move |e| {
+                    idle.set(e.value());
+                    apply();
+                },
+            }
+            div { class: "flex items-center gap-2",
+                span { class: "text-[10px] text-gray-500 whitespace-nowrap px-1", "Max line bytes" }
+                input {
+                    class: "w-full bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                    value: "{max_line}",
+                    oninput: move |e| {
+                        max_line.set(e.value());
+                        apply();
+                    },
+                }
+            }
+            if let Some(msg) = error() {
+                span { class: "text-[10px] text-red-400 px-1", "{msg}" }
+            }
+        }
+    }
+}
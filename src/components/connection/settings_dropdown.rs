@@ -1,12 +1,13 @@
+use crate::components::connection::{LevelDetectionSettings, RxFramingSettings};
 use crate::components::ui::CustomSelect;
-use crate::state::AppState;
+use crate::state::{AppState, TextEncoding};
 use dioxus::prelude::*;
 
 #[component]
 pub fn SettingsDropdown(is_open: bool, onclose: EventHandler<()>) -> Element {
     let state = use_context::<AppState>();
 
-    // Signals removed, using direct state access in rsx!
+    // Settings stay editable while connected; changes are applied via reconfigure
 
     rsx! {
         if is_open {
@@ -31,7 +32,7 @@ pub fn SettingsDropdown(is_open: bool, onclose: EventHandler<()>) -> Element {
                                 state.serial.set_data_bits(b);
                             }
                         },
-                        disabled: state.conn.is_connected(),
+                        disabled: (state.conn.is_busy)(),
                     }
                 }
                 div { class: "flex flex-col gap-1.5",
@@ -46,7 +47,7 @@ pub fn SettingsDropdown(is_open: bool, onclose: EventHandler<()>) -> Element {
                                 state.serial.set_stop_bits(b);
                             }
                         },
-                        disabled: state.conn.is_connected(),
+                        disabled: (state.conn.is_busy)(),
                     }
                 }
                 div { class: "flex flex-col gap-1.5",
@@ -68,7 +69,7 @@ pub fn SettingsDropdown(is_open: bool, onclose: EventHandler<()>) -> Element {
                             };
                             state.serial.set_parity(p);
                         },
-                        disabled: state.conn.is_connected(),
+                        disabled: (state.conn.is_busy)(),
                     }
                 }
                 div { class: "flex flex-col gap-1.5",
@@ -88,9 +89,25 @@ pub fn SettingsDropdown(is_open: bool, onclose: EventHandler<()>) -> Element {
                             };
                             state.serial.set_flow_control(f);
                         },
-                        disabled: state.conn.is_connected(),
+                        disabled: (state.conn.is_busy)(),
                     }
                 }
+                div { class: "flex flex-col gap-1.5 col-span-2",
+                    label { class: "text-[10px] font-bold text-gray-500 uppercase tracking-widest px-1",
+                        "Encoding"
+                    }
+                    CustomSelect {
+                        options: TextEncoding::ALL.iter().map(|e| e.label()).collect::<Vec<_>>(),
+                        selected: (state.serial.encoding)().label().to_string(),
+                        onchange: move |val: String| {
+                            if let Some(e) = TextEncoding::ALL.iter().find(|e| e.label() == val) {
+                                state.serial.set_encoding(*e);
+                            }
+                        },
+                    }
+                }
+                RxFramingSettings {}
+                LevelDetectionSettings {}
             }
         }
     }
@@ -0,0 +1,174 @@
+use crate::components::ui::PanelHeader;
+use crate::hooks::transfer::use_transfer_controller;
+use crate::state::{AppState, TransferStatus};
+use crate::transfer::xmodem::Variant;
+use crate::utils::format::format_bytes;
+use dioxus::prelude::*;
+
+#[component]
+pub fn TransferButton(is_open: Signal<bool>) -> Element {
+    let state = use_context::<AppState>();
+    let running = state.transfer.is_running();
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-9 w-9",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#16181a] border border-[#2a2e33] hover:border-primary/50",
+                class: if is_open() || running { "border-primary text-primary" } else { "text-gray-500 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "File Transfer",
+                span {
+                    class: "material-symbols-outlined text-[20px]",
+                    class: if running { "animate-pulse" },
+                    "swap_vert"
+                }
+            }
+
+            if is_open() {
+                TransferPanel {}
+            }
+        }
+    }
+}
+
+/// XMODEM/YMODEM send and receive
+#[component]
+fn TransferPanel() -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let mut variant = use_signal(|| Variant::Ymodem);
+    let status = (state.transfer.status)();
+    let idle = state.conn.is_connected() && !state.transfer.is_running();
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-80 z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right text-left",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                PanelHeader { title: "File Transfer", subtitle: None }
+
+                div { class: "flex gap-1",
+                    for v in Variant::ALL {
+                        button {
+                            key: "{v}",
+                            class: "flex-1 py-1 rounded text-[10px] font-bold border transition-colors",
+                            class: if variant() == v { "border-primary text-primary bg-primary/10" } else { "border-[#2a2e33] text-gray-500 hover:text-white" },
+                            onclick: move |_| variant.set(v),
+                            "{v}"
+                        }
+                    }
+                }
+
+                div { class: "flex gap-2",
+                    label {
+                        class: "flex-1 flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+                        class: if idle { "text-gray-400 hover:text-white hover:border-gray-500 cursor-pointer" } else { "text-gray-700 pointer-events-none" },
+                        span { class: "material-symbols-outlined text-[16px]", "upload" }
+                        "Send File"
+                        input {
+                            class: "hidden",
+                            r#type: "file",
+                            disabled: !idle,
+                            onchange: move |evt: FormEvent| async move {
+                                if let Some(file) = evt.files().into_iter().next() {
+                                    match file.read_bytes().await {
+                                        Ok(bytes) => controller.send_file(variant(), file.name(), bytes.to_vec()),
+                                        Err(e) => state.error(&format!("Failed to read file: {}", e)),
+                                    }
+                                }
+                            },
+                        }
+                    }
+                    button {
+                        class: "flex-1 flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+                        class: if idle { "text-gray-400 hover:text-white hover:border-gray-500" } else { "text-gray-700" },
+                        disabled: !idle,
+                        onclick: move |_| controller.receive_file(variant()),
+                        span { class: "material-symbols-outlined text-[16px]", "download" }
+                        "Receive"
+                    }
+                }
+
+                if !state.conn.is_connected() {
+                    span { class: "text-xs text-gray-600 italic", "Connect to a port to transfer files" }
+                }
+
+                if let Some(status) = status {
+                    TransferProgress { status }
+                }
+            }
+        }
+    }
+}
+
+/// Progress of the session owning the port, with cancel while it runs
+#[component]
+pub fn TransferProgress(status: TransferStatus) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let percent = status.percent();
+    let paused = (state.transfer.paused)();
+    let size = match status.total {
+        Some(total) => format!("{} / {}", format_bytes(status.bytes), format_bytes(total)),
+        None => format_bytes(status.bytes),
+    };
+    let bar_class = match &status.result {
+        Some(Err(_)) => "bg-red-500",
+        Some(Ok(_)) => "bg-green-500",
+        None => "bg-primary",
+    };
+
+    rsx! {
+        div { class: "flex flex-col gap-1.5 p-3 rounded-lg bg-[#0d0f10] border border-[#2a2e33]",
+            div { class: "flex items-center justify-between gap-2",
+                span { class: "truncate text-xs font-mono text-gray-200", title: "{status.title}", "{status.title}" }
+                if status.is_running() {
+                    div { class: "flex gap-3",
+                        if status.can_pause {
+                            button {
+                                class: "text-[10px] uppercase tracking-wider font-bold text-gray-400 hover:text-white",
+                                onclick: move |_| controller.toggle_pause(),
+                                if paused { "Resume" } else { "Pause" }
+                            }
+                        }
+                        button {
+                            class: "text-[10px] uppercase tracking-wider font-bold text-red-400 hover:text-red-300",
+                            onclick: move |_| controller.cancel(),
+                            "Cancel"
+                        }
+                    }
+                } else {
+                    button {
+                        class: "text-[10px] uppercase tracking-wider font-bold text-gray-500 hover:text-white",
+                        onclick: move |_| state.transfer.dismiss(),
+                        "Dismiss"
+                    }
+                }
+            }
+            div { class: "h-1.5 rounded-full bg-white/5 overflow-hidden",
+                div {
+                    class: "h-full transition-all {bar_class}",
+                    class: if percent.is_none() && status.is_running() { "animate-pulse" },
+                    style: "width: {percent.unwrap_or(100.0)}%",
+                }
+            }
+            div { class: "flex justify-between text-[10px] font-mono text-gray-500",
+                span { "{size}" }
+                if status.retries > 0 {
+                    span { class: "text-amber-400", "{status.retries} retries" }
+                }
+            }
+            if let Some(Err(e)) = &status.result {
+                span { class: "text-[10px] text-red-400", "{e}" }
+            }
+        }
+    }
+}
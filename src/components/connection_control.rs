@@ -1,4 +1,6 @@
-use crate::components::connection::{BaudRatePicker, PortStatus, SettingsDropdown};
+use crate::components::connection::{
+    BaudRatePicker, FlashButton, MicroPythonButton, PortStatus, SettingsDropdown, TransferButton,
+};
 use crate::components::ui::IconButton;
 use crate::hooks::use_serial_controller;
 use crate::state::AppState;
@@ -9,6 +11,9 @@ pub fn ConnectionControl() -> Element {
     let state = use_context::<AppState>();
     let controller = use_serial_controller();
     let is_open = (state.ui.show_settings)();
+    let transfer_open = use_signal(|| false);
+    let flash_open = use_signal(|| false);
+    let micropython_open = use_signal(|| false);
 
     let settings_icon_class = if is_open {
         "text-[20px] transition-all duration-300 rotate-45"
@@ -24,6 +29,22 @@ pub fn ConnectionControl() -> Element {
             // Baud Rate
             BaudRatePicker {}
 
+            // Apply settings to the open port without ending the session
+            if state.conn.has_pending_config(state.serial.current_config()) {
+                button {
+                    disabled: (state.conn.is_busy)(),
+                    class: "flex items-center justify-center gap-1 h-9 px-3 bg-primary/10 border border-primary/50 rounded-lg text-primary hover:bg-primary/20 transition-colors active:scale-95",
+                    onclick: move |_| controller.reconfigure(),
+                    title: "Apply settings to the open port",
+                    span { class: "material-symbols-outlined text-[18px]", "sync" }
+                    span { class: "text-[10px] font-bold uppercase tracking-wide", "Apply" }
+                }
+            }
+
+            TransferButton { is_open: transfer_open }
+            FlashButton { is_open: flash_open }
+            MicroPythonButton { is_open: micropython_open }
+
             // Settings Button
             IconButton {
                 icon: "settings",
@@ -0,0 +1,235 @@
+use crate::at::library::{self, LibraryCommand};
+use crate::at::{is_urc, urc_prefix, Block, Exchange, FinalResult};
+use crate::components::ui::{PanelHeader, ToggleSwitch};
+use crate::hooks::at::use_at_controller;
+use crate::state::AppState;
+use dioxus::prelude::*;
+
+#[component]
+pub fn AtButton(is_open: Signal<bool>) -> Element {
+    let state = use_context::<AppState>();
+    let enabled = (state.at.enabled)();
+    let waiting = state.at.log.read().is_waiting();
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-full aspect-square",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#0d0f10] border border-[#2a2e33] hover:border-gray-500",
+                class: if is_open() || enabled { "border-primary text-primary" } else { "text-gray-400 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "AT Console",
+                span {
+                    class: "material-symbols-outlined text-[20px]",
+                    class: if waiting { "animate-pulse" },
+                    "cell_tower"
+                }
+            }
+
+            if is_open() {
+                AtPanel {}
+            }
+        }
+    }
+}
+
+/// AT command console: command/response blocks and a command library
+#[component]
+fn AtPanel() -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_at_controller();
+    let enabled = (state.at.enabled)();
+    let mut command = use_signal(String::new);
+    let mut query = use_signal(String::new);
+    let matches = library::search(&query());
+
+    let send = move |_| {
+        let text = command.peek().trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        controller.set_enabled(true);
+        spawn(async move {
+            match controller.send(text).await {
+                Ok(()) => command.set(String::new()),
+                Err(e) => state.warning(&e),
+            }
+        });
+    };
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-[28rem] z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right text-left",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                div { class: "flex items-start justify-between",
+                    PanelHeader {
+                        title: "AT Console",
+                        subtitle: Some("The send bar waits for each final result code".to_string()),
+                    }
+                    ToggleSwitch {
+                        label: "AT mode",
+                        active: enabled,
+                        onclick: move |_| controller.set_enabled(!enabled),
+                    }
+                }
+
+                AtBlocks {}
+
+                div { class: "flex gap-2",
+                    input {
+                        class: "flex-1 bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                        placeholder: "AT command...",
+                        value: "{command}",
+                        oninput: move |e| command.set(e.value()),
+                        onkeydown: move |e| {
+                            if e.key() == Key::Enter {
+                                send(());
+                            }
+                        },
+                    }
+                    button {
+                        class: "material-symbols-outlined text-[18px] px-2 rounded border border-[#2a2e33] text-gray-400 hover:text-white",
+                        title: "Send",
+                        onclick: move |_| send(()),
+                        "send"
+                    }
+                    button {
+                        class: "material-symbols-outlined text-[18px] px-2 rounded border border-[#2a2e33] text-gray-400 hover:text-white",
+                        title: "Clear",
+                        onclick: move |_| controller.clear(),
+                        "delete_sweep"
+                    }
+                }
+
+                div { class: "flex flex-col gap-1 pt-2 border-t border-white/5",
+                    input {
+                        class: "bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none",
+                        placeholder: "Search commands, e.g. signal, sms, esp...",
+                        value: "{query}",
+                        oninput: move |e| query.set(e.value()),
+                    }
+                    div { class: "flex flex-col max-h-40 overflow-y-auto custom-scrollbar",
+                        for entry in matches {
+                            LibraryEntry {
+                                key: "{entry.command}",
+                                entry,
+                                onpick: move |text: String| command.set(text),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn LibraryEntry(entry: &'static LibraryCommand, onpick: EventHandler<String>) -> Element {
+    let title = if entry.has_placeholder() {
+        "Fill in the placeholders, then send"
+    } else {
+        "Click to edit"
+    };
+
+    rsx! {
+        button {
+            class: "flex items-baseline gap-2 px-2 py-1 rounded text-left hover:bg-white/5",
+            title,
+            onclick: move |_| onpick.call(entry.command.to_string()),
+            span { class: "text-xs font-mono text-white shrink-0", "{entry.command}" }
+            span { class: "flex-1 truncate text-[11px] text-gray-500", "{entry.description}" }
+            span { class: "text-[9px] uppercase font-bold text-gray-600 shrink-0", "{entry.group}" }
+        }
+    }
+}
+
+#[component]
+fn AtBlocks() -> Element {
+    let state = use_context::<AppState>();
+    let log = state.at.log.read();
+
+    rsx! {
+        div {
+            class: "flex flex-col gap-1 h-64 overflow-y-auto custom-scrollbar rounded border border-[#2a2e33] bg-[#0d0f10] p-2 font-mono text-xs",
+            onmounted: move |e| async move {
+                let _ = e.scroll_to(ScrollBehavior::Instant).await;
+            },
+            if log.blocks().is_empty() {
+                span { class: "m-auto text-gray-600 italic font-sans", "Nothing sent yet" }
+            }
+            for (i , block) in log.blocks().iter().enumerate() {
+                match block {
+                    Block::Exchange(exchange) => rsx! {
+                        ExchangeBlock { key: "{i}", exchange: exchange.clone() }
+                    },
+                    Block::Unsolicited(line) => rsx! {
+                        UnsolicitedLine { key: "{i}", line: line.clone() }
+                    },
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn ExchangeBlock(exchange: Exchange) -> Element {
+    let (badge, badge_class) = match &exchange.result {
+        None => ("…".to_string(), "text-gray-500 animate-pulse"),
+        Some(FinalResult::Timeout) => ("TIMEOUT".to_string(), "text-amber-400"),
+        Some(result) if result.is_success() => (result.to_string(), "text-green-400"),
+        Some(result) => (result.to_string(), "text-red-400"),
+    };
+    let description = exchange.result.as_ref().and_then(FinalResult::description);
+
+    rsx! {
+        div { class: "flex flex-col rounded bg-white/[0.03] px-2 py-1",
+            div { class: "flex items-baseline gap-2",
+                span { class: "text-primary", ">" }
+                span { class: "flex-1 text-white break-all", "{exchange.command}" }
+                if exchange.result.is_some() {
+                    span { class: "text-[10px] text-gray-600", "{exchange.elapsed_ms:.0} ms" }
+                }
+                span { class: "text-[10px] font-bold {badge_class}", "{badge}" }
+            }
+            for (i , line) in exchange.response.iter().enumerate() {
+                span { key: "{i}", class: "pl-4 text-gray-300 break-all whitespace-pre-wrap", "{line}" }
+            }
+            if let Some(description) = description {
+                span { class: "pl-4 text-red-400/80 font-sans text-[11px]", "{description}" }
+            }
+        }
+    }
+}
+
+#[component]
+fn UnsolicitedLine(line: String) -> Element {
+    let state = use_context::<AppState>();
+    let known = is_urc(&line);
+    let prefix = urc_prefix(&line).to_string();
+
+    rsx! {
+        div {
+            class: "group flex items-baseline gap-2 px-2",
+            class: if known { "text-amber-300" } else { "text-gray-500" },
+            span { class: "text-[9px] font-bold font-sans", if known { "URC" } else { "RX" } }
+            span { class: "flex-1 break-all", "{line}" }
+            button {
+                class: "opacity-0 group-hover:opacity-100 material-symbols-outlined text-[14px] text-gray-500 hover:text-white",
+                title: "Highlight \"{prefix}\" in the monitor",
+                onclick: move |_| {
+                    let color = state.log.next_highlight_color();
+                    state.log.add_highlight(prefix.clone(), color);
+                },
+                "ink_highlighter"
+            }
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use crate::components::ui::{IconButton, PanelHeader, ToggleSwitch};
+use crate::hooks::use_worker_controller;
+use crate::state::{AppState, SymbolInfo};
+use dioxus::prelude::*;
+
+#[component]
+pub fn BacktraceButton(is_open: Signal<bool>) -> Element {
+    let state = use_context::<AppState>();
+    let loaded = state.log.elf.read().is_some();
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-full aspect-square",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#0d0f10] border border-[#2a2e33] hover:border-gray-500",
+                class: if is_open() || loaded { "border-primary text-primary" } else { "text-gray-400 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "Backtrace Symbols",
+                span { class: "material-symbols-outlined text-[20px]", "bug_report" }
+            }
+
+            if is_open() {
+                BacktracePanel {}
+            }
+        }
+    }
+}
+
+/// Firmware ELF selection, defmt decoding and the frames of the latest backtrace
+#[component]
+fn BacktracePanel() -> Element {
+    let state = use_context::<AppState>();
+    let bridge = use_worker_controller();
+    let elf = (state.log.elf)();
+    let frames = (state.log.backtrace)();
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-[28rem] z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                PanelHeader {
+                    title: "Backtrace",
+                    subtitle: Some(format!("{} frames", frames.len())),
+                }
+
+                div { class: "flex items-center gap-2",
+                    if let Some(info) = elf.as_ref() {
+                        span { class: "material-symbols-outlined text-[16px] text-primary", "memory" }
+                        span { class: "flex-1 truncate text-xs font-mono text-gray-200", title: "{info.name}", "{info.name}" }
+                        if !info.has_debug_info {
+                            span { class: "text-[10px] text-amber-400", title: "Only function names can be resolved", "no DWARF" }
+                        }
+                        IconButton {
+                            icon: "close",
+                            icon_class: "text-[14px]",
+                            class: "w-5 h-5 rounded-full",
+                            onclick: move |_| bridge.unload_elf(),
+                        }
+                    } else {
+                        span { class: "flex-1 text-xs text-gray-600 italic", "Load the firmware ELF to resolve addresses" }
+                    }
+                    label { class: "px-3 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] hover:border-gray-500 text-[10px] uppercase tracking-wider font-bold text-gray-400 hover:text-white cursor-pointer transition-all",
+                        "ELF"
+                        input {
+                            class: "hidden",
+                            r#type: "file",
+                            accept: ".elf,.axf,.out",
+                            onchange: move |evt: FormEvent| async move {
+                                if let Some(file) = evt.files().into_iter().next() {
+                                    match file.read_bytes().await {
+                                        Ok(bytes) => bridge.load_elf(&file.name(), &bytes),
+                                        Err(e) => state.error(&format!("Failed to read ELF: {}", e)),
+                                    }
+                                }
+                            },
+                        }
+                    }
+                }
+
+                if elf.as_ref().is_some_and(|info| info.has_defmt) {
+                    div { class: "flex items-center justify-between",
+                        span { class: "text-[10px] uppercase text-gray-500 font-bold", "Decode defmt frames" }
+                        ToggleSwitch {
+                            label: "",
+                            active: (state.serial.defmt)(),
+                            onclick: move |_| state.serial.toggle_defmt(),
+                        }
+                    }
+                }
+
+                div { class: "flex flex-col max-h-80 overflow-y-auto custom-scrollbar bg-[#0d0f10] rounded border border-[#2a2e33] font-mono text-xs",
+                    if frames.is_empty() {
+                        span { class: "text-gray-600 italic px-3 py-2 font-sans", "No backtrace received yet" }
+                    }
+                    for (i , frame) in frames.into_iter().enumerate() {
+                        FrameRow { key: "{i}", index: i, frame }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn FrameRow(index: usize, frame: SymbolInfo) -> Element {
+    let function = frame.function.clone().unwrap_or_else(|| "??".to_string());
+    let location = frame.location().unwrap_or_default();
+    let path = frame.file.clone().unwrap_or_default();
+
+    rsx! {
+        div { class: "flex items-baseline gap-2 px-3 py-1 border-b border-white/5 last:border-b-0",
+            span { class: "w-6 text-right text-gray-600", "#{index}" }
+            span { class: "text-gray-500", "0x{frame.address:08x}" }
+            span { class: "flex-1 truncate text-cyan-300", title: "{function}", "{function}" }
+            span { class: "text-gray-500 truncate", title: "{path}", "{location}" }
+        }
+    }
+}
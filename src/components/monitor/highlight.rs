@@ -1,6 +1,5 @@
 use crate::components::monitor::utils::style::get_highlight_classes;
 use crate::components::ui::{IconButton, PanelHeader};
-use crate::config::HIGHLIGHT_COLORS;
 use crate::state::AppState;
 use dioxus::prelude::*;
 
@@ -103,16 +102,7 @@ fn HighlightInput() -> Element {
         let text = new_text.read().trim().to_string();
         if !text.is_empty() {
             let state = use_context::<AppState>();
-            let list = state.log.highlights.read().clone();
-
-            let used_colors: std::collections::HashSet<&str> =
-                list.iter().map(|h| h.color).collect();
-            let color = HIGHLIGHT_COLORS
-                .iter()
-                .find(|&&c| !used_colors.contains(c))
-                .copied()
-                .unwrap_or_else(|| HIGHLIGHT_COLORS[list.len() % HIGHLIGHT_COLORS.len()]);
-
+            let color = state.log.next_highlight_color();
             state.log.add_highlight(text, color);
             new_text.set(String::new());
         }
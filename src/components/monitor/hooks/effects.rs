@@ -1,5 +1,6 @@
 use crate::hooks::WorkerController;
 use crate::state::AppState;
+use crate::worker::byte_search::compile_pattern;
 use dioxus::prelude::*;
 
 pub fn use_settings_sync(bridge: WorkerController) {
@@ -9,6 +10,52 @@ pub fn use_settings_sync(bridge: WorkerController) {
         let show = (state.ui.show_timestamps)();
         bridge.set_timestamp_state(show);
     });
+
+    use_effect(move || {
+        let framing = (state.serial.rx_framing)();
+        bridge.set_rx_framing(framing);
+    });
+
+    use_effect(move || {
+        let encoding = (state.serial.encoding)();
+        bridge.set_encoding(encoding);
+    });
+
+    use_effect(move || {
+        let enabled = (state.serial.defmt)();
+        bridge.set_defmt(enabled);
+    });
+
+    use_effect(move || {
+        let layout = (state.ui.hex_layout)();
+        bridge.set_hex_layout(layout);
+    });
+
+    use_effect(move || {
+        let config = (state.log.level_detection)();
+        bridge.set_level_detection(config);
+    });
+
+    use_effect(move || {
+        let mask = (state.log.level_mask)();
+        // Displayed line numbers change with the filter
+        state.log.set_inspect_selection(None);
+        bridge.set_level_filter(mask);
+    });
+
+    use_effect(move || {
+        let filter = (state.log.tag_filter)();
+        state.log.set_inspect_selection(None);
+        bridge.set_tag_filter(filter);
+    });
+
+    use_effect(move || {
+        if let Some((start, end)) = (state.log.inspect_selection)() {
+            if (state.ui.show_inspector)() {
+                bridge.request_raw(start, end);
+            }
+        }
+    });
 }
 
 pub fn use_search_sync(bridge: WorkerController) {
@@ -19,11 +66,25 @@ pub fn use_search_sync(bridge: WorkerController) {
         let match_case = (state.log.match_case)();
         let use_regex = (state.log.use_regex)();
         let invert = (state.log.invert_filter)();
+        let byte_search = (state.log.byte_search)();
 
         async move {
             // Debounce 300ms
             gloo_timers::future::TimeoutFuture::new(300).await;
-            bridge.search(query, match_case, use_regex, invert);
+            // Displayed line numbers change with the filter
+            state.log.set_inspect_selection(None);
+            if byte_search {
+                // Hits are shown in the unfiltered log
+                bridge.search(String::new(), false, false, false);
+                if compile_pattern(&query, use_regex).is_ok() {
+                    bridge.search_bytes(query, use_regex);
+                } else {
+                    state.log.set_byte_hits(Vec::new(), false);
+                }
+            } else {
+                state.log.set_byte_hits(Vec::new(), false);
+                bridge.search(query, match_case, use_regex, invert);
+            }
         }
     });
 }
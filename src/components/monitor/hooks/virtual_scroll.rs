@@ -8,6 +8,9 @@ use crate::utils::calculate_window_size;
 use dioxus::prelude::*;
 use std::rc::Rc;
 
+/// Lines kept visible above a line that is jumped to
+const JUMP_CONTEXT_LINES: usize = 3;
+
 pub struct VirtualScroll {
     pub total_height: f64,
     pub offset_top: f64,
@@ -42,6 +45,29 @@ pub fn use_virtual_scroll() -> VirtualScroll {
     let (total_height, offset_top, scale_factor) =
         calculate_virtual_metrics(total_lines(), start_index(), console_height(), line_height);
 
+    // Jump requests (e.g. byte search hits) scroll a few lines above the target
+    use_effect(move || {
+        let Some(line) = (state.log.scroll_to_line)() else {
+            return;
+        };
+        state.ui.set_autoscroll(false);
+        let line_height = line_height_from_font(*state.ui.font_size.peek());
+        let (_, _, scale) = calculate_virtual_metrics(
+            *total_lines.peek(),
+            *start_index.peek(),
+            *console_height.peek(),
+            line_height,
+        );
+        let top = (line.saturating_sub(JUMP_CONTEXT_LINES) as f64) * line_height / scale;
+        if let Some(el) = web_sys::window()
+            .and_then(|w| w.document())
+            .and_then(|d| d.get_element_by_id("console-output"))
+        {
+            el.set_scroll_top(top as i32);
+        }
+        { state.log.scroll_to_line }.set(None);
+    });
+
     // Height update task
     let _height_task = use_resource(move || {
         let handle = (console_handle)();
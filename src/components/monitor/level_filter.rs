@@ -0,0 +1,52 @@
+use crate::state::{AppState, LogLevel};
+use dioxus::prelude::*;
+
+fn level_color(code: u8) -> &'static str {
+    match code {
+        1 => "text-red-400 border-red-400/40",
+        2 => "text-amber-400 border-amber-400/40",
+        3 => "text-emerald-400 border-emerald-400/40",
+        4 => "text-sky-400 border-sky-400/40",
+        5 => "text-gray-400 border-gray-400/40",
+        _ => "text-gray-500 border-gray-500/40",
+    }
+}
+
+/// Compact count for a chip: 999, 1.2k, 35k, 1.2M
+pub fn short_count(n: usize) -> String {
+    match n {
+        0..=999 => n.to_string(),
+        1_000..=9_999 => format!("{:.1}k", n as f64 / 1e3),
+        10_000..=999_999 => format!("{}k", n / 1_000),
+        _ => format!("{:.1}M", n as f64 / 1e6),
+    }
+}
+
+/// Toggle chips that show or hide lines by detected level, with line counts
+#[component]
+pub fn LevelChips() -> Element {
+    let state = use_context::<AppState>();
+    let mask = (state.log.level_mask)();
+    let counts = (state.log.level_counts)();
+
+    let chips = LogLevel::ALL
+        .iter()
+        .map(|l| (LogLevel::code(Some(*l)), l.label()))
+        .chain([(0, "---")]);
+
+    rsx! {
+        div { class: "flex items-center gap-1 shrink-0",
+            for (code , label) in chips {
+                button {
+                    key: "{code}",
+                    class: "h-7 px-1.5 flex items-center gap-1 rounded-md border text-[10px] font-mono font-bold transition-all {level_color(code)}",
+                    class: if !mask.shows(code) { "opacity-30 border-transparent" },
+                    title: if code == 0 { "Lines without a level" } else { "Show {label} lines" },
+                    onclick: move |_| state.log.toggle_level(code),
+                    "{label}"
+                    span { class: "font-normal text-gray-500", "{short_count(counts[code as usize])}" }
+                }
+            }
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::components::ui::forms::CommandInputGroup;
+use crate::components::ui::forms::{CommandInputGroup, LineEndSelector};
+use crate::components::ui::ToggleSwitch;
+use crate::hooks::macros::use_macro_runner;
+use crate::hooks::serial::switch_baud_rate;
 use crate::state::{AppState, LineEnding};
+use crate::utils::encoding::encode_text;
 use crate::utils::serial;
-use crate::utils::{parse_hex_string, MacroStorage};
+use crate::utils::{macro_script, parse_hex_string, MacroStorage};
 use dioxus::prelude::*;
 
 #[component]
@@ -10,22 +14,28 @@ pub fn MacroBar() -> Element {
     let mut storage = use_signal(MacroStorage::load);
     let mut show_form = use_signal(|| false);
     let bridge = crate::hooks::use_worker_controller();
+    let runner = use_macro_runner();
+    let running = (state.macros.running)();
 
     let mut new_label = use_signal(String::new);
     let mut new_cmd = use_signal(String::new);
     let mut new_hex = use_signal(|| false);
     let mut new_ending = use_signal(|| LineEnding::None);
+    let mut new_baud = use_signal(String::new);
+    let mut new_is_script = use_signal(|| false);
+    let mut new_script = use_signal(String::new);
     let mut editing_id = use_signal(|| None::<u64>);
     let mut context_menu = use_signal(|| None::<(u64, i32, i32)>); // (id, x, y)
 
-    let mut current_macro = use_signal(|| None::<(String, bool, LineEnding)>);
+    let mut current_macro = use_signal(|| None::<(String, bool, LineEnding, Option<u32>)>);
 
     let mut macro_task = use_resource(move || {
         let macro_data = current_macro();
         let port = (state.conn.port).peek().as_ref().cloned();
+        let encoding = *state.serial.encoding.peek();
 
         async move {
-            if let Some((cmd, is_hex, ending)) = macro_data {
+            if let Some((cmd, is_hex, ending, baud_rate)) = macro_data {
                 let mut data = if is_hex {
                     match parse_hex_string(&cmd) {
                         Ok(d) => d,
@@ -37,7 +47,13 @@ pub fn MacroBar() -> Element {
                         }
                     }
                 } else {
-                    cmd.into_bytes()
+                    match encode_text(&cmd, encoding) {
+                        Ok(d) => d,
+                        Err(e) => {
+                            state.error(&e);
+                            return;
+                        }
+                    }
                 };
 
                 match ending {
@@ -49,12 +65,25 @@ pub fn MacroBar() -> Element {
                     }
                     _ => {}
                 }
+                if state.conn.is_tapped() {
+                    state.warning("The port is in use by a transfer");
+                    return;
+                }
                 if let Some(conn_port) = port {
-                    if serial::send_data(&conn_port, &data).await.is_ok()
-                        && (state.serial.tx_local_echo)()
-                    {
-                        let array = js_sys::Uint8Array::from(data.as_slice());
-                        bridge.append_chunk(array, false);
+                    if !data.is_empty() {
+                        if serial::send_data(&conn_port, &data).await.is_err() {
+                            return;
+                        }
+                        if (state.serial.tx_local_echo)() {
+                            bridge.record_tx(data);
+                        }
+                    }
+                    if let Some(baud) = baud_rate {
+                        // Give the device time to drain the command before the line rate changes
+                        gloo_timers::future::TimeoutFuture::new(50).await;
+                        if let Err(msg) = switch_baud_rate(state, bridge, baud).await {
+                            state.error(&msg);
+                        }
                     }
                 }
             }
@@ -69,16 +98,30 @@ pub fn MacroBar() -> Element {
                         let cmd = item.command.clone();
                         let is_hex = item.is_hex;
                         let line_ending = item.line_ending;
+                        let baud_rate = item.baud_rate;
                         let id = item.id;
                         let label = item.label.clone();
-                        let cmd_title = item.command.clone();
+                        let is_script = item.script.is_some();
+                        let is_running = running.as_ref().is_some_and(|r| r.id == id);
+                        let cmd_title = match (&item.script, item.baud_rate) {
+                            (Some(script), _) => format!("Script, {} lines", script.lines().count()),
+                            (None, Some(b)) => format!("{} (then {} baud)", item.command, b),
+                            (None, None) => item.command.clone(),
+                        };
                         rsx! {
                             button {
                                 key: "{id}",
-                                class: "shrink-0 px-3 py-1 bg-[#2a2e33] hover:bg-primary hover:text-white rounded text-xs font-mono transition-colors border border-gray-700 select-none whitespace-nowrap",
+                                class: "shrink-0 px-3 py-1 bg-[#2a2e33] hover:bg-primary hover:text-white rounded text-xs font-mono transition-colors border select-none whitespace-nowrap flex items-center gap-1",
+                                class: if is_running { "border-primary text-primary" } else { "border-gray-700" },
                                 onclick: move |_| {
-                                    current_macro.set(Some((cmd.clone(), is_hex, line_ending)));
-                                    macro_task.restart();
+                                    if is_running {
+                                        runner.stop();
+                                    } else if is_script {
+                                        runner.run(&item);
+                                    } else {
+                                        current_macro.set(Some((cmd.clone(), is_hex, line_ending, baud_rate)));
+                                        macro_task.restart();
+                                    }
                                 },
                                 oncontextmenu: move |evt: MouseEvent| {
                                     evt.prevent_default();
@@ -86,12 +129,36 @@ pub fn MacroBar() -> Element {
                                     context_menu.set(Some((id, coords.x as i32, coords.y as i32)));
                                 },
                                 title: "{cmd_title}",
+                                if is_script {
+                                    span {
+                                        class: "material-symbols-outlined text-[14px]",
+                                        class: if is_running { "animate-pulse" },
+                                        if is_running { "stop" } else { "play_arrow" }
+                                    }
+                                }
                                 "{label}"
                             }
                         }
                     }
                 }
 
+                if let Some(status) = running.as_ref() {
+                    div { class: "shrink-0 flex items-center gap-2 px-2 py-0.5 rounded border border-primary/30 bg-primary/10 text-[11px] text-primary whitespace-nowrap",
+                        span { class: "material-symbols-outlined text-[14px] animate-spin", "progress_activity" }
+                        span { class: "font-bold", "{status.label}" }
+                        span { class: "text-gray-400", "line {status.line}" }
+                        if let Some(pattern) = &status.waiting {
+                            span { class: "text-gray-400 font-mono max-w-48 truncate", "waiting for /{pattern}/" }
+                        }
+                        button {
+                            class: "material-symbols-outlined text-[14px] text-gray-400 hover:text-red-400",
+                            title: "Stop",
+                            onclick: move |_| runner.stop(),
+                            "stop_circle"
+                        }
+                    }
+                }
+
                 // Add Button
                 button {
                     class: "shrink-0 w-6 h-6 flex items-center justify-center bg-[#1a1c1e] text-gray-400 hover:text-white rounded text-xs border border-dashed border-gray-700 hover:border-gray-500 transition-colors",
@@ -101,6 +168,9 @@ pub fn MacroBar() -> Element {
                         new_cmd.set(String::new());
                         new_hex.set(false);
                         new_ending.set(LineEnding::None);
+                        new_baud.set(String::new());
+                        new_is_script.set(false);
+                        new_script.set(String::new());
                         show_form.set(true);
                     },
                     title: "Add Macro",
@@ -128,6 +198,9 @@ pub fn MacroBar() -> Element {
                                     new_cmd.set(item.command);
                                     new_hex.set(item.is_hex);
                                     new_ending.set(item.line_ending);
+                                    new_baud.set(item.baud_rate.map(|b| b.to_string()).unwrap_or_default());
+                                    new_is_script.set(item.script.is_some());
+                                    new_script.set(item.script.unwrap_or_default());
                                     show_form.set(true);
                                 }
                             },
@@ -161,9 +234,18 @@ pub fn MacroBar() -> Element {
             // Form Modal
             if show_form() {
                 div { class: "fixed inset-0 z-50 flex items-center justify-center bg-black/50 backdrop-blur-sm",
-                    div { class: "bg-[#16181a] p-4 rounded-xl border border-[#2a2e33] w-80 shadow-2xl",
-                        h3 { class: "text-sm font-bold text-gray-300 mb-3",
-                            if editing_id().is_some() { "Edit Macro" } else { "Add Quick Command" }
+                    div {
+                        class: "bg-[#16181a] p-4 rounded-xl border border-[#2a2e33] shadow-2xl",
+                        class: if new_is_script() { "w-[28rem]" } else { "w-80" },
+                        div { class: "flex items-center justify-between mb-3",
+                            h3 { class: "text-sm font-bold text-gray-300",
+                                if editing_id().is_some() { "Edit Macro" } else { "Add Quick Command" }
+                            }
+                            ToggleSwitch {
+                                label: "Script",
+                                active: new_is_script(),
+                                onclick: move |_| new_is_script.set(!new_is_script()),
+                            }
                         }
                         div { class: "space-y-3",
                             div {
@@ -178,18 +260,54 @@ pub fn MacroBar() -> Element {
                                     autofocus: true,
                                 }
                             }
-                            div {
-                                label { class: "block text-[10px] uppercase text-gray-500 font-bold mb-1",
-                                    "Command"
+                            if new_is_script() {
+                                div {
+                                    label { class: "block text-[10px] uppercase text-gray-500 font-bold mb-1",
+                                        "Script"
+                                    }
+                                    textarea {
+                                        class: "w-full h-48 bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono resize-none custom-scrollbar",
+                                        placeholder: "send AT+CSQ\nexpect /\\+CSQ: (\\d+)/ 2000\nif $1 < 10\n  log weak signal $1\nend",
+                                        spellcheck: false,
+                                        value: "{new_script}",
+                                        oninput: move |e| new_script.set(e.value()),
+                                    }
+                                    p { class: "text-[10px] text-gray-600 mt-1 leading-relaxed",
+                                        "send, hex, delay, baud, expect /re/ ms, wait /re/ ms, set, if/else/end, loop n/end, while/end, break, stop, fail, log. Captures are $1, $2..."
+                                    }
                                 }
-                                CommandInputGroup {
-                                    value: new_cmd,
-                                    is_hex: new_hex,
-                                    line_ending: new_ending,
-                                    echo: None,
-                                    placeholder: "e.g. AT+RST",
-                                    on_submit: None,
-                                    id: None,
+                                LineEndSelector {
+                                    label: "Send ending",
+                                    selected: new_ending(),
+                                    onselect: move |ending| new_ending.set(ending),
+                                    active_class: "bg-primary/10 text-primary border-primary/30",
+                                    is_rx: false,
+                                }
+                            } else {
+                                div {
+                                    label { class: "block text-[10px] uppercase text-gray-500 font-bold mb-1",
+                                        "Command"
+                                    }
+                                    CommandInputGroup {
+                                        value: new_cmd,
+                                        is_hex: new_hex,
+                                        line_ending: new_ending,
+                                        echo: None,
+                                        placeholder: "e.g. AT+RST",
+                                        on_submit: None,
+                                        id: None,
+                                    }
+                                }
+                                div {
+                                    label { class: "block text-[10px] uppercase text-gray-500 font-bold mb-1",
+                                        "Then Switch Baud (Optional)"
+                                    }
+                                    input {
+                                        class: "w-full bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                                        placeholder: "e.g. 921600",
+                                        value: "{new_baud}",
+                                        oninput: move |e| new_baud.set(e.value()),
+                                    }
                                 }
                             }
                         }
@@ -214,8 +332,31 @@ pub fn MacroBar() -> Element {
                             button {
                                 class: "px-3 py-1.5 text-xs bg-primary text-white rounded hover:bg-primary-hover shadow-lg shadow-primary/20 transition-all active:scale-95",
                                 onclick: move |_| {
-                                    if !new_label().is_empty() && !new_cmd().is_empty() {
-                                        if new_hex() {
+                                    let script = if new_is_script() {
+                                        if new_label().is_empty() || new_script().trim().is_empty() {
+                                            state.error("Please fill in all fields");
+                                            return;
+                                        }
+                                        if let Err(e) = macro_script::parse(&new_script()) {
+                                            state.error(&format!("Script Error: {}", e));
+                                            return;
+                                        }
+                                        Some(new_script())
+                                    } else {
+                                        None
+                                    };
+                                    let baud_text = if script.is_some() { String::new() } else { new_baud().trim().to_string() };
+                                    let baud_rate = if baud_text.is_empty() {
+                                        None
+                                    } else if let Ok(b) = baud_text.parse::<u32>() {
+                                        Some(b)
+                                    } else {
+                                        state.error("Invalid baud rate");
+                                        return;
+                                    };
+                                    if !new_label().is_empty() && (script.is_some() || !new_cmd().is_empty() || baud_rate.is_some()) {
+                                        let command = if script.is_some() { String::new() } else { new_cmd() };
+                                        if new_hex() && script.is_none() {
                                             if let Err(e) = parse_hex_string(&new_cmd()) {
                                                 state.error(&format!("Macro Hex Error: {}", e));
                                                 return;
@@ -223,10 +364,10 @@ pub fn MacroBar() -> Element {
                                         }
 
                                         if let Some(id) = editing_id() {
-                                            storage.write().update(id, new_label(), new_cmd(), new_hex(), new_ending());
+                                            storage.write().update(id, new_label(), command, new_hex() && script.is_none(), new_ending(), baud_rate, script);
                                             state.success("Macro Updated");
                                         } else {
-                                            storage.write().add(new_label(), new_cmd(), new_hex(), new_ending());
+                                            storage.write().add(new_label(), command, new_hex() && script.is_none(), new_ending(), baud_rate, script);
                                             state.success("Macro Added");
                                         }
 
@@ -234,6 +375,9 @@ pub fn MacroBar() -> Element {
                                         new_cmd.set(String::new());
                                         new_hex.set(false);
                                         new_ending.set(LineEnding::None);
+                                        new_baud.set(String::new());
+                                        new_is_script.set(false);
+                                        new_script.set(String::new());
                                         show_form.set(false);
                                     } else {
                                         state.error("Please fill in all fields");
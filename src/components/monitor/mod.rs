@@ -1,18 +1,29 @@
+pub mod at_panel;
+pub mod backtrace_panel;
 pub mod highlight;
 pub mod hooks;
+pub mod level_filter;
 pub mod macro_bar;
 pub mod monitor_header;
 pub mod monitor_log_line;
 pub mod monitor_toolbar;
 pub mod monitor_view;
 pub mod monitor_viewport;
+pub mod raw_inspector;
 pub mod search_bar;
+pub mod send_file_panel;
+pub mod tag_panel;
 pub mod transmit_bar;
 pub mod utils;
 
+pub use at_panel::AtButton;
+pub use backtrace_panel::BacktraceButton;
 pub use highlight::HighlightButton;
+pub use level_filter::LevelChips;
 pub use macro_bar::MacroBar;
 pub use monitor_toolbar::MonitorToolbar;
 pub use monitor_view::Monitor;
 pub use search_bar::SearchBar;
+pub use send_file_panel::SendFileButton;
+pub use tag_panel::TagButton;
 pub use transmit_bar::TransmitBar;
@@ -1,7 +1,7 @@
 use crate::components::ui::console::{
     ConsoleSeparator, ConsoleToggleButton, UnifiedConsoleToolbar,
 };
-use crate::state::AppState;
+use crate::state::{AppState, HexLayout};
 use dioxus::prelude::*;
 
 #[component]
@@ -13,11 +13,20 @@ pub fn MonitorHeader(
     ontoggle_autoscroll: EventHandler<MouseEvent>,
 ) -> Element {
     let state = use_context::<AppState>();
+    let invalid = (state.log.invalid_sequences)();
+    let encoding = (state.serial.encoding)();
 
     rsx! {
         UnifiedConsoleToolbar {
             left: rsx! {
                 span { class: "text-[10px] text-gray-500 font-mono hidden sm:block", "[ LINES: {count} / OPFS ENABLED ]" }
+                if invalid > 0 {
+                    span {
+                        class: "text-[10px] text-red-400 font-mono whitespace-nowrap",
+                        title: "Malformed sequences for {encoding.label()} this session",
+                        "{invalid} INVALID"
+                    }
+                }
                 ConsoleSeparator {}
                 div { class: "flex items-center gap-1",
                     ConsoleToggleButton {
@@ -32,6 +41,28 @@ pub fn MonitorHeader(
                         active: (state.ui.is_hex_view)(),
                         onclick: move |_| state.ui.toggle_hex_view(),
                     }
+                    if (state.ui.is_hex_view)() {
+                        HexLayoutControls {}
+                    } else {
+                        ConsoleToggleButton {
+                            icon: "format_paragraph",
+                            title: "Show Control Characters",
+                            active: (state.ui.show_control_chars)(),
+                            onclick: move |_| state.ui.toggle_control_chars(),
+                        }
+                    }
+                    ConsoleToggleButton {
+                        icon: "wrap_text",
+                        title: "Wrap Long Lines",
+                        active: (state.ui.wrap_lines)(),
+                        onclick: move |_| state.ui.toggle_wrap_lines(),
+                    }
+                    ConsoleToggleButton {
+                        icon: "memory",
+                        title: "Inspect Raw Bytes",
+                        active: (state.ui.show_inspector)(),
+                        onclick: move |_| state.ui.toggle_inspector(),
+                    }
                 }
             },
             font_size: state.ui.font_size,
@@ -45,3 +76,45 @@ pub fn MonitorHeader(
         }
     }
 }
+
+/// Next value in `options` after `current`, wrapping around
+fn next_option(options: &[usize], current: usize) -> usize {
+    let pos = options.iter().position(|&o| o == current).unwrap_or(0);
+    options[(pos + 1) % options.len()]
+}
+
+#[component]
+fn HexLayoutControls() -> Element {
+    let state = use_context::<AppState>();
+    let layout = (state.ui.hex_layout)();
+    let group_label = if layout.group_size > 1 {
+        format!("G{}", layout.group_size)
+    } else {
+        "G-".to_string()
+    };
+
+    rsx! {
+        button {
+            class: "px-1 h-5 rounded text-[10px] font-mono text-gray-500 hover:text-gray-300 hover:bg-white/10 transition-colors",
+            title: "Bytes per row",
+            onclick: move |_| {
+                state.ui.set_hex_layout(HexLayout {
+                    bytes_per_row: next_option(&HexLayout::ROW_WIDTHS, layout.bytes_per_row),
+                    ..layout
+                });
+            },
+            "{layout.bytes_per_row}B"
+        }
+        button {
+            class: "px-1 h-5 rounded text-[10px] font-mono text-gray-500 hover:text-gray-300 hover:bg-white/10 transition-colors",
+            title: "Byte grouping",
+            onclick: move |_| {
+                state.ui.set_hex_layout(HexLayout {
+                    group_size: next_option(&HexLayout::GROUP_SIZES, layout.group_size),
+                    ..layout
+                });
+            },
+            "{group_label}"
+        }
+    }
+}
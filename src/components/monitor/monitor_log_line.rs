@@ -1,25 +1,222 @@
-use crate::config::line_height_from_font;
-use crate::state::{AppState, Highlight};
+use crate::config::{line_height_from_font, CONTINUATION_MARKER, OVERWRITTEN_MARKER};
+use crate::state::{AppState, ByteToken, Highlight, LineAttrs, SymbolInfo, TokenKind};
 use crate::utils::decode_ansi_text;
+use crate::utils::hexdump::{byte_class, gutter_char, parse_row, ByteClass, CHUNK_BOUNDARY};
 use dioxus::prelude::*;
+use regex::Regex;
 
+fn byte_class_style(b: u8) -> &'static str {
+    match byte_class(b) {
+        ByteClass::Control => "text-amber-400",
+        ByteClass::Printable => "text-gray-300",
+        ByteClass::High => "text-fuchsia-400",
+    }
+}
+
+/// Spaces standing in for the missing bytes of a partial hex row
+fn hex_row_padding(filled: usize, bytes_per_row: usize, group_size: usize) -> String {
+    (filled..bytes_per_row)
+        .map(|i| {
+            if group_size > 1 && i.is_multiple_of(group_size) {
+                "    "
+            } else {
+                "   "
+            }
+        })
+        .collect()
+}
+
+/// Splits `text` after each code address that resolved to a function
+fn split_at_symbols<'a>(
+    text: &'a str,
+    symbols: &'a [SymbolInfo],
+) -> Vec<(&'a str, Option<&'a SymbolInfo>)> {
+    thread_local! {
+        static ADDRESS_RE: Regex = Regex::new(r"0x([0-9a-fA-F]+)").unwrap();
+    }
+    if symbols.is_empty() {
+        return vec![(text, None)];
+    }
+    let mut pieces = Vec::new();
+    let mut last = 0;
+    ADDRESS_RE.with(|re| {
+        for caps in re.captures_iter(text) {
+            let Ok(address) = u64::from_str_radix(&caps[1], 16) else {
+                continue;
+            };
+            let end = caps.get(0).map_or(last, |m| m.end());
+            if let Some(symbol) = symbols
+                .iter()
+                .find(|s| s.address == address && s.function.is_some())
+            {
+                pieces.push((&text[last..end], Some(symbol)));
+                last = end;
+            }
+        }
+    });
+    pieces.push((&text[last..], None));
+    pieces
+}
+
+#[component]
+fn SymbolTag(symbol: SymbolInfo) -> Element {
+    let function = symbol.function.clone().unwrap_or_default();
+    let location = symbol
+        .location()
+        .map(|l| format!(" {}", l))
+        .unwrap_or_default();
+    let path = symbol.file.clone().unwrap_or_default();
+
+    rsx! {
+        span {
+            class: "text-cyan-400/80 select-none",
+            title: "{path}",
+            " ‹{function}{location}›"
+        }
+    }
+}
+
+/// Received bytes shown in place of text, styled by kind
 #[component]
-pub fn MonitorLogLine(text: String, highlights: Vec<Highlight>, show_highlights: bool) -> Element {
+pub fn ByteTokenTag(token: ByteToken) -> Element {
+    let (class, title) = match token.kind {
+        TokenKind::Invalid => (
+            "text-red-400 bg-red-900/30 rounded-sm",
+            "Invalid byte for the selected encoding",
+        ),
+        TokenKind::Control | TokenKind::Content => (
+            "text-amber-400 bg-amber-900/30 rounded-sm",
+            "Control character",
+        ),
+    };
+    rsx! {
+        span { class, title, "{token.label()}" }
+    }
+}
+
+#[component]
+pub fn MonitorLogLine(
+    text: String,
+    attrs: LineAttrs,
+    highlights: Vec<Highlight>,
+    show_highlights: bool,
+    /// Displayed line number; None for the line still being received
+    line_idx: Option<usize>,
+    selected: bool,
+    /// Long lines wrap instead of overflowing the row
+    wrap: bool,
+) -> Element {
     let state = use_context::<AppState>();
     let font_size = *state.ui.font_size.read();
     let line_height = line_height_from_font(font_size);
-    let segments = decode_ansi_text(&text, &highlights, show_highlights);
+    let show_controls = (state.ui.show_control_chars)();
+    let continued = attrs.continued;
+    // States a bare CR overwrote can be expanded under the line
+    let mut show_history = use_signal(|| false);
+    let history = attrs.history;
+    let expanded = show_history() && !history.is_empty();
+    let text = text.as_str();
+    let hex_row = parse_row(text);
+    let segments = if hex_row.is_some() {
+        Vec::new()
+    } else {
+        decode_ansi_text(
+            text,
+            &attrs.tokens,
+            &highlights,
+            show_highlights,
+            show_controls,
+        )
+    };
+    let layout = *state.ui.hex_layout.peek();
+    let symbols = state.log.symbols.read();
 
     rsx! {
         div {
-            style: "height: {line_height}px; line-height: {line_height}px;",
-            class: "text-gray-300 whitespace-pre font-mono",
+            style: if wrap || expanded { "min-height: {line_height}px;" } else { "height: {line_height}px;" },
+            style: "line-height: {line_height}px;",
+            class: "text-gray-300 font-mono",
+            class: if wrap { "whitespace-pre-wrap break-all" } else { "whitespace-pre" },
+            class: if selected { "bg-primary/15" },
             style: "font-size: {font_size}px;",
-            for (content , color) in segments {
-                if let Some(c) = color {
-                    span { class: "font-bold", style: "color: {c};", "{content}" }
+            onclick: move |evt: MouseEvent| {
+                if let Some(idx) = line_idx {
+                    if (state.ui.show_inspector)() {
+                        state.log.select_line(idx, evt.modifiers().contains(Modifiers::SHIFT));
+                    }
+                }
+            },
+            if let Some(row) = hex_row {
+                span { class: "text-gray-500", "{row.prefix}" }
+                span { class: "text-gray-600", "{row.offset}" }
+                for cell in row.cells.iter() {
+                    if cell.group_start {
+                        " "
+                    }
+                    if cell.boundary {
+                        span { class: "text-sky-700", title: "Chunk boundary", "{CHUNK_BOUNDARY}" }
+                    } else {
+                        " "
+                    }
+                    span { class: byte_class_style(cell.byte), "{cell.byte:02X}" }
+                }
+                "{hex_row_padding(row.cells.len(), layout.bytes_per_row, layout.group_size)}  "
+                span { class: "text-gray-600", "|" }
+                for cell in row.cells.iter() {
+                    span { class: byte_class_style(cell.byte), "{gutter_char(cell.byte)}" }
+                }
+                span { class: "text-gray-600", "|" }
+            }
+            for seg in segments {
+                if let Some(token) = seg.token.clone() {
+                    ByteTokenTag { token }
                 } else {
-                    "{content}"
+                    {
+                        let css = seg.css();
+                        rsx! {
+                            for (part , symbol) in split_at_symbols(&seg.text, &symbols) {
+                                if css.is_empty() {
+                                    "{part}"
+                                } else {
+                                    span { style: "{css}", "{part}" }
+                                }
+                                if let Some(symbol) = symbol {
+                                    SymbolTag { symbol: symbol.clone() }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            if continued {
+                span {
+                    class: "text-gray-600 select-none",
+                    title: "Continues on the next line",
+                    "{CONTINUATION_MARKER}"
+                }
+            }
+            if !history.is_empty() {
+                span {
+                    class: "ml-2 px-1 rounded-sm text-gray-500 hover:text-gray-300 hover:bg-white/10 cursor-pointer select-none",
+                    title: "States overwritten by a carriage return",
+                    onclick: move |evt: MouseEvent| {
+                        evt.stop_propagation();
+                        show_history.toggle();
+                    },
+                    "{OVERWRITTEN_MARKER} {history.len()}"
+                }
+            }
+            if expanded {
+                for (i , (state , tokens)) in history.iter().enumerate() {
+                    div { key: "{i}", class: "opacity-40 pl-4",
+                        for seg in decode_ansi_text(state, tokens, &[], false, show_controls) {
+                            if let Some(token) = seg.token {
+                                ByteTokenTag { token }
+                            } else {
+                                span { style: "{seg.css()}", "{seg.text}" }
+                            }
+                        }
+                    }
                 }
             }
         }
@@ -1,20 +1,32 @@
-use crate::components::monitor::{HighlightButton, SearchBar, TransmitBar};
+use crate::components::monitor::{
+    AtButton, BacktraceButton, HighlightButton, LevelChips, SearchBar, SendFileButton, TagButton,
+    TransmitBar,
+};
 use dioxus::prelude::*;
 
 #[component]
 pub fn MonitorToolbar() -> Element {
     let highlight_open = use_signal(|| false);
+    let tag_open = use_signal(|| false);
+    let backtrace_open = use_signal(|| false);
+    let send_file_open = use_signal(|| false);
+    let at_open = use_signal(|| false);
 
     rsx! {
         div {
             class: "shrink-0 p-2 bg-background-dark relative",
-            class: if highlight_open() { "z-60" } else { "z-40" },
+            class: if highlight_open() || tag_open() || backtrace_open() || send_file_open() || at_open() { "z-60" } else { "z-40" },
             div { class: "flex gap-2 h-10 items-stretch min-w-[600px]",
                 HighlightButton { is_open: highlight_open }
                 SearchBar {}
+                LevelChips {}
+                TagButton { is_open: tag_open }
+                BacktraceButton { is_open: backtrace_open }
                 // --- Divider ---
                 div { class: "w-px bg-[#2a2e33] my-2 mx-1" }
                 TransmitBar {}
+                SendFileButton { is_open: send_file_open }
+                AtButton { is_open: at_open }
             }
         }
     }
@@ -1,6 +1,7 @@
 use crate::components::monitor::hooks::effects::{use_search_sync, use_settings_sync};
 use crate::components::monitor::monitor_header::MonitorHeader;
 use crate::components::monitor::monitor_viewport::MonitorViewport;
+use crate::components::monitor::raw_inspector::RawInspector;
 use crate::components::ui::buttons::ResumeScrollButton;
 use crate::components::ui::console::ConsoleFrame;
 use crate::hooks::use_worker_controller;
@@ -45,6 +46,10 @@ pub fn Monitor() -> Element {
                 onmounted_sentinel: move |evt: MountedEvent| vs.sentinel_handle.set(Some(evt.data())),
             }
 
+            if (state.ui.show_inspector)() {
+                RawInspector {}
+            }
+
             if !(state.ui.autoscroll)() {
                 ResumeScrollButton {
                     onclick: move |_| {
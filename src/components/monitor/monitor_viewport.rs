@@ -3,7 +3,6 @@ use crate::config::{CONSOLE_BOTTOM_PADDING, CONSOLE_TOP_PADDING};
 use crate::state::{AppState, LineEnding};
 use crate::utils::serial;
 use dioxus::prelude::*;
-use js_sys::Uint8Array;
 
 #[component]
 pub fn MonitorViewport(
@@ -17,6 +16,20 @@ pub fn MonitorViewport(
     let bridge = crate::hooks::use_worker_controller();
     let visible_logs = state.log.visible_logs;
     let total_lines = state.log.total_lines;
+    let wrap = (state.ui.wrap_lines)();
+    let is_at_bottom = visible_logs
+        .read()
+        .last()
+        .map(|(idx, _, _)| *idx + 1 == total_lines())
+        .unwrap_or(total_lines() == 0);
+    // Wrapped rows can be taller than a line; at the bottom the content is
+    // anchored to the end so the newest lines stay in view
+    let content_top = if wrap && is_at_bottom {
+        format!("max({offset_top}px, calc({total_height}px - 100%))")
+    } else {
+        format!("{offset_top}px")
+    };
+    let content_width = if wrap { "100%" } else { "max-content" };
 
     rsx! {
         div {
@@ -48,13 +61,15 @@ pub fn MonitorViewport(
                     Key::Escape => vec![0x1B],
                     _ => return,
                 };
+                if state.conn.is_tapped() {
+                    return;
+                }
                 let port = state.conn.port.peek().as_ref().cloned();
                 let local_echo = *state.serial.tx_local_echo.peek();
                 spawn(async move {
                     if let Some(p) = port {
                         if serial::send_data(&p, &data).await.is_ok() && local_echo {
-                            let array = Uint8Array::from(data.as_slice());
-                            bridge.append_chunk(array, false);
+                            bridge.record_tx(data);
                         }
                     }
                 });
@@ -64,33 +79,37 @@ pub fn MonitorViewport(
 
             // Virtual Scroll Spacer & Content
             div { style: "height: {total_height}px; width: 100%; position: absolute; top: 0; left: 0; pointer-events: none;" }
-            div { style: "position: absolute; top: 0; left: 0; right: 0; transform: translateY({offset_top}px); padding: {CONSOLE_TOP_PADDING}px 0 {CONSOLE_BOTTOM_PADDING}px 0; pointer-events: auto; min-width: 100%; width: max-content;",
+            div { style: "position: absolute; top: 0; left: 0; right: 0; transform: translateY({content_top}); padding: {CONSOLE_TOP_PADDING}px 0 {CONSOLE_BOTTOM_PADDING}px 0; pointer-events: auto; min-width: 100%; width: {content_width};",
                 {
                     let highlights = (state.log.highlights)().clone();
                     let show_highlights = (state.ui.show_highlights)();
                     let active_line = (state.log.active_line)();
+                    let selection = (state.log.inspect_selection)();
                     let logs = visible_logs.read();
-                    let is_at_bottom = logs
-
-                        .last()
-                        .map(|(idx, _)| *idx + 1 == total_lines())
-                        .unwrap_or(total_lines() == 0);
                     rsx! {
-                        for (line_idx , text) in logs.iter() {
+                        for (line_idx , text , attrs) in logs.iter() {
                             MonitorLogLine {
                                 key: "{line_idx}",
+                                line_idx: Some(*line_idx),
+                                selected: selection.is_some_and(|(s, e)| (s..e).contains(line_idx)),
                                 text: text.clone(),
+                                attrs: attrs.clone(),
                                 highlights: highlights.clone(),
                                 show_highlights,
+                                wrap,
                             }
                         }
                         if is_at_bottom {
-                            if let Some(text) = active_line {
+                            if let Some((text, attrs)) = active_line {
                                 MonitorLogLine {
                                     key: "{0}",
-                                    text: text.clone(),
+                                    line_idx: None,
+                                    selected: false,
+                                    text,
+                                    attrs,
                                     highlights: highlights.clone(),
                                     show_highlights: false,
+                                    wrap,
                                 }
                             }
                         }
@@ -0,0 +1,160 @@
+use crate::components::monitor::monitor_log_line::ByteTokenTag;
+use crate::hooks::use_worker_controller;
+use crate::state::{AppState, ByteToken, HexLayout, RawCapture, TextEncoding, TokenKind};
+use crate::utils::ansi_decoder::strip_ansi_bytes;
+use crate::utils::encoding::{mark_control_bytes, take_tokens, StreamDecoder};
+use crate::utils::hexdump::format_row;
+use dioxus::prelude::*;
+
+const TABS: [&str; 3] = ["Hex", "Text", "ANSI-stripped"];
+/// Hexdump rows for the captured bytes, in the hex view layout
+fn hex_rows(bytes: &[u8], base_offset: u64, layout: HexLayout) -> Vec<String> {
+    bytes
+        .chunks(layout.bytes_per_row)
+        .enumerate()
+        .map(|(i, row)| {
+            let offset = base_offset + (i * layout.bytes_per_row) as u64;
+            format_row(offset, row, &[], layout)
+        })
+        .collect()
+}
+
+/// Decodes the bytes on their own, returning the text, its byte tokens and
+/// the number of undecodable bytes
+fn decode(
+    bytes: &[u8],
+    encoding: TextEncoding,
+    show_control: bool,
+) -> (String, Vec<ByteToken>, u64) {
+    let mut decoder = StreamDecoder::new(encoding);
+    let text = decoder.decode(bytes, true);
+    let (text, tokens) = if show_control {
+        take_tokens(&String::from_utf8_lossy(&mark_control_bytes(
+            text.as_bytes(),
+            TokenKind::Control,
+        )))
+    } else {
+        take_tokens(&text)
+    };
+    (text, tokens, decoder.invalid_count())
+}
+
+#[component]
+fn DecodedText(text: String, tokens: Vec<ByteToken>) -> Element {
+    let mut pieces = Vec::new();
+    let mut pos = 0;
+    for token in tokens {
+        pieces.push((text[pos..token.at].to_string(), Some(token.clone())));
+        pos = token.at;
+    }
+    pieces.push((text[pos..].to_string(), None));
+    rsx! {
+        for (part , token) in pieces {
+            "{part}"
+            if let Some(token) = token {
+                ByteTokenTag { token }
+            }
+        }
+    }
+}
+
+#[component]
+pub fn RawInspector() -> Element {
+    let state = use_context::<AppState>();
+    let bridge = use_worker_controller();
+    let mut tab = use_signal(|| TABS[0]);
+
+    let selection = (state.log.inspect_selection)();
+    let capture = (state.log.raw_capture)();
+    let encoding = (state.serial.encoding)();
+    let layout = (state.ui.hex_layout)();
+    let show_control = (state.ui.show_control_chars)();
+
+    let summary = match (&selection, &capture) {
+        (None, _) => "Click a line to inspect its raw bytes (Shift+Click extends)".to_string(),
+        (Some(_), None) => "Loading...".to_string(),
+        (Some(_), Some(c)) => {
+            let lines = if c.end_line - c.start_line > 1 {
+                format!("Lines {}-{}", c.start_line + 1, c.end_line)
+            } else {
+                format!("Line {}", c.start_line + 1)
+            };
+            format!(
+                "{} · offset 0x{:X} · {} bytes",
+                lines, c.offset, c.total_len
+            )
+        }
+    };
+
+    let mut invalid = 0;
+    let body = capture.as_ref().map(|c: &RawCapture| match tab() {
+        "Hex" => rsx! {
+            for row in hex_rows(&c.bytes, c.offset, layout) {
+                div { "{row}" }
+            }
+        },
+        name => {
+            let (text, tokens, count) = if name == "Text" {
+                decode(&c.bytes, encoding, show_control)
+            } else {
+                decode(&strip_ansi_bytes(&c.bytes), encoding, show_control)
+            };
+            invalid = count;
+            rsx! {
+                DecodedText { text, tokens }
+            }
+        }
+    });
+    let truncated = capture
+        .as_ref()
+        .is_some_and(|c| (c.bytes.len() as u64) < c.total_len);
+
+    rsx! {
+        div { class: "shrink-0 h-[35%] min-h-[120px] border-t border-[#222629] bg-[#0d0f10] flex flex-col z-0 relative",
+            div { class: "shrink-0 h-6 bg-[#16181a] border-b border-[#222629] flex items-center gap-3 px-3",
+                span { class: "text-[10px] font-bold text-gray-500 uppercase tracking-widest", "Raw" }
+                div { class: "flex items-center gap-1",
+                    for name in TABS {
+                        button {
+                            class: "px-2 text-[10px] rounded transition-colors",
+                            class: if tab() == name { "text-primary bg-primary/10" } else { "text-gray-500 hover:text-gray-300" },
+                            onclick: move |_| tab.set(name),
+                            "{name}"
+                        }
+                    }
+                }
+                span { class: "text-[10px] text-gray-500 font-mono truncate", "{summary}" }
+                if invalid > 0 {
+                    span { class: "text-[10px] text-red-400 font-mono whitespace-nowrap",
+                        "{invalid} invalid for {encoding.label()}"
+                    }
+                }
+                div { class: "ml-auto flex items-center gap-1",
+                    button {
+                        class: "flex items-center gap-1 px-2 text-[10px] text-gray-500 hover:text-gray-300 hover:bg-white/10 rounded transition-colors",
+                        title: if selection.is_some() { "Export the raw bytes received with the selected lines" } else { "Export the whole raw capture of this session" },
+                        onclick: move |_| bridge.export_raw(selection),
+                        span { class: "material-symbols-outlined text-[14px]", "download" }
+                        if selection.is_some() {
+                            "Export selection .bin"
+                        } else {
+                            "Export .bin"
+                        }
+                    }
+                    button {
+                        class: "flex items-center justify-center w-5 h-5 rounded text-gray-500 hover:text-gray-300 hover:bg-white/10 transition-colors",
+                        title: "Close",
+                        onclick: move |_| state.ui.toggle_inspector(),
+                        span { class: "material-symbols-outlined text-[14px]", "close" }
+                    }
+                }
+            }
+            div { class: "flex-1 overflow-auto scrollbar-custom px-3 py-2 font-mono text-xs text-gray-300 whitespace-pre",
+                {body}
+                if truncated {
+                    div { class: "text-gray-600 pt-1", "Showing the first {capture.as_ref().map_or(0, |c| c.bytes.len())} bytes; export .bin for the full capture" }
+                }
+            }
+        }
+    }
+}
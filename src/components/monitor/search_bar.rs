@@ -1,10 +1,63 @@
 use crate::components::ui::FilterOptionButton;
 use crate::state::AppState;
+use crate::worker::byte_search::compile_pattern;
 use dioxus::prelude::*;
 
+#[component]
+fn ByteHitNav() -> Element {
+    let state = use_context::<AppState>();
+    let hits = state.log.byte_hits.read();
+    let count = hits.len();
+    let index = (state.log.byte_hit_index)();
+    let more = if (state.log.byte_hits_truncated)() {
+        "+"
+    } else {
+        ""
+    };
+
+    let label = match hits.get(index) {
+        Some(hit) => format!("{}/{}{} @0x{:X}", index + 1, count, more, hit.offset),
+        None => "0/0".to_string(),
+    };
+    let hidden = hits.get(index).is_some_and(|h| h.line.is_none());
+
+    rsx! {
+        span {
+            class: "text-[10px] font-mono whitespace-nowrap px-1",
+            class: if hidden { "text-gray-600" } else { "text-gray-400" },
+            title: if hidden { "This hit's line is no longer in the log" } else { "" },
+            "{label}"
+        }
+        button {
+            class: "w-6 h-7 flex items-center justify-center rounded-md text-gray-500 hover:text-white hover:bg-[#2a2e33] transition-all disabled:opacity-30",
+            title: "Previous hit",
+            disabled: count == 0,
+            onclick: move |_| state.log.jump_to_byte_hit((index + count - 1) % count),
+            span { class: "material-symbols-outlined text-[16px]", "keyboard_arrow_up" }
+        }
+        button {
+            class: "w-6 h-7 flex items-center justify-center rounded-md text-gray-500 hover:text-white hover:bg-[#2a2e33] transition-all disabled:opacity-30",
+            title: "Next hit",
+            disabled: count == 0,
+            onclick: move |_| state.log.jump_to_byte_hit((index + 1) % count),
+            span { class: "material-symbols-outlined text-[16px]", "keyboard_arrow_down" }
+        }
+    }
+}
+
 #[component]
 pub fn SearchBar() -> Element {
     let mut state = use_context::<AppState>();
+    let byte_search = (state.log.byte_search)();
+    let query = (state.log.filter_query)();
+    let pattern_error = (byte_search && !query.trim().is_empty())
+        .then(|| compile_pattern(&query, (state.log.use_regex)()).err())
+        .flatten();
+    let regex_title = if byte_search {
+        "Regex (regex::bytes syntax)"
+    } else {
+        "Regex"
+    };
 
     rsx! {
         div { class: "flex-[0.7] relative group flex items-center min-w-0",
@@ -12,24 +65,31 @@ pub fn SearchBar() -> Element {
                 "search"
             }
             input {
-                class: "w-full h-full bg-[#0d0f10] text-xs font-medium text-white placeholder-gray-600 pl-9 pr-24 rounded-lg border border-[#2a2e33] focus:border-primary/50 focus:shadow-glow outline-none shadow-inset-input transition-all",
-                placeholder: "Filter logs...",
+                class: "w-full h-full bg-[#0d0f10] text-xs font-medium text-white placeholder-gray-600 pl-9 rounded-lg border focus:shadow-glow outline-none shadow-inset-input transition-all",
+                class: if byte_search { "pr-64 font-mono" } else { "pr-24" },
+                class: if pattern_error.is_some() { "border-red-500/60" } else { "border-[#2a2e33] focus:border-primary/50" },
+                placeholder: if byte_search { "Byte pattern, e.g. AA 55 ?? 01" } else { "Filter logs..." },
+                title: pattern_error.unwrap_or_default(),
                 "type": "text",
-                value: "{state.log.filter_query}",
+                value: "{query}",
                 oninput: move |evt| state.log.filter_query.set(evt.value()),
             }
             div { class: "absolute right-1 flex items-center gap-0.5",
-                FilterOptionButton {
-                    title: "Match Case",
-                    label: "Aa",
-                    active: (state.log.match_case)(),
-                    onclick: move |_| {
-                        let v = (state.log.match_case)();
-                        state.log.match_case.set(!v);
-                    },
+                if byte_search {
+                    ByteHitNav {}
+                } else {
+                    FilterOptionButton {
+                        title: "Match Case",
+                        label: "Aa",
+                        active: (state.log.match_case)(),
+                        onclick: move |_| {
+                            let v = (state.log.match_case)();
+                            state.log.match_case.set(!v);
+                        },
+                    }
                 }
                 FilterOptionButton {
-                    title: "Regex",
+                    title: regex_title,
                     label: ".*",
                     active: (state.log.use_regex)(),
                     onclick: move |_| {
@@ -37,14 +97,22 @@ pub fn SearchBar() -> Element {
                         state.log.use_regex.set(!v);
                     },
                 }
+                if !byte_search {
+                    FilterOptionButton {
+                        title: "Invert",
+                        label: "!",
+                        active: (state.log.invert_filter)(),
+                        onclick: move |_| {
+                            let v = (state.log.invert_filter)();
+                            state.log.invert_filter.set(!v);
+                        },
+                    }
+                }
                 FilterOptionButton {
-                    title: "Invert",
-                    label: "!",
-                    active: (state.log.invert_filter)(),
-                    onclick: move |_| {
-                        let v = (state.log.invert_filter)();
-                        state.log.invert_filter.set(!v);
-                    },
+                    title: "Search raw bytes",
+                    label: "0x",
+                    active: byte_search,
+                    onclick: move |_| state.log.byte_search.set(!byte_search),
                 }
             }
         }
@@ -0,0 +1,191 @@
+use crate::components::connection::TransferProgress;
+use crate::components::ui::PanelHeader;
+use crate::hooks::transfer::use_transfer_controller;
+use crate::state::{AppState, LineEnding};
+use crate::transfer::pacing::Pacing;
+use dioxus::prelude::*;
+use regex::Regex;
+
+/// Pacing settings as entered; kept while the panel is closed
+#[derive(Clone, PartialEq)]
+struct PacingForm {
+    byte_delay_ms: u32,
+    line_delay_ms: u32,
+    prompt: String,
+    prompt_timeout_ms: u32,
+}
+
+impl Default for PacingForm {
+    fn default() -> Self {
+        Self {
+            byte_delay_ms: 0,
+            line_delay_ms: 50,
+            prompt: String::new(),
+            prompt_timeout_ms: 5000,
+        }
+    }
+}
+
+impl PacingForm {
+    fn pacing(&self) -> Result<Pacing, String> {
+        let prompt = if self.prompt.is_empty() {
+            None
+        } else {
+            Some(Regex::new(&self.prompt).map_err(|e| format!("Invalid prompt: {}", e))?)
+        };
+        Ok(Pacing {
+            byte_delay_ms: self.byte_delay_ms,
+            line_delay_ms: self.line_delay_ms,
+            prompt,
+            prompt_timeout_ms: self.prompt_timeout_ms,
+        })
+    }
+}
+
+#[component]
+pub fn SendFileButton(is_open: Signal<bool>) -> Element {
+    let state = use_context::<AppState>();
+    let form = use_signal(PacingForm::default);
+    let running = state.transfer.is_running();
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-full aspect-square",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#0d0f10] border border-[#2a2e33] hover:border-gray-500",
+                class: if is_open() || running { "border-primary text-primary" } else { "text-gray-400 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "Send File",
+                span {
+                    class: "material-symbols-outlined text-[20px]",
+                    class: if running { "animate-pulse" },
+                    "upload_file"
+                }
+            }
+
+            if is_open() {
+                SendFilePanel { form }
+            }
+        }
+    }
+}
+
+/// Streams a text file line by line with delays or prompt waits
+#[component]
+fn SendFilePanel(form: Signal<PacingForm>) -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let status = (state.transfer.status)();
+    let idle = state.conn.is_connected() && !state.transfer.is_running();
+    let ending = match (state.serial.tx_line_ending)() {
+        LineEnding::None => "kept as in the file",
+        LineEnding::NL => "sent as LF",
+        LineEnding::CR => "sent as CR",
+        LineEnding::NLCR => "sent as CRLF",
+    };
+    let values = form();
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-80 z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right text-left",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                PanelHeader { title: "Send File", subtitle: Some(format!("Line endings {}", ending)) }
+
+                div { class: "grid grid-cols-2 gap-2",
+                    DelayInput {
+                        label: "Byte delay",
+                        value: values.byte_delay_ms,
+                        onchange: move |v| form.write().byte_delay_ms = v,
+                    }
+                    DelayInput {
+                        label: "Line delay",
+                        value: values.line_delay_ms,
+                        onchange: move |v| form.write().line_delay_ms = v,
+                    }
+                }
+
+                div { class: "flex flex-col gap-1",
+                    span { class: "text-[10px] uppercase text-gray-500 font-bold", "Wait for prompt" }
+                    div { class: "flex gap-2",
+                        input {
+                            class: "flex-1 min-w-0 bg-[#0d0f10] text-white p-2 rounded border border-[#2a2e33] text-xs focus:border-primary/50 outline-none font-mono",
+                            placeholder: "Regex, e.g. [>#] $",
+                            value: "{values.prompt}",
+                            oninput: move |e| form.write().prompt = e.value(),
+                        }
+                        DelayInput {
+                            label: "",
+                            value: values.prompt_timeout_ms,
+                            onchange: move |v| form.write().prompt_timeout_ms = v,
+                        }
+                    }
+                }
+
+                label {
+                    class: "flex items-center justify-center gap-1 py-1.5 rounded-lg bg-[#0d0f10] border border-[#2a2e33] text-[10px] uppercase tracking-wider font-bold transition-all",
+                    class: if idle { "text-gray-400 hover:text-white hover:border-gray-500 cursor-pointer" } else { "text-gray-700 pointer-events-none" },
+                    span { class: "material-symbols-outlined text-[16px]", "upload" }
+                    "Choose File"
+                    input {
+                        class: "hidden",
+                        r#type: "file",
+                        disabled: !idle,
+                        onchange: move |evt: FormEvent| async move {
+                            let pacing = match form.peek().pacing() {
+                                Ok(pacing) => pacing,
+                                Err(e) => {
+                                    state.error(&e);
+                                    return;
+                                }
+                            };
+                            if let Some(file) = evt.files().into_iter().next() {
+                                match file.read_bytes().await {
+                                    Ok(bytes) => controller.send_paced(file.name(), bytes.to_vec(), pacing),
+                                    Err(e) => state.error(&format!("Failed to read file: {}", e)),
+                                }
+                            }
+                        },
+                    }
+                }
+
+                if !state.conn.is_connected() {
+                    span { class: "text-xs text-gray-600 italic", "Connect to a port to send files" }
+                }
+
+                if let Some(status) = status {
+                    TransferProgress { status }
+                }
+            }
+        }
+    }
+}
+
+/// Millisecond field, empty or invalid input counts as zero
+#[component]
+fn DelayInput(label: &'static str, value: u32, onchange: EventHandler<u32>) -> Element {
+    rsx! {
+        div { class: "flex flex-col gap-1",
+            if !label.is_empty() {
+                span { class: "text-[10px] uppercase text-gray-500 font-bold", "{label}" }
+            }
+            div { class: "flex items-center gap-1 bg-[#0d0f10] rounded border border-[#2a2e33] focus-within:border-primary/50 px-2",
+                input {
+                    class: "w-16 bg-transparent text-white py-2 text-xs outline-none font-mono",
+                    r#type: "number",
+                    min: "0",
+                    value: "{value}",
+                    oninput: move |e| onchange.call(e.value().parse().unwrap_or(0)),
+                }
+                span { class: "text-[10px] text-gray-500", "ms" }
+            }
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use crate::components::monitor::level_filter::short_count;
+use crate::components::ui::PanelHeader;
+use crate::state::{AppState, TagInfo};
+use dioxus::prelude::*;
+
+/// Ticks as seconds since boot, e.g. `12.345s`
+fn format_ticks(ticks: u64) -> String {
+    format!("{}.{:03}s", ticks / 1000, ticks % 1000)
+}
+
+#[component]
+pub fn TagButton(is_open: Signal<bool>) -> Element {
+    let state = use_context::<AppState>();
+    let filtering = !(state.log.tag_filter)().is_empty();
+
+    rsx! {
+        if is_open() {
+            div {
+                class: "fixed inset-0 z-40 cursor-default",
+                onclick: move |_| is_open.set(false),
+            }
+        }
+        div {
+            class: "relative h-full aspect-square",
+            class: if is_open() { "z-50" },
+            button {
+                class: "h-full w-full rounded-lg flex items-center justify-center transition-all bg-[#0d0f10] border border-[#2a2e33] hover:border-gray-500",
+                class: if is_open() || filtering { "border-primary text-primary" } else { "text-gray-400 hover:text-white" },
+                onclick: move |_| is_open.set(!is_open()),
+                title: "ESP-IDF Tags",
+                span { class: "material-symbols-outlined text-[20px]", "sell" }
+            }
+
+            if is_open() {
+                TagPanel {}
+            }
+        }
+    }
+}
+
+/// Every tag seen with its line count, with mute and solo toggles
+#[component]
+fn TagPanel() -> Element {
+    let state = use_context::<AppState>();
+    let tags = (state.log.tag_stats)();
+    let filter = (state.log.tag_filter)();
+
+    rsx! {
+        div {
+            class: "absolute top-full right-0 mt-2 w-80 z-50 bg-[#16181a] rounded-xl border border-white/10 shadow-2xl p-4 animate-in fade-in zoom-in-95 duration-200 origin-top-right",
+            onclick: |evt| evt.stop_propagation(),
+            div { class: "flex flex-col gap-3",
+                PanelHeader {
+                    title: "ESP-IDF Tags",
+                    subtitle: Some(format!("{} tags", tags.len())),
+                }
+
+                div { class: "flex flex-col max-h-80 overflow-y-auto custom-scrollbar bg-[#0d0f10] rounded border border-[#2a2e33]",
+                    if tags.is_empty() {
+                        span { class: "text-xs text-gray-600 italic px-3 py-2", "No tagged lines yet" }
+                    }
+                    for tag in tags {
+                        {
+                            let muted = filter.muted.contains(&tag.name);
+                            let soloed = filter.solo.contains(&tag.name);
+                            let hidden = !filter.shows(Some(&tag.name));
+                            rsx! {
+                                TagRow {
+                                    key: "{tag.name}",
+                                    tag,
+                                    muted,
+                                    soloed,
+                                    hidden,
+                                }
+                            }
+                        }
+                    }
+                }
+
+                if !filter.is_empty() {
+                    button {
+                        class: "text-[10px] uppercase tracking-wider font-bold text-gray-500 hover:text-white self-end",
+                        onclick: move |_| { state.log.tag_filter }.set(Default::default()),
+                        "Reset"
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[component]
+fn TagRow(tag: TagInfo, muted: bool, soloed: bool, hidden: bool) -> Element {
+    let state = use_context::<AppState>();
+    let last_seen = tag.last_ticks.map(format_ticks).unwrap_or_default();
+    let (mute_name, solo_name) = (tag.name.clone(), tag.name.clone());
+
+    rsx! {
+        div {
+            class: "flex items-center gap-2 px-3 py-1.5 border-b border-white/5 last:border-b-0",
+            class: if hidden { "opacity-40" },
+            span { class: "flex-1 truncate text-xs font-mono text-gray-200", title: "{tag.name}", "{tag.name}" }
+            span { class: "text-[10px] font-mono text-gray-600", title: "Last seen", "{last_seen}" }
+            span { class: "w-10 text-right text-[10px] font-mono text-gray-500", "{short_count(tag.count)}" }
+            button {
+                class: "w-6 h-6 rounded text-[10px] font-bold border transition-colors",
+                class: if muted { "border-red-400/60 text-red-400" } else { "border-[#2a2e33] text-gray-500 hover:text-white" },
+                title: "Mute",
+                onclick: move |_| state.log.toggle_tag_mute(&mute_name),
+                "M"
+            }
+            button {
+                class: "w-6 h-6 rounded text-[10px] font-bold border transition-colors",
+                class: if soloed { "border-primary text-primary" } else { "border-[#2a2e33] text-gray-500 hover:text-white" },
+                title: "Solo",
+                onclick: move |_| state.log.toggle_tag_solo(&solo_name),
+                "S"
+            }
+        }
+    }
+}
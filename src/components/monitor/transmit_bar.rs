@@ -1,10 +1,11 @@
 use crate::components::ui::forms::CommandInputGroup;
+use crate::hooks::at::use_at_controller;
 use crate::state::{AppState, LineEnding};
+use crate::utils::encoding::encode_text;
 use crate::utils::serial;
 use crate::utils::{parse_hex_string, CommandHistory};
 use dioxus::prelude::*;
 use futures_util::StreamExt;
-use js_sys::Uint8Array;
 
 #[component]
 pub fn TransmitBar() -> Element {
@@ -15,6 +16,7 @@ pub fn TransmitBar() -> Element {
     let is_hex_input = use_signal(|| false);
 
     let bridge = crate::hooks::use_worker_controller();
+    let at = use_at_controller();
 
     let send_task = use_coroutine(move |mut rx| async move {
         while rx.next().await.is_some() {
@@ -26,6 +28,22 @@ pub fn TransmitBar() -> Element {
             }
 
             let is_hex = is_hex_input();
+
+            // In AT mode the command is tracked until its final result code
+            if *state.at.enabled.peek() && !is_hex {
+                if text.is_empty() {
+                    continue;
+                }
+                match at.send(text.clone()).await {
+                    Ok(()) => {
+                        input_value.set(String::new());
+                        history.write().add(text);
+                        history_index.set(None);
+                    }
+                    Err(e) => state.warning(&e),
+                }
+                continue;
+            }
             let local_echo = *state.serial.tx_local_echo.peek();
             let port = state.conn.port.peek().as_ref().cloned();
 
@@ -44,24 +62,25 @@ pub fn TransmitBar() -> Element {
                     }
                 }
             } else {
-                text.clone().into_bytes()
+                match encode_text(&text, *state.serial.encoding.peek()) {
+                    Ok(d) => d,
+                    Err(e) => {
+                        state.error(&e);
+                        continue;
+                    }
+                }
             };
 
-            match ending {
-                LineEnding::NL => data.push(b'\n'),
-                LineEnding::CR => data.push(b'\r'),
-                LineEnding::NLCR => {
-                    data.push(b'\r');
-                    data.push(b'\n');
-                }
-                _ => {}
-            }
+            data.extend_from_slice(ending.as_bytes());
 
+            if state.conn.is_tapped() {
+                state.warning("The port is in use by a transfer");
+                continue;
+            }
             if let Some(conn_port) = port {
                 if serial::send_data(&conn_port, &data).await.is_ok() {
                     if local_echo {
-                        let array = Uint8Array::from(data.as_slice());
-                        bridge.append_chunk(array, false);
+                        bridge.record_tx(data);
                     }
                     input_value.set(String::new());
                     if !text.is_empty() {
@@ -73,6 +92,12 @@ pub fn TransmitBar() -> Element {
         }
     });
 
+    let placeholder = if (state.at.enabled)() {
+        "AT command..."
+    } else {
+        "Send command..."
+    };
+
     rsx! {
         div {
             class: "flex-1 relative flex gap-2 min-w-0",
@@ -81,7 +106,7 @@ pub fn TransmitBar() -> Element {
                 is_hex: is_hex_input,
                 line_ending: state.serial.tx_line_ending,
                 echo: Some(state.serial.tx_local_echo),
-                placeholder: "Send command...",
+                placeholder,
                 id: Some("transmit-input"),
                 on_submit: move |_| send_task.send(()),
                 on_keydown: move |evt: KeyboardEvent| {
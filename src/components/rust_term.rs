@@ -4,23 +4,18 @@ use dioxus::prelude::*;
 use super::monitor::{MacroBar, Monitor, MonitorToolbar};
 use super::terminal::{AutoDisposeTerminal, TerminalView};
 use crate::components::header::Header;
-use crate::hooks::use_worker_controller;
 use crate::state::ViewMode;
 
 #[component]
 pub fn RusTerm() -> Element {
     let app_state = crate::state::use_provide_app_state();
-    let bridge = use_worker_controller();
     let view_mode = app_state.ui.view_mode;
     let mut term_instance = use_signal(|| None::<AutoDisposeTerminal>);
 
     use_effect(move || {
-        let mode = view_mode();
-        bridge.set_mode(mode);
-        match mode {
+        // Everything received is stored in both modes, so the log is kept across switches
+        match view_mode() {
             ViewMode::Monitoring => {
-                bridge.clear();
-                app_state.log.clear();
                 // Clear terminal instance when leaving Terminal mode to ensure re-attachment
                 term_instance.set(None);
             }
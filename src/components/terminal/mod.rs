@@ -1,8 +1,11 @@
 mod hooks;
+mod playback;
 mod toolbar;
+mod zmodem_prompt;
 
 use crate::components::ui::buttons::ResumeScrollButton;
 use crate::components::ui::console::ConsoleFrame;
+use crate::hooks::use_worker_controller;
 use crate::state::AppState;
 use crate::utils::terminal_bindings::{Terminal, XtermFitAddon};
 use dioxus::prelude::*;
@@ -11,21 +14,30 @@ use std::rc::Rc;
 use wasm_bindgen::JsCast;
 use web_sys::window;
 
+pub use playback::PlaybackView;
 pub use toolbar::TerminalToolbar;
+pub use zmodem_prompt::ZmodemPrompt;
 
 pub struct AutoDisposeTerminal(pub Terminal);
 
 #[component]
 pub fn TerminalView(term_instance: Signal<Option<AutoDisposeTerminal>>) -> Element {
     let app_state = use_context::<AppState>();
+    let replaying = use_memo(move || app_state.terminal.playback.read().is_some());
 
     rsx! {
         ConsoleFrame {
             // Toolbar
             TerminalToolbar { term_instance }
 
-            // Terminal Content
-            Xterm { term_instance }
+            ZmodemPrompt {}
+
+            if replaying() {
+                PlaybackView {}
+            }
+
+            // Terminal Content, kept live but hidden while a recording plays
+            Xterm { term_instance, hidden: replaying() }
 
             // Resume Scroll Button
             if !*app_state.terminal.autoscroll.read() {
@@ -58,6 +70,8 @@ impl std::ops::Deref for AutoDisposeTerminal {
 #[derive(Props, Clone, PartialEq)]
 pub struct XtermProps {
     term_instance: Signal<Option<AutoDisposeTerminal>>,
+    #[props(default)]
+    hidden: bool,
 }
 
 #[component]
@@ -66,6 +80,7 @@ pub fn Xterm(props: XtermProps) -> Element {
     let term_instance = props.term_instance;
     let fit_addon = use_signal(|| None::<XtermFitAddon>);
     let state = use_context::<AppState>();
+    let bridge = use_worker_controller();
 
     // Buffers for throttled operations - persisted across renders
     let aggregation_buffer = use_signal(|| Rc::new(RefCell::new(Vec::<u8>::new())));
@@ -112,6 +127,7 @@ pub fn Xterm(props: XtermProps) -> Element {
         let _ = state.terminal.received_data.read();
         let data = state.terminal.take_data();
         if !data.is_empty() {
+            state.terminal.record_output(&data);
             aggregation_buffer.read().borrow_mut().extend(data);
         }
     });
@@ -140,6 +156,16 @@ pub fn Xterm(props: XtermProps) -> Element {
         }
     });
 
+    // The container had no size while hidden, so refit once it is shown again
+    let hidden = props.hidden;
+    use_effect(use_reactive!(|hidden| {
+        if !hidden {
+            if let Some(fit) = fit_addon.peek().as_ref() {
+                fit.fit();
+            }
+        }
+    }));
+
     // Serial send loop (60Hz)
     use_resource(move || async move {
         loop {
@@ -152,9 +178,17 @@ pub fn Xterm(props: XtermProps) -> Element {
                 }
                 std::mem::take(&mut *buf)
             };
+            // Keystrokes are dropped while a transfer owns the port
+            if state.conn.is_tapped() {
+                continue;
+            }
             if let Some(port) = state.conn.port.peek().clone() {
-                if let Err(e) = crate::utils::serial_api::send_data(&port, &data).await {
-                    web_sys::console::error_1(&e);
+                match crate::utils::serial_api::send_data(&port, &data).await {
+                    Ok(()) => {
+                        state.terminal.record_input(&data);
+                        bridge.record_tx(data);
+                    }
+                    Err(e) => web_sys::console::error_1(&e),
                 }
             }
         }
@@ -164,6 +198,7 @@ pub fn Xterm(props: XtermProps) -> Element {
         // Terminal Container
         div {
             class: "flex-1 w-full bg-transparent overflow-hidden pl-2",
+            class: if props.hidden { "hidden" },
             id: "xterm-container",
             onmounted: move |_| {
                 if let Some(element) = window()
@@ -0,0 +1,159 @@
+use super::AutoDisposeTerminal;
+use crate::components::ui::IconButton;
+use crate::state::AppState;
+use crate::utils::terminal_bindings::Terminal;
+use dioxus::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::window;
+
+const PLAYBACK_SPEEDS: [f64; 5] = [0.5, 1.0, 2.0, 4.0, 8.0];
+
+/// Replays a loaded `.cast` recording in its own terminal, leaving the live one untouched
+#[component]
+pub fn PlaybackView() -> Element {
+    let state = use_context::<AppState>();
+    // Dropped with this component, which disposes the terminal when playback closes
+    let mut term_instance = use_signal(|| None::<AutoDisposeTerminal>);
+
+    // Playback loop (60Hz)
+    use_resource(move || async move {
+        let mut last = js_sys::Date::now();
+        loop {
+            gloo_timers::future::TimeoutFuture::new(16).await;
+            let now = js_sys::Date::now();
+            let elapsed = (now - last) / 1000.0;
+            last = now;
+            let playing = state
+                .terminal
+                .playback
+                .peek()
+                .as_ref()
+                .is_some_and(|p| p.playing);
+            if !playing {
+                continue;
+            }
+            let output = match { state.terminal.playback }.write().as_mut() {
+                Some(player) => player.tick(elapsed),
+                None => continue,
+            };
+            if let Some(term) = term_instance.peek().as_ref() {
+                if !output.is_empty() {
+                    term.write(&output);
+                }
+            }
+        }
+    });
+
+    rsx! {
+        PlaybackBar { term_instance }
+        div {
+            class: "flex-1 w-full bg-transparent overflow-hidden pl-2",
+            id: "playback-container",
+            onmounted: move |_| {
+                let Some(div) = window()
+                    .unwrap()
+                    .document()
+                    .unwrap()
+                    .get_element_by_id("playback-container")
+                    .and_then(|element| element.dyn_into::<web_sys::HtmlElement>().ok())
+                else {
+                    return;
+                };
+                let (width, height) = match state.terminal.playback.peek().as_ref() {
+                    Some(player) => (player.cast.header.width, player.cast.header.height),
+                    None => return,
+                };
+                let options = js_sys::Object::new();
+                js_sys::Reflect::set(
+                    &options,
+                    &"fontSize".into(),
+                    &(*state.ui.font_size.peek()).into(),
+                )
+                .unwrap();
+                let term = Terminal::new(&options);
+                term.open(&div);
+                term.resize(width.into(), height.into());
+                term_instance.set(Some(AutoDisposeTerminal(term)));
+            },
+        }
+    }
+}
+
+/// Play/pause, seek and speed controls for a loaded `.cast` recording
+#[component]
+pub fn PlaybackBar(term_instance: Signal<Option<AutoDisposeTerminal>>) -> Element {
+    let state = use_context::<AppState>();
+    let mut playback = state.terminal.playback;
+    let Some(player) = playback.read().clone() else {
+        return rsx! {};
+    };
+    let duration = player.duration();
+    let play_icon = if player.playing {
+        "pause"
+    } else {
+        "play_arrow"
+    };
+
+    // Rewrites the terminal from the start of the recording
+    let show = move |output: String| {
+        if let Some(term) = term_instance.peek().as_ref() {
+            term.reset();
+            term.write(&output);
+        }
+    };
+
+    rsx! {
+        div { class: "flex items-center gap-3 px-3 py-1.5 border-b border-white/5 bg-[#0d0f10]",
+            span { class: "material-symbols-outlined text-[16px] text-primary", "movie" }
+            span { class: "max-w-40 truncate text-xs font-mono text-gray-300", title: "{player.name}", "{player.name}" }
+            IconButton {
+                icon: play_icon,
+                title: "Play / Pause",
+                class: "w-6 h-6 rounded",
+                onclick: move |_| {
+                    let restart = playback.write().as_mut().and_then(|p| p.toggle());
+                    if let Some(output) = restart {
+                        show(output);
+                    }
+                },
+            }
+            input {
+                class: "flex-1 accent-[#00bfff] cursor-pointer",
+                r#type: "range",
+                min: "0",
+                max: "{duration}",
+                step: "0.01",
+                value: "{player.position}",
+                oninput: move |evt| {
+                    if let Ok(position) = evt.value().parse::<f64>() {
+                        let output = playback.write().as_mut().map(|p| p.seek(position));
+                        if let Some(output) = output {
+                            show(output);
+                        }
+                    }
+                },
+            }
+            span { class: "w-28 text-right text-[10px] font-mono text-gray-500",
+                "{player.position:.1}s / {duration:.1}s"
+            }
+            button {
+                class: "w-10 h-6 rounded border border-[#2a2e33] text-[10px] font-bold text-gray-400 hover:text-white transition-colors",
+                title: "Playback speed",
+                onclick: move |_| {
+                    if let Some(p) = playback.write().as_mut() {
+                        let next = PLAYBACK_SPEEDS.iter().position(|&s| s == p.speed).map_or(1, |i| i + 1);
+                        p.speed = PLAYBACK_SPEEDS[next % PLAYBACK_SPEEDS.len()];
+                    }
+                },
+                "{player.speed}x"
+            }
+            IconButton {
+                icon: "close",
+                title: "Close playback",
+                icon_class: "text-[16px]",
+                class: "w-6 h-6 rounded",
+                onclick: move |_| playback.set(None),
+            }
+        }
+    }
+}
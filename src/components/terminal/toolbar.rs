@@ -1,5 +1,8 @@
 use crate::components::ui::console::UnifiedConsoleToolbar;
+use crate::config::EXPORT_CHUNK_SIZE;
 use crate::state::AppState;
+use crate::utils::asciicast::{Cast, CastPlayer};
+use crate::utils::file_save::{chunk_stream, save_stream_to_disk};
 use dioxus::prelude::*;
 
 #[component]
@@ -15,6 +18,7 @@ pub fn TerminalToolbar(term_instance: Signal<Option<super::AutoDisposeTerminal>>
                     ScrollbackInput {}
                     span { class: "text-[10px] text-gray-500 font-mono", "]" }
                 }
+                RecordingControls { term_instance }
             },
             font_size: state.ui.font_size,
             is_autoscroll: *state.terminal.autoscroll.read(),
@@ -75,3 +79,67 @@ fn ScrollbackInput() -> Element {
         }
     }
 }
+
+/// Records the session as asciicast v2 and opens `.cast` files for playback
+#[component]
+fn RecordingControls(term_instance: Signal<Option<super::AutoDisposeTerminal>>) -> Element {
+    let state = use_context::<AppState>();
+    let recording = state.terminal.is_recording();
+    let record_input = (state.terminal.record_input)();
+
+    rsx! {
+        div { class: "flex items-center gap-1",
+            button {
+                class: "flex items-center gap-1 h-5 px-1.5 rounded text-[10px] font-mono transition-colors",
+                class: if recording { "text-red-400 bg-red-500/10" } else { "text-gray-500 hover:text-white" },
+                title: if recording { "Stop and save recording" } else { "Record session (.cast)" },
+                onclick: move |_| {
+                    if let Some(cast) = state.terminal.stop_recording() {
+                        let chunks = cast.to_chunks(EXPORT_CHUNK_SIZE as usize);
+                        save_stream_to_disk(chunk_stream(chunks), "session.cast");
+                    } else if let Some(term) = term_instance.read().as_ref() {
+                        let encoding = *state.serial.encoding.peek();
+                        state.terminal.start_recording(term.cols() as u16, term.rows() as u16, encoding);
+                    }
+                },
+                span { class: "material-symbols-outlined text-[14px]",
+                    if recording { "stop_circle" } else { "radio_button_checked" }
+                }
+                if recording { "REC" }
+            }
+            button {
+                class: "h-5 px-1 rounded transition-colors",
+                class: if record_input { "text-primary" } else { "text-gray-600 hover:text-white" },
+                title: "Include typed input in recordings",
+                disabled: recording,
+                onclick: move |_| state.terminal.toggle_record_input(),
+                span { class: "material-symbols-outlined text-[14px]", "keyboard" }
+            }
+            label {
+                class: "h-5 px-1 rounded text-gray-500 hover:text-white cursor-pointer transition-colors",
+                title: "Play a recording (.cast)",
+                span { class: "material-symbols-outlined text-[14px]", "smart_display" }
+                input {
+                    class: "hidden",
+                    r#type: "file",
+                    accept: ".cast",
+                    onchange: move |evt: FormEvent| async move {
+                        if let Some(file) = evt.files().into_iter().next() {
+                            let cast = file
+                                .read_string()
+                                .await
+                                .map_err(|e| e.to_string())
+                                .and_then(|text| Cast::parse(&text));
+                            match cast {
+                                Ok(cast) => {
+                                    { state.terminal.playback }.set(Some(CastPlayer::new(cast, &file.name())))
+                                }
+                                Err(e) => state.error(&format!("Failed to load recording: {}", e)),
+                            }
+                        }
+                    },
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use crate::components::connection::TransferProgress;
+use crate::hooks::transfer::use_transfer_controller;
+use crate::state::{AppState, ZmodemDirection};
+use dioxus::prelude::*;
+
+/// Answers a ZMODEM session started from the terminal (`sz` or `rz` on the
+/// device) and shows its progress until dismissed
+#[component]
+pub fn ZmodemPrompt() -> Element {
+    let state = use_context::<AppState>();
+    let controller = use_transfer_controller();
+    let offer = (state.transfer.zmodem)();
+    let status = (state.transfer.status)().filter(|s| s.title.starts_with("ZMODEM"));
+
+    rsx! {
+        if let Some(direction) = offer {
+            div { class: "flex items-center gap-3 px-3 py-1.5 border-b border-white/5 bg-[#0d0f10]",
+                span { class: "material-symbols-outlined text-[16px] text-primary", "swap_vert" }
+                match direction {
+                    ZmodemDirection::Receive => rsx! {
+                        span { class: "flex-1 text-xs text-gray-300", "The device is sending a file over ZMODEM" }
+                        button {
+                            class: "px-2 py-1 rounded border border-primary text-primary text-[10px] uppercase tracking-wider font-bold hover:bg-primary/10",
+                            onclick: move |_| controller.receive_zmodem(),
+                            "Save…"
+                        }
+                    },
+                    ZmodemDirection::Send => rsx! {
+                        span { class: "flex-1 text-xs text-gray-300", "The device is waiting for a file over ZMODEM" }
+                        label { class: "px-2 py-1 rounded border border-primary text-primary text-[10px] uppercase tracking-wider font-bold hover:bg-primary/10 cursor-pointer",
+                            "Upload…"
+                            input {
+                                class: "hidden",
+                                r#type: "file",
+                                onchange: move |evt: FormEvent| async move {
+                                    if let Some(file) = evt.files().into_iter().next() {
+                                        match file.read_bytes().await {
+                                            Ok(bytes) => controller.send_zmodem(file.name(), bytes.to_vec()),
+                                            Err(e) => state.error(&format!("Failed to read file: {}", e)),
+                                        }
+                                    }
+                                },
+                            }
+                        }
+                    },
+                }
+                button {
+                    class: "px-2 py-1 rounded text-[10px] uppercase tracking-wider font-bold text-gray-500 hover:text-white",
+                    onclick: move |_| controller.decline_zmodem(),
+                    "Skip"
+                }
+            }
+        } else if let Some(status) = status {
+            div { class: "px-3 py-1.5 border-b border-white/5 bg-[#0d0f10]",
+                TransferProgress { status }
+            }
+        }
+    }
+}
@@ -1,7 +1,20 @@
 /// --- Networking & Buffer Config ---
 pub const READ_BUFFER_SIZE: usize = 64 * 1024;
 pub const EXPORT_CHUNK_SIZE: u64 = 64 * 1024;
-pub const MAX_LINE_BYTES: usize = 256;
+/// Most raw bytes sent to the inspector for one selection
+pub const RAW_INSPECT_LIMIT: usize = 64 * 1024;
+/// Default cap for one stored line; longer lines continue on the next stored line
+pub const MAX_LINE_BYTES: usize = 4096;
+/// Largest cap the user can configure (the line parser width is a u16)
+pub const LINE_BYTES_HARD_LIMIT: usize = u16::MAX as usize;
+/// Shown after a line that was cut at the cap and continues on the next one
+pub const CONTINUATION_MARKER: char = '\u{21A9}';
+/// Shown on a line that keeps the states a bare CR overwrote
+pub const OVERWRITTEN_MARKER: char = '\u{21BA}';
+/// Most overwritten states kept with one line; older ones are dropped
+pub const MAX_OVERWRITE_HISTORY: usize = 32;
+/// Starts the log line recording a line typed in the terminal
+pub const TX_LINE_PREFIX: &str = "TX> ";
 pub const HEX_VIEW_BYTES: usize = 16;
 
 /// --- UI Timing & Intervals ---
@@ -0,0 +1,103 @@
+use crate::at::library;
+use crate::hooks::worker::LineReader;
+use crate::hooks::{use_worker_controller, WorkerController};
+use crate::state::{AppState, LineEnding};
+use crate::utils::encoding::encode_text;
+use dioxus::prelude::*;
+use gloo_timers::future::TimeoutFuture;
+
+/// How often received lines are filed while AT mode is on
+const FOLLOW_POLL_MS: u32 = 20;
+
+pub fn use_at_controller() -> AtController {
+    let state = use_context::<AppState>();
+    let bridge = use_worker_controller();
+    AtController { state, bridge }
+}
+
+#[derive(Clone, Copy)]
+pub struct AtController {
+    state: AppState,
+    bridge: WorkerController,
+}
+
+impl AtController {
+    /// Turns AT mode on or off; while on, received lines are filed into the
+    /// AT log
+    pub fn set_enabled(&self, enabled: bool) {
+        let state = self.state;
+        if enabled == *state.at.enabled.peek() {
+            return;
+        }
+        { state.at.enabled }.set(enabled);
+        if enabled {
+            let session = *state.at.session.peek() + 1;
+            { state.at.session }.set(session);
+            spawn(follow_responses(state, self.bridge, session));
+        } else {
+            let mut log = state.at.log;
+            if log.peek().is_waiting() {
+                log.write().abandon();
+            }
+        }
+    }
+
+    /// Sends `command` and waits in the background for its final result.
+    /// Commands end with the selected TX line ending, CR if there is none.
+    pub async fn send(&self, command: String) -> Result<(), String> {
+        let (state, bridge) = (self.state, self.bridge);
+        if state.conn.is_tapped() {
+            return Err("The port is in use by a transfer".to_string());
+        }
+        if state.at.log.peek().is_waiting() {
+            return Err("Still waiting for the result of the last command".to_string());
+        }
+        let Some(port) = state.conn.port.peek().clone() else {
+            return Err("Not connected to a serial port".to_string());
+        };
+        let mut data = encode_text(&command, *state.serial.encoding.peek())?;
+        match *state.serial.tx_line_ending.peek() {
+            LineEnding::None => data.push(b'\r'),
+            ending => data.extend_from_slice(ending.as_bytes()),
+        }
+
+        // Filed before writing so a fast reply finds its command
+        let timeout_ms = library::timeout_for(&command);
+        { state.at.log }
+            .write()
+            .begin(command, timeout_ms, js_sys::Date::now());
+        if let Err(e) = crate::utils::serial_api::send_data(&port, &data).await {
+            { state.at.log }.write().abandon();
+            return Err(format!("Write failed: {:?}", e));
+        }
+        if *state.serial.tx_local_echo.peek() {
+            bridge.record_tx(data);
+        }
+        Ok(())
+    }
+
+    pub fn clear(&self) {
+        { self.state.at.log }.write().clear();
+    }
+}
+
+/// Files received lines into the AT log until AT mode is turned off or
+/// turned on again
+async fn follow_responses(state: AppState, bridge: WorkerController, session: u32) {
+    let mut reader = LineReader::new(state, bridge);
+    let mut log = state.at.log;
+    while *state.at.enabled.peek() && *state.at.session.peek() == session {
+        let lines = reader.read();
+        let now = js_sys::Date::now();
+        if !lines.is_empty() {
+            let mut log = log.write();
+            for line in &lines {
+                log.feed(line, now);
+            }
+        }
+        if log.peek().is_overdue(now) {
+            log.write().check_timeout(now);
+        }
+        TimeoutFuture::new(FOLLOW_POLL_MS).await;
+    }
+}
@@ -0,0 +1,199 @@
+use crate::hooks::serial::switch_baud_rate;
+use crate::hooks::worker::LineReader;
+use crate::hooks::{use_worker_controller, WorkerController};
+use crate::state::{AppState, LineEnding, MacroRunStatus};
+use crate::utils::encoding::encode_text;
+use crate::utils::macro_script::{self, Action, Interpreter, Script};
+use crate::utils::macros::MacroItem;
+use dioxus::prelude::*;
+use gloo_timers::future::TimeoutFuture;
+use std::collections::VecDeque;
+
+/// How often received lines are checked and a stop is noticed while waiting
+const POLL_MS: u32 = 20;
+
+pub fn use_macro_runner() -> MacroRunner {
+    let state = use_context::<AppState>();
+    let bridge = use_worker_controller();
+    MacroRunner { state, bridge }
+}
+
+#[derive(Clone, Copy)]
+pub struct MacroRunner {
+    state: AppState,
+    bridge: WorkerController,
+}
+
+impl MacroRunner {
+    /// Starts the macro's script in the background
+    pub fn run(&self, item: &MacroItem) {
+        let state = self.state;
+        let Some(text) = &item.script else {
+            return;
+        };
+        if state.macros.running.peek().is_some() {
+            state.warning("Another macro script is running");
+            return;
+        }
+        let script = match macro_script::parse(text) {
+            Ok(script) => script,
+            Err(e) => {
+                state.error(&format!("{}: {}", item.label, e));
+                return;
+            }
+        };
+        { state.macros.stop_requested }.set(false);
+        { state.macros.running }.set(Some(MacroRunStatus {
+            id: item.id,
+            label: item.label.clone(),
+            line: 0,
+            waiting: None,
+        }));
+        spawn(run_script(
+            state,
+            self.bridge,
+            item.label.clone(),
+            script,
+            item.line_ending,
+        ));
+    }
+
+    pub fn stop(&self) {
+        { self.state.macros.stop_requested }.set(true);
+    }
+}
+
+fn stopped(state: AppState) -> bool {
+    *state.macros.stop_requested.peek()
+}
+
+fn set_status(state: AppState, line: usize, waiting: Option<String>) {
+    let mut running = state.macros.running;
+    let changed = running
+        .peek()
+        .as_ref()
+        .is_some_and(|s| s.line != line || s.waiting != waiting);
+    if changed {
+        if let Some(status) = running.write().as_mut() {
+            status.line = line;
+            status.waiting = waiting;
+        }
+    }
+}
+
+async fn send(state: AppState, bridge: WorkerController, data: &[u8]) -> Result<(), String> {
+    if state.conn.is_tapped() {
+        return Err("The port is in use by a transfer".to_string());
+    }
+    let Some(port) = state.conn.port.peek().clone() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    crate::utils::serial_api::send_data(&port, data)
+        .await
+        .map_err(|e| format!("Write failed: {:?}", e))?;
+    if *state.serial.tx_local_echo.peek() {
+        bridge.record_tx(data.to_vec());
+    }
+    Ok(())
+}
+
+/// Sleeps for `ms`, waking early when the script is stopped
+async fn sleep(state: AppState, ms: u32) {
+    let until = js_sys::Date::now() + ms as f64;
+    while !stopped(state) {
+        let left = until - js_sys::Date::now();
+        if left <= 0.0 {
+            break;
+        }
+        TimeoutFuture::new((left as u32).min(POLL_MS)).await;
+    }
+}
+
+async fn run_script(
+    state: AppState,
+    bridge: WorkerController,
+    label: String,
+    script: Script,
+    ending: LineEnding,
+) {
+    let mut reader = LineReader::new(state, bridge);
+    // Lines received but not yet offered to an `expect`, so a reply that
+    // arrives together with the one being waited for is not lost
+    let mut pending: VecDeque<String> = VecDeque::new();
+    let mut interpreter = Interpreter::new(script);
+
+    // None when stopped by the user
+    let result = loop {
+        if stopped(state) {
+            break None;
+        }
+        let action = interpreter.next();
+        set_status(state, interpreter.line(), None);
+        match action {
+            Action::Send(text) => {
+                let data = encode_text(&text, *state.serial.encoding.peek()).map(|mut data| {
+                    data.extend_from_slice(ending.as_bytes());
+                    data
+                });
+                if let Err(e) = match data {
+                    Ok(data) => send(state, bridge, &data).await,
+                    Err(e) => Err(e),
+                } {
+                    break Some(Err(format!("Line {}: {}", interpreter.line(), e)));
+                }
+            }
+            Action::SendBytes(data) => {
+                if let Err(e) = send(state, bridge, &data).await {
+                    break Some(Err(format!("Line {}: {}", interpreter.line(), e)));
+                }
+            }
+            Action::Delay(ms) => sleep(state, ms).await,
+            Action::Baud(baud) => {
+                if let Err(e) = switch_baud_rate(state, bridge, baud).await {
+                    break Some(Err(format!("Line {}: {}", interpreter.line(), e)));
+                }
+            }
+            Action::Expect {
+                pattern,
+                timeout_ms,
+            } => {
+                set_status(state, interpreter.line(), Some(pattern));
+                let deadline = js_sys::Date::now() + timeout_ms as f64;
+                loop {
+                    pending.extend(reader.read());
+                    let mut matched = false;
+                    while let Some(line) = pending.pop_front() {
+                        if interpreter.offer(&line) {
+                            matched = true;
+                            break;
+                        }
+                    }
+                    if matched || stopped(state) {
+                        break;
+                    }
+                    if js_sys::Date::now() >= deadline {
+                        interpreter.time_out();
+                        break;
+                    }
+                    TimeoutFuture::new(POLL_MS).await;
+                }
+            }
+            Action::Log(text) => bridge.insert_marker(format!("--- {}: {} ---", label, text)),
+            Action::Yield => TimeoutFuture::new(0).await,
+            Action::Done => break Some(Ok(())),
+            Action::Failed(e) => break Some(Err(e)),
+        }
+    };
+
+    drop(reader);
+    { state.macros.running }.set(None);
+    { state.macros.stop_requested }.set(false);
+    match result {
+        None => state.info(&format!("{} stopped", label)),
+        Some(Ok(())) => state.success(&format!("{} finished", label)),
+        Some(Err(e)) => {
+            bridge.insert_marker(format!("--- {} failed: {} ---", label, e));
+            state.error(&format!("{}: {}", label, e));
+        }
+    }
+}
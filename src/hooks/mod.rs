@@ -1,4 +1,7 @@
+pub mod at;
+pub mod macros;
 pub mod serial;
+pub mod transfer;
 pub mod worker;
 pub use serial::use_serial_controller;
 pub use worker::{use_worker_controller, WorkerController};
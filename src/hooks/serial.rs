@@ -1,5 +1,6 @@
 use crate::hooks::{use_worker_controller, WorkerController};
-use crate::state::AppState;
+use crate::state::{AppState, PortConfig, ViewMode};
+use crate::transfer::zmodem::Detector as ZmodemDetector;
 use dioxus::prelude::*;
 use gloo_timers::future::TimeoutFuture;
 use wasm_bindgen::JsCast;
@@ -46,16 +47,10 @@ impl SerialController {
                 return;
             };
 
-            if crate::utils::serial_api::open_port(
-                &port,
-                (state.serial.baud_rate)(),
-                (state.serial.data_bits)(),
-                (state.serial.stop_bits)(),
-                &(state.serial.parity)().to_string(),
-                &(state.serial.flow_control)().to_string(),
-            )
-            .await
-            .is_err()
+            let config = state.serial.current_config();
+            if crate::utils::serial_api::open_port_with_config(&port, &config)
+                .await
+                .is_err()
             {
                 state.error("Failed to Open Port");
                 state.conn.set_busy(false);
@@ -63,6 +58,7 @@ impl SerialController {
             };
 
             bridge.new_session();
+            state.conn.set_active_config(Some(config));
 
             // Start the read task explicitly
             start_read_task(state, bridge, port);
@@ -88,6 +84,18 @@ impl SerialController {
         });
     }
 
+    /// Reopens the connected port with the currently selected settings,
+    /// keeping the worker session (and everything captured so far) intact.
+    pub fn reconfigure(&self) {
+        let state = self.state;
+        let bridge = self.bridge;
+        spawn(async move {
+            if let Err(msg) = reconfigure_port(state, bridge).await {
+                state.error(&msg);
+            }
+        });
+    }
+
     pub fn start_simulation(&self) {
         self.state.conn.set_simulating(true);
         self.state.success("Simulation Started");
@@ -141,9 +149,145 @@ async fn cleanup_serial_connection(state: AppState) {
 
     // 4. Final State Reset
     state.conn.set_connected(None, None);
+    state.conn.set_active_config(None);
     // state.conn.set_busy(false); // Caller is now responsible for setting busy to false
 }
 
+/// Switches the open port to the settings currently selected in `SerialSettings`.
+pub async fn reconfigure_port(state: AppState, bridge: WorkerController) -> Result<(), String> {
+    apply_port_config(state, bridge, state.serial.current_config()).await
+}
+
+/// Switches the open port to `baud_rate`, keeping the other active settings.
+/// The settings saved in `SerialSettings` are left as they are.
+pub async fn switch_baud_rate(
+    state: AppState,
+    bridge: WorkerController,
+    baud_rate: u32,
+) -> Result<(), String> {
+    let Some(config) = *state.conn.active_config.peek() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    apply_port_config(
+        state,
+        bridge,
+        PortConfig {
+            baud_rate,
+            ..config
+        },
+    )
+    .await
+}
+
+/// Switches the open port to `new_config`.
+///
+/// The reader is cancelled and the port closed and reopened, then the read task
+/// resumes in the same worker session. A marker line with the old and new
+/// settings is written to the log so the switch point is visible in the capture.
+pub async fn apply_port_config(
+    state: AppState,
+    bridge: WorkerController,
+    new_config: PortConfig,
+) -> Result<(), String> {
+    let Some(port) = (state.conn.port)() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    let Some(old_config) = (state.conn.active_config)() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    if old_config == new_config {
+        return Ok(());
+    }
+    if (state.conn.is_busy)() {
+        return Err("Port is busy".to_string());
+    }
+    // Busy keeps the read task from treating the cancelled reader as a lost connection
+    state.conn.set_busy(true);
+
+    if let Some(reader) = (state.conn.reader)() {
+        let _ = crate::utils::serial_api::cancel_reader(&reader).await;
+    }
+
+    let mut retries = 0;
+    while (state.conn.is_reading)() && retries < 50 {
+        TimeoutFuture::new(50).await;
+        retries += 1;
+    }
+
+    if crate::utils::serial_api::close_port(&port).await.is_err() {
+        web_sys::console::warn_1(&"Failed to close port for reconfigure".into());
+    }
+
+    let (applied, result) =
+        match crate::utils::serial_api::open_port_with_config(&port, &new_config).await {
+            Ok(()) => (new_config, Ok(())),
+            Err(_) => {
+                // Fall back to the previous settings so the session stays alive
+                if crate::utils::serial_api::open_port_with_config(&port, &old_config)
+                    .await
+                    .is_err()
+                {
+                    cleanup_serial_connection(state).await;
+                    state.conn.set_busy(false);
+                    return Err("Failed to reopen port".to_string());
+                }
+                (
+                    old_config,
+                    Err(format!(
+                        "Failed to apply {}, kept {}",
+                        new_config, old_config
+                    )),
+                )
+            }
+        };
+
+    if applied != old_config {
+        bridge.insert_marker(format!(
+            "--- Port reconfigured: {} -> {} ---",
+            old_config, applied
+        ));
+    }
+    state.conn.set_active_config(Some(applied));
+    start_read_task(state, bridge, port);
+    state.conn.set_busy(false);
+
+    if result.is_ok() {
+        state.success(&format!("Switched to {}", applied));
+    }
+    result
+}
+
+/// Delivers a received chunk: to a transfer owning the port, otherwise to the
+/// worker and, in terminal mode, to the terminal. A ZMODEM session starting in
+/// terminal mode takes the port from its first header on.
+fn route_chunk(
+    state: AppState,
+    bridge: WorkerController,
+    detector: &mut ZmodemDetector,
+    data: js_sys::Uint8Array,
+) {
+    if state.conn.feed_tap(&data) {
+        return;
+    }
+    state.conn.feed_watch(&data);
+    let data = if (state.ui.view_mode)() == ViewMode::Terminal {
+        let (pass, session) = detector.scan(&data.to_vec());
+        if let Some((direction, session)) = session {
+            state.transfer.hold_zmodem(&state.conn, direction, session);
+        }
+        if pass.is_empty() {
+            return;
+        }
+        // The worker stores every byte; the terminal only shows them
+        state.terminal.push_data(pass.clone());
+        js_sys::Uint8Array::from(&pass[..])
+    } else {
+        data
+    };
+    let is_hex = (state.ui.is_hex_view)();
+    bridge.append_chunk(data, is_hex);
+}
+
 /// Starts an explicit read task that handles the serial read loop and retries
 fn start_read_task(state: AppState, bridge: WorkerController, port: web_sys::SerialPort) {
     use crate::utils::serial_api::ReadStatus;
@@ -163,13 +307,9 @@ fn start_read_task(state: AppState, bridge: WorkerController, port: web_sys::Ser
         state.conn.set_reading(true);
 
         // 3. Run Loop
+        let mut detector = ZmodemDetector::default();
         let status = crate::utils::serial_api::read_loop(reader, move |data| {
-            if (state.ui.view_mode)() == crate::state::ViewMode::Terminal {
-                state.terminal.push_data(data.to_vec());
-            } else {
-                let is_hex = (state.ui.is_hex_view)();
-                bridge.append_chunk(data, is_hex);
-            }
+            route_chunk(state, bridge, &mut detector, data);
         })
         .await;
 
@@ -225,13 +365,9 @@ fn start_simulation_task(state: AppState, bridge: WorkerController) {
         state.conn.set_reading(true);
 
         // Run Loop
+        let mut detector = ZmodemDetector::default();
         let _ = crate::utils::serial_api::read_loop(reader, move |data| {
-            if (state.ui.view_mode)() == crate::state::ViewMode::Terminal {
-                state.terminal.push_data(data.to_vec());
-            } else {
-                let is_hex = (state.ui.is_hex_view)();
-                bridge.append_chunk(data, is_hex);
-            }
+            route_chunk(state, bridge, &mut detector, data);
         })
         .await;
 
@@ -0,0 +1,583 @@
+use crate::hooks::serial::apply_port_config;
+use crate::hooks::{use_worker_controller, WorkerController};
+use crate::state::{AppState, FlowControl, Parity, PortConfig, TransferStatus};
+use crate::transfer::esp::{self, EspOptions};
+use crate::transfer::image::Segment;
+use crate::transfer::micropython::{self, Action, Entry};
+use crate::transfer::pacing::{split_lines, Pacing, PromptWatch};
+use crate::transfer::stm32::{self, FlashOptions};
+use crate::transfer::xmodem::{Receiver, Sender, Variant};
+use crate::transfer::{zmodem, Protocol, StepState};
+use crate::utils::file_save::{channel_stream, save_stream_to_disk};
+use crate::utils::format::format_bytes;
+use dioxus::prelude::*;
+use gloo_timers::future::TimeoutFuture;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// How often the tap is checked for received bytes
+const TAP_POLL_MS: u32 = 5;
+/// How often a paused or sleeping sender checks for cancel
+const PAUSE_POLL_MS: u32 = 50;
+
+pub fn use_transfer_controller() -> TransferController {
+    let state = use_context::<AppState>();
+    let bridge = use_worker_controller();
+    TransferController { state, bridge }
+}
+
+#[derive(Clone, Copy)]
+pub struct TransferController {
+    state: AppState,
+    bridge: WorkerController,
+}
+
+impl TransferController {
+    pub fn send_file(&self, variant: Variant, name: String, data: Vec<u8>) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("{} send {}", variant, name);
+        spawn(async move {
+            let mut sender = Sender::new(variant, &name, data);
+            let result = run_protocol(state, bridge, &title, &mut sender, |_| {}).await;
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Opens the save dialog right away (it needs the click) and streams the
+    /// received file into it
+    pub fn receive_file(&self, variant: Variant) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("{} receive", variant);
+        let (stream, sink) = channel_stream();
+        let suggested = format!("{}_download.bin", variant.to_string().to_lowercase());
+        save_stream_to_disk(stream, &suggested);
+        spawn(async move {
+            let mut receiver = Receiver::new(variant);
+            let result = run_protocol(state, bridge, &title, &mut receiver, |receiver| {
+                let data = receiver.take_data();
+                if !data.is_empty() {
+                    sink.send(data);
+                }
+            })
+            .await;
+            if let Err(e) = &result {
+                sink.abort(e);
+            }
+            if let Some(name) = receiver.file_name() {
+                state.info(&format!("Received {}", name));
+            }
+            if receiver.skipped_files() > 0 {
+                state.warning(&format!(
+                    "Only the first file was kept, {} more were skipped",
+                    receiver.skipped_files()
+                ));
+            }
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Accepts a ZMODEM download offered in the terminal
+    pub fn receive_zmodem(&self) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = "ZMODEM receive".to_string();
+        let (stream, sink) = channel_stream();
+        save_stream_to_disk(stream, "zmodem_download.bin");
+        spawn(async move {
+            let mut receiver = zmodem::Receiver::new();
+            let result = run_protocol(state, bridge, &title, &mut receiver, |receiver| {
+                let data = receiver.take_data();
+                if !data.is_empty() {
+                    sink.send(data);
+                }
+            })
+            .await;
+            if let Err(e) = &result {
+                sink.abort(e);
+            }
+            if let Some(name) = receiver.file_name() {
+                state.info(&format!("Received {}", name));
+            }
+            if receiver.skipped_files() > 0 {
+                state.warning(&format!(
+                    "Only the first file was kept, {} more were skipped",
+                    receiver.skipped_files()
+                ));
+            }
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Answers a waiting `rz` with a file
+    pub fn send_zmodem(&self, name: String, data: Vec<u8>) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("ZMODEM send {}", name);
+        spawn(async move {
+            let mut sender = zmodem::Sender::new(&name, data);
+            let result = run_protocol(state, bridge, &title, &mut sender, |_| {}).await;
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Aborts a ZMODEM session offered in the terminal and gives the port back
+    pub fn decline_zmodem(&self) {
+        let state = self.state;
+        if state.transfer.zmodem.peek().is_none() {
+            return;
+        }
+        { state.transfer.zmodem }.set(None);
+        { state.conn.tap }.set(None);
+        let Some(port) = state.conn.port.peek().clone() else {
+            return;
+        };
+        spawn(async move {
+            let _ = crate::utils::serial_api::send_data(&port, &zmodem::cancel_sequence()).await;
+        });
+    }
+
+    /// Sends a text file line by line, paced for devices with small buffers
+    pub fn send_paced(&self, name: String, data: Vec<u8>, pacing: Pacing) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("Send {}", name);
+        spawn(async move {
+            let result = run_paced(state, bridge, &title, &data, pacing).await;
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Flashes an STM32 through its UART bootloader. The port runs 8E1 for
+    /// the session and returns to its settings afterwards.
+    pub fn flash_stm32(&self, name: String, segments: Vec<Segment>, options: FlashOptions) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("STM32 flash {}", name);
+        spawn(async move {
+            let mut flasher = stm32::Flasher::new(segments, options);
+            let result = run_in_bootloader(state, bridge, &title, Parity::Even, &mut flasher).await;
+            if let (Some(id), Some(version)) = (flasher.chip_id(), flasher.version()) {
+                state.info(&format!(
+                    "Bootloader v{}.{}, chip ID 0x{:03X}",
+                    version >> 4,
+                    version & 0x0F,
+                    id
+                ));
+            }
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Flashes an Espressif chip through its ROM loader at 8N1, optionally
+    /// switching to a faster baud rate once synced
+    pub fn flash_esp(&self, name: String, segments: Vec<Segment>, options: EspOptions) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("ESP flash {}", name);
+        spawn(async move {
+            let mut flasher = esp::Flasher::new(segments, options);
+            let result = run_in_bootloader(state, bridge, &title, Parity::None, &mut flasher).await;
+            if let Some(chip) = flasher.chip() {
+                state.info(&format!("Detected {}", chip));
+            }
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Runs a script in the MicroPython raw REPL; its output goes to the monitor
+    pub fn run_micropython(&self, code: String) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = "MicroPython run".to_string();
+        spawn(async move {
+            let action = Action::Exec { code, echo: true };
+            let (result, _) = run_repl(state, bridge, &title, vec![action]).await;
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Lists a directory on the MicroPython device into `files`
+    pub fn list_micropython(&self, dir: String, files: Signal<Vec<Entry>>) {
+        let (state, bridge) = (self.state, self.bridge);
+        spawn(list_repl_dir(state, bridge, dir, files));
+    }
+
+    /// Writes a file to the MicroPython device and refreshes the listing
+    pub fn upload_micropython(
+        &self,
+        dir: String,
+        name: String,
+        data: Vec<u8>,
+        files: Signal<Vec<Entry>>,
+    ) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("MicroPython upload {}", name);
+        spawn(async move {
+            let actions = micropython::upload(&micropython::join_path(&dir, &name), &data);
+            let (result, _) = run_repl(state, bridge, &title, actions).await;
+            let ok = result.is_ok();
+            finish(state, bridge, &title, result);
+            if ok {
+                list_repl_dir(state, bridge, dir, files).await;
+            }
+        });
+    }
+
+    /// Opens the save dialog right away (it needs the click) and fills it
+    /// with the file read from the MicroPython device
+    pub fn download_micropython(&self, path: String) {
+        let (state, bridge) = (self.state, self.bridge);
+        let name = path.rsplit('/').next().unwrap_or(&path).to_string();
+        let title = format!("MicroPython download {}", name);
+        let (stream, sink) = channel_stream();
+        save_stream_to_disk(stream, &name);
+        spawn(async move {
+            let (result, outputs) =
+                run_repl(state, bridge, &title, vec![micropython::download(&path)]).await;
+            let result = result.and_then(|()| micropython::parse_download(&outputs[0]));
+            let result = match result {
+                Ok(data) => {
+                    state.info(&format!(
+                        "Received {} ({})",
+                        name,
+                        format_bytes(data.len() as u64)
+                    ));
+                    sink.send(data);
+                    Ok(())
+                }
+                Err(e) => {
+                    sink.abort(&e);
+                    Err(e)
+                }
+            };
+            finish(state, bridge, &title, result);
+        });
+    }
+
+    /// Deletes a file or an empty directory and refreshes the listing
+    pub fn remove_micropython(&self, dir: String, entry: Entry, files: Signal<Vec<Entry>>) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = format!("MicroPython delete {}", entry.name);
+        spawn(async move {
+            let path = micropython::join_path(&dir, &entry.name);
+            let (result, _) = run_repl_quietly(
+                state,
+                bridge,
+                &title,
+                vec![micropython::remove(&path, entry.is_dir)],
+            )
+            .await;
+            match result {
+                Ok(()) => list_repl_dir(state, bridge, dir, files).await,
+                Err(e) => state.error(&format!("{} failed: {}", title, e)),
+            }
+        });
+    }
+
+    /// Soft-resets the MicroPython device; its boot output shows in the monitor
+    pub fn soft_reset_micropython(&self) {
+        let (state, bridge) = (self.state, self.bridge);
+        let title = "MicroPython soft reset".to_string();
+        spawn(async move {
+            let (result, _) =
+                run_repl_quietly(state, bridge, &title, vec![Action::SoftReset]).await;
+            match result {
+                Ok(()) => bridge.insert_marker(format!("--- {} ---", title)),
+                Err(e) => state.error(&format!("{} failed: {}", title, e)),
+            }
+        });
+    }
+
+    pub fn cancel(&self) {
+        self.state.transfer.request_cancel();
+    }
+
+    pub fn toggle_pause(&self) {
+        self.state.transfer.toggle_pause();
+    }
+}
+
+/// Logs the outcome as a marker line and a toast
+fn finish(state: AppState, bridge: WorkerController, title: &str, result: Result<(), String>) {
+    match &result {
+        Ok(()) => {
+            let bytes = state.transfer.status.peek().as_ref().map_or(0, |s| s.bytes);
+            bridge.insert_marker(format!("--- {}: {} bytes ---", title, bytes));
+            state.success(&format!("{} complete", title));
+        }
+        Err(e) => {
+            bridge.insert_marker(format!("--- {} failed: {} ---", title, e));
+            state.error(&format!("{} failed: {}", title, e));
+        }
+    }
+    state.transfer.update(|s| s.result = Some(result));
+}
+
+/// Takes over the connected port and drives `engine` until it finishes.
+/// Meanwhile received bytes go to the engine instead of the monitor and the
+/// terminal; the port is handed back afterwards. `after_step` runs after
+/// every engine step, e.g. to drain received file data.
+pub async fn run_protocol<P: Protocol>(
+    state: AppState,
+    bridge: WorkerController,
+    title: &str,
+    engine: &mut P,
+    mut after_step: impl FnMut(&mut P),
+) -> Result<(), String> {
+    let Some(port) = state.conn.port.peek().clone() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    // A ZMODEM session detected in the terminal already holds the port and
+    // its first bytes; the engine picks them up from there
+    let held = state.conn.tap.peek().clone();
+    let tap = match held {
+        Some(tap) if state.transfer.zmodem.peek().is_some() => {
+            { state.transfer.zmodem }.set(None);
+            tap
+        }
+        Some(_) => return Err("Another transfer is using the port".to_string()),
+        None => {
+            let tap = Rc::new(RefCell::new(Vec::new()));
+            { state.conn.tap }.set(Some(tap.clone()));
+            tap
+        }
+    };
+    { state.transfer.cancel_requested }.set(false);
+    { state.transfer.status }.set(Some(TransferStatus::new(title.to_string())));
+
+    let mut step = engine.start();
+    let result = loop {
+        if !step.send.is_empty() {
+            if let Err(e) = crate::utils::serial_api::send_data(&port, &step.send).await {
+                break Err(format!("Write failed: {:?}", e));
+            }
+        }
+        if let Some(baud_rate) = engine.take_baud_rate() {
+            let config = (*state.conn.active_config.peek()).map(|c| PortConfig { baud_rate, ..c });
+            if let Some(config) = config {
+                if let Err(e) = apply_port_config(state, bridge, config).await {
+                    break Err(e);
+                }
+            }
+        }
+        after_step(engine);
+        let progress = engine.progress();
+        state.transfer.update(|s| {
+            s.bytes = progress.bytes;
+            s.total = progress.total;
+            s.retries = progress.retries;
+        });
+
+        let timeout_ms = match step.state {
+            StepState::Wait(ms) => ms,
+            StepState::Done => break Ok(()),
+            StepState::Failed(reason) => break Err(reason),
+        };
+        // Timers run late in background tabs, so the timeout is counted in wall time
+        let deadline = js_sys::Date::now() + timeout_ms as f64;
+        let input = loop {
+            if *state.transfer.cancel_requested.peek() {
+                break None;
+            }
+            let data = std::mem::take(&mut *tap.borrow_mut());
+            if !data.is_empty() || js_sys::Date::now() >= deadline {
+                break Some(data);
+            }
+            TimeoutFuture::new(TAP_POLL_MS).await;
+        };
+        step = match input {
+            None => {
+                let _ = crate::utils::serial_api::send_data(&port, &engine.cancel()).await;
+                break Err("Cancelled".to_string());
+            }
+            Some(data) if data.is_empty() => engine.timeout(),
+            Some(data) => engine.receive(&data),
+        };
+    };
+
+    { state.conn.tap }.set(None);
+    result
+}
+
+/// Runs `actions` in one raw REPL session, showing echoed output in the
+/// monitor, and returns the captured output of each action
+async fn run_repl(
+    state: AppState,
+    bridge: WorkerController,
+    title: &str,
+    actions: Vec<Action>,
+) -> (Result<(), String>, Vec<Vec<u8>>) {
+    let mut session = micropython::Session::new(actions);
+    let result = run_protocol(state, bridge, title, &mut session, |session| {
+        let output = session.take_output();
+        if !output.is_empty() {
+            bridge.show_output(output);
+        }
+    })
+    .await;
+    (result, session.outputs().to_vec())
+}
+
+/// Like `run_repl` for quick housekeeping that leaves no progress behind
+async fn run_repl_quietly(
+    state: AppState,
+    bridge: WorkerController,
+    title: &str,
+    actions: Vec<Action>,
+) -> (Result<(), String>, Vec<Vec<u8>>) {
+    let outcome = run_repl(state, bridge, title, actions).await;
+    { state.transfer.status }.set(None);
+    outcome
+}
+
+async fn list_repl_dir(
+    state: AppState,
+    bridge: WorkerController,
+    dir: String,
+    mut files: Signal<Vec<Entry>>,
+) {
+    let (result, outputs) = run_repl_quietly(
+        state,
+        bridge,
+        "MicroPython list",
+        vec![micropython::list_dir(&dir)],
+    )
+    .await;
+    match result {
+        Ok(()) => files.set(micropython::parse_list(&outputs[0])),
+        Err(e) => state.error(&format!("Listing {} failed: {}", dir, e)),
+    }
+}
+
+/// Runs a bootloader session with the port at 8 data bits, one stop bit,
+/// `parity` and no flow control, then switches back to the previous settings
+async fn run_in_bootloader<P: Protocol>(
+    state: AppState,
+    bridge: WorkerController,
+    title: &str,
+    parity: Parity,
+    engine: &mut P,
+) -> Result<(), String> {
+    let Some(original) = *state.conn.active_config.peek() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    let bootloader_config = PortConfig {
+        data_bits: 8,
+        stop_bits: 1,
+        parity,
+        flow_control: FlowControl::None,
+        ..original
+    };
+    apply_port_config(state, bridge, bootloader_config).await?;
+    let result = run_protocol(state, bridge, title, engine, |_| {}).await;
+    if let Err(e) = apply_port_config(state, bridge, original).await {
+        state.error(&e);
+    }
+    result
+}
+
+/// Writes `data` with the delays and prompts of `pacing`, translating line
+/// endings to the selected TX ending. Unlike `run_protocol` the port stays
+/// shared, so the device's replies still show up in the monitor.
+async fn run_paced(
+    state: AppState,
+    bridge: WorkerController,
+    title: &str,
+    data: &[u8],
+    pacing: Pacing,
+) -> Result<(), String> {
+    let Some(port) = state.conn.port.peek().clone() else {
+        return Err("Not connected to a serial port".to_string());
+    };
+    if state.conn.is_tapped() || state.transfer.is_running() {
+        return Err("Another transfer is using the port".to_string());
+    }
+    let lines = split_lines(data, *state.serial.tx_line_ending.peek());
+    let local_echo = *state.serial.tx_local_echo.peek();
+    let mut status = TransferStatus::new(title.to_string());
+    status.total = Some(lines.iter().map(|l| l.len() as u64).sum());
+    status.can_pause = true;
+    { state.transfer.status }.set(Some(status));
+    { state.transfer.cancel_requested }.set(false);
+    { state.transfer.paused }.set(false);
+
+    let mut prompt = pacing.prompt.map(PromptWatch::new);
+    let watch = Rc::new(RefCell::new(Vec::new()));
+    if prompt.is_some() {
+        { state.conn.watch }.set(Some(watch.clone()));
+    }
+
+    let mut sent = 0u64;
+    let result = 'lines: {
+        for (i, line) in lines.iter().enumerate() {
+            if !wait_while_paused(state).await {
+                break 'lines Err("Cancelled".to_string());
+            }
+            watch.borrow_mut().clear();
+            let chunks: Vec<&[u8]> = if pacing.byte_delay_ms > 0 {
+                line.chunks(1).collect()
+            } else {
+                vec![line]
+            };
+            for (j, chunk) in chunks.iter().enumerate() {
+                if j > 0 && !sleep_unless_cancelled(state, pacing.byte_delay_ms).await {
+                    break 'lines Err("Cancelled".to_string());
+                }
+                if let Err(e) = crate::utils::serial_api::send_data(&port, chunk).await {
+                    break 'lines Err(format!("Write failed: {:?}", e));
+                }
+                if local_echo {
+                    bridge.record_tx(chunk.to_vec());
+                }
+                sent += chunk.len() as u64;
+                state.transfer.update(|s| s.bytes = sent);
+            }
+            if i + 1 == lines.len() {
+                break;
+            }
+            if let Some(prompt) = prompt.as_mut() {
+                prompt.reset();
+                let deadline = js_sys::Date::now() + pacing.prompt_timeout_ms as f64;
+                loop {
+                    if *state.transfer.cancel_requested.peek() {
+                        break 'lines Err("Cancelled".to_string());
+                    }
+                    let received = std::mem::take(&mut *watch.borrow_mut());
+                    if prompt.feed(&received) {
+                        break;
+                    }
+                    if js_sys::Date::now() >= deadline {
+                        break 'lines Err(format!("No prompt after line {}", i + 1));
+                    }
+                    TimeoutFuture::new(TAP_POLL_MS).await;
+                }
+            }
+            if !sleep_unless_cancelled(state, pacing.line_delay_ms).await {
+                break 'lines Err("Cancelled".to_string());
+            }
+        }
+        Ok(())
+    };
+
+    { state.conn.watch }.set(None);
+    result
+}
+
+/// Waits out a pause; false when cancelled meanwhile
+async fn wait_while_paused(state: AppState) -> bool {
+    while *state.transfer.paused.peek() {
+        if *state.transfer.cancel_requested.peek() {
+            return false;
+        }
+        TimeoutFuture::new(PAUSE_POLL_MS).await;
+    }
+    !*state.transfer.cancel_requested.peek()
+}
+
+/// Sleeps `ms` in short steps; false when cancelled meanwhile
+async fn sleep_unless_cancelled(state: AppState, ms: u32) -> bool {
+    let mut left = ms;
+    while left > 0 {
+        if *state.transfer.cancel_requested.peek() {
+            return false;
+        }
+        let step = left.min(PAUSE_POLL_MS);
+        TimeoutFuture::new(step).await;
+        left -= step;
+    }
+    !*state.transfer.cancel_requested.peek()
+}
@@ -1,6 +1,6 @@
 use crate::state::AppState;
 use crate::types::WorkerMsg;
-use crate::utils::{send_chunk_to_worker, send_worker_msg};
+use crate::utils::{send_chunk_to_worker, send_elf_to_worker, send_worker_msg};
 use dioxus::prelude::*;
 use wasm_bindgen::prelude::Closure;
 use wasm_bindgen::JsCast;
@@ -34,20 +34,77 @@ impl WorkerController {
         });
     }
 
+    pub fn search_bytes(&self, pattern: String, use_regex: bool) {
+        self.send(WorkerMsg::SearchBytes { pattern, use_regex });
+    }
+
     pub fn export(&self, include_timestamp: bool) {
         self.send(WorkerMsg::ExportLogs { include_timestamp });
     }
 
+    pub fn export_raw(&self, lines: Option<(usize, usize)>) {
+        self.send(WorkerMsg::ExportRaw(lines));
+    }
+
+    pub fn request_raw(&self, start_line: usize, end_line: usize) {
+        self.send(WorkerMsg::RequestRaw {
+            start_line,
+            end_line,
+        });
+    }
+
     pub fn append_chunk(&self, chunk: js_sys::Uint8Array, is_hex: bool) {
         if let Some(w) = self.worker_sig.read().as_ref() {
             send_chunk_to_worker(w, chunk, is_hex);
         }
     }
 
+    pub fn load_elf(&self, name: &str, data: &[u8]) {
+        if let Some(w) = self.worker_sig.read().as_ref() {
+            send_elf_to_worker(w, name, data);
+        }
+    }
+
+    pub fn unload_elf(&self) {
+        self.send(WorkerMsg::UnloadElf);
+    }
+
+    pub fn set_defmt(&self, enabled: bool) {
+        self.send(WorkerMsg::SetDefmt(enabled));
+    }
+
     pub fn set_timestamp_state(&self, enabled: bool) {
         self.send(WorkerMsg::SetTimestampState(enabled));
     }
 
+    pub fn set_rx_framing(&self, framing: crate::types::RxFraming) {
+        self.send(WorkerMsg::SetRxFraming(framing));
+    }
+
+    pub fn set_encoding(&self, encoding: crate::types::TextEncoding) {
+        self.send(WorkerMsg::SetEncoding(encoding));
+    }
+
+    pub fn set_hex_layout(&self, layout: crate::types::HexLayout) {
+        self.send(WorkerMsg::SetHexLayout(layout));
+    }
+
+    pub fn set_level_detection(&self, config: crate::types::LevelDetection) {
+        self.send(WorkerMsg::SetLevelDetection(config));
+    }
+
+    pub fn set_level_filter(&self, mask: crate::types::LevelMask) {
+        self.send(WorkerMsg::SetLevelFilter(mask));
+    }
+
+    pub fn set_tag_filter(&self, filter: crate::types::TagFilter) {
+        self.send(WorkerMsg::SetTagFilter(filter));
+    }
+
+    pub fn insert_marker(&self, text: String) {
+        self.send(WorkerMsg::InsertMarker(text));
+    }
+
     pub fn request_window(&self, start_line: usize, count: usize) {
         self.send(WorkerMsg::RequestWindow { start_line, count });
     }
@@ -56,8 +113,56 @@ impl WorkerController {
         self.send(WorkerMsg::NewSession);
     }
 
-    pub fn set_mode(&self, mode: crate::state::ViewMode) {
-        self.send(WorkerMsg::SetMode(mode));
+    pub fn record_tx(&self, data: Vec<u8>) {
+        self.send(WorkerMsg::RecordTx(data));
+    }
+
+    pub fn show_output(&self, data: Vec<u8>) {
+        self.send(WorkerMsg::ShowOutput(data));
+    }
+
+    pub fn set_line_feed(&self, enabled: bool) {
+        self.send(WorkerMsg::SetLineFeed(enabled));
+    }
+}
+
+/// Follows the lines received from now on. The worker sends plain lines
+/// while at least one reader is alive.
+pub struct LineReader {
+    state: AppState,
+    bridge: WorkerController,
+    cursor: u64,
+}
+
+impl LineReader {
+    pub fn new(state: AppState, bridge: WorkerController) -> Self {
+        let mut readers = state.conn.line_readers;
+        if *readers.peek() == 0 {
+            bridge.set_line_feed(true);
+        }
+        *readers.write() += 1;
+        let cursor = state.conn.received_lines.peek().end();
+        Self {
+            state,
+            bridge,
+            cursor,
+        }
+    }
+
+    /// Lines received since the last call
+    pub fn read(&mut self) -> Vec<String> {
+        self.state.conn.received_lines.peek().read(&mut self.cursor)
+    }
+}
+
+impl Drop for LineReader {
+    fn drop(&mut self) {
+        let mut readers = self.state.conn.line_readers;
+        let left = readers.peek().saturating_sub(1);
+        readers.set(left);
+        if left == 0 {
+            self.bridge.set_line_feed(false);
+        }
     }
 }
 
@@ -93,7 +198,11 @@ fn setup_worker_message_handler(worker: &web_sys::Worker, state: AppState) {
             if let Ok(msg_type) = js_sys::Reflect::get(&obj, &"type".into()) {
                 if msg_type.as_string() == Some("EXPORT_STREAM".to_string()) {
                     if let Ok(stream) = js_sys::Reflect::get(&obj, &"stream".into()) {
-                        crate::utils::file_save::save_stream_to_disk(stream);
+                        let filename = js_sys::Reflect::get(&obj, &"filename".into())
+                            .ok()
+                            .and_then(|v| v.as_string())
+                            .unwrap_or_else(|| "serial_log.txt".to_string());
+                        crate::utils::file_save::save_stream_to_disk(stream, &filename);
                         return;
                     }
                 }
@@ -116,9 +225,50 @@ fn setup_worker_message_handler(worker: &web_sys::Worker, state: AppState) {
                     WorkerMsg::Error(msg) => {
                         state.error(&format!("Worker Error: {}", msg));
                     }
+                    WorkerMsg::LevelCounts(counts) => {
+                        { state.log.level_counts }.set(counts);
+                    }
+                    WorkerMsg::ElfLoaded(info) => {
+                        { state.log.elf }.set(info);
+                    }
+                    WorkerMsg::Symbols(symbols) => {
+                        { state.log.symbols }.set(symbols);
+                    }
+                    WorkerMsg::Backtrace(frames) => {
+                        { state.log.backtrace }.set(frames);
+                    }
+                    WorkerMsg::TagStats(tags) => {
+                        { state.log.tag_stats }.set(tags);
+                    }
+                    WorkerMsg::InvalidSequences(count) => {
+                        { state.log.invalid_sequences }.set(count);
+                    }
+                    WorkerMsg::ReceivedLines(lines) => {
+                        { state.conn.received_lines }.write().extend(lines);
+                    }
                     WorkerMsg::ActiveLine(line) => {
                         { state.log.active_line }.set(line);
                     }
+                    WorkerMsg::ByteSearchResults { hits, truncated } => {
+                        state.log.set_byte_hits(hits, truncated);
+                        state.log.jump_to_byte_hit(0);
+                    }
+                    WorkerMsg::RawBytes {
+                        start_line,
+                        end_line,
+                        offset,
+                        total_len,
+                        bytes,
+                    } if *state.log.inspect_selection.peek() == Some((start_line, end_line)) => {
+                        // Replies for a selection that has changed since are dropped
+                        { state.log.raw_capture }.set(Some(crate::types::RawCapture {
+                            start_line,
+                            end_line,
+                            offset,
+                            total_len,
+                            bytes,
+                        }));
+                    }
                     _ => {}
                 }
             }
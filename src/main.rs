@@ -1,9 +1,11 @@
 use dioxus::prelude::*;
 
+mod at;
 mod components;
 mod config;
 mod hooks;
 mod state;
+mod transfer;
 pub mod types;
 mod utils;
 mod worker;
@@ -1,7 +1,13 @@
+use crate::at::AtLog;
 use crate::components::ui::{ToastMessage, ToastType};
+use crate::config::HIGHLIGHT_COLORS;
+pub use crate::transfer::zmodem::Direction as ZmodemDirection;
 pub use crate::types::*;
+use crate::utils::asciicast::{Cast, CastPlayer, CastRecorder};
 use dioxus::prelude::*;
 use gloo_timers::future::TimeoutFuture;
+use std::cell::RefCell;
+use std::rc::Rc;
 use web_sys::{ReadableStreamDefaultReader, SerialPort};
 
 #[derive(Clone, Copy)]
@@ -11,8 +17,14 @@ pub struct UIState {
     pub show_timestamps: Signal<bool>,
     pub autoscroll: Signal<bool>,
     pub is_hex_view: Signal<bool>,
+    pub hex_layout: Signal<HexLayout>,
     pub view_mode: Signal<ViewMode>,
     pub font_size: Signal<u32>,
+    pub show_inspector: Signal<bool>,
+    /// Text view shows control bytes as `<HH>` tokens
+    pub show_control_chars: Signal<bool>,
+    /// Long lines wrap in the monitor instead of scrolling sideways
+    pub wrap_lines: Signal<bool>,
 }
 
 #[derive(Clone, Copy)]
@@ -25,6 +37,11 @@ pub struct SerialSettings {
 
     pub tx_line_ending: Signal<LineEnding>,
     pub tx_local_echo: Signal<bool>,
+
+    pub rx_framing: Signal<RxFraming>,
+    pub encoding: Signal<TextEncoding>,
+    /// Received bytes are defmt frames, decoded with the loaded ELF
+    pub defmt: Signal<bool>,
 }
 
 #[derive(Clone, Copy)]
@@ -35,19 +52,78 @@ pub struct ConnectionState {
     pub log_worker: Signal<Option<web_sys::Worker>>,
     pub is_busy: Signal<bool>,
     pub is_reading: Signal<bool>,
+    pub active_config: Signal<Option<PortConfig>>,
+    /// While a transfer owns the port, received bytes collect here instead
+    /// of going to the monitor and terminal
+    pub tap: Signal<Option<Rc<RefCell<Vec<u8>>>>>,
+    /// Gets a copy of received bytes for a sender waiting on the device's
+    /// output, which still goes to the monitor and terminal as well
+    pub watch: Signal<Option<Rc<RefCell<Vec<u8>>>>>,
+    /// Plain lines from the worker, filled while `line_readers` is non-zero
+    pub received_lines: Signal<ReceivedLines>,
+    pub line_readers: Signal<usize>,
+}
+
+#[derive(Clone, Copy)]
+pub struct TransferState {
+    pub status: Signal<Option<TransferStatus>>,
+    pub cancel_requested: Signal<bool>,
+    pub paused: Signal<bool>,
+    /// A ZMODEM session started in the terminal and holds the port until answered
+    pub zmodem: Signal<Option<ZmodemDirection>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct AtState {
+    /// The transmit bar sends AT commands and waits for their result
+    pub enabled: Signal<bool>,
+    pub log: Signal<AtLog>,
+    /// Bumped each time AT mode is turned on, so a stale follower stops
+    pub session: Signal<u32>,
+}
+
+#[derive(Clone, Copy)]
+pub struct MacroState {
+    /// The script being run; only one runs at a time
+    pub running: Signal<Option<MacroRunStatus>>,
+    pub stop_requested: Signal<bool>,
 }
 
 #[derive(Clone, Copy)]
 pub struct LogState {
     pub total_lines: Signal<usize>,
-    pub visible_logs: Signal<Vec<(usize, String)>>,
+    pub visible_logs: Signal<Vec<(usize, String, LineAttrs)>>,
     pub filter_query: Signal<String>,
     pub match_case: Signal<bool>,
     pub use_regex: Signal<bool>,
     pub invert_filter: Signal<bool>,
     pub highlights: Signal<Vec<Highlight>>,
     pub toasts: Signal<Vec<ToastMessage>>,
-    pub active_line: Signal<Option<String>>,
+    pub active_line: Signal<Option<(String, LineAttrs)>>,
+    /// Displayed lines `start..end` selected for the raw inspector
+    pub inspect_selection: Signal<Option<(usize, usize)>>,
+    pub raw_capture: Signal<Option<RawCapture>>,
+    /// Malformed byte sequences decoded this session
+    pub invalid_sequences: Signal<u64>,
+    pub level_detection: Signal<LevelDetection>,
+    /// Levels shown; applied on top of the text filter
+    pub level_mask: Signal<LevelMask>,
+    pub level_counts: Signal<LevelCounts>,
+    /// ESP-IDF tags seen this session, most frequent first
+    pub tag_stats: Signal<Vec<TagInfo>>,
+    pub tag_filter: Signal<TagFilter>,
+    /// Firmware ELF loaded for backtrace symbolization
+    pub elf: Signal<Option<ElfInfo>>,
+    /// Resolved code addresses of the lines on screen
+    pub symbols: Signal<Vec<SymbolInfo>>,
+    pub backtrace: Signal<Vec<SymbolInfo>>,
+    /// The search bar matches byte patterns in the raw capture instead of filtering
+    pub byte_search: Signal<bool>,
+    pub byte_hits: Signal<Vec<ByteHit>>,
+    pub byte_hits_truncated: Signal<bool>,
+    pub byte_hit_index: Signal<usize>,
+    /// Displayed line the viewport should scroll to
+    pub scroll_to_line: Signal<Option<usize>>,
 }
 
 #[derive(Clone, Copy)]
@@ -56,6 +132,14 @@ pub struct TerminalState {
     pub scrollback: Signal<u32>,
     pub lines: Signal<usize>,
     pub autoscroll: Signal<bool>,
+    /// Session being recorded, appended to without notifying readers
+    pub recorder: Signal<Option<Rc<RefCell<CastRecorder>>>>,
+    /// `Date.now()` when the recording started
+    pub recording_started: Signal<f64>,
+    /// Recordings also capture typed input
+    pub record_input: Signal<bool>,
+    /// Loaded `.cast` file; live data is held back while it is shown
+    pub playback: Signal<Option<CastPlayer>>,
 }
 
 #[derive(Clone, Copy)]
@@ -65,6 +149,9 @@ pub struct AppState {
     pub conn: ConnectionState,
     pub log: LogState,
     pub terminal: TerminalState,
+    pub transfer: TransferState,
+    pub at: AtState,
+    pub macros: MacroState,
 }
 
 impl UIState {
@@ -86,9 +173,21 @@ impl UIState {
     pub fn toggle_hex_view(&self) {
         { self.is_hex_view }.toggle();
     }
+    pub fn set_hex_layout(&self, layout: HexLayout) {
+        { self.hex_layout }.set(layout);
+    }
     pub fn set_view_mode(&self, mode: ViewMode) {
         { self.view_mode }.set(mode);
     }
+    pub fn toggle_inspector(&self) {
+        { self.show_inspector }.toggle();
+    }
+    pub fn toggle_control_chars(&self) {
+        { self.show_control_chars }.toggle();
+    }
+    pub fn toggle_wrap_lines(&self) {
+        { self.wrap_lines }.toggle();
+    }
 }
 
 impl SerialSettings {
@@ -107,6 +206,26 @@ impl SerialSettings {
     pub fn set_flow_control(&self, f: FlowControl) {
         { self.flow_control }.set(f);
     }
+    pub fn set_rx_framing(&self, framing: RxFraming) {
+        { self.rx_framing }.set(framing);
+    }
+    pub fn set_encoding(&self, encoding: TextEncoding) {
+        { self.encoding }.set(encoding);
+    }
+
+    pub fn toggle_defmt(&self) {
+        { self.defmt }.toggle();
+    }
+
+    pub fn current_config(&self) -> PortConfig {
+        PortConfig {
+            baud_rate: (self.baud_rate)(),
+            data_bits: (self.data_bits)(),
+            stop_bits: (self.stop_bits)(),
+            parity: (self.parity)(),
+            flow_control: (self.flow_control)(),
+        }
+    }
 }
 
 impl ConnectionState {
@@ -132,12 +251,114 @@ impl ConnectionState {
     pub fn set_reading(&self, reading: bool) {
         { self.is_reading }.set(reading);
     }
+    pub fn set_active_config(&self, config: Option<PortConfig>) {
+        { self.active_config }.set(config);
+    }
+
+    /// True while a transfer has taken over the port; user input is held off meanwhile
+    pub fn is_tapped(&self) -> bool {
+        self.tap.peek().is_some()
+    }
+
+    /// Hands received bytes to the transfer owning the port; false when there is none
+    pub fn feed_tap(&self, data: &js_sys::Uint8Array) -> bool {
+        match self.tap.peek().as_ref() {
+            Some(tap) => {
+                tap.borrow_mut().extend(data.to_vec());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Copies received bytes to a waiting sender, if any
+    pub fn feed_watch(&self, data: &js_sys::Uint8Array) {
+        if let Some(watch) = self.watch.peek().as_ref() {
+            watch.borrow_mut().extend(data.to_vec());
+        }
+    }
+
+    /// True when the port is open with settings that differ from the ones currently selected
+    pub fn has_pending_config(&self, selected: PortConfig) -> bool {
+        (self.active_config)().is_some_and(|active| active != selected)
+    }
+}
+
+fn toggle_tag(list: &mut Vec<String>, tag: &str) {
+    match list.iter().position(|t| t == tag) {
+        Some(i) => {
+            list.remove(i);
+        }
+        None => list.push(tag.to_string()),
+    }
 }
 
 impl LogState {
     pub fn clear(&self) {
         { self.total_lines }.set(0);
         { self.visible_logs }.set(Vec::new());
+        self.set_inspect_selection(None);
+        self.set_byte_hits(Vec::new(), false);
+    }
+
+    /// Shows or hides lines of one `LogLevel::code`
+    pub fn toggle_level(&self, code: u8) {
+        let mask = *self.level_mask.peek();
+        { self.level_mask }.set(mask.toggled(code));
+    }
+
+    /// Hides or shows lines with `tag`; muting a soloed tag unsolos it
+    pub fn toggle_tag_mute(&self, tag: &str) {
+        let mut filter = self.tag_filter.peek().clone();
+        filter.solo.retain(|t| t != tag);
+        toggle_tag(&mut filter.muted, tag);
+        { self.tag_filter }.set(filter);
+    }
+
+    /// Adds or removes `tag` from the soloed tags; soloing a muted tag unmutes it
+    pub fn toggle_tag_solo(&self, tag: &str) {
+        let mut filter = self.tag_filter.peek().clone();
+        filter.muted.retain(|t| t != tag);
+        toggle_tag(&mut filter.solo, tag);
+        { self.tag_filter }.set(filter);
+    }
+
+    pub fn set_level_detection(&self, config: LevelDetection) {
+        { self.level_detection }.set(config);
+    }
+
+    pub fn set_byte_hits(&self, hits: Vec<ByteHit>, truncated: bool) {
+        { self.byte_hits }.set(hits);
+        { self.byte_hits_truncated }.set(truncated);
+        { self.byte_hit_index }.set(0);
+    }
+
+    /// Selects a byte hit and scrolls its line into view
+    pub fn jump_to_byte_hit(&self, index: usize) {
+        let Some(hit) = self.byte_hits.peek().get(index).copied() else {
+            return;
+        };
+        { self.byte_hit_index }.set(index);
+        if let Some(line) = hit.line {
+            { self.scroll_to_line }.set(Some(line));
+            self.set_inspect_selection(Some((line, line + 1)));
+        }
+    }
+
+    pub fn set_inspect_selection(&self, selection: Option<(usize, usize)>) {
+        { self.inspect_selection }.set(selection);
+        if selection.is_none() {
+            { self.raw_capture }.set(None);
+        }
+    }
+
+    /// Selects a displayed line, or extends the selection up to it
+    pub fn select_line(&self, line: usize, extend: bool) {
+        let selection = match (*self.inspect_selection.peek(), extend) {
+            (Some((start, end)), true) => (start.min(line), (end - 1).max(line) + 1),
+            _ => (line, line + 1),
+        };
+        self.set_inspect_selection(Some(selection));
     }
 
     pub fn add_toast(&self, message: &str, type_: ToastType) {
@@ -167,11 +388,64 @@ impl LogState {
         });
     }
 
+    /// First palette color not used by a highlight yet
+    pub fn next_highlight_color(&self) -> &'static str {
+        let list = self.highlights.read();
+        let used: std::collections::HashSet<&str> = list.iter().map(|h| h.color).collect();
+        HIGHLIGHT_COLORS
+            .iter()
+            .find(|&&c| !used.contains(c))
+            .copied()
+            .unwrap_or_else(|| HIGHLIGHT_COLORS[list.len() % HIGHLIGHT_COLORS.len()])
+    }
+
     pub fn remove_highlight(&self, id: usize) {
         { self.highlights }.write().retain(|h| h.id != id);
     }
 }
 
+impl TransferState {
+    pub fn is_running(&self) -> bool {
+        self.status.read().as_ref().is_some_and(|s| s.is_running())
+    }
+
+    pub fn update(&self, f: impl FnOnce(&mut TransferStatus)) {
+        if let Some(status) = { self.status }.write().as_mut() {
+            f(status);
+        }
+    }
+
+    pub fn request_cancel(&self) {
+        { self.cancel_requested }.set(true);
+    }
+
+    pub fn toggle_pause(&self) {
+        let paused = *self.paused.peek();
+        { self.paused }.set(!paused);
+    }
+
+    /// Holds the port for a ZMODEM session found in terminal output, starting
+    /// with its first header, until the user answers the prompt
+    pub fn hold_zmodem(
+        &self,
+        conn: &ConnectionState,
+        direction: ZmodemDirection,
+        session: Vec<u8>,
+    ) {
+        if conn.tap.peek().is_some() {
+            return;
+        }
+        { conn.tap }.set(Some(Rc::new(RefCell::new(session))));
+        { self.zmodem }.set(Some(direction));
+    }
+
+    pub fn dismiss(&self) {
+        if !self.is_running() {
+            { self.status }.set(None);
+        }
+    }
+}
+
 impl TerminalState {
     pub fn push_data(&self, data: Vec<u8>) {
         let mut signal = self.received_data;
@@ -192,6 +466,50 @@ impl TerminalState {
     pub fn clear(&self) {
         { self.received_data }.set(Vec::new());
     }
+
+    pub fn is_recording(&self) -> bool {
+        self.recorder.read().is_some()
+    }
+
+    pub fn start_recording(&self, cols: u16, rows: u16, encoding: TextEncoding) {
+        let now = js_sys::Date::now();
+        let recorder = CastRecorder::new(
+            cols,
+            rows,
+            (now / 1000.0) as u64,
+            (self.record_input)(),
+            encoding,
+        );
+        { self.recording_started }.set(now);
+        { self.recorder }.set(Some(Rc::new(RefCell::new(recorder))));
+    }
+
+    pub fn stop_recording(&self) -> Option<Cast> {
+        let recorder = { self.recorder }.take()?;
+        let cast = recorder.borrow_mut().finish();
+        Some(cast)
+    }
+
+    fn recording_time(&self) -> f64 {
+        (js_sys::Date::now() - *self.recording_started.peek()) / 1000.0
+    }
+
+    pub fn record_output(&self, data: &[u8]) {
+        if let Some(recorder) = self.recorder.peek().as_ref() {
+            recorder.borrow_mut().output(self.recording_time(), data);
+        }
+    }
+
+    pub fn record_input(&self, data: &[u8]) {
+        if let Some(recorder) = self.recorder.peek().as_ref() {
+            let text = String::from_utf8_lossy(data);
+            recorder.borrow_mut().input(self.recording_time(), &text);
+        }
+    }
+
+    pub fn toggle_record_input(&self) {
+        { self.record_input }.toggle();
+    }
 }
 
 pub fn use_provide_app_state() -> AppState {
@@ -202,8 +520,12 @@ pub fn use_provide_app_state() -> AppState {
             show_timestamps: use_signal(|| false),
             autoscroll: use_signal(|| true),
             is_hex_view: use_signal(|| false),
+            hex_layout: use_signal(HexLayout::default),
             view_mode: use_signal(|| ViewMode::Monitoring),
             font_size: use_signal(|| 14),
+            show_inspector: use_signal(|| false),
+            show_control_chars: use_signal(|| false),
+            wrap_lines: use_signal(|| false),
         },
         serial: SerialSettings {
             baud_rate: use_signal(|| 115200u32),
@@ -214,6 +536,10 @@ pub fn use_provide_app_state() -> AppState {
 
             tx_line_ending: use_signal(|| LineEnding::None),
             tx_local_echo: use_signal(|| false),
+
+            rx_framing: use_signal(RxFraming::default),
+            encoding: use_signal(TextEncoding::default),
+            defmt: use_signal(|| false),
         },
         conn: ConnectionState {
             port: use_signal(|| None),
@@ -222,10 +548,15 @@ pub fn use_provide_app_state() -> AppState {
             log_worker: use_signal(|| None::<web_sys::Worker>),
             is_busy: use_signal(|| false),
             is_reading: use_signal(|| false),
+            active_config: use_signal(|| None),
+            tap: use_signal(|| None),
+            watch: use_signal(|| None),
+            received_lines: use_signal(ReceivedLines::default),
+            line_readers: use_signal(|| 0),
         },
         log: LogState {
             total_lines: use_signal(|| 0usize),
-            visible_logs: use_signal(Vec::<(usize, String)>::new),
+            visible_logs: use_signal(Vec::<(usize, String, LineAttrs)>::new),
             filter_query: use_signal(String::new),
             match_case: use_signal(|| false),
             use_regex: use_signal(|| false),
@@ -233,12 +564,47 @@ pub fn use_provide_app_state() -> AppState {
             highlights: use_signal(Vec::new),
             toasts: use_signal(Vec::new),
             active_line: use_signal(|| None),
+            inspect_selection: use_signal(|| None),
+            raw_capture: use_signal(|| None),
+            invalid_sequences: use_signal(|| 0),
+            level_detection: use_signal(LevelDetection::default),
+            level_mask: use_signal(LevelMask::default),
+            level_counts: use_signal(|| [0; 6]),
+            tag_stats: use_signal(Vec::new),
+            tag_filter: use_signal(TagFilter::default),
+            elf: use_signal(|| None),
+            symbols: use_signal(Vec::new),
+            backtrace: use_signal(Vec::new),
+            byte_search: use_signal(|| false),
+            byte_hits: use_signal(Vec::new),
+            byte_hits_truncated: use_signal(|| false),
+            byte_hit_index: use_signal(|| 0),
+            scroll_to_line: use_signal(|| None),
         },
         terminal: TerminalState {
             received_data: use_signal(Vec::new),
             scrollback: use_signal(|| 1000),
             lines: use_signal(|| 0),
             autoscroll: use_signal(|| true),
+            recorder: use_signal(|| None),
+            recording_started: use_signal(|| 0.0),
+            record_input: use_signal(|| false),
+            playback: use_signal(|| None),
+        },
+        transfer: TransferState {
+            status: use_signal(|| None),
+            cancel_requested: use_signal(|| false),
+            paused: use_signal(|| false),
+            zmodem: use_signal(|| None),
+        },
+        at: AtState {
+            enabled: use_signal(|| false),
+            log: use_signal(AtLog::default),
+            session: use_signal(|| 0),
+        },
+        macros: MacroState {
+            running: use_signal(|| None),
+            stop_requested: use_signal(|| false),
         },
     };
 
@@ -0,0 +1,794 @@
+//! Espressif ROM serial bootloader (the protocol spoken by esptool), without
+//! the flasher stub: SLIP framed commands, chip detection, flash writes in
+//! 1 KB blocks and an MD5 check of every region. The chip has to be started
+//! in download mode (BOOT/GPIO0 low during reset).
+
+use super::image::{image_size, Segment};
+use super::{Progress, Protocol, Step, StepState};
+
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+const FLASH_BEGIN: u8 = 0x02;
+const FLASH_DATA: u8 = 0x03;
+const FLASH_END: u8 = 0x04;
+const SYNC: u8 = 0x08;
+const READ_REG: u8 = 0x0A;
+const SPI_SET_PARAMS: u8 = 0x0B;
+const SPI_ATTACH: u8 = 0x0D;
+const CHANGE_BAUDRATE: u8 = 0x0F;
+const SPI_FLASH_MD5: u8 = 0x13;
+
+const CHIP_DETECT_MAGIC_REG: u32 = 0x4000_1000;
+/// Block size the ROM loader accepts for FLASH_DATA
+const FLASH_BLOCK: usize = 0x400;
+const FLASH_SECTOR: u32 = 0x1000;
+/// Announced to the ROM so any address of the largest parts is accepted
+const FLASH_SIZE: u32 = 16 * 1024 * 1024;
+const CHECKSUM_SEED: u8 = 0xEF;
+
+const SYNC_TIMEOUT_MS: u32 = 300;
+const SYNC_ATTEMPTS: u32 = 10;
+const REPLY_TIMEOUT_MS: u32 = 3000;
+/// Erasing takes up to about 30 s per MB
+const ERASE_MS_PER_KB: u32 = 30;
+/// The ROM answers a SYNC several times; extra replies arrive within this
+const SYNC_SETTLE_MS: u32 = 100;
+/// Pause after a baud rate change before talking again
+const BAUD_SETTLE_MS: u32 = 50;
+
+/// Wraps a packet in a SLIP frame
+fn slip_encode(packet: &[u8]) -> Vec<u8> {
+    let mut out = vec![SLIP_END];
+    for &b in packet {
+        match b {
+            SLIP_END => out.extend([SLIP_ESC, SLIP_ESC_END]),
+            SLIP_ESC => out.extend([SLIP_ESC, SLIP_ESC_ESC]),
+            b => out.push(b),
+        }
+    }
+    out.push(SLIP_END);
+    out
+}
+
+/// Collects SLIP frames from bytes arriving in arbitrary chunks
+#[derive(Default)]
+struct SlipDecoder {
+    frame: Vec<u8>,
+    in_frame: bool,
+    escaped: bool,
+}
+
+impl SlipDecoder {
+    fn feed(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = Vec::new();
+        for &b in data {
+            if b == SLIP_END {
+                if self.in_frame && !self.frame.is_empty() {
+                    frames.push(std::mem::take(&mut self.frame));
+                }
+                // An END may also open the next frame
+                self.in_frame = true;
+                self.escaped = false;
+                continue;
+            }
+            if !self.in_frame {
+                // Boot messages and other output between frames
+                continue;
+            }
+            if self.escaped {
+                self.escaped = false;
+                self.frame.push(match b {
+                    SLIP_ESC_END => SLIP_END,
+                    SLIP_ESC_ESC => SLIP_ESC,
+                    other => other,
+                });
+            } else if b == SLIP_ESC {
+                self.escaped = true;
+            } else {
+                self.frame.push(b);
+            }
+        }
+        frames
+    }
+}
+
+fn command(op: u8, data: &[u8], checksum: u32) -> Vec<u8> {
+    let mut packet = vec![0x00, op];
+    packet.extend((data.len() as u16).to_le_bytes());
+    packet.extend(checksum.to_le_bytes());
+    packet.extend_from_slice(data);
+    slip_encode(&packet)
+}
+
+fn words(values: &[u32]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+fn data_checksum(data: &[u8]) -> u32 {
+    data.iter().fold(CHECKSUM_SEED, |a, b| a ^ b) as u32
+}
+
+/// MD5 digest (RFC 1321), as the ROM reports it for flash regions
+pub fn md5(data: &[u8]) -> [u8; 16] {
+    const S: [u32; 64] = [
+        7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 7, 12, 17, 22, 5, 9, 14, 20, 5, 9, 14, 20, 5,
+        9, 14, 20, 5, 9, 14, 20, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 4, 11, 16, 23, 6, 10,
+        15, 21, 6, 10, 15, 21, 6, 10, 15, 21, 6, 10, 15, 21,
+    ];
+    let k: Vec<u32> = (0..64)
+        .map(|i| ((i as f64 + 1.0).sin().abs() * 4_294_967_296.0) as u32)
+        .collect();
+    let mut state: [u32; 4] = [0x6745_2301, 0xEFCD_AB89, 0x98BA_DCFE, 0x1032_5476];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend(((data.len() as u64).wrapping_mul(8)).to_le_bytes());
+
+    for chunk in message.chunks(64) {
+        let m: Vec<u32> = chunk
+            .chunks(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect();
+        let [mut a, mut b, mut c, mut d] = state;
+        for i in 0..64 {
+            let (f, g) = match i / 16 {
+                0 => ((b & c) | (!b & d), i),
+                1 => ((d & b) | (!d & c), (5 * i + 1) % 16),
+                2 => (b ^ c ^ d, (3 * i + 5) % 16),
+                _ => (c ^ (b | !d), (7 * i) % 16),
+            };
+            let rotated = a
+                .wrapping_add(f)
+                .wrapping_add(k[i])
+                .wrapping_add(m[g])
+                .rotate_left(S[i]);
+            (a, b, c, d) = (d, b.wrapping_add(rotated), b, c);
+        }
+        for (s, v) in state.iter_mut().zip([a, b, c, d]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+    let mut digest = [0; 16];
+    for (out, word) in digest.chunks_mut(4).zip(state) {
+        out.copy_from_slice(&word.to_le_bytes());
+    }
+    digest
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chip {
+    Esp8266,
+    Esp32,
+    Esp32S2,
+    Esp32S3,
+    Esp32C2,
+    Esp32C3,
+    Esp32C6,
+    Esp32H2,
+}
+
+impl Chip {
+    /// Identifies the chip by the value of its magic register
+    fn from_magic(magic: u32) -> Option<Self> {
+        Some(match magic {
+            0xFFF0_C101 => Chip::Esp8266,
+            0x00F0_1D83 => Chip::Esp32,
+            0x0000_07C6 => Chip::Esp32S2,
+            0x0000_0009 => Chip::Esp32S3,
+            0x6F51_306F | 0x7C41_A06F => Chip::Esp32C2,
+            0x6921_506F | 0x1B31_506F | 0x4881_606F | 0x4361_606F => Chip::Esp32C3,
+            0x2CE0_806F => Chip::Esp32C6,
+            0xD7B7_3E80 => Chip::Esp32H2,
+            _ => return None,
+        })
+    }
+
+    /// The ESP8266 ROM ends replies with two status bytes, the others with four
+    fn status_len(self) -> usize {
+        if self == Chip::Esp8266 {
+            2
+        } else {
+            4
+        }
+    }
+
+    /// Newer ROMs expect an "encrypted" flag in FLASH_BEGIN
+    fn begin_has_encrypted_flag(self) -> bool {
+        !matches!(self, Chip::Esp8266 | Chip::Esp32)
+    }
+}
+
+impl std::fmt::Display for Chip {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Chip::Esp8266 => "ESP8266",
+            Chip::Esp32 => "ESP32",
+            Chip::Esp32S2 => "ESP32-S2",
+            Chip::Esp32S3 => "ESP32-S3",
+            Chip::Esp32C2 => "ESP32-C2",
+            Chip::Esp32C3 => "ESP32-C3",
+            Chip::Esp32C6 => "ESP32-C6",
+            Chip::Esp32H2 => "ESP32-H2",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+fn rom_error(code: u8) -> &'static str {
+    match code {
+        0x05 => "invalid message",
+        0x06 => "failed to act on message",
+        0x07 => "invalid CRC",
+        0x08 => "flash write error",
+        0x09 => "flash read error",
+        0x0A => "flash read length error",
+        0x0B => "deflate error",
+        _ => "unknown error",
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EspOptions {
+    /// Baud rate to switch to after syncing
+    pub baud_rate: Option<u32>,
+    /// Compare the MD5 of every written region
+    pub verify: bool,
+    /// Leave the loader and boot the new firmware
+    pub reboot: bool,
+}
+
+impl Default for EspOptions {
+    fn default() -> Self {
+        Self {
+            baud_rate: Some(460_800),
+            verify: true,
+            reboot: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Sync,
+    /// Synced, extra SYNC replies are still coming
+    SyncSettle,
+    DetectChip,
+    Attach,
+    SetParams,
+    ChangeBaud,
+    /// The port switches to the new baud rate
+    BaudSettle,
+    Begin,
+    Data,
+    Md5,
+    End,
+}
+
+impl Stage {
+    fn op(self) -> u8 {
+        match self {
+            Stage::Sync | Stage::SyncSettle => SYNC,
+            Stage::DetectChip => READ_REG,
+            Stage::Attach => SPI_ATTACH,
+            Stage::SetParams => SPI_SET_PARAMS,
+            Stage::ChangeBaud | Stage::BaudSettle => CHANGE_BAUDRATE,
+            Stage::Begin => FLASH_BEGIN,
+            Stage::Data => FLASH_DATA,
+            Stage::Md5 => SPI_FLASH_MD5,
+            Stage::End => FLASH_END,
+        }
+    }
+}
+
+/// Writes regions of flash through the ROM loader
+pub struct Flasher {
+    segments: Vec<Segment>,
+    options: EspOptions,
+    stage: Stage,
+    decoder: SlipDecoder,
+    sync_attempts: u32,
+    chip: Option<Chip>,
+    /// Baud rate the port has to switch to before the next write
+    pending_baud: Option<u32>,
+    /// Timeout of the current wait, restarted by unrelated input
+    wait_ms: u32,
+    segment: usize,
+    /// Sequence number of the block being written
+    block: usize,
+    written: u64,
+}
+
+impl Flasher {
+    pub fn new(mut segments: Vec<Segment>, options: EspOptions) -> Self {
+        // Flash is written in words; the ROM hashes the padded region
+        for segment in &mut segments {
+            segment
+                .data
+                .resize(segment.data.len().next_multiple_of(4), 0xFF);
+        }
+        Self {
+            segments,
+            options,
+            stage: Stage::Sync,
+            decoder: SlipDecoder::default(),
+            sync_attempts: 0,
+            chip: None,
+            pending_baud: None,
+            wait_ms: SYNC_TIMEOUT_MS,
+            segment: 0,
+            block: 0,
+            written: 0,
+        }
+    }
+
+    pub fn chip(&self) -> Option<Chip> {
+        self.chip
+    }
+
+    fn send(&mut self, stage: Stage, packet: Vec<u8>, timeout_ms: u32) -> Step {
+        self.stage = stage;
+        self.wait_ms = timeout_ms;
+        Step::wait(packet, timeout_ms)
+    }
+
+    fn sync_packet() -> Vec<u8> {
+        let mut data = vec![0x07, 0x07, 0x12, 0x20];
+        data.extend([0x55; 32]);
+        command(SYNC, &data, 0)
+    }
+
+    fn detect_chip(&mut self) -> Step {
+        let packet = command(READ_REG, &words(&[CHIP_DETECT_MAGIC_REG]), 0);
+        self.send(Stage::DetectChip, packet, REPLY_TIMEOUT_MS)
+    }
+
+    fn set_params(&mut self) -> Step {
+        // id, total size, block, sector, page, status mask
+        let params = words(&[0, FLASH_SIZE, 0x10000, FLASH_SECTOR, 0x100, 0xFFFF]);
+        self.send(
+            Stage::SetParams,
+            command(SPI_SET_PARAMS, &params, 0),
+            REPLY_TIMEOUT_MS,
+        )
+    }
+
+    fn begin_segment(&mut self) -> Step {
+        let chip = self.chip.unwrap_or(Chip::Esp32);
+        let segment = &self.segments[self.segment];
+        let size = segment.data.len() as u32;
+        let blocks = segment.data.len().div_ceil(FLASH_BLOCK) as u32;
+        let erase_size = size.next_multiple_of(FLASH_SECTOR);
+        let mut params = words(&[erase_size, blocks, FLASH_BLOCK as u32, segment.address]);
+        if chip.begin_has_encrypted_flag() {
+            params.extend(words(&[0]));
+        }
+        self.block = 0;
+        let timeout = REPLY_TIMEOUT_MS + erase_size / 1024 * ERASE_MS_PER_KB;
+        self.send(Stage::Begin, command(FLASH_BEGIN, &params, 0), timeout)
+    }
+
+    fn write_block(&mut self) -> Step {
+        let data = &self.segments[self.segment].data;
+        let start = self.block * FLASH_BLOCK;
+        let mut block = data[start..(start + FLASH_BLOCK).min(data.len())].to_vec();
+        block.resize(FLASH_BLOCK, 0xFF);
+        let mut payload = words(&[FLASH_BLOCK as u32, self.block as u32, 0, 0]);
+        payload.extend_from_slice(&block);
+        let packet = command(FLASH_DATA, &payload, data_checksum(&block));
+        self.send(Stage::Data, packet, REPLY_TIMEOUT_MS)
+    }
+
+    fn after_segment(&mut self) -> Step {
+        if self.options.verify && self.chip != Some(Chip::Esp8266) {
+            let segment = &self.segments[self.segment];
+            let params = words(&[segment.address, segment.data.len() as u32, 0, 0]);
+            return self.send(
+                Stage::Md5,
+                command(SPI_FLASH_MD5, &params, 0),
+                REPLY_TIMEOUT_MS,
+            );
+        }
+        self.next_segment()
+    }
+
+    fn next_segment(&mut self) -> Step {
+        self.segment += 1;
+        if self.segment < self.segments.len() {
+            return self.begin_segment();
+        }
+        if self.options.reboot {
+            // 0 asks the ROM to run the application
+            return self.send(
+                Stage::End,
+                command(FLASH_END, &words(&[0]), 0),
+                REPLY_TIMEOUT_MS,
+            );
+        }
+        Step::done(Vec::new())
+    }
+
+    /// Handles the reply to the command of the current stage
+    fn on_reply(&mut self, value: u32, data: &[u8]) -> Step {
+        match self.stage {
+            Stage::Sync | Stage::SyncSettle => {
+                self.send(Stage::SyncSettle, Vec::new(), SYNC_SETTLE_MS)
+            }
+            Stage::BaudSettle => Step::wait(Vec::new(), self.wait_ms),
+            Stage::DetectChip => {
+                let Some(chip) = Chip::from_magic(value) else {
+                    let reason = format!("Unknown chip (magic 0x{:08X})", value);
+                    return Step::failed(Vec::new(), reason);
+                };
+                self.chip = Some(chip);
+                if chip == Chip::Esp8266 {
+                    return self.set_params();
+                }
+                // Default SPI pins, not legacy
+                let packet = command(SPI_ATTACH, &words(&[0, 0]), 0);
+                self.send(Stage::Attach, packet, REPLY_TIMEOUT_MS)
+            }
+            Stage::Attach => self.set_params(),
+            Stage::SetParams => match self.options.baud_rate {
+                Some(baud) => {
+                    // The ROM takes 0 as the current rate
+                    let packet = command(CHANGE_BAUDRATE, &words(&[baud, 0]), 0);
+                    self.send(Stage::ChangeBaud, packet, REPLY_TIMEOUT_MS)
+                }
+                None => self.begin_segment(),
+            },
+            Stage::ChangeBaud => {
+                self.pending_baud = self.options.baud_rate;
+                self.send(Stage::BaudSettle, Vec::new(), BAUD_SETTLE_MS)
+            }
+            Stage::Begin => self.write_block(),
+            Stage::Data => {
+                let data_len = self.segments[self.segment].data.len();
+                let written = ((self.block + 1) * FLASH_BLOCK).min(data_len);
+                self.written += (written - self.block * FLASH_BLOCK) as u64;
+                self.block += 1;
+                if written < data_len {
+                    self.write_block()
+                } else {
+                    self.after_segment()
+                }
+            }
+            Stage::Md5 => {
+                let segment = &self.segments[self.segment];
+                let expected: String = md5(&segment.data)
+                    .iter()
+                    .map(|b| format!("{:02x}", b))
+                    .collect();
+                let reported =
+                    String::from_utf8_lossy(data.get(..32).unwrap_or_default()).to_lowercase();
+                if reported != expected {
+                    let reason = format!("MD5 mismatch for the region at 0x{:X}", segment.address);
+                    return Step::failed(Vec::new(), reason);
+                }
+                self.next_segment()
+            }
+            Stage::End => Step::done(Vec::new()),
+        }
+    }
+}
+
+impl Protocol for Flasher {
+    fn start(&mut self) -> Step {
+        if self.segments.is_empty() {
+            return Step::failed(Vec::new(), "The image contains no data");
+        }
+        Step::wait(Self::sync_packet(), SYNC_TIMEOUT_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        let mut step = None;
+        for frame in self.decoder.feed(data) {
+            if frame.len() < 8 || frame[0] != 0x01 || frame[1] != self.stage.op() {
+                continue;
+            }
+            let value = u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]);
+            let body = &frame[8..];
+            let status_len = match self.chip {
+                Some(chip) => chip.status_len(),
+                // Before detection, READ_REG replies carry just the status
+                None if body.len() == 2 => 2,
+                None => 4,
+            };
+            if body.len() < status_len {
+                return Step::failed(Vec::new(), "Malformed reply from the ROM");
+            }
+            let status = &body[body.len() - status_len..];
+            // Later SYNC replies may repeat; only the current command counts
+            if status[0] != 0 && self.stage != Stage::SyncSettle {
+                let reason = format!(
+                    "The ROM reported {} (0x{:02X})",
+                    rom_error(status[1]),
+                    status[1]
+                );
+                return Step::failed(Vec::new(), reason);
+            }
+            let next = self.on_reply(value, &body[..body.len() - status_len]);
+            let moved_on = !next.send.is_empty() || !matches!(next.state, StepState::Wait(_));
+            step = Some(next);
+            if moved_on {
+                break;
+            }
+        }
+        step.unwrap_or_else(|| Step::wait(Vec::new(), self.wait_ms))
+    }
+
+    fn timeout(&mut self) -> Step {
+        match self.stage {
+            Stage::Sync => {
+                self.sync_attempts += 1;
+                if self.sync_attempts >= SYNC_ATTEMPTS {
+                    return Step::failed(
+                        Vec::new(),
+                        "No answer from the ROM loader, is the chip in download mode?",
+                    );
+                }
+                self.send(Stage::Sync, Self::sync_packet(), SYNC_TIMEOUT_MS)
+            }
+            Stage::SyncSettle => self.detect_chip(),
+            Stage::BaudSettle => self.begin_segment(),
+            // The chip may reboot before it answers
+            Stage::End => Step::done(Vec::new()),
+            _ => Step::failed(
+                Vec::new(),
+                format!("No reply to command 0x{:02X}", self.stage.op()),
+            ),
+        }
+    }
+
+    fn take_baud_rate(&mut self) -> Option<u32> {
+        self.pending_baud.take()
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.written,
+            total: Some(image_size(&self.segments)),
+            retries: self.sync_attempts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::loopback;
+
+    fn hex(digest: [u8; 16]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Answers like the ROM loader of `chip` with 2 MB of flash
+    struct SimRom {
+        chip: Chip,
+        magic: u32,
+        decoder: SlipDecoder,
+        flash: Vec<u8>,
+        region: Option<(usize, u32)>,
+        baud_rate: Option<u32>,
+        rebooted: bool,
+        corrupt_writes: bool,
+        reject_data: bool,
+    }
+
+    impl SimRom {
+        fn new(chip: Chip, magic: u32) -> Self {
+            Self {
+                chip,
+                magic,
+                decoder: SlipDecoder::default(),
+                flash: vec![0; 2 * 1024 * 1024],
+                region: None,
+                baud_rate: None,
+                rebooted: false,
+                corrupt_writes: false,
+                reject_data: false,
+            }
+        }
+
+        fn reply(&self, op: u8, value: u32, data: &[u8], error: u8) -> Vec<u8> {
+            let mut body = data.to_vec();
+            body.push(u8::from(error != 0));
+            body.push(error);
+            if self.chip.status_len() == 4 {
+                body.extend([0, 0]);
+            }
+            let mut packet = vec![0x01, op];
+            packet.extend((body.len() as u16).to_le_bytes());
+            packet.extend(value.to_le_bytes());
+            packet.extend(body);
+            slip_encode(&packet)
+        }
+
+        fn handle(&mut self, frame: &[u8]) -> Vec<u8> {
+            let op = frame[1];
+            let checksum = u32::from_le_bytes([frame[4], frame[5], frame[6], frame[7]]);
+            let data = &frame[8..];
+            let word = |i: usize| {
+                u32::from_le_bytes([
+                    data[i * 4],
+                    data[i * 4 + 1],
+                    data[i * 4 + 2],
+                    data[i * 4 + 3],
+                ])
+            };
+            match op {
+                // The ROM answers a SYNC more than once
+                SYNC => [0; 3]
+                    .iter()
+                    .flat_map(|_| self.reply(SYNC, 0, &[], 0))
+                    .collect(),
+                READ_REG => self.reply(op, self.magic, &[], 0),
+                SPI_ATTACH | SPI_SET_PARAMS => self.reply(op, 0, &[], 0),
+                CHANGE_BAUDRATE => {
+                    self.baud_rate = Some(word(0));
+                    self.reply(op, 0, &[], 0)
+                }
+                FLASH_BEGIN => {
+                    let expected_len = if self.chip.begin_has_encrypted_flag() {
+                        20
+                    } else {
+                        16
+                    };
+                    if data.len() != expected_len {
+                        return self.reply(op, 0, &[], 0x05);
+                    }
+                    let (erase, offset) = (word(0) as usize, word(3) as usize);
+                    self.flash[offset..offset + erase].fill(0xFF);
+                    self.region = Some((offset, 0));
+                    self.reply(op, 0, &[], 0)
+                }
+                FLASH_DATA => {
+                    let (offset, seq) = self.region.unwrap();
+                    let block = &data[16..];
+                    if self.reject_data || word(1) != seq || data_checksum(block) != checksum {
+                        return self.reply(op, 0, &[], 0x07);
+                    }
+                    let start = offset + seq as usize * FLASH_BLOCK;
+                    self.flash[start..start + block.len()].copy_from_slice(block);
+                    if self.corrupt_writes {
+                        self.flash[start] ^= 0x80;
+                    }
+                    self.region = Some((offset, seq + 1));
+                    self.reply(op, 0, &[], 0)
+                }
+                SPI_FLASH_MD5 => {
+                    let (address, size) = (word(0) as usize, word(1) as usize);
+                    let digest = hex(md5(&self.flash[address..address + size]));
+                    self.reply(op, 0, digest.as_bytes(), 0)
+                }
+                FLASH_END => {
+                    self.rebooted = word(0) == 0;
+                    self.reply(op, 0, &[], 0)
+                }
+                _ => self.reply(op, 0, &[], 0x05),
+            }
+        }
+    }
+
+    impl Protocol for SimRom {
+        fn start(&mut self) -> Step {
+            Step::wait(Vec::new(), u32::MAX)
+        }
+
+        fn receive(&mut self, data: &[u8]) -> Step {
+            let mut out = Vec::new();
+            for frame in self.decoder.feed(data) {
+                out.extend(self.handle(&frame));
+            }
+            Step::wait(out, u32::MAX)
+        }
+
+        fn timeout(&mut self) -> Step {
+            Step::done(Vec::new())
+        }
+
+        fn progress(&self) -> Progress {
+            Progress::default()
+        }
+    }
+
+    fn image() -> Vec<Segment> {
+        vec![
+            Segment {
+                address: 0x1000,
+                data: vec![0xE9, 0x03, 0x02, 0x20, 0xC0, 0xDB],
+            },
+            Segment {
+                address: 0x10000,
+                data: (0..3000).map(|i| (i % 251) as u8).collect(),
+            },
+        ]
+    }
+
+    #[test]
+    fn test_md5_vectors() {
+        assert_eq!(hex(md5(b"")), "d41d8cd98f00b204e9800998ecf8427e");
+        assert_eq!(hex(md5(b"abc")), "900150983cd24fb0d6963f7d28e17f72");
+        let long =
+            b"12345678901234567890123456789012345678901234567890123456789012345678901234567890";
+        assert_eq!(hex(md5(long)), "57edf4a22be3c955ac49da2e2107b67a");
+    }
+
+    #[test]
+    fn test_slip_round_trip() {
+        let packet = [0x01, SLIP_END, 0x02, SLIP_ESC, 0x03];
+        let mut wire = b"boot log\r\n".to_vec();
+        wire.extend(slip_encode(&packet));
+        let mut decoder = SlipDecoder::default();
+        let (first, second) = wire.split_at(wire.len() - 3);
+        assert!(decoder.feed(first).is_empty());
+        assert_eq!(decoder.feed(second), vec![packet.to_vec()]);
+    }
+
+    #[test]
+    fn test_flash_esp32() {
+        let mut flasher = Flasher::new(image(), EspOptions::default());
+        let mut rom = SimRom::new(Chip::Esp32, 0x00F0_1D83);
+        let (state, _) = loopback::run(&mut flasher, &mut rom, |_, _| {});
+        assert_eq!(state, StepState::Done);
+        assert_eq!(flasher.chip(), Some(Chip::Esp32));
+        assert_eq!(&rom.flash[0x1000..0x1006], &image()[0].data[..]);
+        // Padded to a word, the rest of the sector erased
+        assert_eq!(&rom.flash[0x1006..0x1009], &[0xFF; 3]);
+        assert_eq!(&rom.flash[0x10000..0x10000 + 3000], &image()[1].data[..]);
+        assert_eq!(rom.baud_rate, Some(460_800));
+        assert_eq!(flasher.take_baud_rate(), Some(460_800));
+        assert!(rom.rebooted);
+        assert_eq!(flasher.progress().bytes, 3008);
+    }
+
+    #[test]
+    fn test_flash_esp32c3_without_baud_change() {
+        let options = EspOptions {
+            baud_rate: None,
+            verify: true,
+            reboot: false,
+        };
+        let mut flasher = Flasher::new(image(), options);
+        let mut rom = SimRom::new(Chip::Esp32C3, 0x1B31_506F);
+        let (state, _) = loopback::run(&mut flasher, &mut rom, |_, _| {});
+        assert_eq!(state, StepState::Done);
+        assert_eq!(flasher.chip(), Some(Chip::Esp32C3));
+        assert_eq!(rom.baud_rate, None);
+        assert!(!rom.rebooted);
+    }
+
+    #[test]
+    fn test_md5_mismatch_and_rom_errors() {
+        let mut flasher = Flasher::new(image(), EspOptions::default());
+        let mut rom = SimRom::new(Chip::Esp32S3, 0x9);
+        rom.corrupt_writes = true;
+        let (state, _) = loopback::run(&mut flasher, &mut rom, |_, _| {});
+        assert_eq!(
+            state,
+            StepState::Failed("MD5 mismatch for the region at 0x1000".into())
+        );
+
+        let mut flasher = Flasher::new(image(), EspOptions::default());
+        let mut rom = SimRom::new(Chip::Esp32, 0x00F0_1D83);
+        rom.reject_data = true;
+        let (state, _) = loopback::run(&mut flasher, &mut rom, |_, _| {});
+        assert_eq!(
+            state,
+            StepState::Failed("The ROM reported invalid CRC (0x07)".into())
+        );
+
+        let mut flasher = Flasher::new(image(), EspOptions::default());
+        let mut rom = SimRom::new(Chip::Esp32, 0x1234_5678);
+        let (state, _) = loopback::run(&mut flasher, &mut rom, |_, _| {});
+        assert_eq!(
+            state,
+            StepState::Failed("Unknown chip (magic 0x12345678)".into())
+        );
+    }
+}
@@ -0,0 +1,265 @@
+//! Firmware images for the flashers: Intel HEX, Motorola S-record and raw
+//! binary files, turned into contiguous memory segments
+
+/// Bytes to be written from `address` on
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub address: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ImageFormat {
+    IntelHex,
+    SRecord,
+    Binary,
+}
+
+impl ImageFormat {
+    /// Guesses the format from the file extension, then from the content
+    pub fn detect(name: &str, bytes: &[u8]) -> Self {
+        let ext = name.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("hex" | "ihex" | "ihx") => return Self::IntelHex,
+            Some("s19" | "s28" | "s37" | "srec" | "mot") => return Self::SRecord,
+            Some("bin") => return Self::Binary,
+            _ => {}
+        }
+        let start = bytes.trim_ascii_start();
+        if start.starts_with(b":") && start.is_ascii() {
+            Self::IntelHex
+        } else if start.len() > 1
+            && start[0] == b'S'
+            && start[1].is_ascii_digit()
+            && start.is_ascii()
+        {
+            Self::SRecord
+        } else {
+            Self::Binary
+        }
+    }
+}
+
+/// Reads an image; raw binaries are placed at `base`
+pub fn parse_image(name: &str, bytes: &[u8], base: u32) -> Result<Vec<Segment>, String> {
+    let chunks = match ImageFormat::detect(name, bytes) {
+        ImageFormat::Binary => vec![(base, bytes.to_vec())],
+        ImageFormat::IntelHex => parse_intel_hex(text(bytes)?)?,
+        ImageFormat::SRecord => parse_srecord(text(bytes)?)?,
+    };
+    let segments = merge(chunks)?;
+    if segments.is_empty() {
+        return Err("The image contains no data".to_string());
+    }
+    Ok(segments)
+}
+
+/// Combines the segments of several images, e.g. binaries at different offsets
+pub fn combine(images: Vec<Vec<Segment>>) -> Result<Vec<Segment>, String> {
+    let chunks = images
+        .into_iter()
+        .flatten()
+        .map(|s| (s.address, s.data))
+        .collect();
+    merge(chunks)
+}
+
+/// Total number of data bytes
+pub fn image_size(segments: &[Segment]) -> u64 {
+    segments.iter().map(|s| s.data.len() as u64).sum()
+}
+
+fn text(bytes: &[u8]) -> Result<&str, String> {
+    std::str::from_utf8(bytes).map_err(|_| "The image is not a text file".to_string())
+}
+
+/// Hex digits of a record, without its type prefix
+fn record_bytes(hex: &str, line: usize) -> Result<Vec<u8>, String> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(format!("Line {}: malformed record", line));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Line {}: malformed record", line))
+}
+
+fn parse_intel_hex(text: &str) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let mut chunks = Vec::new();
+    let mut upper = 0u32;
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let n = i + 1;
+        let hex = line
+            .strip_prefix(':')
+            .ok_or_else(|| format!("Line {}: missing ':'", n))?;
+        let bytes = record_bytes(hex, n)?;
+        if bytes.len() < 5 || bytes.len() != bytes[0] as usize + 5 {
+            return Err(format!("Line {}: wrong record length", n));
+        }
+        if bytes.iter().fold(0u8, |a, &b| a.wrapping_add(b)) != 0 {
+            return Err(format!("Line {}: bad checksum", n));
+        }
+        let offset = u16::from_be_bytes([bytes[1], bytes[2]]) as u32;
+        let data = &bytes[4..bytes.len() - 1];
+        match bytes[3] {
+            0x00 => chunks.push((upper.wrapping_add(offset), data.to_vec())),
+            0x01 => break,
+            0x02 if data.len() == 2 => upper = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4,
+            0x04 if data.len() == 2 => {
+                upper = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16
+            }
+            // Start addresses
+            0x03 | 0x05 => {}
+            ty => return Err(format!("Line {}: unsupported record type {:02X}", n, ty)),
+        }
+    }
+    Ok(chunks)
+}
+
+fn parse_srecord(text: &str) -> Result<Vec<(u32, Vec<u8>)>, String> {
+    let mut chunks = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let n = i + 1;
+        let (ty, hex) = line
+            .strip_prefix('S')
+            .and_then(|rest| rest.split_at_checked(1))
+            .ok_or_else(|| format!("Line {}: missing 'S'", n))?;
+        let bytes = record_bytes(hex, n)?;
+        if bytes.is_empty() || bytes.len() != bytes[0] as usize + 1 {
+            return Err(format!("Line {}: wrong record length", n));
+        }
+        if bytes.iter().fold(0u8, |a, &b| a.wrapping_add(b)) != 0xFF {
+            return Err(format!("Line {}: bad checksum", n));
+        }
+        let address_len = match ty {
+            "1" => 2,
+            "2" => 3,
+            "3" => 4,
+            // Header, record counts and start addresses
+            "0" | "5" | "6" | "7" | "8" | "9" => continue,
+            _ => return Err(format!("Line {}: unsupported record type S{}", n, ty)),
+        };
+        let body = &bytes[1..bytes.len() - 1];
+        if body.len() < address_len {
+            return Err(format!("Line {}: wrong record length", n));
+        }
+        let address = body[..address_len]
+            .iter()
+            .fold(0u32, |a, &b| (a << 8) | b as u32);
+        chunks.push((address, body[address_len..].to_vec()));
+    }
+    Ok(chunks)
+}
+
+/// Sorts records by address and joins adjacent ones
+fn merge(mut chunks: Vec<(u32, Vec<u8>)>) -> Result<Vec<Segment>, String> {
+    chunks.retain(|(_, data)| !data.is_empty());
+    chunks.sort_by_key(|(address, _)| *address);
+    let mut segments: Vec<Segment> = Vec::new();
+    for (address, data) in chunks {
+        if let Some(last) = segments.last_mut() {
+            let end = last.address as u64 + last.data.len() as u64;
+            if (address as u64) < end {
+                return Err(format!("Overlapping data at 0x{:08X}", address));
+            }
+            if address as u64 == end {
+                last.data.extend(data);
+                continue;
+            }
+        }
+        segments.push(Segment { address, data });
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intel_hex() {
+        let hex = ":020000040800F2\n\
+                   :0400000001020304F2\n\
+                   :0400040005060708DE\n\
+                   :02001000AABB89\n\
+                   :04000005080001C12D\n\
+                   :00000001FF\n";
+        let segments = parse_image("fw.hex", hex.as_bytes(), 0).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    address: 0x0800_0000,
+                    data: vec![1, 2, 3, 4, 5, 6, 7, 8]
+                },
+                Segment {
+                    address: 0x0800_0010,
+                    data: vec![0xAA, 0xBB]
+                },
+            ]
+        );
+        let bad = hex.replace("F2\n:04", "F3\n:04");
+        assert!(parse_image("fw.hex", bad.as_bytes(), 0)
+            .unwrap_err()
+            .contains("checksum"));
+    }
+
+    #[test]
+    fn test_srecord() {
+        let srec = "S00600004844521B\n\
+                    S3090800000001020304E4\n\
+                    S1050010AABB85\n\
+                    S70508000000F2\n";
+        let segments = parse_image("fw.s37", srec.as_bytes(), 0).unwrap();
+        assert_eq!(
+            segments,
+            vec![
+                Segment {
+                    address: 0x10,
+                    data: vec![0xAA, 0xBB]
+                },
+                Segment {
+                    address: 0x0800_0000,
+                    data: vec![1, 2, 3, 4]
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_binary_and_detection() {
+        let segments = parse_image("fw.bin", b":S1", 0x0800_0000).unwrap();
+        assert_eq!(segments[0].address, 0x0800_0000);
+        assert_eq!(segments[0].data, b":S1");
+        assert_eq!(
+            ImageFormat::detect("fw", b"\n:00000001FF"),
+            ImageFormat::IntelHex
+        );
+        assert_eq!(
+            ImageFormat::detect("fw", b"S00600004844521B"),
+            ImageFormat::SRecord
+        );
+        assert_eq!(
+            ImageFormat::detect("fw", &[0x00, 0x20, 0x00, 0x20]),
+            ImageFormat::Binary
+        );
+        assert!(parse_image("fw.bin", b"", 0).is_err());
+
+        let app = parse_image("app.bin", &[1, 2], 0x10000).unwrap();
+        let boot = parse_image("boot.bin", &[3], 0x1000).unwrap();
+        let combined = combine(vec![app.clone(), boot]).unwrap();
+        assert_eq!(combined[0].address, 0x1000);
+        assert!(combine(vec![app.clone(), app])
+            .unwrap_err()
+            .contains("Overlapping"));
+    }
+}
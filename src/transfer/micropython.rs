@@ -0,0 +1,761 @@
+//! MicroPython raw REPL: runs code on the device like mpremote does. Code is
+//! sent in raw-paste mode (with flow control) when the firmware supports it,
+//! otherwise in plain raw mode. File operations are small scripts whose
+//! output is parsed here.
+
+use super::{Progress, Protocol, Step};
+
+const CTRL_A: u8 = 0x01;
+const CTRL_B: u8 = 0x02;
+const CTRL_C: u8 = 0x03;
+const CTRL_D: u8 = 0x04;
+const CTRL_E: u8 = 0x05;
+
+const RAW_BANNER: &[u8] = b"raw REPL; CTRL-B to exit\r\n>";
+const RAW_PASTE_REQUEST: &[u8] = &[CTRL_E, b'A', CTRL_A];
+
+/// Wait after Ctrl-C for a running program to stop
+const INTERRUPT_MS: u32 = 200;
+const PROMPT_TIMEOUT_MS: u32 = 2000;
+/// File operations finish quickly; scripts may run until stopped
+const CAPTURE_TIMEOUT_MS: u32 = 10_000;
+const SOFT_RESET_TIMEOUT_MS: u32 = 5000;
+/// Plain raw mode has no flow control, so code goes out in small pieces
+const RAW_CHUNK: usize = 256;
+const RAW_CHUNK_DELAY_MS: u32 = 10;
+/// Bytes of a file written per exec
+const UPLOAD_CHUNK: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Runs code; with `echo` its output is shown as it arrives, otherwise it
+    /// is kept for the caller
+    Exec {
+        code: String,
+        echo: bool,
+    },
+    SoftReset,
+}
+
+impl Action {
+    pub fn exec(code: impl Into<String>) -> Self {
+        Action::Exec {
+            code: code.into(),
+            echo: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Interrupt,
+    EnterRaw,
+    /// Raw-paste was requested, the device answers whether it supports it
+    PasteProbe,
+    PasteSend,
+    /// All code sent, waiting for the device to acknowledge the end
+    PasteEnd,
+    RawSend,
+    RawOk,
+    Stdout,
+    Stderr,
+    Prompt,
+    SoftReset,
+    Done,
+}
+
+/// Runs `actions` in one raw REPL session and returns to the friendly REPL
+pub struct Session {
+    actions: Vec<Action>,
+    stage: Stage,
+    rx: Vec<u8>,
+    /// Action in progress
+    current: usize,
+    code: Vec<u8>,
+    sent: usize,
+    window: usize,
+    window_left: usize,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+    /// Output of echoed actions not yet taken
+    echo: Vec<u8>,
+    captured: Vec<Vec<u8>>,
+    raw_paste: Option<bool>,
+    /// Code bytes sent over all actions, and the total to send
+    bytes_sent: u64,
+    bytes_total: u64,
+}
+
+impl Session {
+    pub fn new(actions: Vec<Action>) -> Self {
+        let bytes_total = actions
+            .iter()
+            .map(|a| match a {
+                Action::Exec { code, .. } => code.len() as u64,
+                Action::SoftReset => 0,
+            })
+            .sum();
+        Self {
+            actions,
+            stage: Stage::Interrupt,
+            rx: Vec::new(),
+            current: 0,
+            code: Vec::new(),
+            sent: 0,
+            window: 0,
+            window_left: 0,
+            stdout: Vec::new(),
+            stderr: Vec::new(),
+            echo: Vec::new(),
+            captured: Vec::new(),
+            raw_paste: None,
+            bytes_sent: 0,
+            bytes_total,
+        }
+    }
+
+    /// Output of each exec action so far, in order
+    pub fn outputs(&self) -> &[Vec<u8>] {
+        &self.captured
+    }
+
+    /// Output of echoed actions since the last call
+    pub fn take_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.echo)
+    }
+
+    fn echoing(&self) -> bool {
+        matches!(
+            self.actions.get(self.current),
+            Some(Action::Exec { echo: true, .. })
+        )
+    }
+
+    fn output_timeout(&self) -> u32 {
+        if self.echoing() {
+            u32::MAX
+        } else {
+            CAPTURE_TIMEOUT_MS
+        }
+    }
+
+    /// Removes `rx` up to and including `pattern`, if it has arrived
+    fn take_through(&mut self, pattern: &[u8]) -> Option<Vec<u8>> {
+        let end = self.rx.windows(pattern.len()).position(|w| w == pattern)? + pattern.len();
+        Some(self.rx.drain(..end).collect())
+    }
+
+    fn next_action(&mut self) -> Step {
+        let Some(action) = self.actions.get(self.current).cloned() else {
+            self.stage = Stage::Done;
+            return Step::done(vec![CTRL_B]);
+        };
+        match action {
+            Action::Exec { code, .. } => {
+                self.code = code.into_bytes();
+                self.sent = 0;
+                self.stdout.clear();
+                self.stderr.clear();
+                if self.raw_paste == Some(false) {
+                    return self.send_raw();
+                }
+                self.stage = Stage::PasteProbe;
+                Step::wait(RAW_PASTE_REQUEST.to_vec(), PROMPT_TIMEOUT_MS)
+            }
+            Action::SoftReset => {
+                self.stage = Stage::SoftReset;
+                Step::wait(vec![CTRL_D], SOFT_RESET_TIMEOUT_MS)
+            }
+        }
+    }
+
+    /// Sends as much code as the window allows
+    fn send_paste(&mut self) -> Step {
+        let n = self.window_left.min(self.code.len() - self.sent);
+        let mut out = self.code[self.sent..self.sent + n].to_vec();
+        self.sent += n;
+        self.bytes_sent += n as u64;
+        self.window_left -= n;
+        if self.sent == self.code.len() {
+            out.push(CTRL_D);
+            self.stage = Stage::PasteEnd;
+        }
+        Step::wait(out, PROMPT_TIMEOUT_MS)
+    }
+
+    fn send_raw(&mut self) -> Step {
+        self.stage = Stage::RawSend;
+        let n = RAW_CHUNK.min(self.code.len() - self.sent);
+        let mut out = self.code[self.sent..self.sent + n].to_vec();
+        self.sent += n;
+        self.bytes_sent += n as u64;
+        if self.sent < self.code.len() {
+            return Step::wait(out, RAW_CHUNK_DELAY_MS);
+        }
+        out.push(CTRL_D);
+        self.stage = Stage::RawOk;
+        Step::wait(out, PROMPT_TIMEOUT_MS)
+    }
+
+    /// Finishes the exec once stdout, stderr and the prompt are in
+    fn finish_exec(&mut self) -> Step {
+        if self.echoing() {
+            self.echo.extend_from_slice(&self.stderr);
+        }
+        if !self.stderr.is_empty() {
+            let text = String::from_utf8_lossy(&self.stderr);
+            let reason = text
+                .lines()
+                .rfind(|l| !l.trim().is_empty())
+                .unwrap_or("Error");
+            // Leave the raw REPL before giving up
+            return Step::failed(vec![CTRL_B], reason.trim());
+        }
+        self.captured.push(std::mem::take(&mut self.stdout));
+        self.current += 1;
+        self.next_action()
+    }
+
+    fn step(&mut self) -> Option<Step> {
+        match self.stage {
+            Stage::Interrupt | Stage::RawSend | Stage::Done => None,
+            Stage::EnterRaw => {
+                self.take_through(RAW_BANNER)?;
+                Some(self.next_action())
+            }
+            Stage::PasteProbe => {
+                if self.rx.starts_with(b"R\x01") && self.rx.len() >= 4 {
+                    self.window = u16::from_le_bytes([self.rx[2], self.rx[3]]) as usize;
+                    self.window_left = self.window;
+                    self.rx.drain(..4);
+                    self.raw_paste = Some(true);
+                    self.stage = Stage::PasteSend;
+                    return Some(self.send_paste());
+                }
+                if self.rx.starts_with(b"R\x00") {
+                    self.rx.drain(..2);
+                } else {
+                    // Firmware without raw-paste repeats the banner instead
+                    self.take_through(RAW_BANNER)?;
+                }
+                self.raw_paste = Some(false);
+                Some(self.send_raw())
+            }
+            Stage::PasteSend => {
+                let b = *self.rx.first()?;
+                self.rx.remove(0);
+                match b {
+                    CTRL_A => {
+                        self.window_left += self.window;
+                        Some(self.send_paste())
+                    }
+                    // The device ends the paste early, e.g. on a syntax error
+                    CTRL_D => {
+                        self.stage = Stage::PasteEnd;
+                        Some(Step::wait(vec![CTRL_D], PROMPT_TIMEOUT_MS))
+                    }
+                    _ => Some(Step::wait(Vec::new(), PROMPT_TIMEOUT_MS)),
+                }
+            }
+            Stage::PasteEnd => {
+                // Window updates may still come before the acknowledgement
+                let end = self.rx.iter().position(|&b| b == CTRL_D)?;
+                self.rx.drain(..=end);
+                self.stage = Stage::Stdout;
+                Some(Step::wait(Vec::new(), self.output_timeout()))
+            }
+            Stage::RawOk => {
+                if self.rx.len() < 2 {
+                    return None;
+                }
+                if !self.rx.starts_with(b"OK") {
+                    return Some(Step::failed(
+                        vec![CTRL_B],
+                        "The raw REPL did not accept the code",
+                    ));
+                }
+                self.rx.drain(..2);
+                self.stage = Stage::Stdout;
+                Some(Step::wait(Vec::new(), self.output_timeout()))
+            }
+            Stage::Stdout => {
+                let end = self.rx.iter().position(|&b| b == CTRL_D);
+                let chunk: Vec<u8> = self.rx.drain(..end.unwrap_or(self.rx.len())).collect();
+                if self.echoing() {
+                    self.echo.extend_from_slice(&chunk);
+                }
+                self.stdout.extend(chunk);
+                end?;
+                self.rx.remove(0);
+                self.stage = Stage::Stderr;
+                Some(Step::wait(Vec::new(), self.output_timeout()))
+            }
+            Stage::Stderr => {
+                let end = self.rx.iter().position(|&b| b == CTRL_D)?;
+                self.stderr.extend(self.rx.drain(..end));
+                self.rx.remove(0);
+                self.stage = Stage::Prompt;
+                Some(Step::wait(Vec::new(), PROMPT_TIMEOUT_MS))
+            }
+            Stage::Prompt => {
+                self.take_through(b">")?;
+                Some(self.finish_exec())
+            }
+            Stage::SoftReset => {
+                self.take_through(RAW_BANNER)?;
+                self.captured.push(Vec::new());
+                self.current += 1;
+                Some(self.next_action())
+            }
+        }
+    }
+}
+
+impl Protocol for Session {
+    fn start(&mut self) -> Step {
+        // Stop a running program, twice in case it catches the first
+        Step::wait(b"\r\x03\r\x03".to_vec(), INTERRUPT_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        self.rx.extend_from_slice(data);
+        let mut last = None;
+        while let Some(step) = self.step() {
+            let idle = step.send.is_empty() && matches!(step.state, super::StepState::Wait(_));
+            last = Some(step);
+            if !idle || self.rx.is_empty() {
+                break;
+            }
+        }
+        match last {
+            Some(step) => step,
+            None => Step::wait(Vec::new(), self.wait_ms()),
+        }
+    }
+
+    fn timeout(&mut self) -> Step {
+        match self.stage {
+            Stage::Interrupt => {
+                self.rx.clear();
+                self.stage = Stage::EnterRaw;
+                Step::wait(vec![b'\r', CTRL_A], PROMPT_TIMEOUT_MS)
+            }
+            Stage::RawSend => self.send_raw(),
+            Stage::EnterRaw => Step::failed(
+                Vec::new(),
+                "No raw REPL prompt, is MicroPython running on the port?",
+            ),
+            Stage::SoftReset => {
+                Step::failed(vec![CTRL_B], "The device did not come back after the reset")
+            }
+            _ => Step::failed(vec![CTRL_C, CTRL_B], "The device stopped answering"),
+        }
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        vec![CTRL_C, CTRL_C, CTRL_B]
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.bytes_sent,
+            total: Some(self.bytes_total),
+            retries: 0,
+        }
+    }
+}
+
+impl Session {
+    fn wait_ms(&self) -> u32 {
+        match self.stage {
+            Stage::Interrupt => INTERRUPT_MS,
+            Stage::RawSend => RAW_CHUNK_DELAY_MS,
+            Stage::Stdout | Stage::Stderr => self.output_timeout(),
+            Stage::SoftReset => SOFT_RESET_TIMEOUT_MS,
+            _ => PROMPT_TIMEOUT_MS,
+        }
+    }
+}
+
+/// A path as a Python string literal
+fn quote(path: &str) -> String {
+    format!("'{}'", path.replace('\\', "\\\\").replace('\'', "\\'"))
+}
+
+/// Joins a directory and a name the way the device expects
+pub fn join_path(dir: &str, name: &str) -> String {
+    if dir.ends_with('/') {
+        format!("{}{}", dir, name)
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// A directory entry on the device
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+}
+
+pub fn list_dir(path: &str) -> Action {
+    Action::exec(format!(
+        "import os\nfor e in os.ilistdir({}):\n print('d' if e[1] & 0x4000 else 'f', e[3] if len(e) > 3 else 0, e[0])\n",
+        quote(path)
+    ))
+}
+
+/// Parses the output of `list_dir`, directories first
+pub fn parse_list(output: &[u8]) -> Vec<Entry> {
+    let text = String::from_utf8_lossy(output);
+    let mut entries: Vec<Entry> = text
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let kind = parts.next()?;
+            let size = parts.next()?.parse().ok()?;
+            let name = parts.next()?.to_string();
+            Some(Entry {
+                name,
+                is_dir: kind == "d",
+                size,
+            })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    entries
+}
+
+/// Writes `data` to `path`, a chunk per exec to fit small heaps
+pub fn upload(path: &str, data: &[u8]) -> Vec<Action> {
+    let mut actions = vec![Action::exec(format!(
+        "f = open({}, 'wb')\nw = f.write",
+        quote(path)
+    ))];
+    for chunk in data.chunks(UPLOAD_CHUNK) {
+        let mut literal = String::from("w(b'");
+        for &b in chunk {
+            match b {
+                b'\'' | b'\\' => literal.push_str(&format!("\\x{:02x}", b)),
+                0x20..=0x7E => literal.push(b as char),
+                _ => literal.push_str(&format!("\\x{:02x}", b)),
+            }
+        }
+        literal.push_str("')");
+        actions.push(Action::exec(literal));
+    }
+    actions.push(Action::exec("f.close()"));
+    actions
+}
+
+/// Prints the file at `path` as lines of hex
+pub fn download(path: &str) -> Action {
+    Action::exec(format!(
+        "import binascii\nf = open({}, 'rb')\nwhile True:\n b = f.read(256)\n if not b:\n  break\n print(binascii.hexlify(b).decode())\nf.close()\n",
+        quote(path)
+    ))
+}
+
+/// Decodes the output of `download`
+pub fn parse_download(output: &[u8]) -> Result<Vec<u8>, String> {
+    let text = String::from_utf8_lossy(output);
+    let mut data = Vec::new();
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        data.extend(
+            crate::utils::parse_hex_string(line).map_err(|_| "Malformed file data".to_string())?,
+        );
+    }
+    Ok(data)
+}
+
+pub fn remove(path: &str, is_dir: bool) -> Action {
+    let call = if is_dir { "rmdir" } else { "remove" };
+    Action::exec(format!("import os\nos.{}({})", call, quote(path)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::{loopback, StepState};
+    use std::collections::HashMap;
+
+    /// A MicroPython device that understands the snippets built above
+    struct SimDevice {
+        rx: Vec<u8>,
+        raw: bool,
+        paste_support: bool,
+        window: u16,
+        /// Code of the exec being received, and whether it came via raw-paste
+        code: Option<(Vec<u8>, bool)>,
+        files: HashMap<String, Vec<u8>>,
+        open_file: Option<String>,
+        soft_resets: u32,
+        execs: u32,
+    }
+
+    impl SimDevice {
+        fn new(paste_support: bool) -> Self {
+            Self {
+                rx: Vec::new(),
+                raw: false,
+                paste_support,
+                window: 32,
+                code: None,
+                files: HashMap::new(),
+                open_file: None,
+                soft_resets: 0,
+                execs: 0,
+            }
+        }
+
+        /// Runs the code: (stdout, stderr)
+        fn run(&mut self, code: &str) -> (String, String) {
+            self.execs += 1;
+            let arg = |code: &str| {
+                let start = code.find('\'').unwrap() + 1;
+                let end = code[start..].find('\'').unwrap() + start;
+                code[start..end].to_string()
+            };
+            if code.starts_with("print(") {
+                return (format!("{}\r\n", arg(code)), String::new());
+            }
+            if code.starts_with("raise") {
+                return (
+                    String::new(),
+                    "Traceback (most recent call last):\r\nValueError: boom\r\n".into(),
+                );
+            }
+            if code.contains("ilistdir") {
+                let mut out = String::new();
+                for (name, data) in &self.files {
+                    out.push_str(&format!(
+                        "f {} {}\r\n",
+                        data.len(),
+                        name.trim_start_matches('/')
+                    ));
+                }
+                return (out, String::new());
+            }
+            if code.starts_with("f = open(") {
+                let path = arg(code);
+                self.files.insert(path.clone(), Vec::new());
+                self.open_file = Some(path);
+                return (String::new(), String::new());
+            }
+            if let Some(literal) = code.strip_prefix("w(b'").and_then(|c| c.strip_suffix("')")) {
+                let mut data = Vec::new();
+                let mut rest = literal.as_bytes();
+                while let Some((&b, tail)) = rest.split_first() {
+                    if b == b'\\' {
+                        data.push(
+                            u8::from_str_radix(std::str::from_utf8(&tail[1..3]).unwrap(), 16)
+                                .unwrap(),
+                        );
+                        rest = &tail[3..];
+                    } else {
+                        data.push(b);
+                        rest = tail;
+                    }
+                }
+                let path = self.open_file.clone().unwrap();
+                self.files.get_mut(&path).unwrap().extend(data);
+                return (String::new(), String::new());
+            }
+            if code == "f.close()" {
+                self.open_file = None;
+                return (String::new(), String::new());
+            }
+            if code.contains("hexlify") {
+                return match self.files.get(&arg(code)) {
+                    Some(data) => {
+                        let lines: String = data
+                            .chunks(256)
+                            .map(|c| {
+                                c.iter().map(|b| format!("{:02x}", b)).collect::<String>() + "\r\n"
+                            })
+                            .collect();
+                        (lines, String::new())
+                    }
+                    None => (
+                        String::new(),
+                        "Traceback:\r\nOSError: [Errno 2] ENOENT\r\n".into(),
+                    ),
+                };
+            }
+            if code.contains("os.remove(") {
+                self.files.remove(&arg(code));
+                return (String::new(), String::new());
+            }
+            (String::new(), format!("NameError: {}\r\n", code))
+        }
+
+        fn reply_exec(&mut self, out: &mut Vec<u8>, code: &[u8], paste: bool) {
+            let (stdout, stderr) = self.run(std::str::from_utf8(code).unwrap());
+            if !paste {
+                out.extend(b"OK");
+            } else {
+                out.push(CTRL_D);
+            }
+            out.extend(stdout.as_bytes());
+            out.push(CTRL_D);
+            out.extend(stderr.as_bytes());
+            out.push(CTRL_D);
+            out.push(b'>');
+        }
+    }
+
+    impl Protocol for SimDevice {
+        fn start(&mut self) -> Step {
+            Step::wait(b"MicroPython v1.22\r\n>>> ".to_vec(), u32::MAX)
+        }
+
+        fn receive(&mut self, data: &[u8]) -> Step {
+            let mut out = Vec::new();
+            for &b in data {
+                if let Some((code, paste)) = self.code.as_mut() {
+                    if b == CTRL_D {
+                        let (code, paste) = self.code.take().unwrap();
+                        self.reply_exec(&mut out, &code, paste);
+                        continue;
+                    }
+                    code.push(b);
+                    // Grant another window once the current one is used up
+                    if *paste && code.len() % self.window as usize == 0 {
+                        out.push(CTRL_A);
+                    }
+                    continue;
+                }
+                self.rx.push(b);
+                if !self.raw {
+                    if self.rx.ends_with(&[CTRL_A]) {
+                        self.raw = true;
+                        self.rx.clear();
+                        out.extend(b"raw REPL; CTRL-B to exit\r\n>");
+                    }
+                    continue;
+                }
+                if self.rx.ends_with(RAW_PASTE_REQUEST) {
+                    self.rx.clear();
+                    if self.paste_support {
+                        out.extend(b"R\x01");
+                        out.extend(self.window.to_le_bytes());
+                        self.code = Some((Vec::new(), true));
+                    } else {
+                        out.extend(b"R\x00");
+                        self.code = Some((Vec::new(), false));
+                    }
+                } else if self.rx == [CTRL_D] {
+                    self.rx.clear();
+                    self.soft_resets += 1;
+                    out.extend(b"OK\r\nMPY: soft reboot\r\nraw REPL; CTRL-B to exit\r\n>");
+                } else if self.rx == [CTRL_B] {
+                    self.rx.clear();
+                    self.raw = false;
+                    out.extend(b"\r\n>>> ");
+                } else if !self.rx.is_empty() && !RAW_PASTE_REQUEST.starts_with(&self.rx) {
+                    // The start of plain raw code
+                    let code = std::mem::take(&mut self.rx);
+                    self.code = Some((code, false));
+                }
+            }
+            Step::wait(out, u32::MAX)
+        }
+
+        fn timeout(&mut self) -> Step {
+            Step::done(Vec::new())
+        }
+
+        fn progress(&self) -> Progress {
+            Progress::default()
+        }
+    }
+
+    fn run_script(device: &mut SimDevice, actions: Vec<Action>) -> (Session, StepState) {
+        let mut session = Session::new(actions);
+        let (state, _) = loopback::run(&mut session, device, |_, _| {});
+        (session, state)
+    }
+
+    #[test]
+    fn test_run_script_with_raw_paste() {
+        let mut device = SimDevice::new(true);
+        let long = format!("print('{}')", "x".repeat(100));
+        let actions = vec![
+            Action::Exec {
+                code: long,
+                echo: true,
+            },
+            Action::exec("print('hi')"),
+        ];
+        let (mut session, state) = run_script(&mut device, actions);
+        assert_eq!(state, StepState::Done);
+        assert_eq!(
+            session.take_output(),
+            format!("{}\r\n", "x".repeat(100)).into_bytes()
+        );
+        assert_eq!(session.outputs()[1], b"hi\r\n");
+        assert!(!device.raw, "left the raw REPL");
+    }
+
+    #[test]
+    fn test_fallback_to_raw_mode() {
+        let mut device = SimDevice::new(false);
+        let long = format!("print('{}')", "y".repeat(600));
+        let (session, state) = run_script(&mut device, vec![Action::exec(long)]);
+        assert_eq!(state, StepState::Done);
+        assert_eq!(session.outputs()[0].len(), 602);
+    }
+
+    #[test]
+    fn test_error_and_soft_reset() {
+        let mut device = SimDevice::new(true);
+        let actions = vec![
+            Action::SoftReset,
+            Action::exec("raise ValueError('boom')"),
+            Action::exec("print('never')"),
+        ];
+        let (_, state) = run_script(&mut device, actions);
+        assert_eq!(state, StepState::Failed("ValueError: boom".into()));
+        assert_eq!(device.soft_resets, 1);
+        assert_eq!(device.execs, 1);
+        assert!(!device.raw);
+    }
+
+    #[test]
+    fn test_file_round_trip() {
+        let mut device = SimDevice::new(true);
+        let data: Vec<u8> = (0..=255u8).chain(b"it's \\ done".iter().copied()).collect();
+        let (_, state) = run_script(&mut device, upload("/main.py", &data));
+        assert_eq!(state, StepState::Done);
+        assert_eq!(device.files["/main.py"], data);
+
+        let (session, state) = run_script(&mut device, vec![list_dir("/"), download("/main.py")]);
+        assert_eq!(state, StepState::Done);
+        let entries = parse_list(&session.outputs()[0]);
+        assert_eq!(
+            entries,
+            vec![Entry {
+                name: "main.py".into(),
+                is_dir: false,
+                size: data.len() as u64
+            }]
+        );
+        assert_eq!(parse_download(&session.outputs()[1]).unwrap(), data);
+
+        let (_, state) = run_script(&mut device, vec![download("/missing.py")]);
+        assert_eq!(state, StepState::Failed("OSError: [Errno 2] ENOENT".into()));
+        let (_, state) = run_script(&mut device, vec![remove("/main.py", false)]);
+        assert_eq!(state, StepState::Done);
+        assert!(device.files.is_empty());
+    }
+
+    #[test]
+    fn test_parse_list_sorts_directories_first() {
+        let entries = parse_list(b"f 10 boot.py\r\nd 0 lib\r\nf 3 my file.txt\r\n");
+        let names: Vec<_> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["lib", "boot.py", "my file.txt"]);
+        assert_eq!(join_path("/", "a"), "/a");
+        assert_eq!(join_path("/lib", "a"), "/lib/a");
+    }
+}
@@ -0,0 +1,123 @@
+//! Protocols that take over the serial port for a while (file transfers,
+//! bootloaders). They are written sans-IO: the engine gets bytes and timeouts
+//! and answers with bytes to send, so they can be tested against simulated peers.
+
+pub mod esp;
+pub mod image;
+pub mod micropython;
+pub mod pacing;
+pub mod stm32;
+pub mod xmodem;
+pub mod zmodem;
+
+/// Reply of an engine to an event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+    pub send: Vec<u8>,
+    pub state: StepState,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StepState {
+    /// Wait for more input, at most this many milliseconds
+    Wait(u32),
+    Done,
+    Failed(String),
+}
+
+impl Step {
+    pub fn wait(send: Vec<u8>, timeout_ms: u32) -> Self {
+        Self {
+            send,
+            state: StepState::Wait(timeout_ms),
+        }
+    }
+
+    pub fn done(send: Vec<u8>) -> Self {
+        Self {
+            send,
+            state: StepState::Done,
+        }
+    }
+
+    pub fn failed(send: Vec<u8>, reason: impl Into<String>) -> Self {
+        Self {
+            send,
+            state: StepState::Failed(reason.into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Progress {
+    pub bytes: u64,
+    pub total: Option<u64>,
+    pub retries: u32,
+}
+
+/// A sans-IO protocol engine driven by the port owner
+pub trait Protocol {
+    fn start(&mut self) -> Step;
+    /// Bytes read from the port
+    fn receive(&mut self, data: &[u8]) -> Step;
+    /// Nothing arrived within the last `Wait`
+    fn timeout(&mut self) -> Step;
+    /// Bytes that abort the session on the peer's side
+    fn cancel(&mut self) -> Vec<u8> {
+        Vec::new()
+    }
+    /// A baud rate the port has to switch to before the next write
+    fn take_baud_rate(&mut self) -> Option<u32> {
+        None
+    }
+    fn progress(&self) -> Progress;
+}
+
+#[cfg(test)]
+pub mod loopback {
+    use super::*;
+
+    /// Connects two engines back to back. `wire` sees every write (`true` for
+    /// `a` to `b`) and may corrupt or drop bytes. When both sides wait, the one
+    /// with the shorter timeout times out. Returns the final states of `a` and `b`.
+    pub fn run(
+        a: &mut dyn Protocol,
+        b: &mut dyn Protocol,
+        mut wire: impl FnMut(bool, &mut Vec<u8>),
+    ) -> (StepState, StepState) {
+        let mut to_b = Vec::new();
+        let mut to_a = Vec::new();
+        let mut deliver = |from_a: bool, step: Step, queue: &mut Vec<u8>| {
+            let mut bytes = step.send;
+            wire(from_a, &mut bytes);
+            queue.extend(bytes);
+            step.state
+        };
+        let mut state_a = deliver(true, a.start(), &mut to_b);
+        let mut state_b = deliver(false, b.start(), &mut to_a);
+
+        for _ in 0..100_000 {
+            let a_waits = matches!(state_a, StepState::Wait(_));
+            let b_waits = matches!(state_b, StepState::Wait(_));
+            if !a_waits && !b_waits {
+                break;
+            }
+            if !to_b.is_empty() && b_waits {
+                let data = std::mem::take(&mut to_b);
+                state_b = deliver(false, b.receive(&data), &mut to_a);
+            } else if !to_a.is_empty() && a_waits {
+                let data = std::mem::take(&mut to_a);
+                state_a = deliver(true, a.receive(&data), &mut to_b);
+            } else {
+                match (&state_a, &state_b) {
+                    (StepState::Wait(ta), StepState::Wait(tb)) if tb < ta => {
+                        state_b = deliver(false, b.timeout(), &mut to_a)
+                    }
+                    (StepState::Wait(_), _) => state_a = deliver(true, a.timeout(), &mut to_b),
+                    _ => state_b = deliver(false, b.timeout(), &mut to_a),
+                }
+            }
+        }
+        (state_a, state_b)
+    }
+}
@@ -0,0 +1,114 @@
+//! Pacing for text files sent line by line to devices with small input
+//! buffers: delays between bytes and lines, or waiting for a prompt
+
+use crate::types::LineEnding;
+use regex::Regex;
+
+/// Received text kept while waiting for a prompt
+const PROMPT_WINDOW: usize = 4096;
+
+#[derive(Clone, Debug, Default)]
+pub struct Pacing {
+    pub byte_delay_ms: u32,
+    pub line_delay_ms: u32,
+    /// Sent lines wait until received text matches this
+    pub prompt: Option<Regex>,
+    pub prompt_timeout_ms: u32,
+}
+
+/// Splits `data` into lines ending in `ending`. With `LineEnding::None` the
+/// file's own line endings are kept. A last line without a newline stays
+/// unterminated.
+pub fn split_lines(data: &[u8], ending: LineEnding) -> Vec<Vec<u8>> {
+    let mut lines = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let Some(newline) = rest.iter().position(|&b| b == b'\n') else {
+            lines.push(rest.to_vec());
+            break;
+        };
+        let (line, tail) = rest.split_at(newline + 1);
+        rest = tail;
+        if ending == LineEnding::None {
+            lines.push(line.to_vec());
+            continue;
+        }
+        let text = line[..newline]
+            .strip_suffix(b"\r")
+            .unwrap_or(&line[..newline]);
+        let mut line = text.to_vec();
+        line.extend_from_slice(ending.as_bytes());
+        lines.push(line);
+    }
+    lines
+}
+
+/// Matches a prompt in text arriving in arbitrary chunks
+pub struct PromptWatch {
+    regex: Regex,
+    text: String,
+}
+
+impl PromptWatch {
+    pub fn new(regex: Regex) -> Self {
+        Self {
+            regex,
+            text: String::new(),
+        }
+    }
+
+    /// Forgets earlier output, e.g. before the next line is sent
+    pub fn reset(&mut self) {
+        self.text.clear();
+    }
+
+    /// Adds received bytes; true once the prompt has been seen
+    pub fn feed(&mut self, data: &[u8]) -> bool {
+        self.text.push_str(&String::from_utf8_lossy(data));
+        if self.text.len() > PROMPT_WINDOW {
+            let mut cut = self.text.len() - PROMPT_WINDOW;
+            while !self.text.is_char_boundary(cut) {
+                cut += 1;
+            }
+            self.text.drain(..cut);
+        }
+        self.regex.is_match(&self.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_lines_translates_endings() {
+        let data = b"a\r\nb\nc";
+        assert_eq!(
+            split_lines(data, LineEnding::CR),
+            vec![b"a\r".to_vec(), b"b\r".to_vec(), b"c".to_vec()]
+        );
+        assert_eq!(
+            split_lines(data, LineEnding::NLCR),
+            vec![b"a\r\n".to_vec(), b"b\r\n".to_vec(), b"c".to_vec()]
+        );
+        assert_eq!(
+            split_lines(data, LineEnding::None),
+            vec![b"a\r\n".to_vec(), b"b\n".to_vec(), b"c".to_vec()]
+        );
+        assert_eq!(
+            split_lines(b"\n\n", LineEnding::NL),
+            vec![b"\n".to_vec(); 2]
+        );
+        assert!(split_lines(b"", LineEnding::NL).is_empty());
+    }
+
+    #[test]
+    fn test_prompt_watch_across_chunks() {
+        let mut watch = PromptWatch::new(Regex::new(r"(?m)^router# $").unwrap());
+        assert!(!watch.feed(b"ok\r\nrou"));
+        assert!(watch.feed(b"ter# "));
+        watch.reset();
+        assert!(!watch.feed(b"more output\n"));
+        assert!(!watch.feed(&[0xE2; PROMPT_WINDOW * 2]));
+    }
+}
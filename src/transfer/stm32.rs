@@ -0,0 +1,629 @@
+//! STM32 system memory bootloader over UART (ST AN3155). The port has to be
+//! set to 8E1 and the chip started in bootloader mode (BOOT0 high).
+
+use super::image::{image_size, Segment};
+use super::{Progress, Protocol, Step};
+
+const ACK: u8 = 0x79;
+const NACK: u8 = 0x1F;
+const SYNC: u8 = 0x7F;
+
+const CMD_GET: u8 = 0x00;
+const CMD_GET_ID: u8 = 0x02;
+const CMD_READ: u8 = 0x11;
+const CMD_GO: u8 = 0x21;
+const CMD_WRITE: u8 = 0x31;
+const CMD_ERASE: u8 = 0x43;
+const CMD_EXTENDED_ERASE: u8 = 0x44;
+
+/// Largest block for Write Memory and Read Memory
+const BLOCK: usize = 256;
+const SYNC_TIMEOUT_MS: u32 = 500;
+const SYNC_ATTEMPTS: u32 = 10;
+const REPLY_TIMEOUT_MS: u32 = 1000;
+/// A mass erase of a large part takes tens of seconds
+const ERASE_TIMEOUT_MS: u32 = 60_000;
+
+fn command(cmd: u8) -> Vec<u8> {
+    vec![cmd, !cmd]
+}
+
+/// `bytes` followed by their XOR
+fn with_checksum(bytes: &[u8]) -> Vec<u8> {
+    let mut out = bytes.to_vec();
+    out.push(bytes.iter().fold(0, |a, b| a ^ b));
+    out
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FlashOptions {
+    /// Read every block back after writing
+    pub verify: bool,
+    /// Start the new firmware at the image's lowest address
+    pub go: bool,
+}
+
+impl Default for FlashOptions {
+    fn default() -> Self {
+        Self {
+            verify: true,
+            go: true,
+        }
+    }
+}
+
+/// The reply each stage waits for
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Sync,
+    Get,
+    GetId,
+    Erase,
+    EraseArgs,
+    Write,
+    WriteAddress,
+    WriteData,
+    Read,
+    ReadAddress,
+    ReadData,
+    Go,
+    GoAddress,
+    Done,
+}
+
+impl Stage {
+    fn name(self) -> &'static str {
+        match self {
+            Stage::Sync => "sync",
+            Stage::Get => "Get",
+            Stage::GetId => "Get ID",
+            Stage::Erase | Stage::EraseArgs => "Erase",
+            Stage::Write | Stage::WriteAddress | Stage::WriteData => "Write Memory",
+            Stage::Read | Stage::ReadAddress | Stage::ReadData => "Read Memory",
+            Stage::Go | Stage::GoAddress => "Go",
+            Stage::Done => "done",
+        }
+    }
+}
+
+/// Mass-erases the flash, writes the image, reads it back and starts it
+pub struct Flasher {
+    segments: Vec<Segment>,
+    options: FlashOptions,
+    stage: Stage,
+    rx: Vec<u8>,
+    sync_attempts: u32,
+    extended_erase: bool,
+    version: Option<u8>,
+    chip_id: Option<u16>,
+    /// Block being written or verified
+    segment: usize,
+    offset: usize,
+    done_bytes: u64,
+}
+
+impl Flasher {
+    pub fn new(segments: Vec<Segment>, options: FlashOptions) -> Self {
+        Self {
+            segments,
+            options,
+            stage: Stage::Sync,
+            rx: Vec::new(),
+            sync_attempts: 0,
+            extended_erase: false,
+            version: None,
+            chip_id: None,
+            segment: 0,
+            offset: 0,
+            done_bytes: 0,
+        }
+    }
+
+    /// Bootloader protocol version, e.g. 0x31 for 3.1
+    pub fn version(&self) -> Option<u8> {
+        self.version
+    }
+
+    /// Product ID reported by Get ID, e.g. 0x413 for STM32F40x
+    pub fn chip_id(&self) -> Option<u16> {
+        self.chip_id
+    }
+
+    fn send(&mut self, stage: Stage, bytes: Vec<u8>) -> Step {
+        self.stage = stage;
+        let timeout = if stage == Stage::EraseArgs {
+            ERASE_TIMEOUT_MS
+        } else {
+            REPLY_TIMEOUT_MS
+        };
+        Step::wait(bytes, timeout)
+    }
+
+    fn block(&self) -> (u32, &[u8]) {
+        let segment = &self.segments[self.segment];
+        let end = (self.offset + BLOCK).min(segment.data.len());
+        (
+            segment.address + self.offset as u32,
+            &segment.data[self.offset..end],
+        )
+    }
+
+    /// Moves to the next block; false after the last one
+    fn next_block(&mut self) -> bool {
+        let len = self.block().1.len();
+        self.done_bytes += len as u64;
+        self.offset += len;
+        if self.offset >= self.segments[self.segment].data.len() {
+            self.segment += 1;
+            self.offset = 0;
+        }
+        self.segment < self.segments.len()
+    }
+
+    fn start_verify_or_go(&mut self) -> Step {
+        if self.options.verify {
+            self.segment = 0;
+            self.offset = 0;
+            self.send(Stage::Read, command(CMD_READ))
+        } else {
+            self.go_or_done()
+        }
+    }
+
+    fn go_or_done(&mut self) -> Step {
+        if self.options.go {
+            self.send(Stage::Go, command(CMD_GO))
+        } else {
+            self.stage = Stage::Done;
+            Step::done(Vec::new())
+        }
+    }
+
+    /// Number of bytes the reply of the current stage takes, once known
+    fn reply_len(&self) -> Option<usize> {
+        match self.stage {
+            // ACK, N, N + 1 bytes, ACK
+            Stage::Get | Stage::GetId => self.rx.get(1).map(|&n| n as usize + 4),
+            // ACK, then the data
+            Stage::ReadData => Some(1 + self.block().1.len()),
+            _ => Some(1),
+        }
+    }
+
+    /// Handles a complete, acknowledged reply
+    fn advance(&mut self, reply: Vec<u8>) -> Step {
+        match self.stage {
+            Stage::Sync => self.send(Stage::Get, command(CMD_GET)),
+            Stage::Get => {
+                if reply.last() != Some(&ACK) {
+                    return Step::failed(Vec::new(), "Malformed reply to Get");
+                }
+                self.version = reply.get(2).copied();
+                self.extended_erase = reply[3..reply.len() - 1].contains(&CMD_EXTENDED_ERASE);
+                self.send(Stage::GetId, command(CMD_GET_ID))
+            }
+            Stage::GetId => {
+                if reply.last() != Some(&ACK) || reply.len() < 5 {
+                    return Step::failed(Vec::new(), "Malformed reply to Get ID");
+                }
+                self.chip_id = Some(u16::from_be_bytes([reply[2], reply[3]]));
+                let cmd = if self.extended_erase {
+                    CMD_EXTENDED_ERASE
+                } else {
+                    CMD_ERASE
+                };
+                self.send(Stage::Erase, command(cmd))
+            }
+            Stage::Erase => {
+                // Global (mass) erase
+                let args = if self.extended_erase {
+                    vec![0xFF, 0xFF, 0x00]
+                } else {
+                    vec![0xFF, 0x00]
+                };
+                self.send(Stage::EraseArgs, args)
+            }
+            Stage::EraseArgs => self.send(Stage::Write, command(CMD_WRITE)),
+            Stage::Write => {
+                let address = self.block().0;
+                self.send(Stage::WriteAddress, with_checksum(&address.to_be_bytes()))
+            }
+            Stage::WriteAddress => {
+                let mut data = self.block().1.to_vec();
+                // Writes are whole words; erased flash reads 0xFF
+                data.resize(data.len().next_multiple_of(4), 0xFF);
+                let mut payload = vec![(data.len() - 1) as u8];
+                payload.extend(data);
+                self.send(Stage::WriteData, with_checksum(&payload))
+            }
+            Stage::WriteData => {
+                if self.next_block() {
+                    self.send(Stage::Write, command(CMD_WRITE))
+                } else {
+                    self.start_verify_or_go()
+                }
+            }
+            Stage::Read => {
+                let address = self.block().0;
+                self.send(Stage::ReadAddress, with_checksum(&address.to_be_bytes()))
+            }
+            Stage::ReadAddress => {
+                let n = (self.block().1.len() - 1) as u8;
+                self.send(Stage::ReadData, vec![n, !n])
+            }
+            Stage::ReadData => {
+                let (address, expected) = self.block();
+                if let Some(i) = expected.iter().zip(&reply[1..]).position(|(a, b)| a != b) {
+                    let reason = format!("Verify failed at 0x{:08X}", address + i as u32);
+                    return Step::failed(Vec::new(), reason);
+                }
+                if self.next_block() {
+                    self.send(Stage::Read, command(CMD_READ))
+                } else {
+                    self.go_or_done()
+                }
+            }
+            Stage::Go => {
+                let address = self.segments[0].address;
+                self.send(Stage::GoAddress, with_checksum(&address.to_be_bytes()))
+            }
+            Stage::GoAddress | Stage::Done => {
+                self.stage = Stage::Done;
+                Step::done(Vec::new())
+            }
+        }
+    }
+}
+
+impl Protocol for Flasher {
+    fn start(&mut self) -> Step {
+        if self.segments.is_empty() {
+            return Step::failed(Vec::new(), "The image contains no data");
+        }
+        Step::wait(vec![SYNC], SYNC_TIMEOUT_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        self.rx.extend_from_slice(data);
+        if self.stage == Stage::Sync {
+            // A bootloader that was already synced answers NACK
+            let Some(&last) = self.rx.last() else {
+                return Step::wait(Vec::new(), SYNC_TIMEOUT_MS);
+            };
+            self.rx.clear();
+            if last != ACK && last != NACK {
+                return Step::wait(Vec::new(), SYNC_TIMEOUT_MS);
+            }
+            return self.advance(Vec::new());
+        }
+        match self.rx.first() {
+            None => return Step::wait(Vec::new(), REPLY_TIMEOUT_MS),
+            Some(&NACK) => {
+                let reason = format!("The bootloader refused {}", self.stage.name());
+                return Step::failed(Vec::new(), reason);
+            }
+            Some(&ACK) => {}
+            Some(&other) => {
+                let reason = format!("Unexpected reply 0x{:02X} to {}", other, self.stage.name());
+                return Step::failed(Vec::new(), reason);
+            }
+        }
+        match self.reply_len() {
+            Some(len) if self.rx.len() >= len => {
+                let reply = self.rx.drain(..len).collect();
+                self.advance(reply)
+            }
+            _ => Step::wait(Vec::new(), REPLY_TIMEOUT_MS),
+        }
+    }
+
+    fn timeout(&mut self) -> Step {
+        if self.stage == Stage::Sync {
+            self.sync_attempts += 1;
+            if self.sync_attempts >= SYNC_ATTEMPTS {
+                return Step::failed(
+                    Vec::new(),
+                    "No answer from the bootloader, is the chip in bootloader mode?",
+                );
+            }
+            return Step::wait(vec![SYNC], SYNC_TIMEOUT_MS);
+        }
+        Step::failed(Vec::new(), format!("No reply to {}", self.stage.name()))
+    }
+
+    fn progress(&self) -> Progress {
+        let size = image_size(&self.segments);
+        Progress {
+            bytes: self.done_bytes,
+            total: Some(if self.options.verify { size * 2 } else { size }),
+            retries: self.sync_attempts,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transfer::{loopback, StepState};
+
+    const FLASH_BASE: u32 = 0x0800_0000;
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Pending {
+        Command,
+        Address(u8),
+        Erase,
+        WriteData(u32),
+        ReadLength(u32),
+    }
+
+    /// Answers like the ROM bootloader of a part with 64 KB of flash
+    struct SimBootloader {
+        flash: Vec<u8>,
+        rx: Vec<u8>,
+        synced: bool,
+        extended: bool,
+        pending: Pending,
+        jumped: Option<u32>,
+        corrupt_writes: bool,
+        write_protected: bool,
+        mute: bool,
+    }
+
+    impl SimBootloader {
+        fn new(extended: bool) -> Self {
+            Self {
+                flash: vec![0; 64 * 1024],
+                rx: Vec::new(),
+                synced: false,
+                extended,
+                pending: Pending::Command,
+                jumped: None,
+                corrupt_writes: false,
+                write_protected: false,
+                mute: false,
+            }
+        }
+
+        fn offset(&self, address: u32) -> Option<usize> {
+            let offset = address.checked_sub(FLASH_BASE)? as usize;
+            (offset < self.flash.len()).then_some(offset)
+        }
+
+        /// Handles one complete request at the front of `rx`, if there is one
+        fn step(&mut self, out: &mut Vec<u8>) -> bool {
+            if !self.synced {
+                let Some(&b) = self.rx.first() else {
+                    return false;
+                };
+                self.rx.remove(0);
+                if b == SYNC {
+                    self.synced = true;
+                    out.push(ACK);
+                }
+                return true;
+            }
+            let need = match self.pending {
+                Pending::Command | Pending::ReadLength(_) => 2,
+                Pending::Address(_) => 5,
+                Pending::Erase if self.extended => 3,
+                Pending::Erase => 2,
+                Pending::WriteData(_) => match self.rx.first() {
+                    Some(&n) => n as usize + 3,
+                    None => return false,
+                },
+            };
+            if self.rx.len() < need {
+                return false;
+            }
+            let req: Vec<u8> = self.rx.drain(..need).collect();
+            let checksum_ok = req.iter().fold(0, |a, b| a ^ b) == 0;
+            let pending = std::mem::replace(&mut self.pending, Pending::Command);
+            match pending {
+                // Also the case for two 0x7F sent to a synced bootloader
+                Pending::Command if req[0] != !req[1] => out.push(NACK),
+                Pending::Command => match req[0] {
+                    CMD_GET => {
+                        let erase = if self.extended {
+                            CMD_EXTENDED_ERASE
+                        } else {
+                            CMD_ERASE
+                        };
+                        let cmds = [CMD_GET, CMD_GET_ID, CMD_READ, CMD_GO, CMD_WRITE, erase];
+                        out.extend([ACK, cmds.len() as u8, 0x31]);
+                        out.extend(cmds);
+                        out.push(ACK);
+                    }
+                    CMD_GET_ID => out.extend([ACK, 1, 0x04, 0x13, ACK]),
+                    CMD_READ | CMD_WRITE | CMD_GO => {
+                        out.push(ACK);
+                        self.pending = Pending::Address(req[0]);
+                    }
+                    CMD_ERASE if !self.extended => {
+                        out.push(ACK);
+                        self.pending = Pending::Erase;
+                    }
+                    CMD_EXTENDED_ERASE if self.extended => {
+                        out.push(ACK);
+                        self.pending = Pending::Erase;
+                    }
+                    _ => out.push(NACK),
+                },
+                Pending::Address(cmd) => {
+                    let address = u32::from_be_bytes([req[0], req[1], req[2], req[3]]);
+                    if !checksum_ok || self.offset(address).is_none() {
+                        out.push(NACK);
+                        return true;
+                    }
+                    out.push(ACK);
+                    match cmd {
+                        CMD_READ => self.pending = Pending::ReadLength(address),
+                        CMD_WRITE => self.pending = Pending::WriteData(address),
+                        _ => self.jumped = Some(address),
+                    }
+                }
+                Pending::Erase => {
+                    let global = if self.extended {
+                        req == [0xFF, 0xFF, 0x00]
+                    } else {
+                        req == [0xFF, 0x00]
+                    };
+                    if global {
+                        self.flash.fill(0xFF);
+                        out.push(ACK);
+                    } else {
+                        out.push(NACK);
+                    }
+                }
+                Pending::WriteData(address) => {
+                    let data = &req[1..req.len() - 1];
+                    let start = self.offset(address).unwrap();
+                    if !checksum_ok || self.write_protected || !data.len().is_multiple_of(4) {
+                        out.push(NACK);
+                        return true;
+                    }
+                    self.flash[start..start + data.len()].copy_from_slice(data);
+                    if self.corrupt_writes {
+                        self.flash[start] ^= 0x01;
+                    }
+                    out.push(ACK);
+                }
+                Pending::ReadLength(address) => {
+                    if req[0] != !req[1] {
+                        out.push(NACK);
+                        return true;
+                    }
+                    let start = self.offset(address).unwrap();
+                    out.push(ACK);
+                    out.extend(&self.flash[start..start + req[0] as usize + 1]);
+                }
+            }
+            true
+        }
+    }
+
+    impl Protocol for SimBootloader {
+        fn start(&mut self) -> Step {
+            Step::wait(Vec::new(), u32::MAX)
+        }
+
+        fn receive(&mut self, data: &[u8]) -> Step {
+            if self.mute {
+                return Step::wait(Vec::new(), u32::MAX);
+            }
+            self.rx.extend_from_slice(data);
+            let mut out = Vec::new();
+            while self.step(&mut out) {}
+            Step::wait(out, u32::MAX)
+        }
+
+        /// The flasher went quiet, so the session is over
+        fn timeout(&mut self) -> Step {
+            Step::done(Vec::new())
+        }
+
+        fn progress(&self) -> Progress {
+            Progress::default()
+        }
+    }
+
+    fn image() -> Vec<Segment> {
+        vec![
+            Segment {
+                address: FLASH_BASE,
+                data: (0..1000).map(|i| (i * 7) as u8).collect(),
+            },
+            Segment {
+                address: FLASH_BASE + 0x2000,
+                data: vec![0xA5, 0x5A, 0xC3],
+            },
+        ]
+    }
+
+    #[test]
+    fn test_flash_verify_and_go() {
+        let mut flasher = Flasher::new(image(), FlashOptions::default());
+        let mut device = SimBootloader::new(false);
+        let (state, _) = loopback::run(&mut flasher, &mut device, |_, _| {});
+        assert_eq!(state, StepState::Done);
+        assert_eq!(flasher.chip_id(), Some(0x413));
+        assert_eq!(flasher.version(), Some(0x31));
+        for segment in image() {
+            let start = (segment.address - FLASH_BASE) as usize;
+            assert_eq!(
+                &device.flash[start..start + segment.data.len()],
+                &segment.data[..]
+            );
+        }
+        // Padding of the last word and untouched flash stay erased
+        assert_eq!(device.flash[0x2003], 0xFF);
+        assert_eq!(device.flash[0x3000], 0xFF);
+        assert_eq!(device.jumped, Some(FLASH_BASE));
+        assert_eq!(flasher.progress().bytes, flasher.progress().total.unwrap());
+    }
+
+    #[test]
+    fn test_extended_erase_when_already_synced() {
+        let options = FlashOptions {
+            verify: false,
+            go: false,
+        };
+        let mut flasher = Flasher::new(image(), options);
+        let mut device = SimBootloader::new(true);
+        device.synced = true;
+        let (state, _) = loopback::run(&mut flasher, &mut device, |_, _| {});
+        assert_eq!(state, StepState::Done);
+        assert_eq!(&device.flash[..1000], &image()[0].data[..]);
+        assert_eq!(device.jumped, None);
+    }
+
+    #[test]
+    fn test_split_reply() {
+        let mut flasher = Flasher::new(image(), FlashOptions::default());
+        flasher.start();
+        assert_eq!(flasher.receive(&[ACK]).send, command(CMD_GET));
+        assert_eq!(
+            flasher.receive(&[ACK, 2, 0x22]),
+            Step::wait(Vec::new(), REPLY_TIMEOUT_MS)
+        );
+        assert_eq!(
+            flasher.receive(&[CMD_GET, CMD_EXTENDED_ERASE]).send,
+            Vec::<u8>::new()
+        );
+        assert_eq!(flasher.receive(&[ACK]).send, command(CMD_GET_ID));
+        assert_eq!(flasher.version(), Some(0x22));
+        assert!(flasher.extended_erase);
+    }
+
+    #[test]
+    fn test_verify_failure() {
+        let mut flasher = Flasher::new(image(), FlashOptions::default());
+        let mut device = SimBootloader::new(false);
+        device.corrupt_writes = true;
+        let (state, _) = loopback::run(&mut flasher, &mut device, |_, _| {});
+        assert_eq!(
+            state,
+            StepState::Failed("Verify failed at 0x08000000".into())
+        );
+        assert_eq!(device.jumped, None);
+    }
+
+    #[test]
+    fn test_refused_and_silent_bootloader() {
+        let mut flasher = Flasher::new(image(), FlashOptions::default());
+        let mut device = SimBootloader::new(false);
+        device.write_protected = true;
+        let (state, _) = loopback::run(&mut flasher, &mut device, |_, _| {});
+        assert_eq!(
+            state,
+            StepState::Failed("The bootloader refused Write Memory".into())
+        );
+
+        let mut flasher = Flasher::new(image(), FlashOptions::default());
+        let mut device = SimBootloader::new(false);
+        device.mute = true;
+        let (state, _) = loopback::run(&mut flasher, &mut device, |_, _| {});
+        assert!(matches!(state, StepState::Failed(e) if e.starts_with("No answer")));
+        assert_eq!(flasher.progress().retries, SYNC_ATTEMPTS);
+    }
+}
@@ -0,0 +1,717 @@
+use super::{Progress, Protocol, Step};
+
+const SOH: u8 = 0x01;
+const STX: u8 = 0x02;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const SUB: u8 = 0x1A;
+const CRC_START: u8 = b'C';
+
+const MAX_RETRIES: u32 = 10;
+/// How long a sender waits for the receiver to ask for the first block
+const START_TIMEOUT_MS: u32 = 60_000;
+const ACK_TIMEOUT_MS: u32 = 10_000;
+/// Receivers repeat their start request at this interval
+const POLL_INTERVAL_MS: u32 = 3_000;
+const BLOCK_TIMEOUT_MS: u32 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Variant {
+    /// 128-byte blocks, CRC or checksum
+    Xmodem,
+    /// 1024-byte blocks with CRC
+    Xmodem1k,
+    /// XMODEM-1K with a name/size header block and batch end
+    Ymodem,
+}
+
+impl Variant {
+    pub const ALL: [Variant; 3] = [Variant::Xmodem, Variant::Xmodem1k, Variant::Ymodem];
+
+    fn block_size(self) -> usize {
+        match self {
+            Variant::Xmodem => 128,
+            _ => 1024,
+        }
+    }
+}
+
+impl std::fmt::Display for Variant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Variant::Xmodem => "XMODEM",
+            Variant::Xmodem1k => "XMODEM-1K",
+            Variant::Ymodem => "YMODEM",
+        })
+    }
+}
+
+/// CRC-16/XMODEM (poly 0x1021, init 0)
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &b| {
+        (0..8).fold(crc ^ ((b as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+fn block(num: u8, payload: &[u8], size: usize, crc: bool) -> Vec<u8> {
+    let mut data = payload.to_vec();
+    // Header blocks are padded with zeros, data blocks with SUB
+    data.resize(size, if num == 0 { 0 } else { SUB });
+    let mut packet = Vec::with_capacity(size + 5);
+    packet.push(if size == 1024 { STX } else { SOH });
+    packet.extend([num, !num]);
+    packet.extend(&data);
+    if crc {
+        packet.extend(crc16(&data).to_be_bytes());
+    } else {
+        packet.push(checksum(&data));
+    }
+    packet
+}
+
+fn cancel_sequence() -> Vec<u8> {
+    vec![CAN; 8]
+}
+
+/// YMODEM block 0 payload: `name\0size`
+fn header_payload(name: &str, size: u64) -> Vec<u8> {
+    let mut payload = name.as_bytes().to_vec();
+    payload.push(0);
+    payload.extend(size.to_string().bytes());
+    payload
+}
+
+fn parse_header(payload: &[u8]) -> Option<(String, Option<u64>)> {
+    let end = payload.iter().position(|&b| b == 0)?;
+    if end == 0 {
+        return None;
+    }
+    let name = String::from_utf8_lossy(&payload[..end]).into_owned();
+    let rest = &payload[end + 1..];
+    let info = &rest[..rest.iter().position(|&b| b == 0).unwrap_or(rest.len())];
+    let size = std::str::from_utf8(info)
+        .ok()
+        .and_then(|s| s.split_whitespace().next())
+        .and_then(|s| s.parse().ok());
+    Some((name, size))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SendState {
+    AwaitStart,
+    AwaitHeaderAck,
+    /// YMODEM: header acknowledged, waiting for the `C` that starts the data
+    AwaitDataStart,
+    AwaitAck,
+    AwaitEotAck,
+    /// YMODEM: waiting for the `C` that asks for the next (empty) header
+    AwaitBatchEnd,
+    AwaitEndAck,
+    Done,
+}
+
+/// Sends one file to an XMODEM/YMODEM receiver
+pub struct Sender {
+    variant: Variant,
+    name: String,
+    data: Vec<u8>,
+    crc: bool,
+    state: SendState,
+    /// Offset of the block in flight
+    offset: usize,
+    /// Payload length of the block in flight
+    in_flight: usize,
+    num: u8,
+    packet: Vec<u8>,
+    retries: u32,
+    total_retries: u32,
+    cancels: u8,
+}
+
+impl Sender {
+    pub fn new(variant: Variant, name: &str, data: Vec<u8>) -> Self {
+        Self {
+            variant,
+            name: name.to_string(),
+            data,
+            crc: true,
+            state: SendState::AwaitStart,
+            offset: 0,
+            in_flight: 0,
+            num: 0,
+            packet: Vec::new(),
+            retries: 0,
+            total_retries: 0,
+            cancels: 0,
+        }
+    }
+
+    fn send_packet(&mut self, packet: Vec<u8>, state: SendState) -> Vec<u8> {
+        self.packet = packet;
+        self.state = state;
+        self.retries = 0;
+        self.packet.clone()
+    }
+
+    /// The next data block, or EOT when all data was acknowledged
+    fn next_block(&mut self) -> Vec<u8> {
+        let remaining = self.data.len() - self.offset;
+        if remaining == 0 {
+            return self.send_packet(vec![EOT], SendState::AwaitEotAck);
+        }
+        // A short tail goes in a 128-byte block to save padding
+        let size = if remaining <= 128 {
+            128
+        } else {
+            self.variant.block_size()
+        };
+        self.in_flight = remaining.min(size);
+        self.num = self.num.wrapping_add(1);
+        let payload = &self.data[self.offset..self.offset + self.in_flight];
+        let packet = block(self.num, payload, size, self.crc);
+        self.send_packet(packet, SendState::AwaitAck)
+    }
+
+    fn header_block(&mut self, name: &str, size: u64) -> Vec<u8> {
+        let payload = header_payload(name, size);
+        let size = if payload.len() > 128 { 1024 } else { 128 };
+        block(0, &payload, size, self.crc)
+    }
+
+    fn retry(&mut self) -> Step {
+        self.retries += 1;
+        self.total_retries += 1;
+        if self.retries > MAX_RETRIES {
+            return Step::failed(cancel_sequence(), "Too many retries");
+        }
+        Step::wait(self.packet.clone(), ACK_TIMEOUT_MS)
+    }
+}
+
+impl Protocol for Sender {
+    fn start(&mut self) -> Step {
+        Step::wait(Vec::new(), START_TIMEOUT_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        for &byte in data {
+            if byte == CAN {
+                self.cancels += 1;
+                if self.cancels >= 2 {
+                    return Step::failed(Vec::new(), "Cancelled by receiver");
+                }
+                continue;
+            }
+            self.cancels = 0;
+
+            let is_request = byte == CRC_START || byte == NAK;
+            // Anything still buffered was sent before the packet below; drop it
+            let send = match (self.state, byte) {
+                (SendState::AwaitStart, _) if is_request => {
+                    self.crc = byte == CRC_START;
+                    if self.variant == Variant::Ymodem {
+                        let header = self.header_block(&self.name.clone(), self.data.len() as u64);
+                        self.send_packet(header, SendState::AwaitHeaderAck)
+                    } else {
+                        self.next_block()
+                    }
+                }
+                (SendState::AwaitHeaderAck, ACK) => {
+                    self.state = SendState::AwaitDataStart;
+                    continue;
+                }
+                (SendState::AwaitDataStart, _) if is_request => self.next_block(),
+                (SendState::AwaitAck, ACK) => {
+                    self.offset += self.in_flight;
+                    self.next_block()
+                }
+                (SendState::AwaitEotAck, ACK) => {
+                    if self.variant != Variant::Ymodem {
+                        self.state = SendState::Done;
+                        return Step::done(Vec::new());
+                    }
+                    self.state = SendState::AwaitBatchEnd;
+                    continue;
+                }
+                (SendState::AwaitBatchEnd, _) if is_request => {
+                    let end = block(0, &[], 128, self.crc);
+                    self.send_packet(end, SendState::AwaitEndAck)
+                }
+                (SendState::AwaitEndAck, ACK) => {
+                    self.state = SendState::Done;
+                    return Step::done(Vec::new());
+                }
+                (
+                    SendState::AwaitHeaderAck
+                    | SendState::AwaitAck
+                    | SendState::AwaitEotAck
+                    | SendState::AwaitEndAck,
+                    _,
+                ) if is_request => {
+                    return self.retry();
+                }
+                _ => continue,
+            };
+            return Step::wait(send, ACK_TIMEOUT_MS);
+        }
+        let timeout = if self.state == SendState::AwaitStart {
+            START_TIMEOUT_MS
+        } else {
+            ACK_TIMEOUT_MS
+        };
+        Step::wait(Vec::new(), timeout)
+    }
+
+    fn timeout(&mut self) -> Step {
+        match self.state {
+            SendState::AwaitStart => Step::failed(Vec::new(), "Receiver did not start"),
+            SendState::AwaitDataStart | SendState::AwaitBatchEnd => {
+                Step::failed(cancel_sequence(), "Receiver stopped responding")
+            }
+            SendState::Done => Step::done(Vec::new()),
+            _ => self.retry(),
+        }
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        cancel_sequence()
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.offset as u64,
+            total: Some(self.data.len() as u64),
+            retries: self.total_retries,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReceiveState {
+    /// Asking the sender to start with `C` (or NAK for checksum mode)
+    Starting,
+    /// YMODEM: expecting block 0 with the next file name
+    AwaitHeader,
+    Receiving,
+    Done,
+}
+
+/// Receives a file from an XMODEM/YMODEM sender. Only the first file of a
+/// YMODEM batch is kept; further files are acknowledged and dropped.
+pub struct Receiver {
+    variant: Variant,
+    crc: bool,
+    state: ReceiveState,
+    buffer: Vec<u8>,
+    expected: u8,
+    /// Data ready to be written out
+    output: Vec<u8>,
+    /// Last block of an XMODEM file, held back until its padding can be trimmed
+    held: Vec<u8>,
+    name: Option<String>,
+    size: Option<u64>,
+    received: u64,
+    /// Data blocks accepted for the current file
+    blocks: u32,
+    files: u32,
+    eots: u8,
+    retries: u32,
+    total_retries: u32,
+}
+
+impl Receiver {
+    pub fn new(variant: Variant) -> Self {
+        Self {
+            variant,
+            crc: true,
+            state: ReceiveState::Starting,
+            buffer: Vec::new(),
+            expected: 1,
+            output: Vec::new(),
+            held: Vec::new(),
+            name: None,
+            size: None,
+            received: 0,
+            blocks: 0,
+            files: 0,
+            eots: 0,
+            retries: 0,
+            total_retries: 0,
+        }
+    }
+
+    /// File name from the YMODEM header
+    pub fn file_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Files received beyond the first, which were dropped
+    pub fn skipped_files(&self) -> u32 {
+        self.files.saturating_sub(1)
+    }
+
+    /// Received file data not yet taken
+    pub fn take_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    fn start_request(&self) -> u8 {
+        if self.crc {
+            CRC_START
+        } else {
+            NAK
+        }
+    }
+
+    fn keep(&mut self, payload: &[u8]) {
+        let keep = self.files <= 1;
+        let take = match self.size {
+            Some(size) => (size.saturating_sub(self.received) as usize).min(payload.len()),
+            None => payload.len(),
+        };
+        self.received += take as u64;
+        if !keep {
+            return;
+        }
+        if self.size.is_some() {
+            self.output.extend(&payload[..take]);
+        } else {
+            self.output.append(&mut self.held);
+            self.held = payload.to_vec();
+        }
+    }
+
+    fn end_file(&mut self) {
+        let len = self
+            .held
+            .iter()
+            .rposition(|&b| b != SUB)
+            .map_or(0, |i| i + 1);
+        self.held.truncate(len);
+        self.output.append(&mut self.held);
+    }
+
+    fn retry(&mut self, request: u8) -> Step {
+        self.buffer.clear();
+        self.retries += 1;
+        self.total_retries += 1;
+        if self.retries > MAX_RETRIES {
+            return Step::failed(cancel_sequence(), "Too many retries");
+        }
+        let timeout = if self.state == ReceiveState::Starting {
+            POLL_INTERVAL_MS
+        } else {
+            BLOCK_TIMEOUT_MS
+        };
+        Step::wait(vec![request], timeout)
+    }
+
+    fn on_block(&mut self, num: u8, payload: &[u8]) -> Result<Vec<u8>, Step> {
+        let expects_header = self.variant == Variant::Ymodem
+            && matches!(
+                self.state,
+                ReceiveState::Starting | ReceiveState::AwaitHeader
+            );
+        if expects_header {
+            if num != 0 {
+                return Err(self.retry(self.start_request()));
+            }
+            self.retries = 0;
+            return match parse_header(payload) {
+                Some((name, size)) => {
+                    self.files += 1;
+                    if self.files == 1 {
+                        self.name = Some(name);
+                    }
+                    self.size = size;
+                    self.received = 0;
+                    self.blocks = 0;
+                    self.expected = 1;
+                    self.eots = 0;
+                    self.state = ReceiveState::Receiving;
+                    Ok(vec![ACK, CRC_START])
+                }
+                None => {
+                    self.state = ReceiveState::Done;
+                    Err(Step::done(vec![ACK]))
+                }
+            };
+        }
+
+        self.state = ReceiveState::Receiving;
+        if self.variant == Variant::Ymodem && num == 0 && self.blocks == 0 {
+            // The sender missed the ACK of the header and waits for its `C` again
+            return Ok(vec![ACK, CRC_START]);
+        }
+        if num == self.expected {
+            self.retries = 0;
+            self.blocks += 1;
+            self.keep(payload);
+            self.expected = self.expected.wrapping_add(1);
+            Ok(vec![ACK])
+        } else if num == self.expected.wrapping_sub(1) {
+            // The sender missed our ACK
+            Ok(vec![ACK])
+        } else {
+            Err(Step::failed(cancel_sequence(), "Block sequence error"))
+        }
+    }
+
+    fn on_eot(&mut self) -> Result<Vec<u8>, Step> {
+        if self.variant != Variant::Ymodem {
+            // An empty XMODEM file is a bare EOT
+            if self.state == ReceiveState::Done {
+                return Ok(Vec::new());
+            }
+            self.end_file();
+            self.state = ReceiveState::Done;
+            return Err(Step::done(vec![ACK]));
+        }
+        if self.state != ReceiveState::Receiving {
+            return Ok(Vec::new());
+        }
+        // YMODEM senders expect the first EOT to be refused
+        self.eots += 1;
+        if self.eots == 1 {
+            return Ok(vec![NAK]);
+        }
+        self.end_file();
+        self.state = ReceiveState::AwaitHeader;
+        Ok(vec![ACK, CRC_START])
+    }
+}
+
+impl Protocol for Receiver {
+    fn start(&mut self) -> Step {
+        Step::wait(vec![CRC_START], POLL_INTERVAL_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        self.buffer.extend_from_slice(data);
+        let mut send = Vec::new();
+        while let Some(&first) = self.buffer.first() {
+            let reply = match first {
+                SOH | STX => {
+                    let size = if first == STX { 1024 } else { 128 };
+                    let len = 3 + size + if self.crc { 2 } else { 1 };
+                    if self.buffer.len() < len {
+                        break;
+                    }
+                    let packet: Vec<u8> = self.buffer.drain(..len).collect();
+                    let payload = &packet[3..3 + size];
+                    let valid = packet[1] == !packet[2]
+                        && if self.crc {
+                            packet[3 + size..] == crc16(payload).to_be_bytes()
+                        } else {
+                            packet[3 + size] == checksum(payload)
+                        };
+                    if !valid {
+                        let mut step = self.retry(NAK);
+                        step.send.splice(..0, send);
+                        return step;
+                    }
+                    self.on_block(packet[1], payload)
+                }
+                EOT => {
+                    self.buffer.remove(0);
+                    self.on_eot()
+                }
+                CAN => {
+                    if self.buffer.len() < 2 {
+                        break;
+                    }
+                    if self.buffer[1] == CAN {
+                        return Step::failed(send, "Cancelled by sender");
+                    }
+                    self.buffer.remove(0);
+                    continue;
+                }
+                _ => {
+                    // Line noise between packets
+                    self.buffer.remove(0);
+                    continue;
+                }
+            };
+            match reply {
+                Ok(bytes) => send.extend(bytes),
+                Err(mut step) => {
+                    step.send.splice(..0, send);
+                    return step;
+                }
+            }
+        }
+        let timeout = if self.state == ReceiveState::Starting {
+            POLL_INTERVAL_MS
+        } else {
+            BLOCK_TIMEOUT_MS
+        };
+        Step::wait(send, timeout)
+    }
+
+    fn timeout(&mut self) -> Step {
+        match self.state {
+            ReceiveState::Starting => {
+                // Plain XMODEM senders may only know checksum mode
+                if self.variant == Variant::Xmodem && self.retries >= 3 {
+                    self.crc = false;
+                }
+                self.retry(self.start_request())
+            }
+            ReceiveState::AwaitHeader => self.retry(CRC_START),
+            ReceiveState::Receiving => self.retry(NAK),
+            ReceiveState::Done => Step::done(Vec::new()),
+        }
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        cancel_sequence()
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.received,
+            total: self.size,
+            retries: self.total_retries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::loopback;
+    use super::super::StepState;
+    use super::*;
+
+    fn image(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 + i / 256) as u8).collect()
+    }
+
+    fn transfer(variant: Variant, data: &[u8], wire: impl FnMut(bool, &mut Vec<u8>)) -> Receiver {
+        let mut sender = Sender::new(variant, "fw.bin", data.to_vec());
+        let mut receiver = Receiver::new(variant);
+        let states = loopback::run(&mut sender, &mut receiver, wire);
+        assert_eq!(states, (StepState::Done, StepState::Done));
+        assert_eq!(sender.progress().bytes, data.len() as u64);
+        receiver
+    }
+
+    #[test]
+    fn test_crc16() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+    }
+
+    #[test]
+    fn test_each_variant_roundtrip() {
+        for variant in Variant::ALL {
+            for len in [0, 1, 128, 300, 1024, 5000] {
+                let data = image(len);
+                let mut receiver = transfer(variant, &data, |_, _| {});
+                let received = receiver.take_data();
+                if variant == Variant::Ymodem {
+                    assert_eq!(received, data, "{} {}", variant, len);
+                    assert_eq!(receiver.file_name(), Some("fw.bin"));
+                } else {
+                    // XMODEM can't tell padding from data ending in SUB
+                    let trimmed = data.iter().rposition(|&b| b != SUB).map_or(0, |i| i + 1);
+                    assert_eq!(received, data[..trimmed], "{} {}", variant, len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_recovers_from_corruption_and_lost_acks() {
+        let data = image(4000);
+        let mut packets = 0;
+        let mut replies = 0;
+        let mut receiver = transfer(Variant::Ymodem, &data, |from_sender, bytes| {
+            if from_sender && bytes.len() > 100 {
+                packets += 1;
+                // Corrupt the second data block once
+                if packets == 3 {
+                    bytes[50] ^= 0xFF;
+                }
+            } else if !from_sender && bytes == &[ACK] {
+                replies += 1;
+                // Drop one ACK so the sender times out and repeats a block
+                if replies == 3 {
+                    bytes.clear();
+                }
+            }
+        });
+        assert_eq!(receiver.take_data(), data);
+        assert!(receiver.progress().retries >= 1);
+    }
+
+    #[test]
+    fn test_lost_header_ack() {
+        let data = image(3000);
+        let mut lost = false;
+        let mut receiver = transfer(Variant::Ymodem, &data, |from_sender, bytes| {
+            // The reply to block 0 never arrives, so the sender repeats it
+            if !from_sender && !lost && bytes == &[ACK, CRC_START] {
+                lost = true;
+                bytes.clear();
+            }
+        });
+        assert!(lost);
+        assert_eq!(receiver.take_data(), data);
+    }
+
+    #[test]
+    fn test_checksum_fallback_and_cancel() {
+        // A sender that only answers NAK: the receiver falls back after three C's
+        let mut receiver = Receiver::new(Variant::Xmodem);
+        receiver.start();
+        for _ in 0..3 {
+            assert_eq!(receiver.timeout().send, vec![CRC_START]);
+        }
+        assert_eq!(receiver.timeout().send, vec![NAK]);
+        let packet = block(1, b"hello", 128, false);
+        assert_eq!(receiver.receive(&packet).send, vec![ACK]);
+        assert_eq!(receiver.receive(&[EOT]).state, StepState::Done);
+        assert_eq!(receiver.take_data(), b"hello");
+
+        let mut sender = Sender::new(Variant::Xmodem1k, "a", image(10));
+        sender.start();
+        assert!(matches!(sender.receive(b"C").state, StepState::Wait(_)));
+        assert_eq!(
+            sender.receive(&[CAN, CAN]).state,
+            StepState::Failed("Cancelled by receiver".into())
+        );
+    }
+
+    #[test]
+    fn test_sender_gives_up_after_retries() {
+        let mut sender = Sender::new(Variant::Xmodem, "a", image(10));
+        sender.start();
+        sender.receive(&[NAK]);
+        for _ in 0..MAX_RETRIES {
+            assert!(matches!(sender.timeout().state, StepState::Wait(_)));
+        }
+        let step = sender.timeout();
+        assert_eq!(step.state, StepState::Failed("Too many retries".into()));
+        assert_eq!(step.send, cancel_sequence());
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header(b"boot.img\x001234 14567123 100644\0\0"),
+            Some(("boot.img".to_string(), Some(1234)))
+        );
+        assert_eq!(parse_header(&[0; 128]), None);
+    }
+}
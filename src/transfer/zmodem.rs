@@ -0,0 +1,905 @@
+use super::xmodem::crc16;
+use super::{Progress, Protocol, Step};
+
+const ZPAD: u8 = b'*';
+const ZDLE: u8 = 0x18;
+const ZBIN: u8 = b'A';
+const ZHEX: u8 = b'B';
+const ZBIN32: u8 = b'C';
+const XON: u8 = 0x11;
+
+// Frame types
+const ZRQINIT: u8 = 0;
+const ZRINIT: u8 = 1;
+const ZSINIT: u8 = 2;
+const ZACK: u8 = 3;
+const ZFILE: u8 = 4;
+const ZSKIP: u8 = 5;
+const ZNAK: u8 = 6;
+const ZABORT: u8 = 7;
+const ZFIN: u8 = 8;
+const ZRPOS: u8 = 9;
+const ZDATA: u8 = 10;
+const ZEOF: u8 = 11;
+const ZFERR: u8 = 12;
+const ZCOMMAND: u8 = 18;
+
+// Subpacket ends
+const ZCRCE: u8 = b'h';
+const ZCRCG: u8 = b'i';
+const ZCRCQ: u8 = b'j';
+const ZCRCW: u8 = b'k';
+const ZRUB0: u8 = b'l';
+const ZRUB1: u8 = b'm';
+
+/// ZRINIT flags: full duplex, overlapped I/O, 32-bit CRC
+const CANFDX: u8 = 0x01;
+const CANOVIO: u8 = 0x02;
+const CANFC32: u8 = 0x20;
+
+const SUBPACKET_SIZE: usize = 1024;
+/// Data sent before waiting for the receiver's ZACK
+const WINDOW_SIZE: usize = 8 * SUBPACKET_SIZE;
+const MAX_SUBPACKET: usize = 8192;
+const MAX_RETRIES: u32 = 10;
+const START_TIMEOUT_MS: u32 = 60_000;
+const REPLY_TIMEOUT_MS: u32 = 10_000;
+/// How long to wait for the "OO" that closes a session
+const OVER_AND_OUT_MS: u32 = 1_000;
+
+/// Start of every ZMODEM hex header, after its first ZPAD
+const HEX_HEADER: &[u8] = b"*\x18B0";
+
+/// CRC-32 (IEEE 802.3), as used by ZBIN32 frames
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &b| {
+        (0..8).fold(crc ^ b as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+fn needs_escape(b: u8) -> bool {
+    matches!(b, ZDLE | 0x10 | XON | 0x13 | 0x90 | 0x91 | 0x93)
+}
+
+fn escape_into(out: &mut Vec<u8>, bytes: &[u8]) {
+    for &b in bytes {
+        if needs_escape(b) {
+            out.extend([ZDLE, b ^ 0x40]);
+        } else {
+            out.push(b);
+        }
+    }
+}
+
+fn position(data: [u8; 4]) -> u32 {
+    u32::from_le_bytes(data)
+}
+
+fn hex_header(ty: u8, data: [u8; 4]) -> Vec<u8> {
+    let mut raw = vec![ty];
+    raw.extend(data);
+    raw.extend(crc16(&raw).to_be_bytes());
+    let mut out = b"**\x18B".to_vec();
+    for b in raw {
+        out.extend(format!("{:02x}", b).bytes());
+    }
+    out.extend([b'\r', 0x8A]);
+    if ty != ZFIN && ty != ZACK {
+        out.push(XON);
+    }
+    out
+}
+
+fn bin_header(ty: u8, data: [u8; 4], use_crc32: bool) -> Vec<u8> {
+    let mut raw = vec![ty];
+    raw.extend(data);
+    let mut out = vec![ZPAD, ZDLE, if use_crc32 { ZBIN32 } else { ZBIN }];
+    escape_into(&mut out, &raw);
+    if use_crc32 {
+        escape_into(&mut out, &crc32(&raw).to_le_bytes());
+    } else {
+        escape_into(&mut out, &crc16(&raw).to_be_bytes());
+    }
+    out
+}
+
+fn subpacket(data: &[u8], end: u8, use_crc32: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / 8 + 8);
+    escape_into(&mut out, data);
+    out.extend([ZDLE, end]);
+    let mut covered = data.to_vec();
+    covered.push(end);
+    if use_crc32 {
+        escape_into(&mut out, &crc32(&covered).to_le_bytes());
+    } else {
+        escape_into(&mut out, &crc16(&covered).to_be_bytes());
+    }
+    out
+}
+
+/// Aborts the session on the peer: CANs then backspaces to erase them
+pub fn cancel_sequence() -> Vec<u8> {
+    let mut out = vec![ZDLE; 8];
+    out.extend([0x08; 8]);
+    out
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Event {
+    Header(u8, [u8; 4]),
+    Data(Vec<u8>, u8),
+    BadCrc,
+    Cancelled,
+}
+
+enum Unescaped {
+    Byte(u8),
+    End(u8),
+    Invalid,
+}
+
+/// Decodes one ZDLE-escaped byte at `pos`; None when more input is needed
+fn unescape(buf: &[u8], pos: &mut usize) -> Option<Unescaped> {
+    let b = *buf.get(*pos)?;
+    if b != ZDLE {
+        *pos += 1;
+        return Some(Unescaped::Byte(b));
+    }
+    let c = *buf.get(*pos + 1)?;
+    *pos += 2;
+    Some(match c {
+        ZCRCE | ZCRCG | ZCRCQ | ZCRCW => Unescaped::End(c),
+        ZRUB0 => Unescaped::Byte(0x7F),
+        ZRUB1 => Unescaped::Byte(0xFF),
+        _ if c & 0x60 == 0x40 => Unescaped::Byte(c ^ 0x40),
+        _ => Unescaped::Invalid,
+    })
+}
+
+/// Reads `n` escaped bytes, skipping flow control characters
+fn unescape_n(buf: &[u8], pos: &mut usize, n: usize) -> Option<Option<Vec<u8>>> {
+    let mut out = Vec::with_capacity(n);
+    while out.len() < n {
+        if matches!(buf.get(*pos), Some(&(XON | 0x13 | 0x91 | 0x93))) {
+            *pos += 1;
+            continue;
+        }
+        match unescape(buf, pos)? {
+            Unescaped::Byte(b) => out.push(b),
+            _ => return Some(None),
+        }
+    }
+    Some(Some(out))
+}
+
+/// Decodes hex digits byte by byte; line noise is not valid text
+fn decode_hex(digits: &[u8]) -> Option<Vec<u8>> {
+    let nibble = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    if !digits.len().is_multiple_of(2) {
+        return None;
+    }
+    digits
+        .chunks_exact(2)
+        .map(|pair| Some(nibble(pair[0])? << 4 | nibble(pair[1])?))
+        .collect()
+}
+
+/// Splits the byte stream into headers and data subpackets
+#[derive(Default)]
+struct Parser {
+    buffer: Vec<u8>,
+    /// A data subpacket follows (after ZFILE, ZDATA, ZSINIT, ZCOMMAND)
+    in_data: bool,
+    data_crc32: bool,
+}
+
+impl Parser {
+    fn feed(&mut self, bytes: &[u8]) -> Vec<Event> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+        loop {
+            if self.buffer.windows(5).any(|w| w == [ZDLE; 5]) {
+                self.buffer.clear();
+                self.in_data = false;
+                events.push(Event::Cancelled);
+                break;
+            }
+            let parsed = if self.in_data {
+                self.parse_subpacket()
+            } else {
+                self.parse_header()
+            };
+            match parsed {
+                Some(Some(event)) => events.push(event),
+                Some(None) => continue,
+                None => break,
+            }
+        }
+        events
+    }
+
+    /// `None` waits for input, `Some(None)` skipped bytes
+    fn parse_header(&mut self) -> Option<Option<Event>> {
+        let start = self.buffer.iter().position(|&b| b == ZPAD)?;
+        self.buffer.drain(..start);
+        let mut pos = self.buffer.iter().position(|&b| b != ZPAD)?;
+        if self.buffer[pos] != ZDLE {
+            self.buffer.drain(..pos);
+            return Some(None);
+        }
+        let kind = *self.buffer.get(pos + 1)?;
+        pos += 2;
+        let (raw, crc_ok, crc32) = match kind {
+            ZHEX => {
+                let digits = self.buffer.get(pos..pos + 14)?;
+                pos += 14;
+                let Some(raw) = decode_hex(digits) else {
+                    self.buffer.drain(..pos);
+                    return Some(Some(Event::BadCrc));
+                };
+                // CR LF and XON after the header
+                while matches!(self.buffer.get(pos), Some(&(b'\r' | b'\n' | 0x8A | XON))) {
+                    pos += 1;
+                }
+                let crc_ok = crc16(&raw[..5]).to_be_bytes() == raw[5..7];
+                (raw, crc_ok, false)
+            }
+            ZBIN | ZBIN32 => {
+                let crc_len = if kind == ZBIN32 { 4 } else { 2 };
+                let Some(raw) = unescape_n(&self.buffer, &mut pos, 5 + crc_len)? else {
+                    self.buffer.drain(..pos);
+                    return Some(Some(Event::BadCrc));
+                };
+                let crc_ok = if kind == ZBIN32 {
+                    crc32(&raw[..5]).to_le_bytes() == raw[5..9]
+                } else {
+                    crc16(&raw[..5]).to_be_bytes() == raw[5..7]
+                };
+                (raw, crc_ok, kind == ZBIN32)
+            }
+            _ => {
+                self.buffer.drain(..pos);
+                return Some(None);
+            }
+        };
+        self.buffer.drain(..pos);
+        if !crc_ok {
+            return Some(Some(Event::BadCrc));
+        }
+        let ty = raw[0];
+        self.in_data = matches!(ty, ZSINIT | ZFILE | ZDATA | ZCOMMAND);
+        self.data_crc32 = crc32;
+        Some(Some(Event::Header(ty, [raw[1], raw[2], raw[3], raw[4]])))
+    }
+
+    fn parse_subpacket(&mut self) -> Option<Option<Event>> {
+        let mut pos = 0;
+        let mut data = Vec::new();
+        let end = loop {
+            if data.len() > MAX_SUBPACKET {
+                self.in_data = false;
+                self.buffer.drain(..pos);
+                return Some(Some(Event::BadCrc));
+            }
+            if matches!(self.buffer.get(pos), Some(&(XON | 0x13 | 0x91 | 0x93))) {
+                pos += 1;
+                continue;
+            }
+            match unescape(&self.buffer, &mut pos)? {
+                Unescaped::Byte(b) => data.push(b),
+                Unescaped::End(end) => break end,
+                Unescaped::Invalid => {
+                    self.in_data = false;
+                    self.buffer.drain(..pos);
+                    return Some(Some(Event::BadCrc));
+                }
+            }
+        };
+        let crc_len = if self.data_crc32 { 4 } else { 2 };
+        let crc = unescape_n(&self.buffer, &mut pos, crc_len)?;
+        self.buffer.drain(..pos);
+        let mut covered = data.clone();
+        covered.push(end);
+        let crc_ok = crc.is_some_and(|crc| {
+            if self.data_crc32 {
+                crc == crc32(&covered).to_le_bytes()
+            } else {
+                crc == crc16(&covered).to_be_bytes()
+            }
+        });
+        if !crc_ok {
+            self.in_data = false;
+            return Some(Some(Event::BadCrc));
+        }
+        if end == ZCRCE || end == ZCRCW {
+            self.in_data = false;
+        }
+        Some(Some(Event::Data(data, end)))
+    }
+}
+
+/// `name\0size` and the rest of the ZFILE subpacket
+fn parse_file_info(data: &[u8]) -> (String, Option<u64>) {
+    let end = data.iter().position(|&b| b == 0).unwrap_or(data.len());
+    let name = String::from_utf8_lossy(&data[..end]).into_owned();
+    let size = data
+        .get(end + 1..)
+        .and_then(|rest| std::str::from_utf8(rest).ok())
+        .and_then(|rest| rest.trim_end_matches('\0').split_whitespace().next())
+        .and_then(|s| s.parse().ok());
+    (name, size)
+}
+
+/// Which way a detected session goes, seen from this side
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// The device runs `sz` and offers files
+    Receive,
+    /// The device runs `rz` and waits for files
+    Send,
+}
+
+/// Finds the start of a ZMODEM session in terminal output
+#[derive(Default)]
+pub struct Detector {
+    /// Tail of the last chunk that may be the start of a header
+    held: Vec<u8>,
+}
+
+impl Detector {
+    /// Splits `data` into bytes for the terminal and, once a session starts,
+    /// its direction and the bytes from its first header on
+    pub fn scan(&mut self, data: &[u8]) -> (Vec<u8>, Option<(Direction, Vec<u8>)>) {
+        let mut bytes = std::mem::take(&mut self.held);
+        bytes.extend_from_slice(data);
+        let pattern_len = HEX_HEADER.len() + 1;
+        if let Some(i) = bytes
+            .windows(pattern_len)
+            .position(|w| w.starts_with(HEX_HEADER) && matches!(w[4], b'0' | b'1'))
+        {
+            let direction = if bytes[i + 4] == b'0' {
+                Direction::Receive
+            } else {
+                Direction::Send
+            };
+            let start = if i > 0 && bytes[i - 1] == ZPAD {
+                i - 1
+            } else {
+                i
+            };
+            let session = bytes.split_off(start);
+            return (bytes, Some((direction, session)));
+        }
+        // Hold back a tail that could grow into a header; plain "*" passes
+        let tail = (2..pattern_len)
+            .rev()
+            .find(|&n| n <= bytes.len() && HEX_HEADER.starts_with(&bytes[bytes.len() - n..]));
+        if let Some(n) = tail {
+            self.held = bytes.split_off(bytes.len() - n);
+        }
+        (bytes, None)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReceiveState {
+    Waiting,
+    /// ZFILE header seen, its subpacket comes next
+    FileInfo,
+    /// ZSINIT header seen, acknowledged after its subpacket
+    SessionInit,
+    Receiving,
+    /// ZFIN sent, waiting for "OO"
+    Closing,
+    Done,
+}
+
+/// Receives files from `sz`. Only the first file is kept; the sender is
+/// told to skip the rest.
+pub struct Receiver {
+    parser: Parser,
+    state: ReceiveState,
+    /// Data of a ZDATA frame at our position is being accepted
+    accepting: bool,
+    name: Option<String>,
+    size: Option<u64>,
+    pos: u32,
+    output: Vec<u8>,
+    skipped: u32,
+    last_reply: Vec<u8>,
+    retries: u32,
+    total_retries: u32,
+}
+
+impl Default for Receiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Receiver {
+    pub fn new() -> Self {
+        Self {
+            parser: Parser::default(),
+            state: ReceiveState::Waiting,
+            accepting: false,
+            name: None,
+            size: None,
+            pos: 0,
+            output: Vec::new(),
+            skipped: 0,
+            last_reply: Vec::new(),
+            retries: 0,
+            total_retries: 0,
+        }
+    }
+
+    pub fn file_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn skipped_files(&self) -> u32 {
+        self.skipped
+    }
+
+    pub fn take_data(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.output)
+    }
+
+    fn zrinit() -> Vec<u8> {
+        hex_header(ZRINIT, [0, 0, 0, CANFDX | CANOVIO | CANFC32])
+    }
+
+    fn reply(&mut self, bytes: Vec<u8>) -> Vec<u8> {
+        self.last_reply = bytes.clone();
+        bytes
+    }
+
+    fn on_event(&mut self, event: Event) -> Result<Vec<u8>, Step> {
+        match event {
+            Event::Cancelled => Err(Step::failed(Vec::new(), "Cancelled by sender")),
+            Event::BadCrc => {
+                self.total_retries += 1;
+                if self.accepting || self.state == ReceiveState::Receiving {
+                    self.accepting = false;
+                    Ok(self.reply(hex_header(ZRPOS, self.pos.to_le_bytes())))
+                } else {
+                    Ok(hex_header(ZNAK, [0; 4]))
+                }
+            }
+            Event::Header(ty, data) => {
+                self.retries = 0;
+                match ty {
+                    ZRQINIT => Ok(self.reply(Self::zrinit())),
+                    ZSINIT => {
+                        self.state = ReceiveState::SessionInit;
+                        Ok(Vec::new())
+                    }
+                    ZFILE => {
+                        self.state = ReceiveState::FileInfo;
+                        Ok(Vec::new())
+                    }
+                    ZDATA => {
+                        self.accepting = position(data) == self.pos;
+                        if self.accepting {
+                            Ok(Vec::new())
+                        } else {
+                            Ok(self.reply(hex_header(ZRPOS, self.pos.to_le_bytes())))
+                        }
+                    }
+                    ZEOF if position(data) == self.pos => {
+                        self.state = ReceiveState::Waiting;
+                        Ok(self.reply(Self::zrinit()))
+                    }
+                    ZFIN => {
+                        self.state = ReceiveState::Closing;
+                        Ok(self.reply(hex_header(ZFIN, [0; 4])))
+                    }
+                    ZABORT | ZFERR => Err(Step::failed(Vec::new(), "Aborted by sender")),
+                    _ => Ok(Vec::new()),
+                }
+            }
+            Event::Data(payload, end) => match self.state {
+                ReceiveState::SessionInit => {
+                    self.state = ReceiveState::Waiting;
+                    Ok(hex_header(ZACK, [0; 4]))
+                }
+                ReceiveState::FileInfo if self.name.is_some() => {
+                    self.skipped += 1;
+                    self.state = ReceiveState::Waiting;
+                    Ok(self.reply(hex_header(ZSKIP, [0; 4])))
+                }
+                ReceiveState::FileInfo => {
+                    let (name, size) = parse_file_info(&payload);
+                    self.name = Some(name);
+                    self.size = size;
+                    self.pos = 0;
+                    self.state = ReceiveState::Receiving;
+                    Ok(self.reply(hex_header(ZRPOS, [0; 4])))
+                }
+                _ if self.accepting => {
+                    self.pos += payload.len() as u32;
+                    self.output.extend(payload);
+                    if end == ZCRCE || end == ZCRCW {
+                        self.accepting = false;
+                    }
+                    if end == ZCRCQ || end == ZCRCW {
+                        Ok(hex_header(ZACK, self.pos.to_le_bytes()))
+                    } else {
+                        Ok(Vec::new())
+                    }
+                }
+                _ => Ok(Vec::new()),
+            },
+        }
+    }
+}
+
+impl Protocol for Receiver {
+    /// `sz` already sent ZRQINIT when the session was detected; answering it
+    /// again here would make it send ZFILE twice
+    fn start(&mut self) -> Step {
+        self.last_reply = Self::zrinit();
+        Step::wait(Vec::new(), REPLY_TIMEOUT_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        if self.state == ReceiveState::Closing {
+            self.state = ReceiveState::Done;
+            return Step::done(Vec::new());
+        }
+        let mut send = Vec::new();
+        for event in self.parser.feed(data) {
+            match self.on_event(event) {
+                Ok(bytes) => send.extend(bytes),
+                Err(mut step) => {
+                    step.send.splice(..0, send);
+                    return step;
+                }
+            }
+        }
+        let timeout = if self.state == ReceiveState::Closing {
+            OVER_AND_OUT_MS
+        } else {
+            REPLY_TIMEOUT_MS
+        };
+        Step::wait(send, timeout)
+    }
+
+    fn timeout(&mut self) -> Step {
+        if matches!(self.state, ReceiveState::Closing | ReceiveState::Done) {
+            self.state = ReceiveState::Done;
+            return Step::done(Vec::new());
+        }
+        self.retries += 1;
+        self.total_retries += 1;
+        if self.retries > MAX_RETRIES {
+            return Step::failed(cancel_sequence(), "Too many retries");
+        }
+        Step::wait(self.last_reply.clone(), REPLY_TIMEOUT_MS)
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        cancel_sequence()
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.pos as u64,
+            total: self.size,
+            retries: self.total_retries,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SendState {
+    AwaitInit,
+    /// ZFILE sent, waiting for ZRPOS (or ZSKIP)
+    AwaitPosition,
+    /// A window ending in ZCRCW was sent, waiting for its ZACK
+    AwaitAck,
+    /// ZEOF sent, waiting for ZRINIT
+    AwaitEofAck,
+    AwaitFin,
+    Done,
+}
+
+/// Sends one file to `rz`
+pub struct Sender {
+    parser: Parser,
+    state: SendState,
+    name: String,
+    data: Vec<u8>,
+    use_crc32: bool,
+    /// Acknowledged position
+    pos: usize,
+    window_end: usize,
+    last_sent: Vec<u8>,
+    retries: u32,
+    total_retries: u32,
+}
+
+impl Sender {
+    pub fn new(name: &str, data: Vec<u8>) -> Self {
+        Self {
+            parser: Parser::default(),
+            state: SendState::AwaitInit,
+            name: name.to_string(),
+            data,
+            use_crc32: false,
+            pos: 0,
+            window_end: 0,
+            last_sent: Vec::new(),
+            retries: 0,
+            total_retries: 0,
+        }
+    }
+
+    fn send(&mut self, bytes: Vec<u8>, state: SendState) -> Vec<u8> {
+        self.state = state;
+        self.last_sent = bytes.clone();
+        bytes
+    }
+
+    fn file_frame(&mut self) -> Vec<u8> {
+        let mut info = self.name.as_bytes().to_vec();
+        info.push(0);
+        info.extend(format!("{} 0 100644", self.data.len()).bytes());
+        info.push(0);
+        let mut frame = bin_header(ZFILE, [0; 4], self.use_crc32);
+        frame.extend(subpacket(&info, ZCRCW, self.use_crc32));
+        self.send(frame, SendState::AwaitPosition)
+    }
+
+    /// Data from `pos` up to one window, or ZEOF when everything was acknowledged
+    fn window(&mut self) -> Vec<u8> {
+        if self.pos >= self.data.len() {
+            let eof = bin_header(ZEOF, (self.data.len() as u32).to_le_bytes(), self.use_crc32);
+            return self.send(eof, SendState::AwaitEofAck);
+        }
+        self.window_end = (self.pos + WINDOW_SIZE).min(self.data.len());
+        let mut frame = bin_header(ZDATA, (self.pos as u32).to_le_bytes(), self.use_crc32);
+        let chunks: Vec<&[u8]> = self.data[self.pos..self.window_end]
+            .chunks(SUBPACKET_SIZE)
+            .collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let end = if i + 1 == chunks.len() { ZCRCW } else { ZCRCG };
+            frame.extend(subpacket(chunk, end, self.use_crc32));
+        }
+        self.send(frame, SendState::AwaitAck)
+    }
+
+    fn on_header(&mut self, ty: u8, data: [u8; 4]) -> Result<Option<Vec<u8>>, Step> {
+        let reply = match (self.state, ty) {
+            (_, ZABORT | ZFERR) => return Err(Step::failed(Vec::new(), "Aborted by receiver")),
+            (SendState::AwaitInit, ZRINIT) => {
+                self.use_crc32 = data[3] & CANFC32 != 0;
+                self.file_frame()
+            }
+            (SendState::AwaitPosition | SendState::AwaitAck, ZRPOS) => {
+                if self.state == SendState::AwaitAck {
+                    self.total_retries += 1;
+                }
+                self.pos = (position(data) as usize).min(self.data.len());
+                self.window()
+            }
+            (SendState::AwaitAck, ZACK) if position(data) as usize == self.window_end => {
+                self.pos = self.window_end;
+                self.window()
+            }
+            (SendState::AwaitPosition, ZSKIP) | (SendState::AwaitEofAck, ZRINIT) => {
+                let fin = hex_header(ZFIN, [0; 4]);
+                self.send(fin, SendState::AwaitFin)
+            }
+            (SendState::AwaitFin, ZFIN) => {
+                self.state = SendState::Done;
+                return Err(Step::done(b"OO".to_vec()));
+            }
+            (_, ZNAK) => self.last_sent.clone(),
+            _ => return Ok(None),
+        };
+        self.retries = 0;
+        Ok(Some(reply))
+    }
+}
+
+impl Protocol for Sender {
+    fn start(&mut self) -> Step {
+        let init = hex_header(ZRQINIT, [0; 4]);
+        Step::wait(self.send(init, SendState::AwaitInit), START_TIMEOUT_MS)
+    }
+
+    fn receive(&mut self, data: &[u8]) -> Step {
+        for event in self.parser.feed(data) {
+            let reply = match event {
+                Event::Cancelled => return Step::failed(Vec::new(), "Cancelled by receiver"),
+                Event::Header(ty, data) => self.on_header(ty, data),
+                Event::BadCrc | Event::Data(..) => Ok(None),
+            };
+            match reply {
+                // Stale replies still buffered are dropped once something was sent
+                Ok(Some(bytes)) => return Step::wait(bytes, REPLY_TIMEOUT_MS),
+                Ok(None) => {}
+                Err(step) => return step,
+            }
+        }
+        let timeout = if self.state == SendState::AwaitInit {
+            START_TIMEOUT_MS
+        } else {
+            REPLY_TIMEOUT_MS
+        };
+        Step::wait(Vec::new(), timeout)
+    }
+
+    fn timeout(&mut self) -> Step {
+        if self.state == SendState::Done {
+            return Step::done(Vec::new());
+        }
+        self.retries += 1;
+        self.total_retries += 1;
+        if self.retries > MAX_RETRIES || self.state == SendState::AwaitInit {
+            return Step::failed(cancel_sequence(), "Receiver stopped responding");
+        }
+        Step::wait(self.last_sent.clone(), REPLY_TIMEOUT_MS)
+    }
+
+    fn cancel(&mut self) -> Vec<u8> {
+        cancel_sequence()
+    }
+
+    fn progress(&self) -> Progress {
+        Progress {
+            bytes: self.pos as u64,
+            total: Some(self.data.len() as u64),
+            retries: self.total_retries,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::loopback;
+    use super::super::StepState;
+    use super::*;
+
+    fn image(len: usize) -> Vec<u8> {
+        // Every byte value, including the ones that need escaping
+        (0..len).map(|i| (i * 31 + i / 256) as u8).collect()
+    }
+
+    #[test]
+    fn test_crcs_and_real_headers() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        // ZRINIT as sent by lrzsz `rz`
+        assert_eq!(
+            hex_header(ZRINIT, [0, 0, 0, 0x23]),
+            b"**\x18B0100000023be50\r\x8a\x11"
+        );
+        let mut parser = Parser::default();
+        let events = parser.feed(b"rz\r**\x18B00000000000000\r\x8a\x11");
+        assert_eq!(events, vec![Event::Header(ZRQINIT, [0; 4])]);
+    }
+
+    #[test]
+    fn test_hex_header_with_line_noise() {
+        let mut parser = Parser::default();
+        // A two-byte UTF-8 sequence straddling a digit pair
+        let mut stream = b"**\x18B0".to_vec();
+        stream.extend("\u{e9}".as_bytes());
+        stream.extend(b"00000000000\r\x8a\x11");
+        assert_eq!(parser.feed(&stream), vec![Event::BadCrc]);
+        let events = parser.feed(b"**\x18B00000000000000\r\x8a\x11");
+        assert_eq!(events, vec![Event::Header(ZRQINIT, [0; 4])]);
+        assert_eq!(decode_hex(b"+f0a"), None);
+    }
+
+    #[test]
+    fn test_parser_across_chunks() {
+        let mut stream = bin_header(ZDATA, 5u32.to_le_bytes(), true);
+        stream.extend(subpacket(&[0x18, 0x11, 0x7F, 0xFF, b'a'], ZCRCE, true));
+        let mut parser = Parser::default();
+        let mut events = Vec::new();
+        for byte in &stream {
+            events.extend(parser.feed(&[*byte]));
+        }
+        assert_eq!(
+            events,
+            vec![
+                Event::Header(ZDATA, [5, 0, 0, 0]),
+                Event::Data(vec![0x18, 0x11, 0x7F, 0xFF, b'a'], ZCRCE),
+            ]
+        );
+        assert_eq!(parser.feed(&[ZDLE; 5]), vec![Event::Cancelled]);
+    }
+
+    #[test]
+    fn test_loopback_transfer() {
+        for len in [0, 1, 1024, 20_000] {
+            let data = image(len);
+            let mut sender = Sender::new("app.tar", data.clone());
+            let mut receiver = Receiver::new();
+            let states = loopback::run(&mut sender, &mut receiver, |_, _| {});
+            assert_eq!(states, (StepState::Done, StepState::Done), "{}", len);
+            assert_eq!(receiver.take_data(), data);
+            assert_eq!(receiver.file_name(), Some("app.tar"));
+            assert_eq!(receiver.progress().total, Some(len as u64));
+        }
+    }
+
+    #[test]
+    fn test_loopback_recovers_from_corruption() {
+        let data = image(30_000);
+        let mut sender = Sender::new("a.bin", data.clone());
+        let mut receiver = Receiver::new();
+        let mut windows = 0;
+        let states = loopback::run(&mut sender, &mut receiver, |from_sender, bytes| {
+            if from_sender && bytes.len() > WINDOW_SIZE {
+                windows += 1;
+                if windows == 2 {
+                    bytes[3000] ^= 0x01;
+                }
+            }
+        });
+        assert_eq!(states, (StepState::Done, StepState::Done));
+        assert_eq!(receiver.take_data(), data);
+        assert!(sender.progress().retries >= 1);
+    }
+
+    #[test]
+    fn test_receiver_skips_further_files() {
+        let mut receiver = Receiver::new();
+        receiver.start();
+        let file = |name: &str| {
+            let mut frame = bin_header(ZFILE, [0; 4], false);
+            frame.extend(subpacket(
+                format!("{}\0{}", name, 2).as_bytes(),
+                ZCRCW,
+                false,
+            ));
+            frame
+        };
+        assert_eq!(
+            receiver.receive(&file("one")).send,
+            hex_header(ZRPOS, [0; 4])
+        );
+        let mut data = bin_header(ZDATA, [0; 4], false);
+        data.extend(subpacket(b"hi", ZCRCE, false));
+        data.extend(hex_header(ZEOF, [2, 0, 0, 0]));
+        assert_eq!(receiver.receive(&data).send, Receiver::zrinit());
+        assert_eq!(
+            receiver.receive(&file("two")).send,
+            hex_header(ZSKIP, [0; 4])
+        );
+        assert_eq!(receiver.skipped_files(), 1);
+        assert_eq!(receiver.take_data(), b"hi");
+    }
+
+    #[test]
+    fn test_detector() {
+        let mut detector = Detector::default();
+        assert_eq!(
+            detector.scan(b"$ sz fw.bin\r\nrz\r*"),
+            (b"$ sz fw.bin\r\nrz\r*".to_vec(), None)
+        );
+        // A header split across chunks is held back from the terminal
+        assert_eq!(detector.scan(b"*\x18B"), (b"".to_vec(), None));
+        let (pass, found) = detector.scan(b"00000000000000\r\x8a\x11");
+        assert!(pass.is_empty());
+        let (direction, session) = found.unwrap();
+        assert_eq!(direction, Direction::Receive);
+        assert!(session.starts_with(b"*\x18B00"));
+
+        let mut detector = Detector::default();
+        let (pass, found) = detector.scan(b"rz waiting\r\n**\x18B0100000023be50\r\x8a\x11");
+        assert_eq!(pass, b"rz waiting\r\n");
+        assert_eq!(found.unwrap().0, Direction::Send);
+        assert_eq!(detector.scan(b"**bold**"), (b"**bold**".to_vec(), None));
+    }
+}
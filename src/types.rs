@@ -1,6 +1,18 @@
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+/// Raw captured bytes behind a selection of log lines
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct RawCapture {
+    pub start_line: usize,
+    pub end_line: usize,
+    /// Offset of `bytes` in the session's raw journal
+    pub offset: u64,
+    /// Length of the whole range; `bytes` is cut at the inspector limit
+    pub total_len: u64,
+    pub bytes: Vec<u8>,
+}
+
 #[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Highlight {
     pub id: usize,
@@ -17,6 +29,17 @@ pub enum LineEnding {
     NLCR,
 }
 
+impl LineEnding {
+    pub fn as_bytes(&self) -> &'static [u8] {
+        match self {
+            LineEnding::None => b"",
+            LineEnding::NL => b"\n",
+            LineEnding::CR => b"\r",
+            LineEnding::NLCR => b"\r\n",
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
 pub enum ViewMode {
     #[default]
@@ -58,6 +81,469 @@ impl fmt::Display for FlowControl {
     }
 }
 
+/// Character encoding used to decode received bytes and encode transmitted text
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    Latin1,
+    ShiftJis,
+    EucJp,
+    EucKr,
+    Gbk,
+    Big5,
+}
+
+impl TextEncoding {
+    pub const ALL: [TextEncoding; 7] = [
+        TextEncoding::Utf8,
+        TextEncoding::Latin1,
+        TextEncoding::ShiftJis,
+        TextEncoding::EucJp,
+        TextEncoding::EucKr,
+        TextEncoding::Gbk,
+        TextEncoding::Big5,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Latin1 => "Latin-1",
+            TextEncoding::ShiftJis => "Shift-JIS",
+            TextEncoding::EucJp => "EUC-JP",
+            TextEncoding::EucKr => "EUC-KR",
+            TextEncoding::Gbk => "GBK",
+            TextEncoding::Big5 => "Big5",
+        }
+    }
+}
+
+/// Line settings a port is opened with, kept together so a session can be
+/// compared against (and switched away from) the settings it was opened with.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct PortConfig {
+    pub baud_rate: u32,
+    pub data_bits: u8,
+    pub stop_bits: u8,
+    pub parity: Parity,
+    pub flow_control: FlowControl,
+}
+
+impl fmt::Display for PortConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Even => 'E',
+            Parity::Odd => 'O',
+        };
+        write!(
+            f,
+            "{} {}{}{}",
+            self.baud_rate, self.data_bits, parity, self.stop_bits
+        )?;
+        if self.flow_control == FlowControl::Hardware {
+            write!(f, " RTS/CTS")?;
+        }
+        Ok(())
+    }
+}
+
+/// How the incoming byte stream is cut into log lines
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum RxDelimiter {
+    /// LF, CR or CRLF
+    #[default]
+    Newline,
+    /// Any of the given byte sequences ends a line (the delimiter is dropped)
+    Sequences(Vec<Vec<u8>>),
+    /// A regex match over the raw bytes ends a line (the match is dropped)
+    Regex(String),
+    /// Every N bytes form one record
+    FixedLength(usize),
+}
+
+/// What a bare CR (not followed by LF) does in newline framing
+#[derive(Clone, Copy, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub enum CrMode {
+    /// Ends the line, like LF
+    #[default]
+    LineEnd,
+    /// Rewinds the active line like a terminal; only the final state is stored
+    Overwrite,
+    /// Rewinds the active line, keeping the overwritten states with the final line
+    OverwriteKeepHistory,
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct RxFraming {
+    pub delimiter: RxDelimiter,
+    /// Commit a partial line after this much silence on the port
+    pub idle_flush_ms: Option<u32>,
+    /// Longer lines are stored as several segments flagged `LineAttrs::continued`
+    pub max_line_bytes: usize,
+    pub cr_mode: CrMode,
+}
+
+impl Default for RxFraming {
+    fn default() -> Self {
+        Self {
+            delimiter: RxDelimiter::default(),
+            idle_flush_ms: None,
+            max_line_bytes: crate::config::MAX_LINE_BYTES,
+            cr_mode: CrMode::default(),
+        }
+    }
+}
+
+/// Facts about a stored line that are kept next to its text rather than in it
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct LineAttrs {
+    /// The line was cut at the line cap and continues on the next stored line
+    #[serde(default)]
+    pub continued: bool,
+    /// Earlier states of the line that a bare CR overwrote, oldest first,
+    /// each with its own tokens
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<(String, Vec<ByteToken>)>,
+    /// Bytes shown as tokens in the line, by position
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<ByteToken>,
+}
+
+impl LineAttrs {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Received bytes shown as a token in a line rather than as text
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ByteToken {
+    /// Byte offset in the line's text at which the token is shown
+    pub at: usize,
+    pub kind: TokenKind,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub enum TokenKind {
+    /// Bytes the receive encoding could not decode
+    Invalid,
+    /// Control bytes and escape sequences a terminal would act on; shown
+    /// only while visible control characters are on
+    Control,
+    /// Control bytes that are data, such as inside a framed record or a
+    /// typed line; always shown
+    Content,
+}
+
+impl ByteToken {
+    /// `\xHH` per invalid byte; `<HH>` per control byte, with the printable
+    /// rest of an escape sequence as is
+    pub fn label(&self) -> String {
+        self.bytes
+            .iter()
+            .map(|&b| match self.kind {
+                TokenKind::Invalid => format!("\\x{:02X}", b),
+                _ if b.is_ascii_graphic() => char::from(b).to_string(),
+                _ => format!("<{:02X}>", b),
+            })
+            .collect()
+    }
+}
+
+/// Severity detected at the start of a log line
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Verbose,
+}
+
+impl LogLevel {
+    pub const ALL: [LogLevel; 5] = [
+        LogLevel::Error,
+        LogLevel::Warn,
+        LogLevel::Info,
+        LogLevel::Debug,
+        LogLevel::Verbose,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogLevel::Error => "ERR",
+            LogLevel::Warn => "WRN",
+            LogLevel::Info => "INF",
+            LogLevel::Debug => "DBG",
+            LogLevel::Verbose => "VRB",
+        }
+    }
+
+    /// Compact form stored per line in the worker index; 0 means no level
+    pub fn code(level: Option<LogLevel>) -> u8 {
+        level.map_or(0, |l| l as u8 + 1)
+    }
+}
+
+/// Built-in line formats the level is read from
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum LevelPreset {
+    /// `E (123) tag: msg`
+    EspIdf,
+    /// `[00:00:01.000,000] <err> tag: msg`
+    Zephyr,
+    /// `[E][file.cpp:12]`, `[WRN]`, `ERROR:` and similar
+    Generic,
+    /// `<11>msg`, severity from the priority value
+    Syslog,
+}
+
+impl LevelPreset {
+    pub const ALL: [LevelPreset; 4] = [
+        LevelPreset::EspIdf,
+        LevelPreset::Zephyr,
+        LevelPreset::Generic,
+        LevelPreset::Syslog,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LevelPreset::EspIdf => "ESP-IDF",
+            LevelPreset::Zephyr => "Zephyr",
+            LevelPreset::Generic => "Generic",
+            LevelPreset::Syslog => "Syslog",
+        }
+    }
+}
+
+/// A user regex that marks matching lines with a level
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct CustomLevelRule {
+    pub pattern: String,
+    pub level: LogLevel,
+}
+
+/// How line levels are detected; custom rules are tried before the presets
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct LevelDetection {
+    pub presets: Vec<LevelPreset>,
+    pub custom: Vec<CustomLevelRule>,
+}
+
+impl Default for LevelDetection {
+    fn default() -> Self {
+        Self {
+            presets: LevelPreset::ALL.to_vec(),
+            custom: Vec::new(),
+        }
+    }
+}
+
+/// Levels shown by the level filter, one bit per `LogLevel::code`
+/// (bit 0 is lines without a level)
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct LevelMask(pub u8);
+
+impl LevelMask {
+    pub const ALL: LevelMask = LevelMask(0b11_1111);
+
+    pub fn shows(&self, code: u8) -> bool {
+        self.0 & (1 << code) != 0
+    }
+
+    pub fn toggled(self, code: u8) -> Self {
+        LevelMask(self.0 ^ (1 << code))
+    }
+
+    pub fn is_all(&self) -> bool {
+        *self == Self::ALL
+    }
+}
+
+impl Default for LevelMask {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+/// An ESP-IDF tag with the number of lines logged under it
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TagInfo {
+    pub name: String,
+    pub count: usize,
+    /// Tick value (ms since boot) of the latest line with the tag
+    pub last_ticks: Option<u64>,
+}
+
+/// A code address looked up in the loaded firmware ELF
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct SymbolInfo {
+    pub address: u64,
+    pub function: Option<String>,
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+impl SymbolInfo {
+    /// `file:line` with the file's directories dropped
+    pub fn location(&self) -> Option<String> {
+        let file = self.file.as_deref()?;
+        let name = file.rsplit(['/', '\\']).next().unwrap_or(file);
+        Some(match self.line {
+            Some(line) => format!("{}:{}", name, line),
+            None => name.to_string(),
+        })
+    }
+}
+
+/// Firmware ELF loaded in the worker for symbolization
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ElfInfo {
+    pub name: String,
+    pub has_debug_info: bool,
+    /// The ELF carries a defmt table, so defmt decoding is possible
+    pub has_defmt: bool,
+}
+
+/// Per-tag muting and soloing. While any tag is soloed only soloed tags show.
+#[derive(Clone, PartialEq, Debug, Default, Serialize, Deserialize)]
+pub struct TagFilter {
+    pub muted: Vec<String>,
+    pub solo: Vec<String>,
+}
+
+impl TagFilter {
+    pub fn is_empty(&self) -> bool {
+        self.muted.is_empty() && self.solo.is_empty()
+    }
+
+    pub fn shows(&self, tag: Option<&str>) -> bool {
+        match tag {
+            _ if self.is_empty() => true,
+            Some(tag) if !self.solo.is_empty() => self.solo.iter().any(|t| t == tag),
+            Some(tag) => !self.muted.iter().any(|t| t == tag),
+            None => self.solo.is_empty(),
+        }
+    }
+}
+
+/// Line count per `LogLevel::code`
+pub type LevelCounts = [usize; 6];
+
+/// A byte pattern match in the raw capture
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ByteHit {
+    /// Stream offset of the first matched byte
+    pub offset: u64,
+    pub len: u64,
+    /// Displayed line holding the match, if it is currently shown
+    pub line: Option<usize>,
+}
+
+/// Row layout of the hex view
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct HexLayout {
+    pub bytes_per_row: usize,
+    /// Bytes between extra gaps; 1 disables grouping
+    pub group_size: usize,
+}
+
+impl HexLayout {
+    pub const ROW_WIDTHS: [usize; 3] = [8, 16, 32];
+    pub const GROUP_SIZES: [usize; 4] = [1, 2, 4, 8];
+}
+
+impl Default for HexLayout {
+    fn default() -> Self {
+        Self {
+            bytes_per_row: crate::config::HEX_VIEW_BYTES,
+            group_size: 8,
+        }
+    }
+}
+
+/// A macro script being run from the macro bar
+#[derive(Clone, PartialEq, Debug)]
+pub struct MacroRunStatus {
+    pub id: u64,
+    pub label: String,
+    /// Source line of the step being run
+    pub line: usize,
+    /// The pattern an `expect` or `wait` step is waiting for
+    pub waiting: Option<String>,
+}
+
+/// A file transfer or flashing session that owns the port
+#[derive(Clone, PartialEq, Debug)]
+pub struct TransferStatus {
+    pub title: String,
+    pub bytes: u64,
+    pub total: Option<u64>,
+    pub retries: u32,
+    /// The session can be paused by the user
+    pub can_pause: bool,
+    /// Set when the session has ended, with the error if it failed
+    pub result: Option<Result<(), String>>,
+}
+
+impl TransferStatus {
+    pub fn new(title: String) -> Self {
+        Self {
+            title,
+            bytes: 0,
+            total: None,
+            retries: 0,
+            can_pause: false,
+            result: None,
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.result.is_none()
+    }
+
+    /// Completed fraction in percent, when the size is known
+    pub fn percent(&self) -> Option<f64> {
+        let total = self.total.filter(|&t| t > 0)?;
+        Some((self.bytes as f64 * 100.0 / total as f64).min(100.0))
+    }
+}
+
+/// Received lines kept for consumers that each read at their own pace
+#[derive(Clone, Debug, Default)]
+pub struct ReceivedLines {
+    /// Sequence number of the first line kept
+    start: u64,
+    lines: std::collections::VecDeque<String>,
+}
+
+impl ReceivedLines {
+    const KEPT: usize = 1000;
+
+    pub fn extend(&mut self, lines: Vec<String>) {
+        self.lines.extend(lines);
+        while self.lines.len() > Self::KEPT {
+            self.lines.pop_front();
+            self.start += 1;
+        }
+    }
+
+    /// Sequence number of the next line to arrive
+    pub fn end(&self) -> u64 {
+        self.start + self.lines.len() as u64
+    }
+
+    /// Lines from `cursor` on, moving it to the end; lines already dropped
+    /// are skipped
+    pub fn read(&self, cursor: &mut u64) -> Vec<String> {
+        let skip = cursor.saturating_sub(self.start) as usize;
+        *cursor = self.end();
+        self.lines.iter().skip(skip).cloned().collect()
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(tag = "type", content = "data")]
 pub enum WorkerMsg {
@@ -67,6 +553,17 @@ pub enum WorkerMsg {
         is_hex: bool,
     },
     SetTimestampState(bool),
+    InsertMarker(String),
+    SetRxFraming(RxFraming),
+    SetEncoding(TextEncoding),
+    SetHexLayout(HexLayout),
+    SetLevelDetection(LevelDetection),
+    SetLevelFilter(LevelMask),
+    LevelCounts(LevelCounts),
+    SetTagFilter(TagFilter),
+    TagStats(Vec<TagInfo>),
+    /// Malformed byte sequences decoded this session
+    InvalidSequences(u64),
 
     RequestWindow {
         start_line: usize,
@@ -74,7 +571,7 @@ pub enum WorkerMsg {
     },
     LogWindow {
         start_line: usize,
-        lines: Vec<(usize, String)>,
+        lines: Vec<(usize, String, LineAttrs)>,
     },
     TotalLines(usize),
     Clear,
@@ -87,7 +584,45 @@ pub enum WorkerMsg {
     ExportLogs {
         include_timestamp: bool,
     },
-    ActiveLine(Option<String>),
-    SetMode(ViewMode),
+    RequestRaw {
+        start_line: usize,
+        end_line: usize,
+    },
+    RawBytes {
+        start_line: usize,
+        end_line: usize,
+        offset: u64,
+        total_len: u64,
+        bytes: Vec<u8>,
+    },
+    /// Raw bytes behind displayed lines `start..end`, or the whole capture
+    ExportRaw(Option<(usize, usize)>),
+    SearchBytes {
+        pattern: String,
+        use_regex: bool,
+    },
+    ByteSearchResults {
+        hits: Vec<ByteHit>,
+        truncated: bool,
+    },
+    ActiveLine(Option<(String, LineAttrs)>),
+    LoadElf {
+        name: String,
+        data: Vec<u8>,
+    },
+    UnloadElf,
+    SetDefmt(bool),
+    ElfLoaded(Option<ElfInfo>),
+    /// Symbols of the code addresses in the last requested window
+    Symbols(Vec<SymbolInfo>),
+    /// Frames of the latest backtrace received
+    Backtrace(Vec<SymbolInfo>),
+    /// Bytes sent to the port, recorded as TX
+    RecordTx(Vec<u8>),
+    /// Output unwrapped from a transfer protocol, shown but kept out of the raw journal
+    ShowOutput(Vec<u8>),
+    /// Turns the feed of plain received lines on or off
+    SetLineFeed(bool),
+    ReceivedLines(Vec<String>),
     Error(String),
 }
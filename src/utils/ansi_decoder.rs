@@ -1,16 +1,219 @@
-use crate::state::Highlight;
+use crate::state::{ByteToken, Highlight, TokenKind};
 use regex::Regex;
+use std::borrow::Cow;
 
-/// Processes log text to remove timestamps and split into highlight segments including ANSI colors
+/// Text color from an SGR sequence
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AnsiColor {
+    /// Palette index: 0-15 named colors, 16-231 color cube, 232-255 grays
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// Named colors 0-15. Black maps to gray so it stays readable on the dark console.
+const PALETTE: [&str; 16] = [
+    "#9ca3af", "#ef4444", "#10b981", "#f59e0b", "#3b82f6", "#d946ef", "#06b6d4", "#f3f4f6",
+    "#6b7280", "#f87171", "#34d399", "#fbbf24", "#60a5fa", "#e879f9", "#22d3ee", "#ffffff",
+];
+/// Console colors used when inverse video swaps unset colors
+const DEFAULT_FG: &str = "#d1d5db";
+const DEFAULT_BG: &str = "#0d0f10";
+
+impl AnsiColor {
+    fn css(self, background: bool) -> String {
+        match self {
+            // A black background is meant to be black
+            AnsiColor::Indexed(0) if background => "#000000".to_string(),
+            AnsiColor::Indexed(i @ 0..=15) => PALETTE[i as usize].to_string(),
+            AnsiColor::Indexed(i @ 16..=231) => {
+                let level = |v: u8| if v == 0 { 0 } else { 55 + v * 40 };
+                let i = i - 16;
+                format!(
+                    "#{:02x}{:02x}{:02x}",
+                    level(i / 36),
+                    level((i / 6) % 6),
+                    level(i % 6)
+                )
+            }
+            AnsiColor::Indexed(i) => {
+                let v = 8 + (i - 232) * 10;
+                format!("#{:02x}{:02x}{:02x}", v, v, v)
+            }
+            AnsiColor::Rgb(r, g, b) => format!("#{:02x}{:02x}{:02x}", r, g, b),
+        }
+    }
+}
+
+/// Graphic rendition in effect for a run of text
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TextStyle {
+    pub fg: Option<AnsiColor>,
+    pub bg: Option<AnsiColor>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub inverse: bool,
+}
+
+/// Reads an extended color (`5;n` or `2;r;g;b`) following a 38/48 code
+fn extended_color<'a>(params: &mut impl Iterator<Item = &'a str>) -> Option<AnsiColor> {
+    let mut next = || params.next().and_then(|p| p.parse::<u8>().ok());
+    match next()? {
+        5 => Some(AnsiColor::Indexed(next()?)),
+        2 => Some(AnsiColor::Rgb(next()?, next()?, next()?)),
+        _ => None,
+    }
+}
+
+impl TextStyle {
+    /// Applies the parameters of an SGR (`ESC [ ... m`) sequence. An empty
+    /// parameter means 0; invalid ones are skipped.
+    pub fn apply_sgr(&mut self, params: &str) {
+        let mut codes = params.split(';');
+        while let Some(code) = codes.next() {
+            let code = if code.is_empty() {
+                0
+            } else {
+                match code.parse::<u8>() {
+                    Ok(code) => code,
+                    Err(_) => continue,
+                }
+            };
+            match code {
+                0 => *self = TextStyle::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.inverse = true,
+                22 => {
+                    self.bold = false;
+                    self.dim = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.inverse = false,
+                c @ 30..=37 => self.fg = Some(AnsiColor::Indexed(c - 30)),
+                38 => self.fg = extended_color(&mut codes).or(self.fg),
+                39 => self.fg = None,
+                c @ 40..=47 => self.bg = Some(AnsiColor::Indexed(c - 40)),
+                48 => self.bg = extended_color(&mut codes).or(self.bg),
+                49 => self.bg = None,
+                c @ 90..=97 => self.fg = Some(AnsiColor::Indexed(c - 90 + 8)),
+                c @ 100..=107 => self.bg = Some(AnsiColor::Indexed(c - 100 + 8)),
+                _ => {}
+            }
+        }
+    }
+
+    /// Inline CSS for the style; `highlight` replaces the text color
+    pub fn css(&self, highlight: Option<&str>) -> String {
+        let mut fg = self.fg.map(|c| c.css(false));
+        let mut bg = self.bg.map(|c| c.css(true));
+        if self.inverse {
+            (fg, bg) = (
+                Some(bg.unwrap_or_else(|| DEFAULT_BG.to_string())),
+                Some(fg.unwrap_or_else(|| DEFAULT_FG.to_string())),
+            );
+        }
+        if let Some(h) = highlight {
+            fg = Some(h.to_string());
+        }
+
+        let mut css = String::new();
+        if let Some(c) = fg {
+            css.push_str(&format!("color: {};", c));
+        }
+        if let Some(c) = bg {
+            css.push_str(&format!("background-color: {};", c));
+        }
+        if self.bold || highlight.is_some() {
+            css.push_str("font-weight: bold;");
+        }
+        if self.dim {
+            css.push_str("opacity: 0.6;");
+        }
+        if self.italic {
+            css.push_str("font-style: italic;");
+        }
+        if self.underline {
+            css.push_str("text-decoration: underline;");
+        }
+        css
+    }
+}
+
+/// A run of text sharing one style
+#[derive(Clone, PartialEq, Debug)]
+pub struct StyledSegment {
+    pub text: String,
+    pub style: TextStyle,
+    /// Color of the user highlight covering the text, drawn over the ANSI style
+    pub highlight: Option<&'static str>,
+    /// Bytes shown as a token in place of text; `text` is then empty
+    pub token: Option<ByteToken>,
+}
+
+impl StyledSegment {
+    fn new(text: String, style: TextStyle) -> Self {
+        Self {
+            text,
+            style,
+            highlight: None,
+            token: None,
+        }
+    }
+
+    fn token(token: ByteToken, style: TextStyle) -> Self {
+        Self {
+            token: Some(token),
+            ..Self::new(String::new(), style)
+        }
+    }
+
+    pub fn css(&self) -> String {
+        self.style.css(self.highlight)
+    }
+}
+
+/// Pushes the text run `content[from..to]`, cut at the tokens placed inside it.
+/// Tokens left behind inside an escape sequence come first.
+fn push_run(
+    segments: &mut Vec<StyledSegment>,
+    content: &str,
+    (from, to): (usize, usize),
+    style: TextStyle,
+    tokens: &mut std::iter::Peekable<std::slice::Iter<'_, ByteToken>>,
+) {
+    let mut pos = from;
+    while let Some(token) = tokens.next_if(|t| t.at < to || to == content.len()) {
+        let at = token.at.clamp(pos, to);
+        if at > pos {
+            segments.push(StyledSegment::new(content[pos..at].to_string(), style));
+            pos = at;
+        }
+        segments.push(StyledSegment::token(token.clone(), style));
+    }
+    if to > pos {
+        segments.push(StyledSegment::new(content[pos..to].to_string(), style));
+    }
+}
+
+/// Splits log text into styled segments from its SGR sequences, with the
+/// line's byte tokens placed between them and user highlights applied on top.
+/// Control tokens, and the escape sequences left in the text, are only shown
+/// with `show_controls`.
 pub fn decode_ansi_text(
     text: &str,
+    tokens: &[ByteToken],
     highlights: &[Highlight],
     show_highlights: bool,
-) -> Vec<(String, Option<String>)> {
+    show_controls: bool,
+) -> Vec<StyledSegment> {
     let content = text;
 
-    // 2. ANSI Code Parsing
-    // We treat ANSI codes as the base segmentation, then apply user highlights on top.
+    // ANSI codes give the base segmentation, user highlights split it further
     let mut segments = Vec::new();
 
     // Using thread_local for Regex to avoid recompilation
@@ -20,7 +223,13 @@ pub fn decode_ansi_text(
     }
 
     let mut last_pos = 0;
-    let mut current_color: Option<String> = None;
+    let mut style = TextStyle::default();
+    let tokens: Vec<ByteToken> = tokens
+        .iter()
+        .filter(|t| show_controls || t.kind != TokenKind::Control)
+        .cloned()
+        .collect();
+    let mut tokens = tokens.iter().peekable();
 
     ANSI_RE.with(|re| {
         for cap in re.captures_iter(content) {
@@ -34,7 +243,21 @@ pub fn decode_ansi_text(
 
             // Push text before the code
             if start > last_pos {
-                segments.push((content[last_pos..start].to_string(), current_color.clone()));
+                push_run(
+                    &mut segments,
+                    content,
+                    (last_pos, start),
+                    style,
+                    &mut tokens,
+                );
+            }
+            if show_controls {
+                let token = ByteToken {
+                    at: start,
+                    kind: TokenKind::Control,
+                    bytes: m.as_str().as_bytes().to_vec(),
+                };
+                segments.push(StyledSegment::token(token, style));
             }
 
             // Command Processing
@@ -44,38 +267,16 @@ pub fn decode_ansi_text(
 
                 match cmd {
                     "m" => {
-                        // SGR - Select Graphic Rendition (Colors)
-                        if params.is_empty() {
-                            // \x1B[m is equivalent to \x1B[0m (Reset)
-                            current_color = None;
-                        } else {
-                            for code in params.split(';') {
-                                match code {
-                                    "0" => current_color = None,
-                                    // Standard Foreground Colors
-                                    "30" | "90" => current_color = Some("#9ca3af".to_string()), // Gray-400
-                                    "31" | "91" => current_color = Some("#ef4444".to_string()), // Red-500
-                                    "32" | "92" => current_color = Some("#10b981".to_string()), // Emerald-500
-                                    "33" | "93" => current_color = Some("#f59e0b".to_string()), // Amber-500
-                                    "34" | "94" => current_color = Some("#3b82f6".to_string()), // Blue-500
-                                    "35" | "95" => current_color = Some("#d946ef".to_string()), // Fuchsia-500
-                                    "36" | "96" => current_color = Some("#06b6d4".to_string()), // Cyan-500
-                                    "37" | "97" => current_color = Some("#f3f4f6".to_string()), // Gray-100
-                                    // Note: RGB/256 colors structure (38;2;... or 38;5;...) is partially split here.
-                                    // Since this logic processes code-by-code splitting by ';', it's imperfect for multi-param codes.
-                                    // However, for standard logs, it works. True robust parsing needs a stateful iterator.
-                                    _ => {}
-                                }
-                            }
-                        }
+                        // SGR - Select Graphic Rendition; \x1B[m is a reset
+                        style.apply_sgr(params);
                     }
                     "C" => {
                         // CUF - Cursor Forward (Spaces)
                         // \x1B[nC moves right n times. Default 1.
                         let count = params.parse::<usize>().unwrap_or(1);
                         let spaces = " ".repeat(count);
-                        // We push spaces using current color (relevant if background color logic existed)
-                        segments.push((spaces, current_color.clone()));
+                        // Spaces keep the current style so backgrounds stay continuous
+                        segments.push(StyledSegment::new(spaces, style));
                     }
                     "K" => {
                         // EL - Erase in Line
@@ -92,105 +293,201 @@ pub fn decode_ansi_text(
         }
     });
 
-    // Push remaining text
-    if last_pos < content.len() {
-        segments.push((content[last_pos..].to_string(), current_color));
-    } else if segments.is_empty() {
-        // If empty content or fully consumed by codes (unlikely to result in empty segment list if logic is right, but safe guard)
-        // Actually if content was just "\x1B[32m", we have last_pos == len, segments empty? No, last_pos would be len.
-        // Wait, if loop runs once, segments might be updated if start > last_pos.
-        // If content is just ANSI code, start=0, last_pos becomes len. loops end.
-        // Pushes?
-        // No, start > last_pos (0 > 0) is false.
-        // Loop finishes. last_pos == len.
-        // Checked: if last_pos < content.len() -> False.
-        // Result: Empty segments.
-        // But we probably want at least one empty segment if input was non-empty logic-wise?
-        // Actually empty list is fine, log line will just render nothing.
-    }
-
-    // Fallback if no ANSI codes were found, we treat the whole thing as one segment
-    if segments.is_empty() && !content.is_empty() {
-        segments.push((content.to_string(), None));
-    }
-    // If original content was empty, segments is empty, which is correct.
-
-    // 3. User Highlighting Overlay
-    // We process existing segments and split them further if keywords match
+    // Push remaining text and trailing tokens; text made only of codes yields no segments
+    push_run(
+        &mut segments,
+        content,
+        (last_pos, content.len()),
+        style,
+        &mut tokens,
+    );
+
     if show_highlights {
-        for h in highlights {
-            if h.text.is_empty() {
-                continue;
-            }
+        apply_highlights(segments, highlights)
+    } else {
+        segments
+    }
+}
 
-            let mut next_segments = Vec::new();
-
-            for (seg_text, color) in segments {
-                // We allow user highlights to override ANSI colors?
-                // Or only if ANSI color is None?
-                // Typically User Highlight is "Search" or "Important", so it should override.
-
-                // However, splitting logic relies on text.
-                if seg_text.contains(&h.text) {
-                    // Recover delimiters
-
-                    // Simple split_once logic was used before:
-                    // Only highlighted the FIRST occurrence per segment?
-                    // The previous code:
-                    // if let Some((prefix, suffix)) = seg_text.split_once(&h.text)
-
-                    // Let's stick to split_once for simplicity and stability as per previous implementation
-                    if let Some((prefix, suffix)) = seg_text.split_once(&h.text) {
-                        if !prefix.is_empty() {
-                            next_segments.push((prefix.to_string(), color.clone()));
-                        }
-
-                        // The Highlighted Part (Force User Color)
-                        next_segments.push((h.text.clone(), Some(h.color.to_string())));
-
-                        if !suffix.is_empty() {
-                            next_segments.push((suffix.to_string(), color.clone()));
-                            // Keep original background/ANSI color for suffix
-                        }
-                    } else {
-                        // Should not happen if contains is true
-                        next_segments.push((seg_text, color));
-                    }
-                } else {
-                    next_segments.push((seg_text, color));
-                }
+/// Marks every occurrence of each highlight in the visible text. Matching runs
+/// on the segments joined, so a highlight may span a style change; the first
+/// highlight wins where two overlap.
+fn apply_highlights(segments: Vec<StyledSegment>, highlights: &[Highlight]) -> Vec<StyledSegment> {
+    let visible: String = segments.iter().map(|s| s.text.as_str()).collect();
+    let mut marks: Vec<(usize, usize, &'static str)> = Vec::new();
+    for h in highlights.iter().filter(|h| !h.text.is_empty()) {
+        for (start, m) in visible.match_indices(&h.text) {
+            let end = start + m.len();
+            if !marks.iter().any(|&(s, e, _)| start < e && s < end) {
+                marks.push((start, end, h.color));
             }
-            segments = next_segments;
         }
     }
+    if marks.is_empty() {
+        return segments;
+    }
+
+    // Split each segment at mark boundaries, mapping visible offsets back to it
+    let mut out = Vec::new();
+    let mut seg_start = 0;
+    for seg in segments {
+        if seg.token.is_some() {
+            out.push(seg);
+            continue;
+        }
+        let seg_end = seg_start + seg.text.len();
+        let mut cuts = vec![seg_start, seg_end];
+        for &(s, e, _) in &marks {
+            cuts.extend([s, e].into_iter().filter(|&c| seg_start < c && c < seg_end));
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        for pair in cuts.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            out.push(StyledSegment {
+                text: seg.text[from - seg_start..to - seg_start].to_string(),
+                style: seg.style,
+                highlight: marks
+                    .iter()
+                    .find(|&&(s, e, _)| s <= from && to <= e)
+                    .map(|&(_, _, color)| color),
+                token: None,
+            });
+        }
+        seg_start = seg_end;
+    }
+    out
+}
+
+/// Longest escape sequence collected before it is passed on as is
+pub const MAX_ESCAPE_LEN: usize = 256;
+
+/// True once `seq`, which starts with a control byte, is a whole control
+/// byte or escape sequence (CSI, OSC or a short ESC sequence)
+pub fn escape_complete(seq: &[u8]) -> bool {
+    let last = seq[seq.len() - 1];
+    match seq {
+        [b] => *b != 0x1B,
+        _ if seq.len() >= MAX_ESCAPE_LEN => true,
+        [_, b'['] | [_, b']'] => false,
+        [_, b'[', ..] => (0x40..=0x7E).contains(&last),
+        [_, b']', ..] => last == 0x07 || seq.ends_with(b"\x1B\\"),
+        [_, 0x20..=0x2F, ..] => (0x30..=0x7E).contains(&last),
+        _ => true,
+    }
+}
+
+/// CSI, OSC and two-byte ESC sequences
+const ESCAPE_PATTERN: &str = r"\x1B(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1B]*(?:\x07|\x1B\\)|[0-~])";
+
+/// Removes escape sequences (CSI, OSC and two-byte ESC codes) from raw bytes,
+/// leaving everything else, including undecodable bytes, untouched
+pub fn strip_ansi_bytes(bytes: &[u8]) -> Vec<u8> {
+    thread_local! {
+        static ESCAPE_RE: regex::bytes::Regex =
+            regex::bytes::Regex::new(&format!("(?-u){}", ESCAPE_PATTERN)).unwrap();
+    }
+    ESCAPE_RE.with(|re| re.replace_all(bytes, &b""[..]).into_owned())
+}
 
-    segments
+/// The text of a stored line as displayed: escape sequences removed and
+/// cursor-forward gaps turned into the spaces they render as
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if !text.contains('\x1B') {
+        return Cow::Borrowed(text);
+    }
+    thread_local! {
+        static ESCAPE_RE: Regex = Regex::new(ESCAPE_PATTERN).unwrap();
+    }
+    let stripped = ESCAPE_RE.with(|re| {
+        re.replace_all(text, |caps: &regex::Captures| {
+            let seq = &caps[0];
+            match seq.strip_prefix("\x1B[").and_then(|s| s.strip_suffix('C')) {
+                Some(n) => " ".repeat(n.parse::<usize>().unwrap_or(1)),
+                None => String::new(),
+            }
+        })
+        .into_owned()
+    });
+    Cow::Owned(stripped)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::state::Highlight;
+    use crate::state::{Highlight, TokenKind};
+
+    fn fg_css(seg: &StyledSegment) -> Option<String> {
+        seg.style.fg.map(|c| c.css(false))
+    }
 
     #[test]
     fn test_ansi_parsing() {
         let highlights = vec![];
 
         // Green text
-        let res = decode_ansi_text("\x1B[32mHello\x1B[0m", &highlights, false);
+        let res = decode_ansi_text("\x1B[32mHello\x1B[0m", &[], &highlights, false, false);
         assert_eq!(res.len(), 1);
-        assert_eq!(res[0].0, "Hello");
-        assert_eq!(res[0].1.as_deref(), Some("#10b981"));
+        assert_eq!(res[0].text, "Hello");
+        assert_eq!(fg_css(&res[0]).as_deref(), Some("#10b981"));
 
         // Mixed
-        let res = decode_ansi_text("A\x1B[31mB\x1B[0mC", &highlights, false);
+        let res = decode_ansi_text("A\x1B[31mB\x1B[0mC", &[], &highlights, false, false);
         assert_eq!(res.len(), 3);
-        assert_eq!(res[0].0, "A");
-        assert_eq!(res[0].1, None);
-        assert_eq!(res[1].0, "B");
-        assert_eq!(res[1].1.as_deref(), Some("#ef4444"));
-        assert_eq!(res[2].0, "C");
-        assert_eq!(res[2].1, None);
+        assert_eq!(res[0].text, "A");
+        assert_eq!(res[0].style, TextStyle::default());
+        assert_eq!(res[1].text, "B");
+        assert_eq!(fg_css(&res[1]).as_deref(), Some("#ef4444"));
+        assert_eq!(res[2].text, "C");
+        assert_eq!(res[2].style, TextStyle::default());
+    }
+
+    #[test]
+    fn test_extended_sgr() {
+        let res = decode_ansi_text(
+            "\x1B[1;38;5;196;48;2;0;0;128mA\x1B[22;3;4;7mB\x1B[39;49;23mC",
+            &[],
+            &[],
+            false,
+            false,
+        );
+        assert_eq!(res[0].style.fg, Some(AnsiColor::Indexed(196)));
+        assert_eq!(res[0].style.bg, Some(AnsiColor::Rgb(0, 0, 128)));
+        assert!(res[0].style.bold);
+        assert_eq!(
+            res[0].css(),
+            "color: #ff0000;background-color: #000080;font-weight: bold;"
+        );
+
+        // Inverse swaps the colors; bold is cleared by 22
+        assert_eq!(
+            res[1].css(),
+            "color: #000080;background-color: #ff0000;font-style: italic;text-decoration: underline;"
+        );
+
+        // Defaults fill in for unset colors under inverse
+        assert_eq!(
+            res[2].css(),
+            format!(
+                "color: {};background-color: {};text-decoration: underline;",
+                DEFAULT_BG, DEFAULT_FG
+            )
+        );
+        assert_eq!(AnsiColor::Indexed(244).css(false), "#808080");
+    }
+
+    #[test]
+    fn test_invalid_sgr_params_are_skipped() {
+        let mut style = TextStyle::default();
+        style.apply_sgr("1;31");
+        // Out of range, garbage and a bad extended color leave the style alone
+        style.apply_sgr("300;x;38;5;999");
+        assert!(style.bold);
+        assert_eq!(style.fg, Some(AnsiColor::Indexed(1)));
+        // An empty parameter list is a reset
+        style.apply_sgr("");
+        assert_eq!(style, TextStyle::default());
     }
 
     #[test]
@@ -202,13 +499,143 @@ mod tests {
         }];
 
         // ANSI Green text containing "Error"
-        let res = decode_ansi_text("\x1B[32mNoErrorHere\x1B[0m", &highlights, true);
+        let res = decode_ansi_text("\x1B[32mNoErrorHere\x1B[0m", &[], &highlights, true, false);
         assert_eq!(res.len(), 3);
-        assert_eq!(res[0].0, "No");
-        assert_eq!(res[0].1.as_deref(), Some("#10b981")); // Green
-        assert_eq!(res[1].0, "Error");
-        assert_eq!(res[1].1.as_deref(), Some("blue")); // User Blue wins
-        assert_eq!(res[2].0, "Here");
-        assert_eq!(res[2].1.as_deref(), Some("#10b981")); // Green
+        assert_eq!(res[0].text, "No");
+        assert_eq!(fg_css(&res[0]).as_deref(), Some("#10b981")); // Green
+        assert_eq!(res[1].text, "Error");
+        assert_eq!(res[1].highlight, Some("blue")); // User Blue wins
+        assert_eq!(res[2].text, "Here");
+        assert_eq!(fg_css(&res[2]).as_deref(), Some("#10b981")); // Green
+    }
+
+    #[test]
+    fn test_highlight_keeps_sgr_attributes() {
+        let highlights = vec![Highlight {
+            id: 1,
+            text: "Error".to_string(),
+            color: "blue",
+        }];
+
+        // ANSI Green underlined text containing "Error"
+        let res = decode_ansi_text(
+            "\x1B[4;32mNoErrorHere\x1B[0m",
+            &[],
+            &highlights,
+            true,
+            false,
+        );
+        assert_eq!(res.len(), 3);
+        assert_eq!(res[1].text, "Error");
+        // User blue wins over green; the underline stays
+        assert_eq!(
+            res[1].css(),
+            "color: blue;font-weight: bold;text-decoration: underline;"
+        );
+        assert_eq!(fg_css(&res[2]).as_deref(), Some("#10b981")); // Green
+    }
+
+    #[test]
+    fn test_highlight_spans_style_change() {
+        let highlights = vec![Highlight {
+            id: 1,
+            text: "E (12)".to_string(),
+            color: "red",
+        }];
+        let res = decode_ansi_text(
+            "\x1B[31mE\x1B[0m (12) E (12)",
+            &[],
+            &highlights,
+            true,
+            false,
+        );
+        let texts: Vec<&str> = res.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, ["E", " (12)", " ", "E (12)"]);
+        let marked: Vec<bool> = res.iter().map(|s| s.highlight.is_some()).collect();
+        assert_eq!(marked, [true, true, false, true]);
+        // The first part keeps its ANSI style under the highlight
+        assert_eq!(res[0].style.fg, Some(AnsiColor::Indexed(1)));
+    }
+
+    #[test]
+    fn test_tokens_split_text() {
+        let token = |at, b| ByteToken {
+            at,
+            kind: TokenKind::Invalid,
+            bytes: vec![b],
+        };
+        let highlights = vec![Highlight {
+            id: 1,
+            text: "ab".to_string(),
+            color: "red",
+        }];
+        let tokens = [token(1, 0xFF), token(8, 0xFE)];
+        let res = decode_ansi_text("a\x1B[31mbc", &tokens, &highlights, true, false);
+        let kinds: Vec<(&str, bool)> = res
+            .iter()
+            .map(|s| (s.text.as_str(), s.token.is_some()))
+            .collect();
+        // The highlight matches across the token
+        assert_eq!(
+            kinds,
+            [
+                ("a", false),
+                ("", true),
+                ("b", false),
+                ("c", false),
+                ("", true)
+            ]
+        );
+        assert!(res[0].highlight.is_some() && res[2].highlight.is_some());
+        assert_eq!(res[4].token.as_ref().map(|t| t.bytes[0]), Some(0xFE));
+    }
+
+    #[test]
+    fn test_control_tokens_follow_setting() {
+        let tokens = [ByteToken {
+            at: 6,
+            kind: TokenKind::Control,
+            bytes: b"\x1B[K".to_vec(),
+        }];
+        let text = "\x1B[31mA";
+        let hidden = decode_ansi_text(text, &tokens, &[], false, false);
+        assert_eq!(hidden.len(), 1);
+        assert_eq!(hidden[0].text, "A");
+
+        // The SGR sequence kept in the text shows too
+        let shown = decode_ansi_text(text, &tokens, &[], false, true);
+        let labels: Vec<String> = shown
+            .iter()
+            .map(|s| s.token.as_ref().map_or(s.text.clone(), |t| t.label()))
+            .collect();
+        assert_eq!(labels, ["<1B>[31m", "A", "<1B>[K"]);
+    }
+
+    #[test]
+    fn test_escape_complete() {
+        assert!(escape_complete(b"\x07"));
+        assert!(!escape_complete(b"\x1B"));
+        assert!(!escape_complete(b"\x1B[1;3"));
+        assert!(escape_complete(b"\x1B[1;3A"));
+        assert!(!escape_complete(b"\x1B]0;title"));
+        assert!(escape_complete(b"\x1B]0;title\x07"));
+        assert!(!escape_complete(b"\x1B("));
+        assert!(escape_complete(b"\x1B(B"));
+        assert!(escape_complete(b"\x1BM"));
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(
+            strip_ansi("\x1B[0;31mE (1234) wifi:\x1B[0m up\x1B[3Cx"),
+            "E (1234) wifi: up   x"
+        );
+        assert!(matches!(strip_ansi("plain"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_strip_ansi_bytes() {
+        let raw = b"\x1B[1;32mOK\x1B[0m \x1B]0;title\x07done\xFF\x1B7";
+        assert_eq!(strip_ansi_bytes(raw), b"OK done\xFF".to_vec());
     }
 }
@@ -0,0 +1,306 @@
+use crate::types::TextEncoding;
+use crate::utils::encoding::{take_tokens, StreamDecoder};
+use serde::{Deserialize, Serialize};
+use std::rc::Rc;
+
+/// Header line of an asciicast v2 file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u8,
+    pub width: u16,
+    pub height: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Output,
+    Input,
+}
+
+impl EventKind {
+    fn code(self) -> &'static str {
+        match self {
+            EventKind::Output => "o",
+            EventKind::Input => "i",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastEvent {
+    /// Seconds since the start of the recording
+    pub time: f64,
+    pub kind: EventKind,
+    pub data: String,
+}
+
+/// A parsed or recorded session
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cast {
+    pub header: CastHeader,
+    pub events: Vec<CastEvent>,
+}
+
+impl Cast {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        let header: CastHeader = lines
+            .next()
+            .ok_or("Empty recording")
+            .and_then(|l| serde_json::from_str(l).map_err(|_| "Invalid asciicast header"))?;
+        if header.version != 2 {
+            return Err(format!("Unsupported asciicast version {}", header.version));
+        }
+
+        let mut events = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let (time, code, data): (f64, String, String) = serde_json::from_str(line)
+                .map_err(|e| format!("Invalid event on line {}: {}", i + 2, e))?;
+            let kind = match code.as_str() {
+                "o" => EventKind::Output,
+                "i" => EventKind::Input,
+                // Markers and resize events are not replayed
+                _ => continue,
+            };
+            events.push(CastEvent { time, kind, data });
+        }
+        Ok(Self { header, events })
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |e| e.time)
+    }
+
+    /// The file contents split into chunks of about `chunk_size` bytes
+    pub fn to_chunks(&self, chunk_size: usize) -> Vec<Vec<u8>> {
+        let mut chunks = Vec::new();
+        let mut current = serde_json::to_vec(&self.header).unwrap_or_default();
+        current.push(b'\n');
+        for event in &self.events {
+            let time = (event.time * 1e6).round() / 1e6;
+            let line = (time, event.kind.code(), event.data.as_str());
+            if let Ok(json) = serde_json::to_vec(&line) {
+                current.extend(json);
+                current.push(b'\n');
+            }
+            if current.len() >= chunk_size {
+                chunks.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            chunks.push(current);
+        }
+        chunks
+    }
+}
+
+/// Collects terminal traffic into a cast as it happens
+pub struct CastRecorder {
+    cast: Cast,
+    record_input: bool,
+    /// Decodes output with the monitor's encoding, keeping sequences split
+    /// across chunks intact
+    decoder: StreamDecoder,
+}
+
+impl CastRecorder {
+    pub fn new(
+        width: u16,
+        height: u16,
+        timestamp: u64,
+        record_input: bool,
+        encoding: TextEncoding,
+    ) -> Self {
+        Self {
+            cast: Cast {
+                header: CastHeader {
+                    version: 2,
+                    width,
+                    height,
+                    timestamp: Some(timestamp),
+                },
+                events: Vec::new(),
+            },
+            record_input,
+            decoder: StreamDecoder::new(encoding),
+        }
+    }
+
+    pub fn output(&mut self, time: f64, bytes: &[u8]) {
+        let (mut text, tokens) = take_tokens(&self.decoder.decode(bytes, false));
+        // Only malformed bytes come back as tokens; each run becomes U+FFFD
+        for token in tokens.iter().rev() {
+            text.insert(token.at, char::REPLACEMENT_CHARACTER);
+        }
+        self.push(time, EventKind::Output, text);
+    }
+
+    pub fn input(&mut self, time: f64, text: &str) {
+        if self.record_input {
+            self.push(time, EventKind::Input, text.to_string());
+        }
+    }
+
+    fn push(&mut self, time: f64, kind: EventKind, data: String) {
+        if !data.is_empty() {
+            self.cast.events.push(CastEvent { time, kind, data });
+        }
+    }
+
+    /// Takes the recorded session, leaving the recorder empty
+    pub fn finish(&mut self) -> Cast {
+        Cast {
+            header: self.cast.header.clone(),
+            events: std::mem::take(&mut self.cast.events),
+        }
+    }
+}
+
+/// Replays the output of a cast at an adjustable speed
+#[derive(Clone)]
+pub struct CastPlayer {
+    pub cast: Rc<Cast>,
+    pub name: String,
+    pub position: f64,
+    pub playing: bool,
+    pub speed: f64,
+    next: usize,
+}
+
+impl PartialEq for CastPlayer {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.cast, &other.cast)
+            && self.position == other.position
+            && self.playing == other.playing
+            && self.speed == other.speed
+    }
+}
+
+impl CastPlayer {
+    pub fn new(cast: Cast, name: &str) -> Self {
+        Self {
+            cast: Rc::new(cast),
+            name: name.to_string(),
+            position: 0.0,
+            playing: true,
+            speed: 1.0,
+            next: 0,
+        }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.cast.duration()
+    }
+
+    /// Output due after `elapsed` seconds of wall time; pauses at the end
+    pub fn tick(&mut self, elapsed: f64) -> String {
+        if !self.playing {
+            return String::new();
+        }
+        self.position = (self.position + elapsed * self.speed).min(self.duration());
+        if self.position >= self.duration() {
+            self.playing = false;
+        }
+        self.drain_until(self.position)
+    }
+
+    /// Output to write into a freshly reset terminal to show `position`
+    pub fn seek(&mut self, position: f64) -> String {
+        self.position = position.clamp(0.0, self.duration());
+        self.next = 0;
+        self.drain_until(self.position)
+    }
+
+    /// Starts over when play is pressed at the end
+    pub fn toggle(&mut self) -> Option<String> {
+        self.playing = !self.playing;
+        (self.playing && self.position >= self.duration()).then(|| self.seek(0.0))
+    }
+
+    fn drain_until(&mut self, position: f64) -> String {
+        let mut out = String::new();
+        while let Some(event) = self.cast.events.get(self.next) {
+            if event.time > position {
+                break;
+            }
+            if event.kind == EventKind::Output {
+                out.push_str(&event.data);
+            }
+            self.next += 1;
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_parse_roundtrip() {
+        let mut recorder = CastRecorder::new(80, 24, 1_700_000_000, true, TextEncoding::Utf8);
+        // "é" split across two chunks
+        recorder.output(0.1, b"caf\xc3");
+        recorder.output(0.25, b"\xa9\r\n$ ");
+        recorder.input(1.0, "ls\r");
+        let cast = recorder.finish();
+
+        let text = String::from_utf8(cast.to_chunks(16).concat()).unwrap();
+        let mut lines = text.lines();
+        assert_eq!(
+            lines.next(),
+            Some(r#"{"version":2,"width":80,"height":24,"timestamp":1700000000}"#)
+        );
+        assert_eq!(lines.next(), Some(r#"[0.1,"o","caf"]"#));
+        assert_eq!(lines.next(), Some(r#"[0.25,"o","é\r\n$ "]"#));
+        assert_eq!(lines.next(), Some(r#"[1.0,"i","ls\r"]"#));
+        assert_eq!(Cast::parse(&text).unwrap(), cast);
+
+        let mut output_only = CastRecorder::new(80, 24, 0, false, TextEncoding::Utf8);
+        output_only.input(0.5, "x");
+        assert!(output_only.finish().events.is_empty());
+    }
+
+    #[test]
+    fn test_record_uses_encoding() {
+        let mut latin1 = CastRecorder::new(80, 24, 0, false, TextEncoding::Latin1);
+        latin1.output(0.1, b"caf\xe9");
+        let mut utf8 = CastRecorder::new(80, 24, 0, false, TextEncoding::Utf8);
+        utf8.output(0.1, b"a\xff\xfeb");
+        assert_eq!(latin1.finish().events[0].data, "caf\u{e9}");
+        assert_eq!(utf8.finish().events[0].data, "a\u{fffd}b");
+    }
+
+    #[test]
+    fn test_parse_rejects_other_versions() {
+        assert!(Cast::parse(r#"{"version":1,"width":80,"height":24}"#).is_err());
+        assert!(Cast::parse("").is_err());
+        let cast =
+            Cast::parse("{\"version\":2,\"width\":10,\"height\":5}\n[0.5,\"m\",\"\"]\n").unwrap();
+        assert!(cast.events.is_empty());
+    }
+
+    #[test]
+    fn test_player_tick_and_seek() {
+        let cast = Cast::parse(concat!(
+            "{\"version\":2,\"width\":80,\"height\":24}\n",
+            "[0.5,\"o\",\"a\"]\n",
+            "[1.0,\"i\",\"x\"]\n",
+            "[1.5,\"o\",\"b\"]\n",
+            "[3.0,\"o\",\"c\"]\n",
+        ))
+        .unwrap();
+        let mut player = CastPlayer::new(cast, "demo.cast");
+        assert_eq!(player.tick(0.4), "");
+        assert_eq!(player.tick(0.2), "a");
+        player.speed = 2.0;
+        assert_eq!(player.tick(0.5), "b");
+        assert_eq!(player.seek(2.0), "ab");
+        assert_eq!(player.tick(5.0), "c");
+        assert!(!player.playing);
+        assert_eq!(player.toggle(), Some("".to_string()));
+        assert_eq!(player.position, 0.0);
+    }
+}
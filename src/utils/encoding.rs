@@ -0,0 +1,283 @@
+use crate::types::{ByteToken, TextEncoding, TokenKind};
+use encoding_rs::{DecoderResult, EncoderResult, Encoding};
+use std::borrow::Cow;
+
+/// Between decoding and `take_tokens`, bytes to be shown as tokens are carried
+/// in the text as code points of Supplementary Private Use Area-A: the byte
+/// value is added to a base. Genuine characters in the range are preceded by
+/// `ESCAPE_MARK`, so marks never reach storage and real text is never taken for one.
+const INVALID_MARK: u32 = 0xF0000;
+const CONTROL_MARK: u32 = 0xF0100;
+const CONTENT_MARK: u32 = 0xF0200;
+const ESCAPE_MARK: char = '\u{F0300}';
+
+fn is_mark(c: char) -> bool {
+    (INVALID_MARK..=ESCAPE_MARK as u32).contains(&(c as u32))
+}
+
+fn mark_char(base: u32, byte: u8) -> char {
+    char::from_u32(base + byte as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// Mark standing for a byte shown as a token of `kind`
+pub fn token_mark(kind: TokenKind, byte: u8) -> char {
+    let base = match kind {
+        TokenKind::Invalid => INVALID_MARK,
+        TokenKind::Control => CONTROL_MARK,
+        TokenKind::Content => CONTENT_MARK,
+    };
+    mark_char(base, byte)
+}
+
+/// Escapes characters of `text` that would read as marks
+pub fn escape_marks(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(is_mark) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len() + 4);
+    for c in text.chars() {
+        if is_mark(c) {
+            out.push(ESCAPE_MARK);
+        }
+        out.push(c);
+    }
+    Cow::Owned(out)
+}
+
+/// Splits marked text into plain text and the tokens the marks stand for.
+/// Token positions are byte offsets in the returned text.
+pub fn take_tokens(text: &str) -> (String, Vec<ByteToken>) {
+    if !text.chars().any(is_mark) {
+        return (text.to_string(), Vec::new());
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut tokens: Vec<ByteToken> = Vec::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == ESCAPE_MARK {
+            out.extend(chars.next());
+            continue;
+        }
+        if !is_mark(c) {
+            out.push(c);
+            continue;
+        }
+        let code = c as u32;
+        let (kind, base) = match code {
+            c if c < CONTROL_MARK => (TokenKind::Invalid, INVALID_MARK),
+            c if c < CONTENT_MARK => (TokenKind::Control, CONTROL_MARK),
+            _ => (TokenKind::Content, CONTENT_MARK),
+        };
+        let byte = (code - base) as u8;
+        match tokens.last_mut() {
+            Some(t) if t.at == out.len() && t.kind == kind => t.bytes.push(byte),
+            _ => tokens.push(ByteToken {
+                at: out.len(),
+                kind,
+                bytes: vec![byte],
+            }),
+        }
+    }
+    (out, tokens)
+}
+
+pub fn encoding_for(enc: TextEncoding) -> &'static Encoding {
+    match enc {
+        TextEncoding::Utf8 => encoding_rs::UTF_8,
+        TextEncoding::Latin1 => encoding_rs::WINDOWS_1252,
+        TextEncoding::ShiftJis => encoding_rs::SHIFT_JIS,
+        TextEncoding::EucJp => encoding_rs::EUC_JP,
+        TextEncoding::EucKr => encoding_rs::EUC_KR,
+        TextEncoding::Gbk => encoding_rs::GBK,
+        TextEncoding::Big5 => encoding_rs::BIG5,
+    }
+}
+
+/// True for bytes escaped in visible control character mode. Tabs still
+/// expand, since they carry layout rather than hide content.
+pub fn is_escaped_control(b: u8) -> bool {
+    (b < 0x20 && b != b'\t') || b == 0x7F
+}
+
+/// Replaces control bytes in decoded UTF-8 with marks for tokens of `kind`.
+/// Control bytes never occur inside a multi-byte sequence, so this is safe bytewise.
+pub fn mark_control_bytes(text: &[u8], kind: TokenKind) -> Cow<'_, [u8]> {
+    if !text.iter().any(|&b| is_escaped_control(b)) {
+        return Cow::Borrowed(text);
+    }
+    let mut out = Vec::with_capacity(text.len() + 8);
+    for &b in text {
+        if is_escaped_control(b) {
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(token_mark(kind, b).encode_utf8(&mut buf).as_bytes());
+        } else {
+            out.push(b);
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Streaming decoder that keeps multi-byte sequences split across chunks intact
+/// and marks malformed bytes for `take_tokens` instead of writing U+FFFD.
+pub struct StreamDecoder {
+    decoder: encoding_rs::Decoder,
+    invalid_count: u64,
+    invalid_sequences: u64,
+}
+
+impl StreamDecoder {
+    pub fn new(enc: TextEncoding) -> Self {
+        Self {
+            decoder: encoding_for(enc).new_decoder_without_bom_handling(),
+            invalid_count: 0,
+            invalid_sequences: 0,
+        }
+    }
+
+    /// Number of undecodable bytes seen since creation
+    pub fn invalid_count(&self) -> u64 {
+        self.invalid_count
+    }
+
+    /// Number of malformed sequences seen since creation
+    pub fn invalid_sequences(&self) -> u64 {
+        self.invalid_sequences
+    }
+
+    pub fn decode(&mut self, chunk: &[u8], last: bool) -> String {
+        let capacity = self
+            .decoder
+            .max_utf8_buffer_length_without_replacement(chunk.len())
+            .unwrap_or(chunk.len() * 4)
+            .max(4);
+        let mut out = String::with_capacity(capacity);
+        let mut src = chunk;
+
+        loop {
+            let before = out.len();
+            let (result, read) = self
+                .decoder
+                .decode_to_string_without_replacement(src, &mut out, last);
+            if let Cow::Owned(escaped) = escape_marks(&out[before..]) {
+                out.truncate(before);
+                out.push_str(&escaped);
+            }
+            match result {
+                DecoderResult::InputEmpty => break,
+                DecoderResult::OutputFull => out.reserve(capacity),
+                DecoderResult::Malformed(bad, consumed_after) => {
+                    // The malformed bytes end `consumed_after` bytes before `read`.
+                    // Bytes of the sequence that arrived in an earlier chunk are not
+                    // available any more and are reported by count only.
+                    let end = read.saturating_sub(consumed_after as usize);
+                    let start = end.saturating_sub(bad as usize);
+                    for &b in &src[start..end] {
+                        out.push(mark_char(INVALID_MARK, b));
+                    }
+                    for _ in (end - start)..bad as usize {
+                        out.push(char::REPLACEMENT_CHARACTER);
+                    }
+                    self.invalid_count += bad as u64;
+                    self.invalid_sequences += 1;
+                }
+            }
+            src = &src[read..];
+        }
+        if last {
+            // Ready for the next stream, such as the next record
+            self.decoder = self.decoder.encoding().new_decoder_without_bom_handling();
+        }
+        out
+    }
+}
+
+/// Encodes outgoing text, failing on characters the encoding cannot represent
+/// rather than sending HTML numeric references.
+pub fn encode_text(text: &str, enc: TextEncoding) -> Result<Vec<u8>, String> {
+    let encoding = encoding_for(enc);
+    if encoding == encoding_rs::UTF_8 {
+        return Ok(text.as_bytes().to_vec());
+    }
+
+    let mut encoder = encoding.new_encoder();
+    let capacity = encoder
+        .max_buffer_length_from_utf8_without_replacement(text.len())
+        .unwrap_or(text.len() * 4);
+    let mut out = Vec::with_capacity(capacity);
+    let (result, _) = encoder.encode_from_utf8_to_vec_without_replacement(text, &mut out, true);
+    match result {
+        EncoderResult::InputEmpty => Ok(out),
+        EncoderResult::Unmappable(c) => {
+            Err(format!("'{}' cannot be encoded as {}", c, enc.label()))
+        }
+        EncoderResult::OutputFull => Err("Encoding buffer too small".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_split_multibyte_sequence() {
+        // "가" in EUC-KR is B0 A1; split it across two chunks
+        let mut decoder = StreamDecoder::new(TextEncoding::EucKr);
+        assert_eq!(decoder.decode(b"A\xB0", false), "A");
+        assert_eq!(decoder.decode(b"\xA1B", false), "\u{AC00}B");
+        assert_eq!(decoder.invalid_count(), 0);
+    }
+
+    #[test]
+    fn test_decode_invalid_bytes_become_tokens() {
+        let mut decoder = StreamDecoder::new(TextEncoding::Utf8);
+        let text = decoder.decode(b"ok\xFF\xFEok", false);
+        assert_eq!(decoder.invalid_count(), 2);
+        assert_eq!(decoder.invalid_sequences(), 2);
+
+        let (text, tokens) = take_tokens(&text);
+        assert_eq!(text, "okok");
+        assert_eq!(
+            tokens,
+            vec![ByteToken {
+                at: 2,
+                kind: TokenKind::Invalid,
+                bytes: vec![0xFF, 0xFE],
+            }]
+        );
+        assert_eq!(tokens[0].label(), "\\xFF\\xFE");
+    }
+
+    #[test]
+    fn test_private_use_text_is_not_a_token() {
+        // Real text in the mark range survives decoding unchanged
+        let real = "a\u{F0041}\u{F0300}b";
+        let mut decoder = StreamDecoder::new(TextEncoding::Utf8);
+        let decoded = decoder.decode(real.as_bytes(), false);
+        assert_eq!(take_tokens(&decoded), (real.to_string(), Vec::new()));
+        assert_eq!(take_tokens(&escape_marks(real)).0, real);
+    }
+
+    #[test]
+    fn test_mark_control_bytes() {
+        let marked = mark_control_bytes(b"\x1b[1mA\tB\x00", TokenKind::Control);
+        let (text, tokens) = take_tokens(std::str::from_utf8(&marked).unwrap());
+        assert_eq!(text, "[1mA\tB");
+        assert_eq!(tokens.len(), 2);
+        assert_eq!((tokens[0].at, tokens[0].label()), (0, "<1B>".to_string()));
+        assert_eq!((tokens[1].at, tokens[1].label()), (6, "<00>".to_string()));
+        assert!(matches!(
+            mark_control_bytes(b"plain", TokenKind::Control),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_encode_text() {
+        assert_eq!(
+            encode_text("\u{AC00}", TextEncoding::EucKr),
+            Ok(vec![0xB0, 0xA1])
+        );
+        assert_eq!(encode_text("é", TextEncoding::Latin1), Ok(vec![0xE9]));
+        assert!(encode_text("\u{AC00}", TextEncoding::Latin1).is_err());
+    }
+}
@@ -2,6 +2,48 @@ use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(module = "/public/assets/js/file_save.js")]
 extern "C" {
-    pub fn save_stream_to_disk(stream: JsValue);
+    pub fn save_stream_to_disk(stream: JsValue, suggested_name: &str);
     pub fn save_terminal_history(terminal: &JsValue);
 }
+
+/// A ReadableStream over chunks built on the main thread, for `save_stream_to_disk`
+pub fn chunk_stream(chunks: Vec<Vec<u8>>) -> JsValue {
+    let stream = futures_util::stream::iter(
+        chunks
+            .into_iter()
+            .map(|chunk| Ok(JsValue::from(js_sys::Uint8Array::from(&chunk[..])))),
+    );
+    wasm_streams::ReadableStream::from_stream(stream)
+        .into_raw()
+        .into()
+}
+
+/// A ReadableStream fed through the returned sink, for saving data that is
+/// still arriving. Dropping the sink ends the file.
+pub fn channel_stream() -> (JsValue, StreamSink) {
+    use futures_util::StreamExt;
+    let (sender, receiver) = futures_channel::mpsc::unbounded::<Result<Vec<u8>, JsValue>>();
+    let stream = receiver.map(|chunk| chunk.map(|c| js_sys::Uint8Array::from(&c[..]).into()));
+    let stream = wasm_streams::ReadableStream::from_stream(stream)
+        .into_raw()
+        .into();
+    (stream, StreamSink(sender))
+}
+
+/// Sending half of `channel_stream`
+pub struct StreamSink(futures_channel::mpsc::UnboundedSender<Result<Vec<u8>, JsValue>>);
+
+impl StreamSink {
+    pub fn send(&self, chunk: Vec<u8>) {
+        let _ = self.0.unbounded_send(Ok(chunk));
+    }
+
+    /// Errors the stream, so the save is aborted instead of keeping what
+    /// arrived so far as a complete file
+    pub fn abort(self, reason: &str) {
+        let error = js_sys::Error::new(reason);
+        // The save reports other errors itself; the caller already has this one
+        error.set_name("AbortError");
+        let _ = self.0.unbounded_send(Err(error.into()));
+    }
+}
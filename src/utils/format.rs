@@ -9,7 +9,7 @@ pub fn parse_hex_string(input: &str) -> Result<Vec<u8>, String> {
         .replace("-", "")
         .replace("0x", "");
 
-    if clean.len() % 2 != 0 {
+    if !clean.len().is_multiple_of(2) {
         return Err("Hex string must have an even number of characters".to_string());
     }
 
@@ -35,6 +35,80 @@ pub fn format_hex_input(input: &str) -> String {
         .join(" ")
 }
 
+/// Byte count for progress displays, e.g. `512 B`, `12.3 KB`, `1.50 MB`
+pub fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.2} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// Parses a list of delimiters written with escapes (`\n`, `\r`, `\t`, `\0`,
+/// `\xHH`, `\\`, `\|`). Alternatives are separated by `|`.
+pub fn parse_delimiter_list(input: &str) -> Result<Vec<Vec<u8>>, String> {
+    let mut list = Vec::new();
+    let mut current = Vec::new();
+    let mut chars = input.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '|' => list.push(std::mem::take(&mut current)),
+            '\\' => match chars.next() {
+                Some('n') => current.push(b'\n'),
+                Some('r') => current.push(b'\r'),
+                Some('t') => current.push(b'\t'),
+                Some('0') => current.push(0),
+                Some('\\') => current.push(b'\\'),
+                Some('|') => current.push(b'|'),
+                Some('x') => {
+                    let hex: String = chars.by_ref().take(2).collect();
+                    let byte = u8::from_str_radix(&hex, 16)
+                        .ok()
+                        .filter(|_| hex.len() == 2)
+                        .ok_or_else(|| format!("Invalid escape \\x{}", hex))?;
+                    current.push(byte);
+                }
+                Some(other) => return Err(format!("Unknown escape \\{}", other)),
+                None => return Err("Trailing backslash".to_string()),
+            },
+            _ => {
+                let mut buf = [0u8; 4];
+                current.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    list.push(current);
+
+    if list.iter().any(|d| d.is_empty()) {
+        return Err("Empty delimiter".to_string());
+    }
+    Ok(list)
+}
+
+/// Writes delimiters back in the form `parse_delimiter_list` reads
+pub fn format_delimiter_list(list: &[Vec<u8>]) -> String {
+    let mut out = String::new();
+    for (i, delimiter) in list.iter().enumerate() {
+        if i > 0 {
+            out.push('|');
+        }
+        for &b in delimiter {
+            match b {
+                b'\n' => out.push_str("\\n"),
+                b'\r' => out.push_str("\\r"),
+                b'\t' => out.push_str("\\t"),
+                0 => out.push_str("\\0"),
+                b'\\' => out.push_str("\\\\"),
+                b'|' => out.push_str("\\|"),
+                b' '..=b'~' => out.push(b as char),
+                _ => out.push_str(&format!("\\x{:02X}", b)),
+            }
+        }
+    }
+    out
+}
+
 /// Helper to send raw byte chunk to worker
 pub fn send_chunk_to_worker(worker: &web_sys::Worker, arr: js_sys::Uint8Array, is_hex: bool) {
     // 1. Get buffer (JS Heap)
@@ -53,6 +127,20 @@ pub fn send_chunk_to_worker(worker: &web_sys::Worker, arr: js_sys::Uint8Array, i
     let _ = worker.post_message_with_transfer(&obj, &transfer);
 }
 
+/// Sends a firmware ELF to the worker, transferring its buffer like a chunk
+pub fn send_elf_to_worker(worker: &web_sys::Worker, name: &str, data: &[u8]) {
+    let arr = js_sys::Uint8Array::from(data);
+    let buffer = arr.buffer();
+
+    let obj = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&obj, &"cmd".into(), &"LoadElf".into());
+    let _ = js_sys::Reflect::set(&obj, &"name".into(), &name.into());
+    let _ = js_sys::Reflect::set(&obj, &"data".into(), &arr.into());
+
+    let transfer = js_sys::Array::of1(&buffer);
+    let _ = worker.post_message_with_transfer(&obj, &transfer);
+}
+
 /// Helper to send general control messages to worker
 pub fn send_worker_msg(worker: &web_sys::Worker, msg: crate::worker::types::WorkerMsg) {
     if let Ok(msg_str) = serde_json::to_string(&msg) {
@@ -66,6 +154,13 @@ mod tests {
 
     // test_format_hex removed
 
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(12_595), "12.3 KB");
+        assert_eq!(format_bytes(1_572_864), "1.50 MB");
+    }
+
     #[test]
     fn test_parse_hex_string() {
         // Valid cases
@@ -105,4 +200,26 @@ mod tests {
         assert_eq!(format_hex_input("hello world"), "ED"); // h(skip), e(E), l(skip)... d(D).
                                                            // e, d. -> ED
     }
+
+    #[test]
+    fn test_parse_delimiter_list() {
+        assert_eq!(parse_delimiter_list("\\x03"), Ok(vec![vec![0x03]]));
+        assert_eq!(
+            parse_delimiter_list("\\0|\\r\\n|;"),
+            Ok(vec![vec![0], vec![b'\r', b'\n'], vec![b';']])
+        );
+        assert_eq!(parse_delimiter_list("END\\|"), Ok(vec![b"END|".to_vec()]));
+        assert!(parse_delimiter_list("").is_err());
+        assert!(parse_delimiter_list("a||b").is_err());
+        assert!(parse_delimiter_list("\\xZ1").is_err());
+        assert!(parse_delimiter_list("\\q").is_err());
+    }
+
+    #[test]
+    fn test_format_delimiter_list_round_trip() {
+        let list = vec![vec![0, b'|'], b"\r\n".to_vec(), vec![0x03, 0xFF, b'\\']];
+        let text = format_delimiter_list(&list);
+        assert_eq!(text, "\\0\\||\\r\\n|\\x03\\xFF\\\\");
+        assert_eq!(parse_delimiter_list(&text), Ok(list));
+    }
 }
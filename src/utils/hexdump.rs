@@ -0,0 +1,188 @@
+use crate::types::HexLayout;
+use std::fmt::Write;
+
+/// Replaces the space before a byte that starts a new received chunk
+pub const CHUNK_BOUNDARY: char = '\u{2506}';
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ByteClass {
+    Control,
+    Printable,
+    High,
+}
+
+pub fn byte_class(b: u8) -> ByteClass {
+    match b {
+        0x20..=0x7E => ByteClass::Printable,
+        0x80..=0xFF => ByteClass::High,
+        _ => ByteClass::Control,
+    }
+}
+
+/// Character shown for a byte in the ASCII gutter; control bytes use the
+/// Unicode control pictures (␀, ␊, ␛ ...)
+pub fn gutter_char(b: u8) -> char {
+    match b {
+        0x00..=0x1F => char::from_u32(0x2400 + b as u32).unwrap_or('.'),
+        0x7F => '\u{2421}',
+        0x20..=0x7E => b as char,
+        _ => '.',
+    }
+}
+
+fn starts_group(i: usize, layout: HexLayout) -> bool {
+    i == 0 || (layout.group_size > 1 && i.is_multiple_of(layout.group_size))
+}
+
+/// Upper bound of a formatted row in bytes, used as the hex view line cap
+pub fn max_row_len(layout: HexLayout) -> usize {
+    // offset + per byte (gap, 3-byte boundary marker, 2 digits, 3-byte picture) + gutter bars
+    16 + layout.bytes_per_row * 9 + 4
+}
+
+/// Formats one hexdump row: `OFFSET  XX XX ...  XX XX  |ascii|`.
+/// `boundaries` lists the row positions at which a new chunk started.
+/// A partial row is padded so the gutter stays aligned.
+pub fn format_row(offset: u64, bytes: &[u8], boundaries: &[usize], layout: HexLayout) -> String {
+    let mut row = String::with_capacity(max_row_len(layout));
+    let _ = write!(row, "{:08X}", offset);
+
+    for i in 0..layout.bytes_per_row.max(bytes.len()) {
+        if starts_group(i, layout) {
+            row.push(' ');
+        }
+        match bytes.get(i) {
+            Some(b) => {
+                row.push(if boundaries.contains(&i) {
+                    CHUNK_BOUNDARY
+                } else {
+                    ' '
+                });
+                let _ = write!(row, "{:02X}", b);
+            }
+            None => row.push_str("   "),
+        }
+    }
+
+    row.push_str("  |");
+    row.extend(bytes.iter().map(|&b| gutter_char(b)));
+    row.push('|');
+    row
+}
+
+/// One byte of a parsed row
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct HexCell {
+    pub byte: u8,
+    /// The byte is the first of a received chunk
+    pub boundary: bool,
+    /// An extra gap precedes the byte
+    pub group_start: bool,
+}
+
+/// A stored hex view line split back into its parts
+#[derive(PartialEq, Debug)]
+pub struct HexRow<'a> {
+    /// Timestamp prefix, including its trailing space
+    pub prefix: &'a str,
+    pub offset: &'a str,
+    pub cells: Vec<HexCell>,
+}
+
+/// Parses a line produced by `format_row` (optionally timestamped).
+/// Returns None for anything else, so text lines render as before.
+pub fn parse_row(line: &str) -> Option<HexRow<'_>> {
+    let body_start = if line.starts_with('[') {
+        line.find("] ")? + 2
+    } else {
+        0
+    };
+    let (prefix, body) = line.split_at(body_start);
+
+    let offset_len = body.bytes().take_while(|b| b.is_ascii_hexdigit()).count();
+    if offset_len < 8 {
+        return None;
+    }
+    let (offset, rest) = body.split_at(offset_len);
+    let (hex, gutter) = rest.split_once("  |")?;
+    let gutter = gutter.strip_suffix('|')?;
+
+    let mut cells = Vec::new();
+    let mut spaces = 0;
+    let mut boundary = false;
+    let mut chars = hex.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' => spaces += 1,
+            CHUNK_BOUNDARY => boundary = true,
+            _ => {
+                let high = c.to_digit(16)?;
+                let low = chars.next()?.to_digit(16)?;
+                // The separator before a byte counts as one of the preceding spaces
+                let gap = spaces + boundary as usize;
+                cells.push(HexCell {
+                    byte: (high * 16 + low) as u8,
+                    boundary,
+                    group_start: !cells.is_empty() && gap >= 2,
+                });
+                spaces = 0;
+                boundary = false;
+            }
+        }
+    }
+
+    if cells.is_empty() || gutter.chars().count() != cells.len() {
+        return None;
+    }
+    Some(HexRow {
+        prefix,
+        offset,
+        cells,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_row_layout() {
+        let layout = HexLayout {
+            bytes_per_row: 8,
+            group_size: 4,
+        };
+        let row = format_row(0x10, b"AB\n\x1b\xff", &[0, 3], layout);
+        assert_eq!(
+            row,
+            format!(
+                "00000010 {b}41 42 0A{b}1B  FF           |AB\u{240A}\u{241B}.|",
+                b = CHUNK_BOUNDARY
+            )
+        );
+        assert!(row.len() <= max_row_len(layout));
+    }
+
+    #[test]
+    fn test_parse_row_round_trip() {
+        let layout = HexLayout {
+            bytes_per_row: 8,
+            group_size: 4,
+        };
+        let row = format!(
+            "[10:00:00.000] {}",
+            format_row(0, b"\x00abcdef", &[0, 5], layout)
+        );
+        let parsed = parse_row(&row).unwrap();
+        assert_eq!(parsed.prefix, "[10:00:00.000] ");
+        assert_eq!(parsed.offset, "00000000");
+        let bytes: Vec<u8> = parsed.cells.iter().map(|c| c.byte).collect();
+        assert_eq!(bytes, b"\x00abcdef");
+        let boundaries: Vec<bool> = parsed.cells.iter().map(|c| c.boundary).collect();
+        assert_eq!(boundaries, [true, false, false, false, false, true, false]);
+        let groups: Vec<bool> = parsed.cells.iter().map(|c| c.group_start).collect();
+        assert_eq!(groups, [false, false, false, false, true, false, false]);
+
+        assert!(parse_row("hello world").is_none());
+        assert!(parse_row("DEADBEEF  |x|").is_none());
+    }
+}
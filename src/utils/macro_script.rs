@@ -0,0 +1,798 @@
+//! Macro scripts: a small line-based language for command sequences that
+//! wait for the device's replies. One step per line, `#` starts a comment:
+//!
+//! ```text
+//! send AT+CSQ                   text, with the macro's line ending
+//! hex 1B 5B 32 4A               raw bytes
+//! delay 500                     milliseconds
+//! baud 921600                   switch the open port's baud rate
+//! expect /\+CSQ: (\d+)/ 2000    wait for a received line, fail on timeout
+//! wait /READY/ 5000             the same, but carry on with $matched = 0
+//! set n = $n + 1                variables; + - * / % on integers
+//! if $1 < 10 ... else ... end   == != < > <= >=, ~ /regex/, or just a value
+//! loop 3 ... end                repeat; forever without a count
+//! while $n < 5 ... end
+//! break / stop / fail <message> / log <message>
+//! ```
+//!
+//! `$name` or `${name}` inserts a variable. A match sets `$0`, `$1`...,
+//! named groups, `$line` and `$matched`.
+
+use regex::Regex;
+use std::collections::HashMap;
+
+/// Timeout of `expect` and `wait` without one
+const DEFAULT_WAIT_MS: u32 = 5000;
+/// Steps run before handing control back, so a loop without waits cannot
+/// freeze the page
+const STEPS_PER_YIELD: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Compare {
+    Eq,
+    Ne,
+    Lt,
+    Gt,
+    Le,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Cond {
+    Truthy(String),
+    Compare(String, Compare, String),
+    Matches(String, Regex),
+}
+
+#[derive(Debug, Clone)]
+enum Op {
+    Send(String),
+    Hex(String),
+    Delay(String),
+    Baud(String),
+    Expect {
+        pattern: Regex,
+        timeout: String,
+        required: bool,
+    },
+    /// Name, value and whether the value was quoted (never arithmetic)
+    Set(String, String, bool),
+    Log(String),
+    Fail(String),
+    Stop,
+    Jump(usize),
+    JumpUnless(Cond, usize),
+    /// Loads a loop counter
+    Count(usize, String),
+    /// Takes one from a loop counter, or jumps out when it is used up
+    Repeat(usize, usize),
+}
+
+/// A parsed script; each step keeps its source line for status and errors
+#[derive(Debug, Clone)]
+pub struct Script {
+    ops: Vec<(usize, Op)>,
+    counters: usize,
+}
+
+/// What the runner has to do next
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Text to encode and end with the macro's line ending
+    Send(String),
+    SendBytes(Vec<u8>),
+    Delay(u32),
+    /// Switch the open port to this baud rate
+    Baud(u32),
+    /// Offer received lines until one matches or the time is up
+    Expect {
+        pattern: String,
+        timeout_ms: u32,
+    },
+    Log(String),
+    /// Nothing to do, but give the page a turn before continuing
+    Yield,
+    Done,
+    Failed(String),
+}
+
+enum Block {
+    If {
+        line: usize,
+        cond_at: usize,
+        end_jump: Option<usize>,
+    },
+    Loop {
+        line: usize,
+        start: usize,
+        /// The step that leaves the loop, if it has one
+        head: Option<usize>,
+        breaks: Vec<usize>,
+    },
+}
+
+/// A double-quoted argument with `\r \n \t \" \\` escapes, or the text as is
+fn text_arg(text: &str) -> (String, bool) {
+    let text = text.trim();
+    let Some(inner) = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .filter(|_| text.len() >= 2)
+    else {
+        return (text.to_string(), false);
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('r') => out.push('\r'),
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some(other) => out.push(other),
+            None => out.push('\\'),
+        }
+    }
+    (out, true)
+}
+
+/// Splits `/regex/ rest`, with `\/` for a slash inside
+fn regex_arg(text: &str, line: usize) -> Result<(Regex, String), String> {
+    let body = text
+        .trim()
+        .strip_prefix('/')
+        .ok_or_else(|| format!("Line {}: expected /regex/", line))?;
+    let mut escaped = false;
+    let end = body
+        .char_indices()
+        .find(|&(_, c)| {
+            let close = c == '/' && !escaped;
+            escaped = c == '\\' && !escaped;
+            close
+        })
+        .map(|(i, _)| i)
+        .ok_or_else(|| format!("Line {}: missing closing /", line))?;
+    let source = body[..end].replace("\\/", "/");
+    let regex = Regex::new(&source).map_err(|e| format!("Line {}: invalid regex: {}", line, e))?;
+    Ok((regex, body[end + 1..].trim().to_string()))
+}
+
+const OPERATORS: [(&str, Option<Compare>); 7] = [
+    ("==", Some(Compare::Eq)),
+    ("!=", Some(Compare::Ne)),
+    ("<=", Some(Compare::Le)),
+    (">=", Some(Compare::Ge)),
+    ("<", Some(Compare::Lt)),
+    (">", Some(Compare::Gt)),
+    ("~", None),
+];
+
+/// The first operator standing on its own outside a quoted value: its
+/// byte range and comparison (`None` for `~`)
+fn find_operator(text: &str) -> Option<(usize, usize, Option<Compare>)> {
+    let mut quoted = false;
+    let mut escaped = false;
+    let mut after_space = true;
+    for (i, c) in text.char_indices() {
+        if quoted {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => quoted = false,
+                _ => {}
+            }
+        } else if c == '"' {
+            quoted = true;
+        } else if after_space {
+            let found = OPERATORS.iter().find(|(op, _)| {
+                text[i..].starts_with(op)
+                    && text[i + op.len()..]
+                        .chars()
+                        .next()
+                        .is_none_or(char::is_whitespace)
+            });
+            if let Some((op, compare)) = found {
+                return Some((i, i + op.len(), *compare));
+            }
+        }
+        after_space = !quoted && c.is_whitespace();
+    }
+    None
+}
+
+fn parse_cond(text: &str, line: usize) -> Result<Cond, String> {
+    let Some((start, end, compare)) = find_operator(text) else {
+        if text.is_empty() {
+            return Err(format!("Line {}: missing condition", line));
+        }
+        return Ok(Cond::Truthy(text_arg(text).0));
+    };
+    // Operands are taken from the raw text, so quoted values and patterns
+    // keep their spacing
+    let left = text_arg(&text[..start]).0;
+    let right = &text[end..];
+    Ok(match compare {
+        Some(compare) => Cond::Compare(left, compare, text_arg(right).0),
+        None => {
+            let (regex, rest) = regex_arg(right, line)?;
+            if !rest.is_empty() {
+                return Err(format!("Line {}: unexpected '{}'", line, rest));
+            }
+            Cond::Matches(left, regex)
+        }
+    })
+}
+
+fn is_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn patch(op: &mut Op, target: usize) {
+    match op {
+        Op::Jump(to) | Op::JumpUnless(_, to) | Op::Repeat(_, to) => *to = target,
+        _ => {}
+    }
+}
+
+pub fn parse(text: &str) -> Result<Script, String> {
+    let mut ops: Vec<(usize, Op)> = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut counters = 0;
+
+    for (i, raw) in text.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = raw.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let (keyword, rest) = trimmed
+            .split_once(char::is_whitespace)
+            .map_or((trimmed, ""), |(k, r)| (k, r.trim()));
+        let op = match keyword.to_ascii_lowercase().as_str() {
+            "send" => Op::Send(text_arg(rest).0),
+            "hex" => Op::Hex(rest.to_string()),
+            "delay" => Op::Delay(rest.to_string()),
+            "baud" => Op::Baud(rest.to_string()),
+            "expect" | "wait" => {
+                let (pattern, timeout) = regex_arg(rest, line)?;
+                Op::Expect {
+                    pattern,
+                    timeout,
+                    required: keyword.eq_ignore_ascii_case("expect"),
+                }
+            }
+            "set" => {
+                let (name, value) = rest
+                    .split_once('=')
+                    .ok_or_else(|| format!("Line {}: expected set <name> = <value>", line))?;
+                let name = name.trim().trim_start_matches('$');
+                if !is_name(name) {
+                    return Err(format!("Line {}: invalid variable name '{}'", line, name));
+                }
+                let (value, quoted) = text_arg(value);
+                Op::Set(name.to_string(), value, quoted)
+            }
+            "log" => Op::Log(text_arg(rest).0),
+            "fail" => Op::Fail(text_arg(rest).0),
+            "stop" => Op::Stop,
+            "if" => {
+                blocks.push(Block::If {
+                    line,
+                    cond_at: ops.len(),
+                    end_jump: None,
+                });
+                Op::JumpUnless(parse_cond(rest, line)?, 0)
+            }
+            "else" => {
+                let Some(Block::If {
+                    cond_at, end_jump, ..
+                }) = blocks.last_mut()
+                else {
+                    return Err(format!("Line {}: else without if", line));
+                };
+                if end_jump.is_some() {
+                    return Err(format!("Line {}: second else", line));
+                }
+                *end_jump = Some(ops.len());
+                let after = ops.len() + 1;
+                patch(&mut ops[*cond_at].1, after);
+                Op::Jump(0)
+            }
+            "loop" => {
+                let head = if rest.is_empty() {
+                    None
+                } else {
+                    let counter = counters;
+                    counters += 1;
+                    ops.push((line, Op::Count(counter, rest.to_string())));
+                    Some((counter, ops.len()))
+                };
+                blocks.push(Block::Loop {
+                    line,
+                    start: head.map_or(ops.len(), |(_, at)| at),
+                    head: head.map(|(_, at)| at),
+                    breaks: Vec::new(),
+                });
+                match head {
+                    Some((counter, _)) => Op::Repeat(counter, 0),
+                    None => continue,
+                }
+            }
+            "while" => {
+                blocks.push(Block::Loop {
+                    line,
+                    start: ops.len(),
+                    head: Some(ops.len()),
+                    breaks: Vec::new(),
+                });
+                Op::JumpUnless(parse_cond(rest, line)?, 0)
+            }
+            "break" => {
+                let at = ops.len();
+                let Some(breaks) = blocks.iter_mut().rev().find_map(|b| match b {
+                    Block::Loop { breaks, .. } => Some(breaks),
+                    Block::If { .. } => None,
+                }) else {
+                    return Err(format!("Line {}: break outside a loop", line));
+                };
+                breaks.push(at);
+                Op::Jump(0)
+            }
+            "end" => {
+                match blocks.pop() {
+                    Some(Block::If {
+                        cond_at, end_jump, ..
+                    }) => {
+                        let end = ops.len();
+                        patch(&mut ops[end_jump.unwrap_or(cond_at)].1, end);
+                    }
+                    Some(Block::Loop {
+                        start,
+                        head,
+                        breaks,
+                        ..
+                    }) => {
+                        ops.push((line, Op::Jump(start)));
+                        let exit = ops.len();
+                        for at in head.into_iter().chain(breaks) {
+                            patch(&mut ops[at].1, exit);
+                        }
+                    }
+                    None => return Err(format!("Line {}: end without a block", line)),
+                }
+                continue;
+            }
+            other => return Err(format!("Line {}: unknown step '{}'", line, other)),
+        };
+        ops.push((line, op));
+    }
+
+    if let Some(block) = blocks.last() {
+        let line = match block {
+            Block::If { line, .. } | Block::Loop { line, .. } => line,
+        };
+        return Err(format!("Line {}: block is missing its end", line));
+    }
+    Ok(Script { ops, counters })
+}
+
+/// Replaces `$name` and `${name}` with variables; `$$` is a dollar sign
+fn interpolate(template: &str, vars: &HashMap<String, String>) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        let after = &rest[i + 1..];
+        if let Some(tail) = after.strip_prefix('$') {
+            out.push('$');
+            rest = tail;
+        } else if let Some((name, tail)) = after.strip_prefix('{').and_then(|a| a.split_once('}')) {
+            out.push_str(vars.get(name).map_or("", String::as_str));
+            rest = tail;
+        } else {
+            let len = after
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(after.len());
+            if len == 0 {
+                out.push('$');
+            } else {
+                out.push_str(vars.get(&after[..len]).map_or("", String::as_str));
+            }
+            rest = &after[len..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// `a op b` on integers, if the value is one
+fn arithmetic(value: &str) -> Option<Result<i64, String>> {
+    let tokens: Vec<&str> = value.split_whitespace().collect();
+    let [a, op, b] = tokens.as_slice() else {
+        return None;
+    };
+    let (a, b) = (a.parse::<i64>().ok()?, b.parse::<i64>().ok()?);
+    Some(match *op {
+        "+" => Ok(a.wrapping_add(b)),
+        "-" => Ok(a.wrapping_sub(b)),
+        "*" => Ok(a.wrapping_mul(b)),
+        "/" | "%" if b == 0 => Err("division by zero".to_string()),
+        "/" => Ok(a / b),
+        "%" => Ok(a % b),
+        _ => return None,
+    })
+}
+
+fn truthy(value: &str) -> bool {
+    !(value.is_empty() || value == "0" || value.eq_ignore_ascii_case("false"))
+}
+
+/// Steps through a script; the caller performs the actions it returns
+pub struct Interpreter {
+    script: Script,
+    pc: usize,
+    line: usize,
+    vars: HashMap<String, String>,
+    counters: Vec<u64>,
+    expecting: Option<(Regex, bool)>,
+    failure: Option<String>,
+}
+
+impl Interpreter {
+    pub fn new(script: Script) -> Self {
+        let counters = vec![0; script.counters];
+        Self {
+            script,
+            pc: 0,
+            line: 0,
+            vars: HashMap::new(),
+            counters,
+            expecting: None,
+            failure: None,
+        }
+    }
+
+    /// Source line of the step last run
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    fn text(&self, template: &str) -> String {
+        interpolate(template, &self.vars)
+    }
+
+    fn number(&self, template: &str, what: &str) -> Result<u64, String> {
+        let value = self.text(template);
+        value
+            .trim()
+            .parse()
+            .map_err(|_| format!("Line {}: invalid {} '{}'", self.line, what, value))
+    }
+
+    fn check(&self, cond: &Cond) -> bool {
+        match cond {
+            Cond::Truthy(value) => truthy(&self.text(value)),
+            Cond::Matches(value, regex) => regex.is_match(&self.text(value)),
+            Cond::Compare(left, compare, right) => {
+                let (left, right) = (self.text(left), self.text(right));
+                let ordering = match (left.trim().parse::<i64>(), right.trim().parse::<i64>()) {
+                    (Ok(a), Ok(b)) => a.cmp(&b),
+                    _ => left.cmp(&right),
+                };
+                match compare {
+                    Compare::Eq => ordering.is_eq(),
+                    Compare::Ne => ordering.is_ne(),
+                    Compare::Lt => ordering.is_lt(),
+                    Compare::Gt => ordering.is_gt(),
+                    Compare::Le => ordering.is_le(),
+                    Compare::Ge => ordering.is_ge(),
+                }
+            }
+        }
+    }
+
+    pub fn next(&mut self) -> Action {
+        if let Some(failure) = self.failure.take() {
+            return Action::Failed(failure);
+        }
+        for _ in 0..STEPS_PER_YIELD {
+            let Some((line, op)) = self.script.ops.get(self.pc).cloned() else {
+                return Action::Done;
+            };
+            self.line = line;
+            self.pc += 1;
+            let result = match op {
+                Op::Send(template) => return Action::Send(self.text(&template)),
+                Op::Hex(template) => {
+                    let text = self.text(&template);
+                    return match super::parse_hex_string(&text) {
+                        Ok(bytes) => Action::SendBytes(bytes),
+                        Err(e) => Action::Failed(format!("Line {}: {}", line, e)),
+                    };
+                }
+                Op::Delay(template) => self
+                    .number(&template, "delay")
+                    .map(|ms| Some(Action::Delay(ms.min(u32::MAX as u64) as u32))),
+                Op::Baud(template) => self
+                    .number(&template, "baud rate")
+                    .and_then(|baud| {
+                        u32::try_from(baud)
+                            .ok()
+                            .filter(|&baud| baud > 0)
+                            .ok_or_else(|| format!("Line {}: invalid baud rate '{}'", line, baud))
+                    })
+                    .map(|baud| Some(Action::Baud(baud))),
+                Op::Expect {
+                    pattern,
+                    timeout,
+                    required,
+                } => {
+                    let timeout_ms = if timeout.is_empty() {
+                        Ok(DEFAULT_WAIT_MS as u64)
+                    } else {
+                        self.number(&timeout, "timeout")
+                    };
+                    timeout_ms.map(|ms| {
+                        let action = Action::Expect {
+                            pattern: pattern.as_str().to_string(),
+                            timeout_ms: ms.min(u32::MAX as u64) as u32,
+                        };
+                        self.expecting = Some((pattern, required));
+                        Some(action)
+                    })
+                }
+                Op::Set(name, template, quoted) => {
+                    let value = self.text(&template);
+                    match arithmetic(&value).filter(|_| !quoted) {
+                        Some(Ok(n)) => {
+                            self.vars.insert(name, n.to_string());
+                            Ok(None)
+                        }
+                        Some(Err(e)) => Err(format!("Line {}: {}", line, e)),
+                        None => {
+                            self.vars.insert(name, value);
+                            Ok(None)
+                        }
+                    }
+                }
+                Op::Log(template) => return Action::Log(self.text(&template)),
+                Op::Fail(template) => {
+                    let message = self.text(&template);
+                    return Action::Failed(if message.is_empty() {
+                        format!("Line {}: failed", line)
+                    } else {
+                        message
+                    });
+                }
+                Op::Stop => {
+                    self.pc = self.script.ops.len();
+                    return Action::Done;
+                }
+                Op::Jump(to) => {
+                    self.pc = to;
+                    Ok(None)
+                }
+                Op::JumpUnless(cond, to) => {
+                    if !self.check(&cond) {
+                        self.pc = to;
+                    }
+                    Ok(None)
+                }
+                Op::Count(counter, template) => self.number(&template, "count").map(|n| {
+                    self.counters[counter] = n;
+                    None
+                }),
+                Op::Repeat(counter, exit) => {
+                    match self.counters[counter].checked_sub(1) {
+                        Some(left) => self.counters[counter] = left,
+                        None => self.pc = exit,
+                    }
+                    Ok(None)
+                }
+            };
+            match result {
+                Ok(Some(action)) => return action,
+                Ok(None) => {}
+                Err(e) => return Action::Failed(e),
+            }
+        }
+        Action::Yield
+    }
+
+    /// Offers a received line to the waiting `expect`; true when it matched
+    pub fn offer(&mut self, line: &str) -> bool {
+        let Some((pattern, _)) = &self.expecting else {
+            return false;
+        };
+        let Some(captures) = pattern.captures(line) else {
+            return false;
+        };
+        for (i, group) in captures.iter().enumerate() {
+            let value = group.map_or("", |m| m.as_str()).to_string();
+            self.vars.insert(i.to_string(), value);
+        }
+        for name in pattern.capture_names().flatten() {
+            let value = captures.name(name).map_or("", |m| m.as_str()).to_string();
+            self.vars.insert(name.to_string(), value);
+        }
+        self.vars.insert("line".to_string(), line.to_string());
+        self.vars.insert("matched".to_string(), "1".to_string());
+        self.expecting = None;
+        true
+    }
+
+    /// The waiting `expect` ran out of time
+    pub fn time_out(&mut self) {
+        let Some((pattern, required)) = self.expecting.take() else {
+            return;
+        };
+        self.vars.insert("matched".to_string(), "0".to_string());
+        if required {
+            self.failure = Some(format!(
+                "Line {}: timed out waiting for /{}/",
+                self.line,
+                pattern.as_str()
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs a script against canned replies: each `Send` is answered with the
+    /// lines of the first reply whose key it starts with
+    fn run(text: &str, replies: &[(&str, &[&str])]) -> (Vec<Action>, Interpreter) {
+        let mut interpreter = Interpreter::new(parse(text).unwrap());
+        let mut actions = Vec::new();
+        let mut received: Vec<String> = Vec::new();
+        loop {
+            let action = interpreter.next();
+            match &action {
+                Action::Send(text) => {
+                    if let Some((_, lines)) = replies.iter().find(|(k, _)| text.starts_with(k)) {
+                        received.extend(lines.iter().map(|l| l.to_string()));
+                    }
+                }
+                Action::Expect { .. } => {
+                    let matched = (0..received.len()).find(|_| {
+                        let line = received.remove(0);
+                        interpreter.offer(&line)
+                    });
+                    if matched.is_none() {
+                        interpreter.time_out();
+                    }
+                }
+                Action::Done | Action::Failed(_) => {
+                    actions.push(action);
+                    return (actions, interpreter);
+                }
+                _ => {}
+            }
+            actions.push(action);
+        }
+    }
+
+    #[test]
+    fn test_send_expect_and_captures() {
+        let script = "# signal check\n\
+                      send AT+CSQ\n\
+                      expect /\\+CSQ: (?P<rssi>\\d+),(\\d+)/ 1000\n\
+                      if $rssi < 10\n  fail weak signal $rssi\nelse\n  log rssi=${rssi} ber=$2\nend\n\
+                      hex 0d 0A";
+        let (actions, _) = run(script, &[("AT+CSQ", &["AT+CSQ", "+CSQ: 17,99", "OK"])]);
+        assert_eq!(
+            actions,
+            vec![
+                Action::Send("AT+CSQ".into()),
+                Action::Expect {
+                    pattern: "\\+CSQ: (?P<rssi>\\d+),(\\d+)".into(),
+                    timeout_ms: 1000
+                },
+                Action::Log("rssi=17 ber=99".into()),
+                Action::SendBytes(vec![0x0D, 0x0A]),
+                Action::Done,
+            ]
+        );
+        let (actions, _) = run(script, &[("AT+CSQ", &["+CSQ: 5,0"])]);
+        assert_eq!(
+            actions.last(),
+            Some(&Action::Failed("weak signal 5".into()))
+        );
+    }
+
+    #[test]
+    fn test_loops_variables_and_timeouts() {
+        let script = "set n = 0\n\
+                      loop 3\n  set n = $n + 2\nend\n\
+                      while $n > 0\n  set n = $n - 1\n  if $n == 4\n    break\n  end\nend\n\
+                      wait /READY/ 50\n\
+                      if $matched\n  stop\nend\n\
+                      set msg = \"no $$ ready\"\n\
+                      send $msg after $n\n\
+                      expect /OK/";
+        let (actions, interpreter) = run(script, &[]);
+        assert_eq!(interpreter.vars["n"], "4");
+        assert_eq!(
+            actions,
+            vec![
+                Action::Expect {
+                    pattern: "READY".into(),
+                    timeout_ms: 50
+                },
+                Action::Send("no $ ready after 4".into()),
+                Action::Expect {
+                    pattern: "OK".into(),
+                    timeout_ms: DEFAULT_WAIT_MS
+                },
+                Action::Failed("Line 17: timed out waiting for /OK/".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_conditions_keep_spacing() {
+        let script = "set v = \"a  b\"\n\
+                      if $v == \"a  b\"\n  log equal\nend\n\
+                      if $v ~ /^a  b$/\n  log matches\nend\n\
+                      if \"x == y\" != $v\n  log quoted operator\nend";
+        let (actions, _) = run(script, &[]);
+        assert_eq!(
+            actions,
+            vec![
+                Action::Log("equal".into()),
+                Action::Log("matches".into()),
+                Action::Log("quoted operator".into()),
+                Action::Done,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_baud_step() {
+        let (actions, _) = run("set b = 921600\nbaud $b\nbaud fast", &[]);
+        assert_eq!(
+            actions,
+            vec![
+                Action::Baud(921600),
+                Action::Failed("Line 3: invalid baud rate 'fast'".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_endless_loop_yields() {
+        let mut interpreter = Interpreter::new(parse("loop\n  set x = 1\nend").unwrap());
+        assert_eq!(interpreter.next(), Action::Yield);
+        let mut interpreter =
+            Interpreter::new(parse("loop 2\n  loop\n    break\n  end\nend").unwrap());
+        assert_eq!(interpreter.next(), Action::Done);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("if 1\nsend a").unwrap_err(),
+            "Line 1: block is missing its end"
+        );
+        assert_eq!(
+            parse("send a\nend").unwrap_err(),
+            "Line 2: end without a block"
+        );
+        assert_eq!(parse("break").unwrap_err(), "Line 1: break outside a loop");
+        assert_eq!(parse("jump 3").unwrap_err(), "Line 1: unknown step 'jump'");
+        assert!(parse("expect /(/")
+            .unwrap_err()
+            .starts_with("Line 1: invalid regex"));
+        assert_eq!(parse("expect /a\\/b/ 10").unwrap().ops.len(), 1);
+        let mut interpreter = Interpreter::new(parse("delay soon").unwrap());
+        assert_eq!(
+            interpreter.next(),
+            Action::Failed("Line 1: invalid delay 'soon'".into())
+        );
+    }
+}
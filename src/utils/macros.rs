@@ -12,6 +12,13 @@ pub struct MacroItem {
     pub is_hex: bool,
     #[serde(default)]
     pub line_ending: crate::state::LineEnding,
+    /// Baud rate to switch the open port to after the command is sent
+    #[serde(default)]
+    pub baud_rate: Option<u32>,
+    /// Script run step by step instead of sending `command`; its `send`
+    /// steps end with `line_ending`
+    #[serde(default)]
+    pub script: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Default, Clone, Debug)]
@@ -54,6 +61,8 @@ impl MacroStorage {
         command: String,
         is_hex: bool,
         line_ending: crate::state::LineEnding,
+        baud_rate: Option<u32>,
+        script: Option<String>,
     ) {
         let id = js_sys::Date::now() as u64;
         self.items.push(MacroItem {
@@ -62,6 +71,8 @@ impl MacroStorage {
             command,
             is_hex,
             line_ending,
+            baud_rate,
+            script,
         });
         self.save();
     }
@@ -70,6 +81,7 @@ impl MacroStorage {
         self.items.iter().find(|i| i.id == id).cloned()
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn update(
         &mut self,
         id: u64,
@@ -77,12 +89,16 @@ impl MacroStorage {
         command: String,
         is_hex: bool,
         line_ending: crate::state::LineEnding,
+        baud_rate: Option<u32>,
+        script: Option<String>,
     ) {
         if let Some(item) = self.items.iter_mut().find(|i| i.id == id) {
             item.label = label;
             item.command = command;
             item.is_hex = is_hex;
             item.line_ending = line_ending;
+            item.baud_rate = baud_rate;
+            item.script = script;
             self.save();
         }
     }
@@ -1,7 +1,11 @@
 pub mod ansi_decoder;
+pub mod asciicast;
+pub mod encoding;
 pub mod file_save;
 pub mod format;
+pub mod hexdump;
 pub mod history;
+pub mod macro_script;
 pub mod macros;
 pub mod scroll;
 pub mod serial_api;
@@ -9,7 +13,10 @@ pub mod simulation;
 pub mod terminal_bindings;
 
 pub use ansi_decoder::decode_ansi_text;
-pub use format::{format_hex_input, parse_hex_string, send_chunk_to_worker, send_worker_msg};
+pub use format::{
+    format_delimiter_list, format_hex_input, parse_delimiter_list, parse_hex_string,
+    send_chunk_to_worker, send_elf_to_worker, send_worker_msg,
+};
 pub use history::CommandHistory;
 pub use macros::MacroStorage;
 pub use scroll::{calculate_start_index, calculate_window_size};
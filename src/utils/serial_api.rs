@@ -1,3 +1,4 @@
+use crate::types::PortConfig;
 use serde::Serialize;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::JsFuture;
@@ -52,6 +53,18 @@ pub async fn open_port(
     JsFuture::from(promise).await.map(|_| ())
 }
 
+pub async fn open_port_with_config(port: &SerialPort, config: &PortConfig) -> Result<(), JsValue> {
+    open_port(
+        port,
+        config.baud_rate,
+        config.data_bits,
+        config.stop_bits,
+        &config.parity.to_string(),
+        &config.flow_control.to_string(),
+    )
+    .await
+}
+
 #[derive(PartialEq, Clone, Debug)]
 pub enum ReadStatus {
     Done,
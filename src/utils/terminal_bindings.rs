@@ -36,6 +36,18 @@ extern "C" {
     #[wasm_bindgen(method, js_name = clear)]
     pub fn clear(this: &Terminal);
 
+    #[wasm_bindgen(method, js_name = reset)]
+    pub fn reset(this: &Terminal);
+
+    #[wasm_bindgen(method, js_name = resize)]
+    pub fn resize(this: &Terminal, cols: u32, rows: u32);
+
+    #[wasm_bindgen(method, getter)]
+    pub fn cols(this: &Terminal) -> u32;
+
+    #[wasm_bindgen(method, getter)]
+    pub fn rows(this: &Terminal) -> u32;
+
     #[wasm_bindgen(method, js_name = scrollToBottom)]
     pub fn scroll_to_bottom(this: &Terminal);
 
@@ -0,0 +1,213 @@
+use crate::types::ByteHit;
+use crate::utils::hexdump::parse_row;
+use crate::worker::error::LogError;
+use crate::worker::state::WorkerState;
+use gloo_timers::future::TimeoutFuture;
+use regex::bytes::{Regex, RegexBuilder};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// Bytes of the raw journal scanned per step
+const SCAN_BLOCK_SIZE: usize = 1024 * 1024;
+/// Extra bytes read past each block so matches starting near its end are
+/// still found; regex matches longer than this are cut short
+const SCAN_OVERLAP: usize = 4096;
+/// Stop collecting after this many hits
+pub const MAX_BYTE_HITS: usize = 1000;
+/// Hex rows checked after the first line of a hit's chunk to find its exact row
+const HEX_ROW_LOOKAHEAD: usize = 256;
+
+/// Compiles a byte pattern. Wildcard patterns are hex byte pairs with `??`
+/// for any byte (`AA 55 ?? 01`, spaces optional); otherwise `regex::bytes` syntax
+/// with Unicode off, so `\xAA` is the raw byte rather than U+00AA.
+pub fn compile_pattern(pattern: &str, use_regex: bool) -> Result<Regex, String> {
+    if use_regex {
+        return RegexBuilder::new(pattern)
+            .unicode(false)
+            .build()
+            .map_err(|e| e.to_string());
+    }
+
+    let digits: Vec<char> = pattern.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.is_empty() {
+        return Err("Pattern is empty".to_string());
+    }
+    if !digits.len().is_multiple_of(2) {
+        return Err("Pattern must be whole bytes (pairs of hex digits or ??)".to_string());
+    }
+
+    let mut re = String::from("(?s-u)");
+    for pair in digits.chunks(2) {
+        match (pair[0], pair[1]) {
+            ('?', '?') => re.push('.'),
+            (h, l) if h.is_ascii_hexdigit() && l.is_ascii_hexdigit() => {
+                re.push_str(&format!("\\x{}{}", h, l));
+            }
+            (h, l) => return Err(format!("Invalid byte '{}{}'", h, l)),
+        }
+    }
+    Regex::new(&re).map_err(|e| e.to_string())
+}
+
+/// Finds matches that start inside `data[..block_len]`, reported at `base`
+/// offset. True when a match was left out because `hits` is full.
+fn scan_block(
+    re: &Regex,
+    data: &[u8],
+    block_len: usize,
+    base: u64,
+    hits: &mut Vec<ByteHit>,
+) -> bool {
+    for m in re.find_iter(data) {
+        if m.start() >= block_len {
+            break;
+        }
+        if m.is_empty() {
+            continue;
+        }
+        if hits.len() >= MAX_BYTE_HITS {
+            return true;
+        }
+        hits.push(ByteHit {
+            offset: base + m.start() as u64,
+            len: m.len() as u64,
+            line: None,
+        });
+    }
+    false
+}
+
+pub struct ByteSearcher;
+
+impl ByteSearcher {
+    /// Scans the whole raw journal for `re`, then posts the hits with the
+    /// displayed line each one falls on.
+    pub async fn search_async(
+        state_rc: Rc<RefCell<WorkerState>>,
+        re: Regex,
+        search_id: u32,
+    ) -> Result<(), LogError> {
+        let mut hits = Vec::new();
+        let mut pos = 0u64;
+        let mut truncated = false;
+
+        loop {
+            {
+                let state = state_rc.borrow();
+                if state.current_byte_search_id != search_id {
+                    return Ok(());
+                }
+                let journal = &state.proc.journal;
+                if pos >= journal.size() || truncated {
+                    break;
+                }
+                let data = journal.read(
+                    pos,
+                    pos + (SCAN_BLOCK_SIZE + SCAN_OVERLAP) as u64,
+                    SCAN_BLOCK_SIZE + SCAN_OVERLAP,
+                )?;
+                let block_len = data.len().min(SCAN_BLOCK_SIZE);
+                truncated = scan_block(&re, &data, block_len, pos, &mut hits);
+                pos += block_len as u64;
+            }
+            TimeoutFuture::new(0).await;
+        }
+
+        let state = state_rc.borrow();
+        Self::resolve_lines(&state, &mut hits);
+        state.send_msg(crate::worker::types::WorkerMsg::ByteSearchResults { hits, truncated });
+        Ok(())
+    }
+
+    /// Sets the displayed line holding each hit, in one pass over the hits
+    /// sorted by offset. Hex rows carry their stream offset, so the exact row
+    /// is found; otherwise the first line the byte's chunk contributed to is used.
+    fn resolve_lines(state: &WorkerState, hits: &mut [ByteHit]) {
+        let index = &state.proc.repository.index;
+        // First line of the chunk, the row found so far, and the next row to check with its offset
+        let mut cursor: Option<(usize, usize, usize, Option<u64>)> = None;
+        for hit in hits.iter_mut() {
+            let Some(first) = state.proc.journal.first_line_at(hit.offset) else {
+                hit.line = None;
+                continue;
+            };
+            let (mut line, mut next, mut next_row) = match cursor {
+                Some((chunk, line, next, next_row)) if chunk == first => (line, next, next_row),
+                _ => (first, first, Self::hex_row_offset(state, first)),
+            };
+            let end = (first + HEX_ROW_LOOKAHEAD).min(index.line_count);
+            while next < end && next_row.is_some_and(|start| start <= hit.offset) {
+                line = next;
+                next += 1;
+                next_row = if next < end {
+                    Self::hex_row_offset(state, next)
+                } else {
+                    None
+                };
+            }
+            hit.line = index.display_line(line);
+            cursor = Some((first, line, next, next_row));
+        }
+    }
+
+    /// Stream offset of a stored hex row, or None for text lines
+    fn hex_row_offset(state: &WorkerState, line: usize) -> Option<u64> {
+        let repo = &state.proc.repository;
+        let buf = repo.read_line(repo.index.get_absolute_range(line)?).ok()?;
+        let text = String::from_utf8_lossy(&buf);
+        let row = parse_row(text.trim_end_matches('\n'))?;
+        u64::from_str_radix(row.offset, 16).ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_pattern() {
+        let re = compile_pattern("AA 55 ?? 01", false).unwrap();
+        let data = b"\x00\xAA\x55\x0A\x01\xAA\x55\x01";
+        let mut hits = Vec::new();
+        scan_block(&re, data, data.len(), 100, &mut hits);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].offset, 101);
+        assert_eq!(hits[0].len, 4);
+
+        assert!(compile_pattern("AA5", false).is_err());
+        assert!(compile_pattern("ZZ", false).is_err());
+    }
+
+    #[test]
+    fn test_truncated_only_past_the_cap() {
+        let re = compile_pattern("AA", false).unwrap();
+        let data = vec![0xAA; MAX_BYTE_HITS];
+        let mut hits = Vec::new();
+        assert!(!scan_block(&re, &data, data.len(), 0, &mut hits));
+        assert_eq!(hits.len(), MAX_BYTE_HITS);
+        assert!(scan_block(&re, &[0xAA], 1, 0, &mut hits));
+        assert_eq!(hits.len(), MAX_BYTE_HITS);
+    }
+
+    #[test]
+    fn test_scan_block_ignores_overlap_starts() {
+        let re = compile_pattern(r"(?-u)\x7E[^\x7E]*\x7E", true).unwrap();
+        // Block is the first 4 bytes; the frame starting at 5 belongs to the next block
+        let data = b"\x7Eab\x7E\x00\x7Ecd\x7E";
+        let mut hits = Vec::new();
+        scan_block(&re, data, 4, 0, &mut hits);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].offset, hits[0].len), (0, 4));
+    }
+
+    #[test]
+    fn test_regex_matches_raw_bytes() {
+        let re = compile_pattern(r"\xAA[\x80-\xFF]+", true).unwrap();
+        // U+00AA encoded as UTF-8 must not match, the raw bytes must
+        let data = b"\xC2\xAA\x00\xAA\xFF\x90";
+        let mut hits = Vec::new();
+        scan_block(&re, data, data.len(), 0, &mut hits);
+        assert_eq!(hits.len(), 1);
+        assert_eq!((hits[0].offset, hits[0].len), (3, 3));
+    }
+}
@@ -1,80 +1,373 @@
-use crate::config::MAX_LINE_BYTES;
+use crate::config::{MAX_LINE_BYTES, MAX_OVERWRITE_HISTORY};
+use crate::types::{
+    ByteToken, CrMode, HexLayout, LineAttrs, RxDelimiter, RxFraming, TextEncoding, TokenKind,
+};
+use crate::utils::ansi_decoder::escape_complete;
+use crate::utils::encoding::{
+    escape_marks, is_escaped_control, mark_control_bytes, take_tokens, token_mark, StreamDecoder,
+};
+use crate::utils::hexdump::format_row;
 use crate::worker::formatter::LogFormatterStrategy;
-use crate::worker::repository::index::{ByteOffset, LineRange};
+use crate::worker::repository::index::ByteOffset;
 use std::borrow::Cow;
 use vt100::Parser;
 
+/// End offset (relative to the batch) and attributes of each formatted line
+pub type LineEnds = Vec<(ByteOffset, LineAttrs)>;
+/// Text of a line with its attributes
+pub type AttrLine = (String, LineAttrs);
+
+/// Compiled form of `RxDelimiter` used while scanning chunks
+pub enum LineSplitter {
+    Newline(CrMode),
+    Sequences(Vec<Vec<u8>>),
+    Regex(regex::bytes::Regex),
+    FixedLength(usize),
+}
+
+impl LineSplitter {
+    pub fn from_framing(framing: &RxFraming) -> Result<Self, String> {
+        Ok(match &framing.delimiter {
+            RxDelimiter::Newline => LineSplitter::Newline(framing.cr_mode),
+            RxDelimiter::Sequences(seqs) => {
+                let seqs: Vec<Vec<u8>> = seqs.iter().filter(|s| !s.is_empty()).cloned().collect();
+                if seqs.is_empty() {
+                    return Err("At least one delimiter is required".to_string());
+                }
+                LineSplitter::Sequences(seqs)
+            }
+            RxDelimiter::Regex(pattern) => {
+                LineSplitter::Regex(regex::bytes::Regex::new(pattern).map_err(|e| e.to_string())?)
+            }
+            RxDelimiter::FixedLength(0) => {
+                return Err("Record length must be greater than zero".to_string())
+            }
+            RxDelimiter::FixedLength(n) => LineSplitter::FixedLength(*n),
+        })
+    }
+}
+
+/// Why a record was cut off the stream
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum RecordEnd {
+    /// A delimiter (or record length) was reached
+    Complete,
+    /// The line reached the line cap and continues in the next record
+    Continued,
+    /// A bare CR is about to rewind the line; the record is its state before that
+    Overwritten,
+}
+
 /// Handles streaming line processing with leftover buffer management
 pub struct StreamingLineProcessor {
-    pub leftover_buffer: String,
     parser: Parser,
+    splitter: LineSplitter,
+    /// Trailing bytes that may be the start of a multi-byte delimiter
+    pending: Vec<u8>,
+    /// Bytes fed into the current record (used for fixed-length records)
+    record_bytes: usize,
+    /// Raw bytes of the current record under delimiter and fixed-length
+    /// framing, which bypasses the line parser so CR and LF are kept.
+    /// Records are cut before they are decoded.
+    record: Vec<u8>,
+    /// Input is raw received bytes rather than text decoded upstream
+    raw_input: bool,
+    decoder: StreamDecoder,
+    encoding: TextEncoding,
+    /// States of the active line that a bare CR overwrote, kept with it
+    /// when it is committed
+    history: Vec<(String, Vec<ByteToken>)>,
+    /// Parser width; a line reaching it is stored and continues on the next line
+    max_line_bytes: usize,
+    /// Bytes of the hex row being filled, its stream offset and the row
+    /// positions at which a new chunk started
+    hex_row: Vec<u8>,
+    hex_row_offset: u64,
+    hex_boundaries: Vec<usize>,
+    hex_layout: HexLayout,
+    /// Control bytes and escape sequences the parser acted on in the current
+    /// line, with the cursor column at the time, to be shown as tokens.
+    /// SGR sequences are left out, since the formatted row keeps them.
+    consumed: Vec<(u16, Vec<u8>)>,
+    /// Escape sequence still being received
+    escape: Vec<u8>,
 }
 
 impl StreamingLineProcessor {
     pub fn new() -> Self {
         Self {
-            leftover_buffer: String::new(),
             // Height 1 ensures we focus on a single line.
-            // Width MAX_LINE_BYTES prevents arbitrary wrapping of long lines.
+            // Width max_line_bytes prevents arbitrary wrapping of long lines.
             // Scrollback 0 disables history as we extract confirmed lines immediately.
             parser: Parser::new(1, MAX_LINE_BYTES as u16, 0),
+            splitter: LineSplitter::Newline(CrMode::LineEnd),
+            pending: Vec::new(),
+            record_bytes: 0,
+            record: Vec::new(),
+            raw_input: true,
+            decoder: StreamDecoder::new(TextEncoding::default()),
+            encoding: TextEncoding::default(),
+            history: Vec::new(),
+            max_line_bytes: MAX_LINE_BYTES,
+            hex_row: Vec::new(),
+            hex_row_offset: 0,
+            hex_boundaries: Vec::new(),
+            hex_layout: HexLayout::default(),
+            consumed: Vec::new(),
+            escape: Vec::new(),
         }
     }
 
+    /// Changes the line cap. Callers flush the active line first, since
+    /// resizing the parser drops whatever it holds beyond the new width.
+    pub fn set_max_line_bytes(&mut self, max_line_bytes: usize) {
+        self.max_line_bytes = max_line_bytes;
+        self.parser.screen_mut().set_size(1, max_line_bytes as u16);
+    }
+
+    /// Switches the receive encoding. Bytes of a sequence left incomplete
+    /// under the previous encoding are dropped.
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        self.encoding = encoding;
+        self.decoder = StreamDecoder::new(encoding);
+    }
+
+    /// Number of malformed sequences decoded since the last encoding change or clear
+    pub fn invalid_sequences(&self) -> u64 {
+        self.decoder.invalid_sequences()
+    }
+
+    /// Feeds decoded line content to the parser, noting each control byte and
+    /// escape sequence it acts on. Sequences are passed on whole, so the cursor
+    /// column noted is the one they apply at.
+    fn feed_parser(&mut self, bytes: &[u8]) {
+        let mut text_start = 0;
+        for (i, &b) in bytes.iter().enumerate() {
+            if self.escape.is_empty() && !is_escaped_control(b) {
+                continue;
+            }
+            if text_start < i {
+                self.parser.process(&bytes[text_start..i]);
+            }
+            text_start = i + 1;
+            self.escape.push(b);
+            if escape_complete(&self.escape) {
+                let seq = std::mem::take(&mut self.escape);
+                let col = self.parser.screen().cursor_position().1;
+                self.parser.process(&seq);
+                if !(seq.starts_with(b"\x1B[") && seq.ends_with(b"m")) {
+                    self.consumed.push((col, seq));
+                }
+            }
+        }
+        if text_start < bytes.len() {
+            self.parser.process(&bytes[text_start..]);
+        }
+    }
+
+    /// Returns the parser cursor to the line start, erasing the line unless
+    /// it is being overwritten
+    fn rewind_parser(&mut self, erase: bool) {
+        self.parser
+            .process(if erase { b"\r\x1b[2K" } else { b"\r" });
+        self.consumed.clear();
+    }
+
+    /// Adds record content: to the record buffer under record framing,
+    /// otherwise to the line parser
+    fn feed_record(&mut self, bytes: &[u8]) {
+        if self.frames_records() {
+            self.record.extend_from_slice(bytes);
+        } else {
+            self.feed_parser(bytes);
+        }
+    }
+
+    /// True if records are cut by delimiters or length rather than by line endings
+    fn frames_records(&self) -> bool {
+        !matches!(self.splitter, LineSplitter::Newline(_))
+    }
+
+    /// Marked text of a record collected under record framing. Control bytes
+    /// are data here, so they are kept as tokens rather than driving a terminal.
+    fn record_text(raw: bool, decoder: &mut StreamDecoder, record: &[u8], last: bool) -> String {
+        let text = if raw {
+            decoder.decode(record, last)
+        } else {
+            escape_marks(&String::from_utf8_lossy(record)).into_owned()
+        };
+        match mark_control_bytes(text.as_bytes(), TokenKind::Content) {
+            Cow::Owned(marked) => String::from_utf8(marked).unwrap_or_default(),
+            Cow::Borrowed(_) => text,
+        }
+    }
+
+    /// Decodes the current record and starts the next one
+    fn take_record(&mut self, last: bool) -> String {
+        let record = std::mem::take(&mut self.record);
+        Self::record_text(self.raw_input, &mut self.decoder, &record, last)
+    }
+
+    /// The line being received, as the parser or the record buffer holds it.
+    /// A partial record is decoded on its own, leaving the stream decoder untouched.
+    fn current_line(&self) -> Option<String> {
+        if self.frames_records() {
+            let mut preview = StreamDecoder::new(self.encoding);
+            return (!self.record.is_empty())
+                .then(|| Self::record_text(self.raw_input, &mut preview, &self.record, true));
+        }
+        let screen = self.parser.screen();
+        let row = screen
+            .rows_formatted(0, self.max_line_bytes as u16)
+            .next()?;
+        let mut line = String::from_utf8_lossy(&row).into_owned();
+        // Consumed sequences go in where their column starts, rightmost first
+        let mut consumed: Vec<_> = self.consumed.iter().collect();
+        consumed.sort_by_key(|(col, _)| *col);
+        for (col, seq) in consumed.into_iter().rev() {
+            let prefix = screen.rows_formatted(0, *col).next().map_or(0, |p| p.len());
+            let mut at = prefix.min(line.len());
+            while !line.is_char_boundary(at) {
+                at -= 1;
+            }
+            let marks: String = seq
+                .iter()
+                .map(|&b| token_mark(TokenKind::Control, b))
+                .collect();
+            line.insert_str(at, &marks);
+        }
+        Some(line)
+    }
+
+    pub fn set_splitter(&mut self, splitter: LineSplitter) {
+        self.splitter = splitter;
+        self.pending.clear();
+        self.record.clear();
+        self.history.clear();
+    }
+
+    /// Keeps an overwritten state of the active line, dropping the oldest past the cap
+    fn push_history(&mut self, state: &str) {
+        if self.history.len() >= MAX_OVERWRITE_HISTORY {
+            self.history.remove(0);
+        }
+        self.history.push(take_tokens(state));
+    }
+
+    /// True if part of a line has been received but not committed yet
+    pub fn has_active_line(&self) -> bool {
+        !self.hex_row.is_empty()
+            || !self.pending.is_empty()
+            || !self.record.is_empty()
+            || !self.history.is_empty()
+            || !self.parser.screen().contents().trim().is_empty()
+    }
+
+    /// Processes received bytes. Newline framing decodes them first, so the
+    /// parser sees text; record framing cuts records first and decodes each one.
     pub fn process_vt100(
         &mut self,
         chunk: &[u8],
         formatter: &dyn LogFormatterStrategy,
         timestamp: &str,
-        is_filtering: bool,
-        filter_matcher: impl Fn(&str) -> bool,
-    ) -> (String, Vec<ByteOffset>, Vec<LineRange>, Option<String>) {
+    ) -> (String, LineEnds, Option<AttrLine>) {
+        self.raw_input = true;
+        if self.frames_records() {
+            return self.process_input(chunk, formatter, timestamp);
+        }
+        let text = self.decoder.decode(chunk, false);
+        self.process_input(text.as_bytes(), formatter, timestamp)
+    }
+
+    /// Processes text decoded upstream (e.g. defmt frames)
+    pub fn process_text(
+        &mut self,
+        text: &str,
+        formatter: &dyn LogFormatterStrategy,
+        timestamp: &str,
+    ) -> (String, LineEnds, Option<AttrLine>) {
+        self.raw_input = false;
+        let text = escape_marks(text);
+        self.process_input(text.as_bytes(), formatter, timestamp)
+    }
+
+    /// Splits input into lines: marked text under newline framing, raw
+    /// bytes (or text if not `raw_input`) under record framing
+    fn process_input(
+        &mut self,
+        chunk: &[u8],
+        formatter: &dyn LogFormatterStrategy,
+        timestamp: &str,
+    ) -> (String, LineEnds, Option<AttrLine>) {
         let mut batch = String::new();
         let mut offsets = Vec::new();
-        let mut filtered = Vec::new();
         let mut relative_offset = ByteOffset(0);
 
+        // Re-attach bytes held back as a possible delimiter prefix
+        let chunk: Cow<[u8]> = if self.pending.is_empty() {
+            Cow::Borrowed(chunk)
+        } else {
+            let mut joined = std::mem::take(&mut self.pending);
+            joined.extend_from_slice(chunk);
+            Cow::Owned(joined)
+        };
+        let chunk = chunk.as_ref();
+
         let mut start = 0;
         let len = chunk.len();
 
         while start < len {
             // Get current cursor position to determine remaining space on the line.
             // Since height is 1, row is always 0.
-            let (_, col) = self.parser.screen().cursor_position();
-            let remaining = MAX_LINE_BYTES - col as usize;
+            let used = if self.frames_records() {
+                self.record.len()
+            } else {
+                self.parser.screen().cursor_position().1 as usize
+            };
+            let remaining = self.max_line_bytes.saturating_sub(used);
 
-            if let Some((end, next_start)) =
-                Self::find_next_line_ending_or_full(chunk, start, remaining)
+            if let Some((end, next_start, record_end)) =
+                self.find_record_end(chunk, start, remaining)
             {
                 // Process content up to the newline char(s) OR up to the full buffer limit
                 let line_bytes = &chunk[start..end];
-                self.parser.process(line_bytes);
+                self.feed_record(line_bytes);
 
                 // Extract the formatted line immediately
-                if let Some(bytes) = self
-                    .parser
-                    .screen()
-                    .rows_formatted(0, MAX_LINE_BYTES as u16)
-                    .next()
-                {
-                    let line_str = String::from_utf8_lossy(&bytes);
-
+                let line = if self.frames_records() {
+                    Some(self.take_record(record_end == RecordEnd::Complete))
+                } else {
+                    self.current_line()
+                };
+                if record_end == RecordEnd::Overwritten {
+                    if let Some(state) = line.filter(|row| !row.is_empty()) {
+                        self.push_history(&state);
+                    }
+                } else if let Some(line_str) = line {
+                    let attrs = LineAttrs {
+                        continued: record_end == RecordEnd::Continued,
+                        history: std::mem::take(&mut self.history),
+                        ..LineAttrs::default()
+                    };
                     self.process_single_line(
                         &line_str,
+                        attrs,
                         formatter,
                         timestamp,
                         &mut batch,
                         &mut offsets,
-                        &mut filtered,
                         &mut relative_offset,
-                        is_filtering,
-                        &filter_matcher,
                     );
                 }
 
-                // Clear the line in the parser to prepare for the next line
-                // Carriage Return + Clear Line
-                self.parser.process(b"\r\x1b[2K");
+                if record_end == RecordEnd::Overwritten {
+                    // Rewind without erasing, as a terminal would
+                    self.rewind_parser(false);
+                } else {
+                    // Clear the line in the parser to prepare for the next line
+                    self.rewind_parser(true);
+                    self.record_bytes = 0;
+                }
 
                 start = next_start;
             } else {
@@ -83,161 +376,390 @@ impl StreamingLineProcessor {
             }
         }
 
-        // Process any remaining bytes (incomplete line)
+        // Process any remaining bytes (incomplete line), holding back a possible delimiter prefix
         if start < chunk.len() {
-            self.parser.process(&chunk[start..]);
+            let keep_from = self.partial_delimiter_start(chunk, start);
+            self.feed_record(&chunk[start..keep_from]);
+            self.record_bytes += keep_from - start;
+            self.pending = chunk[keep_from..].to_vec();
         }
 
         // Get Current Active Line (Row 0)
         // If the chunk ended with a newline, this will be empty (which is correct)
         let active_line = self
-            .parser
-            .screen()
-            .rows_formatted(0, MAX_LINE_BYTES as u16)
-            .next()
-            .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
-            .filter(|s| !s.trim().is_empty())
-            .filter(|s| !is_filtering || filter_matcher(s));
-
-        (batch, offsets, filtered, active_line)
+            .current_line()
+            .map(|s| take_tokens(&s))
+            .filter(|(text, tokens)| !text.trim().is_empty() || !tokens.is_empty())
+            .map(|(text, tokens)| {
+                let attrs = LineAttrs {
+                    tokens,
+                    ..LineAttrs::default()
+                };
+                (text, attrs)
+            });
+
+        (batch, offsets, active_line)
     }
 
-    /// Processes a hex chunk (Hex mode)
+    /// Processes a chunk in hex view, emitting one hexdump row per full row of bytes.
+    /// `stream_offset` is the position of the chunk in the received byte stream.
     pub fn process_hex_lines(
         &mut self,
-        chunk: &str,
+        chunk: &[u8],
+        stream_offset: u64,
         formatter: &dyn LogFormatterStrategy,
         timestamp: &str,
-        is_filtering: bool,
-        filter_matcher: impl Fn(&str) -> bool,
-    ) -> (String, Vec<ByteOffset>, Vec<LineRange>, Option<String>) {
-        let max_len = formatter.max_line_length();
-
-        // 1. If leftover is already too long, force a split before even adding new chunk
-        if !self.leftover_buffer.is_empty() && self.leftover_buffer.len() >= max_len {
-            self.leftover_buffer.push('\n');
-        }
-
-        let full_text = if self.leftover_buffer.is_empty() {
-            Cow::Borrowed(chunk)
-        } else {
-            Cow::Owned(format!("{}{}", self.leftover_buffer, chunk))
-        };
-
-        let mut batch = String::with_capacity(full_text.len() * 2);
-        let mut offsets = Vec::new(); // Capacity logic changed slightly, let vector handle realloc or use heuristic
-        let mut filtered = Vec::new();
+    ) -> (String, LineEnds, Option<AttrLine>) {
+        let mut batch = String::new();
+        let mut offsets = Vec::new();
         let mut relative_offset = ByteOffset(0);
 
-        let text_bytes = full_text.as_bytes();
-        let len = text_bytes.len();
-        let mut start = 0;
-
-        while start < len {
-            // Hex Mode: Fixed width splitting logic
-            // We split strictly by max_len (e.g. 16 bytes = 48 chars).
-            // No newline searching.
-            let remaining = len - start;
-            if remaining >= max_len {
-                // If we have enough for a full line, extract it.
-                let end = start + max_len;
-                let line_str = &full_text[start..end];
+        // Bytes shown as text in between leave a gap; the partial row ends there
+        if !self.hex_row.is_empty()
+            && self.hex_row_offset + self.hex_row.len() as u64 != stream_offset
+        {
+            let row = self.take_hex_row();
+            self.process_single_line(
+                &row,
+                LineAttrs::default(),
+                formatter,
+                timestamp,
+                &mut batch,
+                &mut offsets,
+                &mut relative_offset,
+            );
+        }
+        if self.hex_row.is_empty() {
+            self.hex_row_offset = stream_offset;
+        }
 
+        self.hex_boundaries.push(self.hex_row.len());
+        for &b in chunk {
+            self.hex_row.push(b);
+            if self.hex_row.len() == self.hex_layout.bytes_per_row {
+                let row = self.take_hex_row();
                 self.process_single_line(
-                    line_str,
+                    &row,
+                    LineAttrs::default(),
                     formatter,
                     timestamp,
                     &mut batch,
                     &mut offsets,
-                    &mut filtered,
                     &mut relative_offset,
-                    is_filtering,
-                    &filter_matcher,
                 );
-                start = end;
-            } else {
-                // Not enough for a full line, buffer it.
-                break;
             }
         }
 
-        // Save remaining partial line
-        let active_line = if start < len {
-            let leftover = full_text[start..].to_string();
-            self.leftover_buffer = leftover.clone();
-            Some(leftover)
-        } else {
-            self.leftover_buffer.clear();
-            None
-        };
+        // The partial row is shown as the active line
+        let active_line = (!self.hex_row.is_empty()).then(|| {
+            let row = format_row(
+                self.hex_row_offset,
+                &self.hex_row,
+                &self.hex_boundaries,
+                self.hex_layout,
+            );
+            (row, LineAttrs::default())
+        });
+
+        (batch, offsets, active_line)
+    }
+
+    /// Formats the pending hex row and starts the next one right after it
+    fn take_hex_row(&mut self) -> String {
+        let row = format_row(
+            self.hex_row_offset,
+            &self.hex_row,
+            &self.hex_boundaries,
+            self.hex_layout,
+        );
+        self.hex_row_offset += self.hex_row.len() as u64;
+        self.hex_row.clear();
+        self.hex_boundaries.clear();
+        row
+    }
 
-        // Return batch, offsets, filtered, and active_line (partial hex line)
-        (batch, offsets, filtered, active_line)
+    /// True if a partial hex row is waiting for more bytes
+    pub fn has_hex_row(&self) -> bool {
+        !self.hex_row.is_empty()
     }
 
+    /// Changes the hex row layout. Callers flush the active line first.
+    pub fn set_hex_layout(&mut self, layout: HexLayout) {
+        self.hex_layout = layout;
+    }
+
+    /// Formats one line into the batch, cutting it at the line cap. Marks in
+    /// `line` become tokens of the segment they fall in. Every segment but the
+    /// last is flagged `continued`, the last one too if `attrs.continued` says
+    /// the line was already cut upstream. The first segment keeps `attrs.history`.
     #[allow(clippy::too_many_arguments)]
     fn process_single_line(
         &self,
         line: &str,
+        attrs: LineAttrs,
         formatter: &dyn LogFormatterStrategy,
         timestamp: &str,
         batch: &mut String,
-        offsets: &mut Vec<ByteOffset>,
-        filtered: &mut Vec<LineRange>,
+        offsets: &mut LineEnds,
         current_relative_offset: &mut ByteOffset,
-        is_filtering: bool,
-        filter_matcher: &impl Fn(&str) -> bool,
     ) {
         let max_len = formatter.max_line_length();
         let mut start = 0;
+        let (text, tokens) = take_tokens(line);
+        let line = text.as_str();
+        let mut tokens = tokens.into_iter().peekable();
 
         // Handle empty line case
         if line.is_empty() {
             let start_pos = batch.len();
             let formatted = formatter.format("", timestamp);
             batch.push_str(&formatted);
+            let prefix = formatted.len() - 1;
+            let attrs = LineAttrs {
+                tokens: tokens.map(|t| ByteToken { at: prefix, ..t }).collect(),
+                ..attrs
+            };
             let line_len = (batch.len() - start_pos) as u64;
-
-            if is_filtering && filter_matcher(&batch[start_pos..]) {
-                filtered.push(LineRange {
-                    start: *current_relative_offset,
-                    end: *current_relative_offset + line_len,
-                });
-            }
             *current_relative_offset = *current_relative_offset + line_len;
-            offsets.push(*current_relative_offset);
+            offsets.push((*current_relative_offset, attrs));
             return;
         }
 
+        let mut history = attrs.history;
         while start < line.len() {
             let mut end = (start + max_len).min(line.len());
             while !line.is_char_boundary(end) {
                 end -= 1;
             }
-            let sub_line = &line[start..end];
+            if end < line.len() {
+                end = Self::avoid_splitting_escape(line, start, end);
+            }
 
             let start_pos = batch.len();
-            let formatted = formatter.format(sub_line, timestamp);
+            let formatted = formatter.format(&line[start..end], timestamp);
             batch.push_str(&formatted);
             let line_len = (batch.len() - start_pos) as u64;
-
-            if is_filtering && filter_matcher(&batch[start_pos..]) {
-                filtered.push(LineRange {
-                    start: *current_relative_offset,
-                    end: *current_relative_offset + line_len,
-                });
-            }
+            // Token positions count from the start of the stored line, timestamp included
+            let prefix = formatted.len() - (end - start) - 1;
+            let last = end == line.len();
+            let segment_tokens = std::iter::from_fn(|| tokens.next_if(|t| last || t.at < end))
+                .map(|t| ByteToken {
+                    at: t.at - start + prefix,
+                    ..t
+                })
+                .collect();
 
             *current_relative_offset = *current_relative_offset + line_len;
-            offsets.push(*current_relative_offset);
+            // Segments cut at the cap are flagged so search and export can rejoin them
+            let segment = LineAttrs {
+                continued: !last || attrs.continued,
+                history: std::mem::take(&mut history),
+                tokens: segment_tokens,
+            };
+            offsets.push((*current_relative_offset, segment));
             start = end;
         }
     }
 
+    /// Moves a split point back before an ANSI escape sequence that would
+    /// otherwise be cut in half. Keeps `end` if the sequence starts the segment.
+    fn avoid_splitting_escape(line: &str, start: usize, end: usize) -> usize {
+        let segment = &line.as_bytes()[start..end];
+        let Some(esc) = segment.iter().rposition(|&b| b == 0x1b) else {
+            return end;
+        };
+        let terminated = segment[esc + 1..]
+            .iter()
+            .skip(1)
+            .any(|b| (0x40..=0x7e).contains(b));
+        if terminated || esc == 0 {
+            end
+        } else {
+            start + esc
+        }
+    }
+
+    /// Commits whatever is pending in the active text line as a complete line.
+    pub fn flush_active_line(
+        &mut self,
+        formatter: &dyn LogFormatterStrategy,
+        timestamp: &str,
+    ) -> (String, LineEnds) {
+        let held = std::mem::take(&mut self.pending);
+        self.feed_record(&held);
+        self.record_bytes = 0;
+
+        let mut pending = String::new();
+        if self.frames_records() {
+            // A record is data even if blank
+            if !self.record.is_empty() {
+                pending = self.take_record(true);
+            }
+        } else if let Some(row) = self.current_line() {
+            // A parser row of spaces is not, whatever sequences it consumed
+            if !take_tokens(&row).0.trim().is_empty() {
+                pending.push_str(&row);
+            }
+        }
+        self.rewind_parser(true);
+        let attrs = LineAttrs {
+            history: std::mem::take(&mut self.history),
+            ..LineAttrs::default()
+        };
+
+        if pending.is_empty() && attrs.history.is_empty() {
+            return (String::new(), Vec::new());
+        }
+        self.format_line_with(&pending, attrs, formatter, timestamp)
+    }
+
+    /// Commits a partial hex row as a complete (short) row.
+    pub fn flush_hex_row(
+        &mut self,
+        formatter: &dyn LogFormatterStrategy,
+        timestamp: &str,
+    ) -> (String, LineEnds) {
+        if self.hex_row.is_empty() {
+            return (String::new(), Vec::new());
+        }
+        let row = self.take_hex_row();
+        self.format_line(&row, formatter, timestamp)
+    }
+
+    /// Formats a standalone line (e.g. a marker) without touching the active line state.
+    pub fn format_line(
+        &self,
+        line: &str,
+        formatter: &dyn LogFormatterStrategy,
+        timestamp: &str,
+    ) -> (String, LineEnds) {
+        let attrs = LineAttrs::default();
+        self.format_line_with(line, attrs, formatter, timestamp)
+    }
+
+    fn format_line_with(
+        &self,
+        line: &str,
+        attrs: LineAttrs,
+        formatter: &dyn LogFormatterStrategy,
+        timestamp: &str,
+    ) -> (String, LineEnds) {
+        let mut batch = String::new();
+        let mut offsets = Vec::new();
+        let mut relative_offset = ByteOffset(0);
+        self.process_single_line(
+            line,
+            attrs,
+            formatter,
+            timestamp,
+            &mut batch,
+            &mut offsets,
+            &mut relative_offset,
+        );
+        (batch, offsets)
+    }
+
     pub fn clear(&mut self) {
-        self.leftover_buffer.clear();
+        self.hex_row.clear();
+        self.hex_boundaries.clear();
+        self.hex_row_offset = 0;
+        self.pending.clear();
+        self.record_bytes = 0;
+        self.record.clear();
+        self.history.clear();
+        self.consumed.clear();
+        self.escape.clear();
+        self.decoder = StreamDecoder::new(self.encoding);
         // Reset parser state
-        self.parser = Parser::new(1, MAX_LINE_BYTES as u16, 0);
+        self.parser = Parser::new(1, self.max_line_bytes as u16, 0);
+    }
+
+    /// Finds the end of the next record according to the configured splitter,
+    /// falling back to a split when the line buffer is full.
+    /// Returns Some((content_end_index, next_start_index, record_end)).
+    fn find_record_end(
+        &self,
+        chunk: &[u8],
+        start: usize,
+        remaining_space: usize,
+    ) -> Option<(usize, usize, RecordEnd)> {
+        let search_limit = std::cmp::min(start + remaining_space, chunk.len());
+
+        match &self.splitter {
+            LineSplitter::Newline(CrMode::LineEnd) => {
+                return Self::find_next_line_ending_or_full(chunk, start, remaining_space).map(
+                    |(end, next)| {
+                        let kind = if end == next {
+                            RecordEnd::Continued
+                        } else {
+                            RecordEnd::Complete
+                        };
+                        (end, next, kind)
+                    },
+                );
+            }
+            LineSplitter::Newline(mode) => {
+                let keep_history = *mode == CrMode::OverwriteKeepHistory;
+                for i in start..search_limit {
+                    match chunk[i] {
+                        // A CR before the LF only rewinds the cursor, which keeps the row intact
+                        b'\n' => return Some((i, i + 1, RecordEnd::Complete)),
+                        b'\r' if keep_history => match chunk.get(i + 1) {
+                            Some(b'\n') => return Some((i, i + 2, RecordEnd::Complete)),
+                            Some(_) => return Some((i, i + 1, RecordEnd::Overwritten)),
+                            // Held back until we know whether an LF follows
+                            None => return None,
+                        },
+                        _ => {}
+                    }
+                }
+            }
+            LineSplitter::Sequences(seqs) => {
+                for i in start..search_limit {
+                    if let Some(seq) = seqs.iter().find(|seq| chunk[i..].starts_with(seq)) {
+                        return Some((i, i + seq.len(), RecordEnd::Complete));
+                    }
+                }
+            }
+            LineSplitter::Regex(re) => {
+                // Empty matches would never advance, so only non-empty ones terminate a record
+                if let Some(m) = re
+                    .find_iter(&chunk[start..search_limit])
+                    .find(|m| !m.is_empty())
+                {
+                    return Some((start + m.start(), start + m.end(), RecordEnd::Complete));
+                }
+            }
+            LineSplitter::FixedLength(n) => {
+                let needed = n.saturating_sub(self.record_bytes).max(1);
+                if start + needed <= search_limit {
+                    return Some((start + needed, start + needed, RecordEnd::Complete));
+                }
+            }
+        }
+
+        Self::split_when_full(chunk, start, remaining_space)
+            .map(|(end, next)| (end, next, RecordEnd::Continued))
+    }
+
+    /// Index from which the tail of `chunk` could still turn into a delimiter
+    /// once more data arrives. Returns `chunk.len()` if nothing needs holding back.
+    /// Only literal sequences are held back; a regex match split across chunks is missed.
+    fn partial_delimiter_start(&self, chunk: &[u8], start: usize) -> usize {
+        let seqs = match &self.splitter {
+            LineSplitter::Sequences(seqs) => seqs,
+            LineSplitter::Newline(CrMode::OverwriteKeepHistory) if chunk.ends_with(b"\r") => {
+                return chunk.len() - 1;
+            }
+            _ => return chunk.len(),
+        };
+        (start..chunk.len())
+            .find(|&i| {
+                let tail = &chunk[i..];
+                seqs.iter()
+                    .any(|seq| seq.len() > tail.len() && seq.starts_with(tail))
+            })
+            .unwrap_or(chunk.len())
     }
 
     /// Helper to find the next line ending OR buffer full point.
@@ -274,6 +796,18 @@ impl StreamingLineProcessor {
             i += 1;
         }
 
+        Self::split_when_full(chunk, start, remaining_space)
+    }
+
+    /// Returns a split point at the end of the remaining line space if the
+    /// chunk is long enough to fill it, or None if the line is not full yet.
+    fn split_when_full(
+        chunk: &[u8],
+        start: usize,
+        remaining_space: usize,
+    ) -> Option<(usize, usize)> {
+        let search_limit = std::cmp::min(start + remaining_space, chunk.len());
+
         // We reached search_limit without finding a newline.
         // If search_limit was determined by remaining_space (i.e., buffer full),
         // we must return a split point here.
@@ -317,6 +851,7 @@ impl Default for StreamingLineProcessor {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::types::TokenKind;
     use crate::worker::formatter::LogFormatterStrategy;
 
     struct MockFormatter;
@@ -324,11 +859,8 @@ mod tests {
         fn format(&self, text: &str, _timestamp: &str) -> String {
             format!("{}\n", text)
         }
-        fn format_chunk(&self, _chunk: &[u8]) -> String {
-            String::new()
-        }
         fn max_line_length(&self) -> usize {
-            1000
+            MAX_LINE_BYTES
         }
     }
 
@@ -344,13 +876,17 @@ mod tests {
 
         // Feed data larger than buffer
         let data = "a".repeat(total_len);
-        let (batch, _, _, active_line) =
-            processor.process_vt100(data.as_bytes(), &formatter, "", false, |_| true);
+        let (batch, offsets, active_line) =
+            processor.process_vt100(data.as_bytes(), &formatter, "");
 
         // Expected behavior:
         // 1. First 'max_len' bytes fill the buffer -> extracted as one line.
         let lines: Vec<&str> = batch.lines().collect();
         assert_eq!(lines.len(), 1, "Should extract exactly one full line");
+        assert!(
+            offsets[0].1.continued,
+            "A line cut at the cap should be flagged as continued"
+        );
         assert_eq!(
             lines[0].len(),
             max_len,
@@ -360,7 +896,7 @@ mod tests {
         // 2. Remaining bytes are in active_line (since no newline at end)
         assert!(active_line.is_some());
         assert_eq!(
-            active_line.unwrap().len(),
+            active_line.unwrap().0.len(),
             overflow,
             "Remaining bytes should be in active line"
         );
@@ -380,8 +916,7 @@ mod tests {
         data.push('\n');
         data.push_str(&"b".repeat(39));
 
-        let (batch, _, _, _) =
-            processor.process_vt100(data.as_bytes(), &formatter, "", false, |_| true);
+        let (batch, offsets, _) = processor.process_vt100(data.as_bytes(), &formatter, "");
 
         let lines: Vec<&str> = batch.lines().collect();
         // We expect:
@@ -394,7 +929,9 @@ mod tests {
 
         assert_eq!(lines.len(), 2);
         assert_eq!(lines[0].len(), max_len);
+        assert!(offsets[0].1.continued);
         assert_eq!(lines[1].len(), overflow);
+        assert!(!offsets[1].1.continued);
     }
 
     #[test]
@@ -408,12 +945,11 @@ mod tests {
         let data1 = "A".repeat(initial_fill);
 
         // First processing: should NOT extract anything yet, just fills buffer
-        let (batch1, _, _, active1) =
-            processor.process_vt100(data1.as_bytes(), &formatter, "", false, |_| true);
+        let (batch1, _, active1) = processor.process_vt100(data1.as_bytes(), &formatter, "");
 
         assert!(batch1.is_empty(), "Should not extract line yet");
         assert_eq!(
-            active1.unwrap().len(),
+            active1.unwrap().0.len(),
             initial_fill,
             "Active line should contain prefilled data"
         );
@@ -423,8 +959,7 @@ mod tests {
         // - First 10 bytes fill the remaining space -> Extract 1 full line (max_len)
         // - Remaining 10 bytes start a new line
         let data2 = "B".repeat(20);
-        let (batch2, _, _, active2) =
-            processor.process_vt100(data2.as_bytes(), &formatter, "", false, |_| true);
+        let (batch2, offsets2, active2) = processor.process_vt100(data2.as_bytes(), &formatter, "");
 
         let lines: Vec<&str> = batch2.lines().collect();
         assert_eq!(
@@ -436,10 +971,11 @@ mod tests {
         // The extracted line should be: [initial_fill 'A'] + [10 'B']
         let expected_line = format!("{}{}", "A".repeat(initial_fill), "B".repeat(10));
         assert_eq!(lines[0], expected_line);
+        assert!(offsets2[0].1.continued);
 
         // The active line should contain the remaining 10 'B's
         assert!(active2.is_some());
-        assert_eq!(active2.unwrap(), "B".repeat(10));
+        assert_eq!(active2.unwrap().0, "B".repeat(10));
     }
 
     #[test]
@@ -447,7 +983,7 @@ mod tests {
         let mut processor = StreamingLineProcessor::new();
         let formatter = MockFormatter;
 
-        let (batch, _, _, _) = processor.process_vt100(b"", &formatter, "", false, |_| true);
+        let (batch, _, _) = processor.process_vt100(b"", &formatter, "");
         assert!(batch.is_empty());
     }
 
@@ -460,12 +996,12 @@ mod tests {
         // 1. Fill buffer slightly less than max
         let prefix_len = max_len - 1;
         let prefix = "A".repeat(prefix_len);
-        processor.process_vt100(prefix.as_bytes(), &formatter, "", false, |_| true);
+        processor.process_vt100(prefix.as_bytes(), &formatter, "");
 
         // 2. Next chunk: a 3-byte Hangul char "가" (0xE3, 0x80, 0x80)
         let hangul = "가"; // 3 bytes
-        let (batch, _, _, active_line) =
-            processor.process_vt100(hangul.as_bytes(), &formatter, "", false, |_| true);
+        let (batch, offsets, active_line) =
+            processor.process_vt100(hangul.as_bytes(), &formatter, "");
 
         let lines: Vec<&str> = batch.lines().collect();
 
@@ -475,12 +1011,12 @@ mod tests {
         // Then loops for remaining chunk ("가").
 
         assert_eq!(lines.len(), 1, "Should flush the buffer");
-        assert_eq!(lines[0].len(), prefix_len);
         assert_eq!(lines[0], prefix);
+        assert!(offsets[0].1.continued);
 
         // The active line should contain "가" now.
         assert!(active_line.is_some());
-        assert_eq!(active_line.unwrap(), "가");
+        assert_eq!(active_line.unwrap().0, "가");
     }
     #[test]
     fn test_process_vt100_stress_mixed_content() {
@@ -489,29 +1025,30 @@ mod tests {
 
         // 1. Massive Chunk Test (No Newline)
         // Simulate a huge burst of data without newlines (e.g. binary data or glitch)
-        // 100KB of 'A's. MAX_LINE_BYTES is 256.
-        // It should split into many 256-byte lines without panic.
+        // 100KB of 'A's, several times MAX_LINE_BYTES.
+        // It should split into many MAX_LINE_BYTES lines without panic.
         let huge_size = 100 * 1024;
         let huge_data = "A".repeat(huge_size);
 
-        let (batch, _, _, _) =
-            processor.process_vt100(huge_data.as_bytes(), &formatter, "", false, |_| true);
+        let (batch, offsets, _) = processor.process_vt100(huge_data.as_bytes(), &formatter, "");
 
-        // We expect (100*1024 / 256) lines = 400 lines exactly?
+        // We expect (100*1024 / MAX_LINE_BYTES) lines exactly.
         // Let's check line count and length.
         let lines: Vec<&str> = batch.lines().collect();
-        assert!(
-            lines.len() >= 400,
+        assert_eq!(
+            lines.len(),
+            huge_size / MAX_LINE_BYTES,
             "Should split huge line into many fragments"
         );
-        for line in &lines {
+        for (line, (_, attrs)) in lines.iter().zip(&offsets) {
             // MockFormatter adds \n, but lines() removes it.
-            // So content length should be 256 (except maybe last one if math is weird, but here it divides evenly).
             assert_eq!(
                 line.len(),
-                256,
+                MAX_LINE_BYTES,
                 "Split line should be exactly MAX_LINE_BYTES"
             );
+            // Every fragment continues on the next one, so each is flagged.
+            assert!(attrs.continued);
         }
 
         // 2. Complex Mixed Content Test
@@ -522,8 +1059,7 @@ mod tests {
         let colored = "\x1b[31mjunk\x1b[0m";
         let mixed_data = format!("Start\n{}{}\nEnd", multi_byte, colored);
 
-        let (batch2, _, _, _) =
-            processor.process_vt100(mixed_data.as_bytes(), &formatter, "", false, |_| true);
+        let (batch2, _, _) = processor.process_vt100(mixed_data.as_bytes(), &formatter, "");
 
         let lines2: Vec<&str> = batch2.lines().collect();
         // "Start"
@@ -539,76 +1075,246 @@ mod tests {
         // "End" is active line (no newline after).
     }
     #[test]
-    fn test_hex_formatter_newline_handling() {
+    fn test_flush_active_line_commits_partial_line() {
+        let mut processor = StreamingLineProcessor::new();
+        let formatter = MockFormatter;
+
+        let (_, _, active) = processor.process_vt100(b"boot: waiting", &formatter, "");
+        assert_eq!(
+            active.as_ref().map(|(t, _)| t.as_str()),
+            Some("boot: waiting")
+        );
+
+        let (batch, offsets) = processor.flush_active_line(&formatter, "");
+        assert_eq!(batch, "boot: waiting\n");
+        assert_eq!(offsets.len(), 1);
+
+        // The active line is empty afterwards, so a second flush commits nothing
+        let (batch, offsets) = processor.flush_active_line(&formatter, "");
+        assert!(batch.is_empty());
+        assert!(offsets.is_empty());
+
+        let (_, _, active) = processor.process_vt100(b"next", &formatter, "");
+        assert_eq!(active.as_ref().map(|(t, _)| t.as_str()), Some("next"));
+    }
+
+    #[test]
+    fn test_hex_formatter_row_length() {
+        use crate::utils::hexdump::max_row_len;
         use crate::worker::formatter::HexFormatter;
-        use crate::worker::formatter::LogFormatterStrategy;
 
-        let formatter = HexFormatter { max_bytes: 16 };
+        let layout = HexLayout::default();
+        let formatter = HexFormatter { layout };
+        assert_eq!(formatter.max_line_length(), max_row_len(layout));
+    }
+
+    #[test]
+    fn test_hex_mode_rows_across_chunks() {
+        let mut processor = StreamingLineProcessor::new();
+        let formatter = MockFormatter;
+        let layout = HexLayout::default();
+
+        // 20 bytes: one full row plus 4 bytes of the next
+        let first: Vec<u8> = (0u8..20).collect();
+        let (batch, _, active) = processor.process_hex_lines(&first, 0, &formatter, "");
+
+        let lines: Vec<&str> = batch.lines().collect();
+        assert_eq!(lines, vec![format_row(0, &first[..16], &[0], layout)]);
+        // The tail of the first chunk continues it, so no boundary yet
+        assert_eq!(
+            active.map(|(t, _)| t),
+            Some(format_row(16, &first[16..], &[], layout))
+        );
+        assert!(processor.has_hex_row());
 
-        // Input with newlines and carriage returns
-        let input = b"A\nB\rC"; // 0x41, 0x0A, 0x42, 0x0D, 0x43
+        // The next chunk completes the row, with a boundary where it started
+        let second: Vec<u8> = (20u8..32).collect();
+        let (batch2, _, active2) = processor.process_hex_lines(&second, 20, &formatter, "");
+        assert!(active2.is_none());
 
-        let formatted = formatter.format_chunk(input);
+        let row: Vec<u8> = (16u8..32).collect();
+        let lines2: Vec<&str> = batch2.lines().collect();
+        assert_eq!(lines2, vec![format_row(16, &row, &[4], layout)]);
+        assert!(!processor.has_hex_row());
+    }
 
-        // Expected: "41 0A 42 0D 43 "
-        assert_eq!(formatted, "41 0A 42 0D 43 ");
+    #[test]
+    fn test_hex_mode_gap_ends_partial_row() {
+        let mut processor = StreamingLineProcessor::new();
+        let formatter = MockFormatter;
+        let layout = HexLayout::default();
 
-        assert_eq!(formatter.max_line_length(), 48);
+        processor.process_hex_lines(b"ab", 0, &formatter, "");
+        // Bytes 2..10 were shown as text, so the hex stream resumes at 10
+        let (batch, _, active) = processor.process_hex_lines(b"cd", 10, &formatter, "");
+
+        assert_eq!(batch, format!("{}\n", format_row(0, b"ab", &[0], layout)));
+        assert_eq!(
+            active.map(|(t, _)| t),
+            Some(format_row(10, b"cd", &[0], layout))
+        );
     }
 
-    struct MockHexFormatter;
-    impl crate::worker::formatter::LogFormatterStrategy for MockHexFormatter {
-        fn format(&self, text: &str, _timestamp: &str) -> String {
-            format!("{}\n", text)
-        }
-        fn format_chunk(&self, _chunk: &[u8]) -> String {
-            String::new()
+    fn control(at: usize, bytes: &[u8]) -> ByteToken {
+        ByteToken {
+            at,
+            kind: TokenKind::Control,
+            bytes: bytes.to_vec(),
         }
-        fn max_line_length(&self) -> usize {
-            16 * 3 // 48
+    }
+
+    fn content(at: usize, bytes: &[u8]) -> ByteToken {
+        ByteToken {
+            kind: TokenKind::Content,
+            ..control(at, bytes)
         }
     }
 
-    #[test]
-    fn test_hex_mode_fixed_width_splitting() {
+    fn framed_processor(delimiter: RxDelimiter) -> StreamingLineProcessor {
         let mut processor = StreamingLineProcessor::new();
-        let formatter = MockHexFormatter;
+        let framing = RxFraming {
+            delimiter,
+            ..RxFraming::default()
+        };
+        processor.set_splitter(LineSplitter::from_framing(&framing).unwrap());
+        processor
+    }
 
-        // Input: "00 11 22 33 44 55 66 77 88 99 AA BB CC DD EE FF " (48 chars)
-        let line1 = "00 11 22 33 44 55 66 77 88 99 AA BB CC DD EE FF ";
-        // Input 2: "10 11 12 13 " (12 chars)
-        let line2_part = "10 11 12 13 ";
+    #[test]
+    fn test_custom_delimiter_split_across_chunks() {
+        let mut processor = framed_processor(RxDelimiter::Sequences(vec![b"<END>".to_vec()]));
+        let formatter = MockFormatter;
 
-        let full_text = format!("{}{}", line1, line2_part); // 60 chars
+        let (batch, _, active) = processor.process_vt100(b"first<END>sec<E", &formatter, "");
+        assert_eq!(batch, "first\n");
+        // The partial delimiter is held back rather than shown
+        assert_eq!(active.as_ref().map(|(t, _)| t.trim_end()), Some("sec"));
 
-        // process_hex_lines takes &str, splits by 48 chars.
-        // It now returns active_line (leftover buffer).
-        let (batch, _, _, active) =
-            processor.process_hex_lines(&full_text, &formatter, "", false, |_| true);
+        let (batch, _, active) = processor.process_vt100(b"ND>", &formatter, "");
+        assert_eq!(batch, "sec\n");
+        assert!(active.is_none());
+    }
 
-        let lines: Vec<&str> = batch.lines().collect();
-        assert_eq!(lines.len(), 1);
-        // lines[0] contains "00 .. FF ", which matches line1 exactly.
-        assert_eq!(lines[0], line1);
-
-        // Check buffer for remainder. It should be returned as active_line too.
-        assert_eq!(processor.leftover_buffer, line2_part);
-        assert_eq!(active, Some(line2_part.to_string()));
-
-        // Send next chunk to complete line 2
-        // We need 36 chars more. "20 .. 2B "
-        let line3_part = "20 21 22 23 24 25 26 27 28 29 2A 2B ";
-        let (batch2, _, _, active2) =
-            processor.process_hex_lines(line3_part, &formatter, "", false, |_| true);
-        assert!(active2.is_none());
+    #[test]
+    fn test_fixed_length_records() {
+        let mut processor = framed_processor(RxDelimiter::FixedLength(4));
+        let formatter = MockFormatter;
 
-        let lines2: Vec<&str> = batch2.lines().collect();
-        assert_eq!(lines2.len(), 1);
+        let (batch, _, _) = processor.process_vt100(b"abcdef", &formatter, "");
+        assert_eq!(batch, "abcd\n");
+
+        let (batch, _, active) = processor.process_vt100(b"ghij", &formatter, "");
+        assert_eq!(batch, "efgh\n");
+        assert_eq!(active.as_ref().map(|(t, _)| t.trim_end()), Some("ij"));
+    }
+
+    #[test]
+    fn test_regex_delimiter() {
+        let mut processor = framed_processor(RxDelimiter::Regex(r"\r?\n> ".to_string()));
+        let formatter = MockFormatter;
 
-        let expected_line2 = format!("{}{}", line2_part, line3_part);
-        // lines2[0] contains "10 .. 2B ", which matches expected_line2 exactly.
-        assert_eq!(lines2[0], expected_line2);
+        let (batch, _, _) = processor.process_vt100(b"ok\r\n> ls\n> ", &formatter, "");
+        assert_eq!(batch, "ok\nls\n");
+    }
+
+    #[test]
+    fn test_records_keep_line_breaks() {
+        let mut processor = framed_processor(RxDelimiter::Sequences(vec![vec![0x03]]));
+        let formatter = MockFormatter;
+
+        let (batch, offsets, active) = processor.process_vt100(b"a\r\nb\x03c\rd", &formatter, "");
+        assert_eq!(batch, "ab\n");
+        assert_eq!(offsets[0].1.tokens, vec![content(1, b"\r\n")]);
+        let (text, attrs) = active.unwrap();
+        assert_eq!(text, "cd");
+        assert_eq!(attrs.tokens, vec![content(1, b"\r")]);
+
+        let mut processor = framed_processor(RxDelimiter::FixedLength(3));
+        let (batch, offsets, _) = processor.process_vt100(b"\r\nxy", &formatter, "");
+        assert_eq!(batch, "x\n");
+        assert_eq!(offsets[0].1.tokens, vec![content(0, b"\r\n")]);
+    }
+
+    #[test]
+    fn test_records_frame_raw_bytes() {
+        // The length counts bytes, undecodable ones included
+        let mut processor = framed_processor(RxDelimiter::FixedLength(3));
+        let formatter = MockFormatter;
+        let (batch, offsets, _) = processor.process_vt100(b"a\xFFbcd\xC3\xA9", &formatter, "");
+        assert_eq!(batch, "ab\ncd\n");
+        assert_eq!(
+            offsets[0].1.tokens,
+            vec![ByteToken {
+                at: 1,
+                kind: TokenKind::Invalid,
+                bytes: vec![0xFF],
+            }]
+        );
+        // A multi-byte character cut by the length is undecodable in both records
+        assert_eq!(offsets[1].1.tokens.len(), 1);
+
+        // A delimiter byte that is not valid text on its own still matches
+        let mut processor = framed_processor(RxDelimiter::Sequences(vec![vec![0xFE]]));
+        let (batch, _, _) = processor.process_vt100(b"one\xFEtwo\xFE", &formatter, "");
+        assert_eq!(batch, "one\ntwo\n");
+    }
+
+    #[test]
+    fn test_cr_overwrite_commits_final_state() {
+        let mut processor = StreamingLineProcessor::new();
+        processor.set_splitter(LineSplitter::Newline(CrMode::Overwrite));
+        let formatter = MockFormatter;
+
+        let (batch, _, active) =
+            processor.process_vt100(b"\rLoading 10%\rLoading 50%", &formatter, "");
+        assert!(batch.is_empty());
+        assert_eq!(
+            active.as_ref().map(|(t, _)| t.as_str()),
+            Some("Loading 50%")
+        );
+
+        let (batch, _, _) = processor.process_vt100(b"\rLoading 99%\r\ndone\n", &formatter, "");
+        assert_eq!(batch, "Loading 99%\ndone\n");
+    }
+
+    #[test]
+    fn test_cr_overwrite_keeps_history() {
+        let mut processor = StreamingLineProcessor::new();
+        processor.set_splitter(LineSplitter::Newline(CrMode::OverwriteKeepHistory));
+        let formatter = MockFormatter;
+
+        // The trailing CR is held until we know it is not part of a CRLF
+        let (batch, _, active) = processor.process_vt100(b"\r10%\r50%\r", &formatter, "");
+        assert!(batch.is_empty());
+        assert_eq!(active.as_ref().map(|(t, _)| t.as_str()), Some("50%"));
+
+        // Overwritten states stay with the final line instead of becoming lines
+        let (batch, offsets, _) = processor.process_vt100(b"\n", &formatter, "");
+        assert_eq!(batch, "50%\n");
+        assert_eq!(offsets[0].1.history, vec![("10%".to_string(), Vec::new())]);
+
+        // A flushed line takes its history too
+        processor.process_vt100(b"a\rb", &formatter, "");
+        let (batch, offsets) = processor.flush_active_line(&formatter, "");
+        assert_eq!(batch, "b\n");
+        assert_eq!(offsets[0].1.history, vec![("a".to_string(), Vec::new())]);
+    }
+
+    #[test]
+    fn test_consumed_controls_become_tokens() {
+        let mut processor = StreamingLineProcessor::new();
+        let formatter = MockFormatter;
 
-        assert!(processor.leftover_buffer.is_empty());
+        // The parser still applies colors; the bell is kept beside the text
+        let (batch, offsets, _) =
+            processor.process_vt100(b"\x1b[31mred\x1b[m\x07\r\n", &formatter, "");
+        assert_eq!(batch, "\x1b[31mred\n");
+        assert_eq!(offsets[0].1.tokens, vec![control(8, b"\x07")]);
+
+        // A sequence cut by the chunk boundary is one token, at the column it acted on
+        processor.process_vt100(b"ab\x1b[", &formatter, "");
+        let (batch, offsets, _) = processor.process_vt100(b"1Dc\r\n", &formatter, "");
+        assert_eq!(batch, "ac\n");
+        assert_eq!(offsets[0].1.tokens, vec![control(2, b"\x1b[1D")]);
     }
 }
@@ -1,10 +1,18 @@
+use crate::config::RAW_INSPECT_LIMIT;
+use crate::types::{
+    ElfInfo, HexLayout, LevelDetection, LevelMask, RxFraming, TagFilter, TextEncoding,
+};
+use crate::worker::byte_search::{compile_pattern, ByteSearcher};
 use crate::worker::commands::command::WorkerCommand;
+use crate::worker::defmt::DefmtDecoder;
 use crate::worker::error::LogError;
 use crate::worker::export::LogExporter;
-use crate::worker::repository::index::LineIndex;
+use crate::worker::levels::{LevelClassifier, LevelDetector};
+use crate::worker::repository::index::{ByteOffset, LineIndex};
 use crate::worker::repository::storage::StorageBackend;
 use crate::worker::search::LogSearcher;
 use crate::worker::state::WorkerState;
+use crate::worker::symbols::{code_addresses, Symbolizer};
 use crate::worker::types::WorkerMsg;
 use std::cell::RefCell;
 use std::rc::Rc;
@@ -35,6 +43,8 @@ impl WorkerCommand for AppendChunkCommand {
         state: &mut WorkerState,
         _state_rc: &Rc<RefCell<WorkerState>>,
     ) -> Result<bool, JsValue> {
+        state.last_chunk_at = js_sys::Date::now();
+        let invalid_before = state.proc.invalid_sequences();
         let active_line = state
             .proc
             .append_chunk(&self.chunk, self.is_hex)
@@ -45,6 +55,27 @@ impl WorkerCommand for AppendChunkCommand {
             // Send None to clear if active line became empty (e.g. newline received)
             state.send_msg(WorkerMsg::ActiveLine(None));
         }
+        let invalid = state.proc.invalid_sequences();
+        if invalid != invalid_before {
+            state.send_msg(WorkerMsg::InvalidSequences(invalid));
+        }
+        let lines = state.proc.take_received_lines();
+        if !lines.is_empty() {
+            state.send_msg(WorkerMsg::ReceivedLines(lines));
+        }
+        Ok(true)
+    }
+}
+
+pub struct SetLineFeedCommand(pub bool);
+
+impl WorkerCommand for SetLineFeedCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.proc.set_line_feed(self.0);
         Ok(true)
     }
 }
@@ -62,6 +93,169 @@ impl WorkerCommand for SetTimestampStateCommand {
     }
 }
 
+pub struct SetRxFramingCommand(pub RxFraming);
+
+impl WorkerCommand for SetRxFramingCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.idle_flush_ms = self.0.idle_flush_ms.filter(|ms| *ms > 0);
+        state.proc.set_rx_framing(&self.0)?;
+        state.send_msg(WorkerMsg::ActiveLine(None));
+        Ok(true)
+    }
+}
+
+pub struct SetEncodingCommand(pub TextEncoding);
+
+impl WorkerCommand for SetEncodingCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.proc.set_encoding(self.0);
+        Ok(true)
+    }
+}
+
+pub struct SetHexLayoutCommand(pub HexLayout);
+
+impl WorkerCommand for SetHexLayoutCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.proc.set_hex_layout(self.0)?;
+        state.send_msg(WorkerMsg::ActiveLine(None));
+        Ok(true)
+    }
+}
+
+pub struct SetLevelDetectionCommand(pub LevelDetection);
+
+impl WorkerCommand for SetLevelDetectionCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.proc.repository.levels = LevelDetector::new(&self.0).map_err(LogError::Regex)?;
+
+        // Lines already stored are re-detected in the background
+        state.current_classify_id += 1;
+        let classify_id = state.current_classify_id;
+        let state_rc_clone = state_rc.clone();
+        spawn_local(async move {
+            if let Err(e) =
+                LevelClassifier::reclassify_async(state_rc_clone.clone(), classify_id).await
+            {
+                state_rc_clone.borrow().send_error(JsValue::from(e));
+            }
+        });
+        Ok(true)
+    }
+}
+
+pub struct SetLevelFilterCommand(pub LevelMask);
+
+impl WorkerCommand for SetLevelFilterCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        let index = &mut state.proc.repository.index;
+        index.level_mask = self.0;
+        let filter = index.active_filter.clone();
+
+        // Cancel a running search; the text filter is reapplied with the new levels
+        state.current_search_id += 1;
+        let state_rc_clone = state_rc.clone();
+        spawn_local(async move {
+            if let Err(e) = LogSearcher::filter_async(state_rc_clone.clone(), filter).await {
+                state_rc_clone.borrow().send_error(JsValue::from(e));
+            }
+        });
+        Ok(true)
+    }
+}
+
+pub struct SetTagFilterCommand(pub TagFilter);
+
+impl WorkerCommand for SetTagFilterCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        let index = &mut state.proc.repository.index;
+        index.tag_filter = self.0.clone();
+        let filter = index.active_filter.clone();
+
+        // Cancel a running search; the text filter is reapplied with the new tags
+        state.current_search_id += 1;
+        let state_rc_clone = state_rc.clone();
+        spawn_local(async move {
+            if let Err(e) = LogSearcher::filter_async(state_rc_clone.clone(), filter).await {
+                state_rc_clone.borrow().send_error(JsValue::from(e));
+            }
+        });
+        Ok(true)
+    }
+}
+
+pub struct InsertMarkerCommand(pub String);
+
+impl WorkerCommand for InsertMarkerCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state
+            .proc
+            .append_marker(&self.0)
+            .map_err(|e| JsValue::from_str(&format!("{:?}", e)))?;
+        state.send_msg(WorkerMsg::ActiveLine(None));
+        Ok(true)
+    }
+}
+
+pub struct ShowOutputCommand(pub Vec<u8>);
+
+impl WorkerCommand for ShowOutputCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        let active_line = state
+            .proc
+            .show_output(&self.0)
+            .map_err(|e| JsValue::from_str(&format!("{:?}", e)))?;
+        state.send_msg(WorkerMsg::ActiveLine(active_line));
+        Ok(true)
+    }
+}
+
+pub struct RecordTxCommand(pub Vec<u8>);
+
+impl WorkerCommand for RecordTxCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        // The received line stays open, so the monitor keeps showing it
+        state.proc.record_tx(&self.0)?;
+        Ok(true)
+    }
+}
+
 pub struct RequestWindowCommand {
     pub start_line: usize,
     pub count: usize,
@@ -78,6 +272,7 @@ impl WorkerCommand for RequestWindowCommand {
             self.start_line.min(total),
             (self.start_line + self.count).min(total),
         );
+        state.last_window = (self.start_line, self.count);
         let mut lines = Vec::with_capacity(e - s);
         let repo = &state.proc.repository;
 
@@ -92,14 +287,101 @@ impl WorkerCommand for RequestWindowCommand {
                     .map_err(|e| JsValue::from_str(&format!("{:?}", e)))?
                     .trim_end_matches('\n')
                     .to_string();
-                lines.push((i, text));
+                let attrs = repo
+                    .index
+                    .absolute_line(LineIndex(i))
+                    .map(|line| repo.index.attrs(line))
+                    .unwrap_or_default();
+                lines.push((i, text, attrs));
             }
         }
 
+        // Code addresses in the window are resolved against the loaded ELF
+        let symbols = state.proc.symbolizer.as_ref().map(|symbolizer| {
+            let mut addresses: Vec<u64> = lines
+                .iter()
+                .flat_map(|(_, text, _)| code_addresses(text))
+                .collect();
+            addresses.sort_unstable();
+            addresses.dedup();
+            addresses
+                .into_iter()
+                .map(|address| symbolizer.lookup(address))
+                .collect()
+        });
+
         state.send_msg(WorkerMsg::LogWindow {
             start_line: self.start_line,
             lines,
         });
+        if let Some(symbols) = symbols {
+            state.send_msg(WorkerMsg::Symbols(symbols));
+        }
+        Ok(true)
+    }
+}
+
+pub struct LoadElfCommand {
+    pub name: String,
+    pub data: Vec<u8>,
+}
+
+impl WorkerCommand for LoadElfCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        let symbolizer = Symbolizer::from_elf(&self.data)
+            .map_err(|e| JsValue::from_str(&format!("{}: {}", self.name, e)))?;
+        // A defmt table that cannot be read still leaves symbolization working
+        let defmt = DefmtDecoder::from_elf(&self.data).unwrap_or_else(|e| {
+            state.send_msg(WorkerMsg::Error(format!(
+                "{}: defmt table: {}",
+                self.name, e
+            )));
+            None
+        });
+        state.send_msg(WorkerMsg::ElfLoaded(Some(ElfInfo {
+            name: self.name.clone(),
+            has_debug_info: symbolizer.has_debug_info,
+            has_defmt: defmt.is_some(),
+        })));
+        state.proc.set_symbolizer(Some(symbolizer));
+        state.proc.set_defmt(defmt);
+
+        // Annotate the lines already on screen
+        let (start_line, count) = state.last_window;
+        RequestWindowCommand { start_line, count }.execute(state, state_rc)
+    }
+}
+
+pub struct UnloadElfCommand;
+
+impl WorkerCommand for UnloadElfCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.proc.set_symbolizer(None);
+        state.proc.set_defmt(None);
+        state.send_msg(WorkerMsg::ElfLoaded(None));
+        state.send_msg(WorkerMsg::Symbols(Vec::new()));
+        Ok(true)
+    }
+}
+
+pub struct SetDefmtCommand(pub bool);
+
+impl WorkerCommand for SetDefmtCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        state.proc.set_defmt_enabled(self.0)?;
+        state.send_msg(WorkerMsg::ActiveLine(None));
         Ok(true)
     }
 }
@@ -117,6 +399,7 @@ impl WorkerCommand for ClearCommand {
             .clear()
             .map_err(|e| JsValue::from_str(&format!("{:?}", e)))?;
         state.send_msg(WorkerMsg::TotalLines(0));
+        state.send_msg(WorkerMsg::InvalidSequences(0));
         Ok(true)
     }
 }
@@ -180,14 +463,128 @@ impl WorkerCommand for ExportLogsCommand {
             .ok_or_else(|| LogError::Storage("OPFS handle missing for export".into()))
             .map_err(JsValue::from)?;
 
-        let stream = LogExporter::export_logs(handle, size).map_err(JsValue::from)?;
+        let index = &repo.index;
+        let breaks = index
+            .line_attrs
+            .range(..index.line_count)
+            .filter(|(_, attrs)| attrs.continued)
+            .map(|(&line, _)| index.line_offsets[line + 1].0 - 1)
+            .collect();
+
+        let stream = LogExporter::export_logs(handle, size, breaks).map_err(JsValue::from)?;
+        post_export_stream(state, &stream, "serial_log.txt");
+        Ok(true)
+    }
+}
+
+/// Exports the raw bytes behind a line range, or the whole capture
+pub struct ExportRawCommand(pub Option<(usize, usize)>);
+
+impl WorkerCommand for ExportRawCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        let journal = &state.proc.journal;
+        let handle = journal
+            .data_handle()
+            .ok_or_else(|| LogError::Storage("Raw capture is not available".into()))
+            .map_err(JsValue::from)?;
+
+        let (start, end, filename) = match self.0 {
+            Some((start_line, end_line)) => {
+                let (start, end) = state
+                    .proc
+                    .raw_range(start_line, end_line)
+                    .ok_or_else(|| LogError::Storage("Selected lines are gone".into()))
+                    .map_err(JsValue::from)?;
+                (start, end, "serial_selection.bin")
+            }
+            None => (0, journal.size(), "serial_capture.bin"),
+        };
+        let stream = LogExporter::export_raw(handle, ByteOffset(start), ByteOffset(end))
+            .map_err(JsValue::from)?;
+        post_export_stream(state, &stream, filename);
+        Ok(true)
+    }
+}
+
+fn post_export_stream(state: &WorkerState, stream: &js_sys::Object, filename: &str) {
+    let resp = js_sys::Object::new();
+    let _ = js_sys::Reflect::set(&resp, &"type".into(), &"EXPORT_STREAM".into());
+    let _ = js_sys::Reflect::set(&resp, &"stream".into(), stream);
+    let _ = js_sys::Reflect::set(&resp, &"filename".into(), &filename.into());
+    let _ = state
+        .scope
+        .post_message_with_transfer(&resp, &js_sys::Array::of1(stream));
+}
+
+pub struct RequestRawCommand {
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+impl WorkerCommand for RequestRawCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        _state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        let (offset, end) = state
+            .proc
+            .raw_range(self.start_line, self.end_line)
+            .unwrap_or((0, 0));
+        let bytes = state
+            .proc
+            .journal
+            .read(offset, end, RAW_INSPECT_LIMIT)
+            .map_err(JsValue::from)?;
+
+        state.send_msg(WorkerMsg::RawBytes {
+            start_line: self.start_line,
+            end_line: self.end_line,
+            offset,
+            total_len: end - offset,
+            bytes,
+        });
+        Ok(true)
+    }
+}
+
+pub struct SearchBytesCommand {
+    pub pattern: String,
+    pub use_regex: bool,
+}
+
+impl WorkerCommand for SearchBytesCommand {
+    fn execute(
+        &self,
+        state: &mut WorkerState,
+        state_rc: &Rc<RefCell<WorkerState>>,
+    ) -> Result<bool, JsValue> {
+        // A newer search (or an empty pattern) cancels the running one
+        state.current_byte_search_id += 1;
+        if self.pattern.trim().is_empty() {
+            state.send_msg(WorkerMsg::ByteSearchResults {
+                hits: Vec::new(),
+                truncated: false,
+            });
+            return Ok(true);
+        }
+
+        let re = compile_pattern(&self.pattern, self.use_regex)
+            .map_err(|e| JsValue::from(LogError::Regex(e)))?;
+        let search_id = state.current_byte_search_id;
+        let state_rc_clone = state_rc.clone();
+
+        spawn_local(async move {
+            if let Err(e) = ByteSearcher::search_async(state_rc_clone.clone(), re, search_id).await
+            {
+                state_rc_clone.borrow().send_error(JsValue::from(e));
+            }
+        });
 
-        let resp = js_sys::Object::new();
-        let _ = js_sys::Reflect::set(&resp, &"type".into(), &"EXPORT_STREAM".into());
-        let _ = js_sys::Reflect::set(&resp, &"stream".into(), &stream);
-        let _ = state
-            .scope
-            .post_message_with_transfer(&resp, &js_sys::Array::of1(&stream));
         Ok(true)
     }
 }
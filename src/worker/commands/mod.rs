@@ -12,11 +12,24 @@ pub fn create_command_from_msg(msg: WorkerMsg) -> Box<dyn WorkerCommand> {
         WorkerMsg::NewSession => Box::new(NewSessionCommand),
         WorkerMsg::AppendChunk { chunk, is_hex } => Box::new(AppendChunkCommand { chunk, is_hex }),
         WorkerMsg::SetTimestampState(enabled) => Box::new(SetTimestampStateCommand(enabled)),
+        WorkerMsg::InsertMarker(text) => Box::new(InsertMarkerCommand(text)),
+        WorkerMsg::RecordTx(data) => Box::new(RecordTxCommand(data)),
+        WorkerMsg::ShowOutput(data) => Box::new(ShowOutputCommand(data)),
+        WorkerMsg::SetLineFeed(enabled) => Box::new(SetLineFeedCommand(enabled)),
+        WorkerMsg::SetRxFraming(framing) => Box::new(SetRxFramingCommand(framing)),
+        WorkerMsg::SetEncoding(encoding) => Box::new(SetEncodingCommand(encoding)),
+        WorkerMsg::SetHexLayout(layout) => Box::new(SetHexLayoutCommand(layout)),
+        WorkerMsg::SetLevelDetection(config) => Box::new(SetLevelDetectionCommand(config)),
+        WorkerMsg::SetLevelFilter(mask) => Box::new(SetLevelFilterCommand(mask)),
+        WorkerMsg::SetTagFilter(filter) => Box::new(SetTagFilterCommand(filter)),
 
         WorkerMsg::RequestWindow { start_line, count } => {
             Box::new(RequestWindowCommand { start_line, count })
         }
         WorkerMsg::Clear => Box::new(ClearCommand),
+        WorkerMsg::LoadElf { name, data } => Box::new(LoadElfCommand { name, data }),
+        WorkerMsg::UnloadElf => Box::new(UnloadElfCommand),
+        WorkerMsg::SetDefmt(enabled) => Box::new(SetDefmtCommand(enabled)),
 
         WorkerMsg::SearchLogs {
             query,
@@ -30,6 +43,17 @@ pub fn create_command_from_msg(msg: WorkerMsg) -> Box<dyn WorkerCommand> {
             invert,
         }),
         WorkerMsg::ExportLogs { .. } => Box::new(ExportLogsCommand),
+        WorkerMsg::RequestRaw {
+            start_line,
+            end_line,
+        } => Box::new(RequestRawCommand {
+            start_line,
+            end_line,
+        }),
+        WorkerMsg::ExportRaw(lines) => Box::new(ExportRawCommand(lines)),
+        WorkerMsg::SearchBytes { pattern, use_regex } => {
+            Box::new(SearchBytesCommand { pattern, use_regex })
+        }
 
         _ => Box::new(NoOpCommand), // Fallback for handled/error messages
     }
@@ -0,0 +1,120 @@
+/// Length of the `[HH:MM:SS.mmm] ` prefix written when timestamps are on
+const TIMESTAMP_PREFIX_LEN: usize = 15;
+
+fn is_timestamp_prefix(bytes: &[u8]) -> bool {
+    bytes.len() >= TIMESTAMP_PREFIX_LEN
+        && bytes[0] == b'['
+        && bytes[13] == b']'
+        && bytes[14] == b' '
+        && bytes[1..13].iter().enumerate().all(|(i, b)| match i {
+            2 | 5 => *b == b':',
+            8 => *b == b'.',
+            _ => b.is_ascii_digit(),
+        })
+}
+
+/// Drops the timestamp a continuation segment was stored with, so rejoined
+/// lines only carry the timestamp of their first segment.
+pub fn strip_segment_timestamp(segment: &str) -> &str {
+    if is_timestamp_prefix(segment.as_bytes()) {
+        &segment[TIMESTAMP_PREFIX_LEN..]
+    } else {
+        segment
+    }
+}
+
+/// Rejoins continued lines in a stream of stored log bytes that may be cut at any point.
+pub struct LineRejoiner {
+    /// Offsets of the line breaks to drop, ascending
+    breaks: Vec<u64>,
+    next_break: usize,
+    /// Offset of the first byte of `carry`
+    pos: u64,
+    carry: Vec<u8>,
+}
+
+impl LineRejoiner {
+    /// `breaks` holds the text log offsets of the '\n' ending each continued line
+    pub fn new(breaks: Vec<u64>) -> Self {
+        Self {
+            breaks,
+            next_break: 0,
+            pos: 0,
+            carry: Vec::new(),
+        }
+    }
+
+    /// Returns the bytes of `chunk` with continuation breaks removed. A break whose
+    /// following timestamp is not complete yet is held back until the next call, unless `is_last`.
+    pub fn push(&mut self, chunk: &[u8], is_last: bool) -> Vec<u8> {
+        let mut data = std::mem::take(&mut self.carry);
+        data.extend_from_slice(chunk);
+        let base = self.pos;
+
+        let mut out = Vec::with_capacity(data.len());
+        let mut i = 0;
+        while i < data.len() {
+            let Some(&at) = self.breaks.get(self.next_break) else {
+                out.extend_from_slice(&data[i..]);
+                i = data.len();
+                break;
+            };
+            let end = (at.saturating_sub(base) as usize).clamp(i, data.len());
+            if end > i {
+                out.extend_from_slice(&data[i..end]);
+                i = end;
+                continue;
+            }
+            if at < base + i as u64 || data[i] != b'\n' {
+                self.next_break += 1;
+                continue;
+            }
+            let next = &data[i + 1..];
+            if !is_last && next.len() < TIMESTAMP_PREFIX_LEN {
+                break;
+            }
+            i += 1;
+            if is_timestamp_prefix(next) {
+                i += TIMESTAMP_PREFIX_LEN;
+            }
+            self.next_break += 1;
+        }
+
+        self.carry = data[i..].to_vec();
+        self.pos = base + i as u64;
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rejoiner_across_chunk_boundaries() {
+        // The first two lines continue; the third ends in a marker-like char but does not
+        let text = "[10:00:00.000] abc\n[10:00:00.001] def\n[10:00:00.002] gh\u{21A9}\nnext\n";
+        let bytes = text.as_bytes();
+        let breaks = vec![18, 37];
+
+        // Feed one byte at a time to hit every possible cut
+        let mut rejoiner = LineRejoiner::new(breaks);
+        let mut out = Vec::new();
+        for (i, b) in bytes.iter().enumerate() {
+            out.extend(rejoiner.push(&[*b], i + 1 == bytes.len()));
+        }
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "[10:00:00.000] abcdefgh\u{21A9}\nnext\n"
+        );
+    }
+
+    #[test]
+    fn test_strip_segment_timestamp() {
+        assert_eq!(strip_segment_timestamp("[01:02:03.456] x"), "x");
+        assert_eq!(
+            strip_segment_timestamp("[not a time]   x"),
+            "[not a time]   x"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use defmt_decoder::{DecodeError, Encoding, Frame, Locations, Table};
+use std::fmt::Write;
+
+/// Reverses rzCOBS for one frame, without its `0x00` separator
+fn rzcobs_decode(data: &[u8]) -> Option<Vec<u8>> {
+    let mut res = Vec::with_capacity(data.len() + data.len() / 7);
+    let mut data = data.iter().rev().copied();
+    while let Some(x) = data.next() {
+        match x {
+            0 => return None,
+            0x01..=0x7f => {
+                for i in 0..7 {
+                    if x & (1 << (6 - i)) == 0 {
+                        res.push(data.next()?);
+                    } else {
+                        res.push(0);
+                    }
+                }
+            }
+            0x80..=0xfe => {
+                res.push(0);
+                for _ in 0..(x & 0x7f) + 7 {
+                    res.push(data.next()?);
+                }
+            }
+            0xff => {
+                for _ in 0..134 {
+                    res.push(data.next()?);
+                }
+            }
+        }
+    }
+    res.reverse();
+    Some(res)
+}
+
+/// Turns a defmt byte stream into text lines using the firmware's defmt table
+pub struct DefmtDecoder {
+    table: Table,
+    locations: Locations,
+    buffer: Vec<u8>,
+}
+
+impl DefmtDecoder {
+    /// None when the ELF has no `.defmt` section
+    pub fn from_elf(data: &[u8]) -> Result<Option<Self>, String> {
+        let Some(table) = Table::parse(data).map_err(|e| e.to_string())? else {
+            return Ok(None);
+        };
+        // Locations need debug info; frames are still decoded without them
+        let locations = table.get_locations(data).unwrap_or_default();
+        Ok(Some(Self::new(table, locations)))
+    }
+
+    fn new(table: Table, locations: Locations) -> Self {
+        Self {
+            table,
+            locations,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Drops a partially received frame
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Decodes the frames completed by `bytes`, one line each. Returns the
+    /// text and the number of frames that could not be decoded.
+    pub fn decode(&mut self, bytes: &[u8]) -> (String, u64) {
+        self.buffer.extend_from_slice(bytes);
+        let mut text = String::new();
+        let mut malformed = 0;
+
+        match self.table.encoding() {
+            Encoding::Rzcobs => {
+                let mut start = 0;
+                while let Some(len) = self.buffer[start..].iter().position(|&b| b == 0) {
+                    let frame = &self.buffer[start..start + len];
+                    start += len + 1;
+                    if frame.is_empty() {
+                        continue;
+                    }
+                    let decoded = rzcobs_decode(frame).ok_or(DecodeError::Malformed);
+                    match decoded.and_then(|raw| self.table.decode(&raw).map(|(f, _)| f)) {
+                        Ok(frame) => self.push_line(&mut text, &frame),
+                        Err(_) => malformed += 1,
+                    }
+                }
+                self.buffer.drain(..start);
+            }
+            _ => loop {
+                match self.table.decode(&self.buffer) {
+                    Ok((frame, consumed)) => {
+                        self.push_line(&mut text, &frame);
+                        self.buffer.drain(..consumed);
+                    }
+                    Err(DecodeError::UnexpectedEof) => break,
+                    Err(DecodeError::Malformed) => {
+                        // Raw frames have no delimiter to resynchronise on
+                        malformed += 1;
+                        self.buffer.clear();
+                        break;
+                    }
+                }
+            },
+        }
+        (text, malformed)
+    }
+
+    /// `LEVEL timestamp message (file:line)`, with the level first so it is detected
+    fn push_line(&self, text: &mut String, frame: &Frame) {
+        if let Some(level) = frame.level() {
+            let _ = write!(text, "{:<5} ", level.as_str().to_uppercase());
+        }
+        if let Some(timestamp) = frame.display_timestamp() {
+            let _ = write!(text, "{} ", timestamp);
+        }
+        let _ = write!(text, "{}", frame.display_message());
+        if let Some(location) = self.locations.get(&frame.index()) {
+            let file = location.file.file_name().unwrap_or_default();
+            let _ = write!(text, " ({}:{})", file.to_string_lossy(), location.line);
+        }
+        text.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn table(encoding: &str) -> Table {
+        serde_json::from_str(&format!(
+            r#"{{
+                "timestamp": {{"string": {{"tag": "Timestamp", "string": "{{=u8}}"}}, "raw_symbol": "ts"}},
+                "entries": {{
+                    "1": {{"string": {{"tag": "Info", "string": "boot {{=u8}}"}}, "raw_symbol": "a"}},
+                    "2": {{"string": {{"tag": "Error", "string": "fault at {{=u16:#x}}"}}, "raw_symbol": "b"}}
+                }},
+                "bitflags": {{}},
+                "encoding": "{}"
+            }}"#,
+            encoding
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_raw_frames_across_chunks() {
+        let mut decoder = DefmtDecoder::new(table("Raw"), Locations::new());
+        // index 1, timestamp 7, arg 3 | index 2, timestamp 9, arg 0x1234
+        let stream = [1, 0, 7, 3, 2, 0, 9, 0x34, 0x12];
+        assert_eq!(
+            decoder.decode(&stream[..5]),
+            ("INFO  7 boot 3\n".to_string(), 0)
+        );
+        assert_eq!(
+            decoder.decode(&stream[5..]),
+            ("ERROR 9 fault at 0x1234\n".to_string(), 0)
+        );
+        assert_eq!(decoder.decode(&[9, 0, 0]).1, 1);
+    }
+
+    #[test]
+    fn test_rzcobs_frames() {
+        let mut decoder = DefmtDecoder::new(table("Rzcobs"), Locations::new());
+        // rzCOBS of [1, 0, 7, 3] is [1, 7, 3, 0x72]; a corrupt frame is skipped
+        let (text, malformed) = decoder.decode(&[0, 1, 7, 3, 0x72, 0, 0xfe, 0, 1, 7]);
+        assert_eq!((text.as_str(), malformed), ("INFO  7 boot 3\n", 1));
+        assert_eq!(decoder.decode(&[3, 0x72, 0]).0, "INFO  7 boot 3\n");
+    }
+}
@@ -1,4 +1,6 @@
-use crate::worker::commands::{create_command_from_msg, AppendChunkCommand, WorkerCommand};
+use crate::worker::commands::{
+    create_command_from_msg, AppendChunkCommand, LoadElfCommand, WorkerCommand,
+};
 use crate::worker::state::WorkerState;
 use crate::worker::types::WorkerMsg;
 use std::cell::RefCell;
@@ -50,6 +52,15 @@ fn handle_object_message(
             let command = AppendChunkCommand { chunk, is_hex };
             command.execute(&mut state, state_rc)?;
         }
+    } else if let Some("LoadElf") = cmd.as_deref() {
+        let name = js_sys::Reflect::get(data, &"name".into())
+            .ok()
+            .and_then(|v| v.as_string())
+            .unwrap_or_default();
+        if let Ok(data_val) = js_sys::Reflect::get(data, &"data".into()) {
+            let data = js_sys::Uint8Array::new(&data_val).to_vec();
+            LoadElfCommand { name, data }.execute(&mut state, state_rc)?;
+        }
     }
     Ok(())
 }
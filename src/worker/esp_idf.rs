@@ -0,0 +1,61 @@
+use crate::types::LogLevel;
+use regex::Regex;
+
+/// Fields of an ESP-IDF log line: `L (ticks) TAG: message`
+#[derive(PartialEq, Debug)]
+pub struct EspIdfLine<'a> {
+    pub level: LogLevel,
+    /// Milliseconds since boot; None when the system time format is used
+    pub ticks: Option<u64>,
+    pub tag: &'a str,
+    pub message: &'a str,
+}
+
+/// Parses the visible text of a line (no escape sequences or stored timestamp)
+pub fn parse(text: &str) -> Option<EspIdfLine<'_>> {
+    thread_local! {
+        static LINE_RE: Regex =
+            Regex::new(r"^([EWIDV]) \((\d+|[0-9:.]+)\) ([^:\s]+): ?").unwrap();
+    }
+    LINE_RE.with(|re| {
+        let caps = re.captures(text)?;
+        let level = match &caps[1] {
+            "E" => LogLevel::Error,
+            "W" => LogLevel::Warn,
+            "I" => LogLevel::Info,
+            "D" => LogLevel::Debug,
+            _ => LogLevel::Verbose,
+        };
+        Some(EspIdfLine {
+            level,
+            ticks: caps[2].parse().ok(),
+            tag: caps.get(3)?.as_str(),
+            message: &text[caps.get(0)?.end()..],
+        })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse("W (1234) wifi: wifi:sta is connecting"),
+            Some(EspIdfLine {
+                level: LogLevel::Warn,
+                ticks: Some(1234),
+                tag: "wifi",
+                message: "wifi:sta is connecting",
+            })
+        );
+
+        let line = parse("I (12:00:01.250) cpu_start: Pro cpu up.").unwrap();
+        assert_eq!((line.ticks, line.tag), (None, "cpu_start"));
+
+        assert_eq!(parse("I (12) : no tag"), None);
+        assert_eq!(parse("Info (12) main: x"), None);
+        assert_eq!(parse("I (123) some text: value"), None);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::config::EXPORT_CHUNK_SIZE;
+use crate::worker::continuation::LineRejoiner;
 use crate::worker::error::LogError;
 use crate::worker::repository::index::ByteOffset;
 use wasm_bindgen::prelude::*;
@@ -13,15 +14,19 @@ impl LogExporter {
         Self
     }
 
-    /// Creates a ReadableStream for exporting logs
+    /// Creates a ReadableStream for exporting logs.
+    /// Lines that were split at the line cap are written back as one line;
+    /// `breaks` holds the offsets of the line breaks that split them.
     pub fn export_logs(
         handle: FileSystemSyncAccessHandle,
         file_size: ByteOffset,
+        breaks: Vec<u64>,
     ) -> Result<js_sys::Object, LogError> {
         let size = file_size;
         let backend = handle;
 
-        let stream = futures_util::stream::unfold(ByteOffset(0), move |off| {
+        let initial = (ByteOffset(0), LineRejoiner::new(breaks));
+        let stream = futures_util::stream::unfold(initial, move |(off, mut rejoiner)| {
             let h = backend.clone();
             async move {
                 if off.0 >= size.0 {
@@ -35,6 +40,34 @@ impl LogExporter {
                     return None;
                 }
 
+                let next = ByteOffset(off.0 + len as u64);
+                let joined = rejoiner.push(&buf, next.0 >= size.0);
+                let res = JsValue::from(js_sys::Uint8Array::from(&joined[..]));
+                Some((Ok(res), (next, rejoiner)))
+            }
+        });
+        Ok(ReadableStream::from_stream(stream).into_raw().into())
+    }
+
+    /// Creates a ReadableStream over bytes `start..end` of the raw byte journal, unmodified.
+    pub fn export_raw(
+        handle: FileSystemSyncAccessHandle,
+        start: ByteOffset,
+        end: ByteOffset,
+    ) -> Result<js_sys::Object, LogError> {
+        let stream = futures_util::stream::unfold(start, move |off| {
+            let h = handle.clone();
+            async move {
+                if off.0 >= end.0 {
+                    return None;
+                }
+                let len = (end.0 - off.0).min(EXPORT_CHUNK_SIZE) as usize;
+                let mut buf = vec![0u8; len];
+                let opts = FileSystemReadWriteOptions::new();
+                opts.set_at(off.0 as f64);
+                if h.read_with_u8_array_and_options(&mut buf, &opts).is_err() {
+                    return None;
+                }
                 let res = JsValue::from(js_sys::Uint8Array::from(&buf[..]));
                 Some((Ok(res), ByteOffset(off.0 + len as u64)))
             }
@@ -1,8 +1,8 @@
-use std::fmt::Write;
+use crate::types::HexLayout;
+use crate::utils::hexdump::max_row_len;
 
 pub trait LogFormatterStrategy {
     fn format(&self, text: &str, timestamp: &str) -> String;
-    fn format_chunk(&self, chunk: &[u8]) -> String;
     fn max_line_length(&self) -> usize;
 }
 
@@ -19,17 +19,13 @@ impl LogFormatterStrategy for DefaultFormatter {
         }
     }
 
-    fn format_chunk(&self, _chunk: &[u8]) -> String {
-        String::new()
-    }
-
     fn max_line_length(&self) -> usize {
         self.max_bytes
     }
 }
 
 pub struct HexFormatter {
-    pub max_bytes: usize,
+    pub layout: HexLayout,
 }
 
 impl LogFormatterStrategy for HexFormatter {
@@ -43,27 +39,23 @@ impl LogFormatterStrategy for HexFormatter {
         }
     }
 
-    fn format_chunk(&self, chunk: &[u8]) -> String {
-        let mut acc = String::with_capacity(chunk.len() * 3);
-        for &b in chunk {
-            // Hex view should display ALL bytes as hex codes, including control characters.
-            let _ = write!(acc, "{:02X} ", b);
-        }
-        acc
-    }
-
     fn max_line_length(&self) -> usize {
-        // Standard Hex View: max_bytes per line (usually 16)
-        // Each byte is 2 hex chars + 1 space = 3 chars
-        self.max_bytes * 3
+        // Rows are built whole by the hexdump layout and never split
+        max_row_len(self.layout)
     }
 }
 
-pub struct LogFormatter;
+pub struct LogFormatter {
+    pub max_line_bytes: usize,
+    pub hex_layout: HexLayout,
+}
 
 impl LogFormatter {
     pub fn new() -> Self {
-        Self
+        Self {
+            max_line_bytes: crate::config::MAX_LINE_BYTES,
+            hex_layout: HexLayout::default(),
+        }
     }
 
     pub fn get_timestamp(&self) -> String {
@@ -79,13 +71,12 @@ impl LogFormatter {
 
     pub fn create_strategy(&self, is_hex: bool) -> Box<dyn LogFormatterStrategy> {
         if is_hex {
-            // Hex Mode uses fixed HEX_VIEW_BYTES from config
             Box::new(HexFormatter {
-                max_bytes: crate::config::HEX_VIEW_BYTES,
+                layout: self.hex_layout,
             })
         } else {
             Box::new(DefaultFormatter {
-                max_bytes: crate::config::MAX_LINE_BYTES,
+                max_bytes: self.max_line_bytes,
             })
         }
     }
@@ -0,0 +1,271 @@
+use crate::types::{LevelDetection, LevelPreset, LogLevel};
+use crate::utils::ansi_decoder::strip_ansi;
+use crate::worker::continuation::strip_segment_timestamp;
+use crate::worker::error::LogError;
+use crate::worker::esp_idf::{self, EspIdfLine};
+use crate::worker::repository::index::{LineMeta, TagTable};
+use crate::worker::repository::storage::StorageBackend;
+use crate::worker::search::LogSearcher;
+use crate::worker::state::WorkerState;
+use gloo_timers::future::TimeoutFuture;
+use regex::Regex;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const CLASSIFY_BATCH_SIZE: usize = 5000;
+
+fn preset_rule(preset: LevelPreset) -> Rule {
+    let pattern = match preset {
+        LevelPreset::EspIdf => return Rule::EspIdf,
+        LevelPreset::Zephyr => r"^(?:\[[^\]]*\] )?<(err|wrn|inf|dbg)> ",
+        LevelPreset::Generic => {
+            r"^(?:\[\s*\d+\])?\[([EWIDV])\]\[|^\[?(?i:(error|err|warning|warn|wrn|info|inf|debug|dbg|trace|verbose|vrb))\]?[:\s]"
+        }
+        LevelPreset::Syslog => r"^<(\d{1,3})>",
+    };
+    Rule::Preset(preset, Regex::new(pattern).unwrap())
+}
+
+/// Level named by a captured token: only its first letter matters
+fn level_from_token(token: &str) -> Option<LogLevel> {
+    match token.chars().next()?.to_ascii_uppercase() {
+        'E' => Some(LogLevel::Error),
+        'W' => Some(LogLevel::Warn),
+        'I' => Some(LogLevel::Info),
+        'D' => Some(LogLevel::Debug),
+        'V' | 'T' => Some(LogLevel::Verbose),
+        _ => None,
+    }
+}
+
+/// Level of a syslog priority value (facility * 8 + severity)
+fn level_from_priority(priority: &str) -> Option<LogLevel> {
+    Some(match priority.parse::<u8>().ok()? % 8 {
+        0..=3 => LogLevel::Error,
+        4 => LogLevel::Warn,
+        5 | 6 => LogLevel::Info,
+        _ => LogLevel::Debug,
+    })
+}
+
+enum Rule {
+    EspIdf,
+    Preset(LevelPreset, Regex),
+    Custom(Regex, LogLevel),
+}
+
+/// Classifies lines by level using the configured presets and custom rules.
+/// Tags and tick values of ESP-IDF lines are extracted whatever the presets.
+pub struct LevelDetector {
+    rules: Vec<Rule>,
+}
+
+impl LevelDetector {
+    pub fn new(config: &LevelDetection) -> Result<Self, String> {
+        let mut rules = Vec::new();
+        for rule in &config.custom {
+            let re = Regex::new(&rule.pattern).map_err(|e| e.to_string())?;
+            rules.push(Rule::Custom(re, rule.level));
+        }
+        for &preset in &config.presets {
+            rules.push(preset_rule(preset));
+        }
+        Ok(Self { rules })
+    }
+
+    /// Level and ESP-IDF fields of a stored line, passed to `f`
+    pub fn inspect<R>(&self, line: &str, f: impl FnOnce(u8, Option<EspIdfLine>) -> R) -> R {
+        let visible = strip_ansi(line);
+        let text = strip_segment_timestamp(&visible);
+        f(
+            LogLevel::code(self.classify_visible(text)),
+            esp_idf::parse(text),
+        )
+    }
+
+    /// Metadata of a stored line, registering its tag in `tags`
+    pub fn detect(&self, line: &str, tags: &mut TagTable) -> LineMeta {
+        self.inspect(line, |level, esp| match esp {
+            Some(esp) => LineMeta {
+                level,
+                tag: tags.intern(esp.tag),
+                ticks: esp.ticks,
+            },
+            None => LineMeta {
+                level,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Level of a line's visible text, with escapes and the stored timestamp removed
+    pub fn classify_visible(&self, text: &str) -> Option<LogLevel> {
+        self.rules.iter().find_map(|rule| match rule {
+            Rule::EspIdf => esp_idf::parse(text).map(|l| l.level),
+            Rule::Custom(re, level) => re.is_match(text).then_some(*level),
+            Rule::Preset(preset, re) => {
+                let caps = re.captures(text)?;
+                let token = caps.iter().skip(1).flatten().next()?.as_str();
+                match preset {
+                    LevelPreset::Syslog => level_from_priority(token),
+                    _ => level_from_token(token),
+                }
+            }
+        })
+    }
+}
+
+impl Default for LevelDetector {
+    fn default() -> Self {
+        Self::new(&LevelDetection::default()).unwrap()
+    }
+}
+
+pub struct LevelClassifier;
+
+impl LevelClassifier {
+    /// Re-detects the level of every stored line with the current detector,
+    /// then reapplies the filter if it depends on levels
+    pub async fn reclassify_async(
+        state_rc: Rc<RefCell<WorkerState>>,
+        classify_id: u32,
+    ) -> Result<(), LogError> {
+        let total_lines = state_rc.borrow().proc.repository.index.line_count;
+        let mut batch_start = 0;
+        let mut buf = Vec::new();
+        // Continued segments take the level and tag of the segment they continue
+        let mut inherited: Option<LineMeta> = None;
+
+        while batch_start < total_lines {
+            {
+                let mut state = state_rc.borrow_mut();
+                if state.current_classify_id != classify_id {
+                    return Ok(());
+                }
+                let repo = &mut state.proc.repository;
+                let batch_end = (batch_start + CLASSIFY_BATCH_SIZE).min(total_lines);
+                // The log was cleared in the meantime
+                if batch_end > repo.index.line_count {
+                    return Ok(());
+                }
+
+                let (s_off, e_off) = (
+                    repo.index.line_offsets[batch_start],
+                    repo.index.line_offsets[batch_end],
+                );
+                let size = (e_off.0 - s_off.0) as usize;
+                buf.resize(size, 0);
+                repo.storage.backend.read_at(s_off, &mut buf)?;
+                let text = String::from_utf8_lossy(&buf);
+
+                for (j, line) in text.trim_end_matches('\n').split('\n').enumerate() {
+                    let continued = repo.index.is_continued(batch_start + j);
+                    let meta = match inherited {
+                        Some(meta) => LineMeta {
+                            ticks: None,
+                            ..meta
+                        },
+                        None => repo.levels.detect(line, &mut repo.index.tags),
+                    };
+                    repo.index.set_meta(batch_start + j, meta);
+                    inherited = continued.then_some(meta);
+                }
+                batch_start = batch_end;
+            }
+            TimeoutFuture::new(0).await;
+        }
+
+        let filter = {
+            let state = state_rc.borrow();
+            let index = &state.proc.repository.index;
+            (!index.level_mask.is_all() || !index.tag_filter.is_empty())
+                .then(|| index.active_filter.clone())
+        };
+        if let Some(filter) = filter {
+            LogSearcher::filter_async(state_rc, filter).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::CustomLevelRule;
+
+    #[test]
+    fn test_presets() {
+        let detector = LevelDetector::default();
+        let cases = [
+            (
+                "\x1b[0;31mE (1234) wifi: fail\x1b[0m",
+                Some(LogLevel::Error),
+            ),
+            ("[10:00:00.000] W (12) boot: slow", Some(LogLevel::Warn)),
+            ("[00:00:01.000,000] <inf> main: up", Some(LogLevel::Info)),
+            ("[   123][D][main.cpp:10] setup(): x", Some(LogLevel::Debug)),
+            ("[WRN] low battery", Some(LogLevel::Warn)),
+            ("ERROR: overflow", Some(LogLevel::Error)),
+            ("<11>disk failed", Some(LogLevel::Error)),
+            ("<14>ready", Some(LogLevel::Info)),
+            ("Information only", None),
+            ("I think so", None),
+        ];
+        for (line, level) in cases {
+            assert_eq!(
+                detector.inspect(line, |l, _| l),
+                LogLevel::code(level),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_tags() {
+        let detector = LevelDetector::default();
+        let mut tags = TagTable::default();
+        let meta = detector.detect("[10:00:00.000] \x1b[0;32mI (42) wifi: up\x1b[0m", &mut tags);
+        assert_eq!(
+            (meta.level, meta.ticks),
+            (LogLevel::code(Some(LogLevel::Info)), Some(42))
+        );
+        assert_eq!(tags.name(meta.tag), Some("wifi"));
+        assert_eq!(detector.detect("[WRN] x", &mut tags).tag, 0);
+
+        let zephyr_only = LevelDetector::new(&LevelDetection {
+            presets: vec![LevelPreset::Zephyr],
+            custom: vec![],
+        })
+        .unwrap();
+        // Without the preset the line has no level but keeps its tag and ticks
+        let meta = zephyr_only.detect("I (42) wifi: up", &mut tags);
+        assert_eq!((meta.level, meta.ticks), (0, Some(42)));
+        assert_eq!(tags.name(meta.tag), Some("wifi"));
+    }
+
+    #[test]
+    fn test_custom_rules_first() {
+        let detector = LevelDetector::new(&LevelDetection {
+            presets: vec![LevelPreset::EspIdf],
+            custom: vec![CustomLevelRule {
+                pattern: "panic|abort".to_string(),
+                level: LogLevel::Error,
+            }],
+        })
+        .unwrap();
+        assert_eq!(
+            detector.inspect("I (5) x: abort()", |l, _| l),
+            LogLevel::code(Some(LogLevel::Error))
+        );
+        assert_eq!(detector.inspect("[WRN] x", |l, _| l), 0);
+        assert!(LevelDetector::new(&LevelDetection {
+            presets: vec![],
+            custom: vec![CustomLevelRule {
+                pattern: "(".to_string(),
+                level: LogLevel::Info,
+            }],
+        })
+        .is_err());
+    }
+}
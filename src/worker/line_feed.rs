@@ -0,0 +1,95 @@
+use crate::types::TextEncoding;
+use crate::utils::encoding::{take_tokens, StreamDecoder};
+
+/// Longest line kept before it is cut, for devices that never send a newline
+const MAX_LINE_CHARS: usize = 4096;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Escape {
+    None,
+    Start,
+    Csi,
+}
+
+/// Splits received text into plain lines for consumers on the main thread,
+/// such as the AT console. Lines end at CR or LF; empty lines, escape
+/// sequences and control characters are dropped.
+pub struct LineFeed {
+    partial: String,
+    escape: Escape,
+    lines: Vec<String>,
+    /// Decodes the raw stream on its own, since the monitor may cut
+    /// records before decoding them
+    decoder: StreamDecoder,
+}
+
+impl LineFeed {
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            partial: String::new(),
+            escape: Escape::None,
+            lines: Vec::new(),
+            decoder: StreamDecoder::new(encoding),
+        }
+    }
+
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        self.decoder = StreamDecoder::new(encoding);
+    }
+
+    /// Adds received bytes; undecodable bytes are dropped
+    pub fn push_bytes(&mut self, chunk: &[u8]) {
+        let text = self.decoder.decode(chunk, false);
+        self.push(&take_tokens(&text).0);
+    }
+
+    pub fn push(&mut self, text: &str) {
+        for c in text.chars() {
+            match (self.escape, c) {
+                (Escape::Start, '[') => self.escape = Escape::Csi,
+                (Escape::Start, _) => self.escape = Escape::None,
+                (Escape::Csi, '\x40'..='\x7e') => self.escape = Escape::None,
+                (Escape::Csi, _) => {}
+                (Escape::None, '\x1b') => self.escape = Escape::Start,
+                (Escape::None, '\r' | '\n') => self.end_line(),
+                (Escape::None, '\t') => self.partial.push(c),
+                (Escape::None, c) if c.is_control() => {}
+                (Escape::None, c) => {
+                    self.partial.push(c);
+                    if self.partial.len() >= MAX_LINE_CHARS {
+                        self.end_line();
+                    }
+                }
+            }
+        }
+    }
+
+    fn end_line(&mut self) {
+        if !self.partial.is_empty() {
+            self.lines.push(std::mem::take(&mut self.partial));
+        }
+    }
+
+    /// Lines completed since the last call
+    pub fn take_lines(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.lines)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_lines_and_strips_escapes() {
+        let mut feed = LineFeed::new(TextEncoding::Utf8);
+        feed.push("AT+CSQ\r\r\n+CSQ: 2");
+        assert_eq!(feed.take_lines(), ["AT+CSQ"]);
+        feed.push("0,99\r\n\r\nOK\r\n\x1b[0;32mI (312) boot: ready\x1b[0m\n");
+        assert_eq!(
+            feed.take_lines(),
+            ["+CSQ: 20,99", "OK", "I (312) boot: ready"]
+        );
+        assert!(feed.take_lines().is_empty());
+    }
+}
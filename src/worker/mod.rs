@@ -1,14 +1,21 @@
+pub mod byte_search;
 pub mod chunk_handler;
 pub mod commands;
+pub mod continuation;
+pub mod defmt;
 pub mod dispatcher;
 pub mod error;
+pub mod esp_idf;
 pub mod export;
 pub mod formatter;
+pub mod levels;
 pub mod lifecycle;
+pub mod line_feed;
 pub mod processor;
 pub mod repository;
 pub mod search;
 pub mod state;
+pub mod symbols;
 pub mod types;
 
 // Re-export public functions
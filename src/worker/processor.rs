@@ -1,26 +1,68 @@
-use crate::worker::chunk_handler::StreamingLineProcessor;
+use crate::config::TX_LINE_PREFIX;
+use crate::types::{HexLayout, RxFraming, SymbolInfo, TextEncoding, TokenKind};
+use crate::utils::ansi_decoder::escape_complete;
+use crate::utils::encoding::{escape_marks, token_mark};
+use crate::worker::chunk_handler::{AttrLine, LineEnds, LineSplitter, StreamingLineProcessor};
+use crate::worker::defmt::DefmtDecoder;
 use crate::worker::error::LogError;
+use crate::worker::line_feed::LineFeed;
 
 use crate::worker::formatter::LogFormatter;
 
+use crate::worker::repository::index::LineIndex;
+use crate::worker::repository::journal::RawJournal;
+use crate::worker::repository::storage::SessionHandles;
 use crate::worker::repository::LogRepository;
-
-use web_sys::FileSystemSyncAccessHandle;
+use crate::worker::symbols::{backtrace_addresses, Symbolizer};
 
 pub struct LogProcessor {
     pub(crate) repository: LogRepository,
+    pub(crate) journal: RawJournal,
     pub(crate) formatter: LogFormatter,
     pub(crate) show_timestamps: bool,
     chunk_handler: StreamingLineProcessor,
+    encoding: TextEncoding,
+    /// Bytes received this session; the stream offset shown in the hex view
+    received_bytes: u64,
+    /// Malformed sequences decoded this session, across encoding changes
+    invalid_sequences: u64,
+    /// Firmware ELF used to resolve code addresses
+    pub(crate) symbolizer: Option<Symbolizer>,
+    /// Program counters of the latest backtrace line
+    last_backtrace: Vec<u64>,
+    /// Bumped when a backtrace arrives or the ELF changes
+    pub(crate) backtrace_version: u64,
+    /// defmt table of the loaded ELF, if it has one
+    defmt: Option<DefmtDecoder>,
+    /// Received text is decoded as defmt frames instead of characters
+    defmt_enabled: bool,
+    /// Terminal keystrokes of the line being typed, one entry per key
+    tx_line: Vec<String>,
+    /// Escape sequence of a key still being typed
+    tx_escape: Vec<u8>,
+    /// Plain received lines for the main thread, while anyone follows them
+    line_feed: Option<LineFeed>,
 }
 
 impl LogProcessor {
     pub fn new() -> Result<Self, LogError> {
         Ok(LogProcessor {
             repository: LogRepository::new()?,
+            journal: RawJournal::new(),
             formatter: LogFormatter::new(),
             show_timestamps: false,
             chunk_handler: StreamingLineProcessor::new(),
+            encoding: TextEncoding::default(),
+            received_bytes: 0,
+            invalid_sequences: 0,
+            symbolizer: None,
+            last_backtrace: Vec::new(),
+            backtrace_version: 0,
+            defmt: None,
+            defmt_enabled: false,
+            tx_line: Vec::new(),
+            tx_escape: Vec::new(),
+            line_feed: None,
         })
     }
 
@@ -29,11 +71,57 @@ impl LogProcessor {
         self.repository.get_line_count() as u32
     }
 
-    pub fn set_sync_handle(&mut self, handle: FileSystemSyncAccessHandle) -> Result<(), LogError> {
-        self.repository.initialize_storage(handle)
+    pub fn set_sync_handle(&mut self, handles: SessionHandles) -> Result<(), LogError> {
+        self.repository
+            .initialize_storage(handles.log, handles.attrs)?;
+        self.journal.initialize(handles.raw)?;
+        self.received_bytes = self.journal.size();
+        Ok(())
+    }
+
+    /// Raw journal byte range behind displayed lines `start..end`
+    pub fn raw_range(&self, start: usize, end: usize) -> Option<(u64, u64)> {
+        let index = &self.repository.index;
+        let first = index.absolute_line(LineIndex(start))?;
+        let last = index.absolute_line(LineIndex(end.checked_sub(1)?.max(start)))?;
+        Some(self.journal.line_range(first, last + 1))
+    }
+
+    pub fn append_chunk(
+        &mut self,
+        chunk: &[u8],
+        is_hex: bool,
+    ) -> Result<Option<AttrLine>, LogError> {
+        // A hex row cut short by text keeps its own line
+        if !is_hex && self.chunk_handler.has_hex_row() {
+            self.flush_active_line()?;
+        }
+
+        // The journal keeps the bytes exactly as received, whatever the view mode
+        self.journal
+            .append(chunk, self.repository.index.line_count)?;
+        let stream_offset = self.received_bytes;
+        self.received_bytes += chunk.len() as u64;
+        self.decode_chunk(chunk, is_hex, stream_offset, true)
     }
 
-    pub fn append_chunk(&mut self, chunk: &[u8], is_hex: bool) -> Result<Option<String>, LogError> {
+    /// Shows text that did not arrive as raw port bytes, such as program
+    /// output unwrapped from a transfer protocol. It is kept out of the raw
+    /// journal and the line feed.
+    pub fn show_output(&mut self, text: &[u8]) -> Result<Option<AttrLine>, LogError> {
+        if self.chunk_handler.has_hex_row() {
+            self.flush_active_line()?;
+        }
+        self.decode_chunk(text, false, self.received_bytes, false)
+    }
+
+    fn decode_chunk(
+        &mut self,
+        chunk: &[u8],
+        is_hex: bool,
+        stream_offset: u64,
+        feed_lines: bool,
+    ) -> Result<Option<AttrLine>, LogError> {
         let formatter = self.formatter.create_strategy(is_hex);
         let timestamp = if self.show_timestamps {
             self.formatter.get_timestamp()
@@ -41,34 +129,249 @@ impl LogProcessor {
             String::new()
         };
 
-        let repo = &self.repository;
-        let is_filtering = repo.is_filtering();
-        let filter_matcher = |text: &str| repo.matches_active_filter(text);
-
-        let (batch, offsets, filtered, active_line) = if is_hex {
-            let text = formatter.format_chunk(chunk);
-            let (b, o, f, active) = self.chunk_handler.process_hex_lines(
-                &text,
-                &*formatter,
-                &timestamp,
-                is_filtering,
-                filter_matcher,
-            );
-            (b, o, f, active)
+        let (batch, offsets, active_line) = if is_hex {
+            if let Some(feed) = self.line_feed.as_mut().filter(|_| feed_lines) {
+                feed.push_bytes(chunk);
+            }
+            self.chunk_handler
+                .process_hex_lines(chunk, stream_offset, &*formatter, &timestamp)
+        } else if let Some(defmt) = self.defmt.as_mut().filter(|_| self.defmt_enabled) {
+            let (text, malformed) = defmt.decode(chunk);
+            self.invalid_sequences += malformed;
+            if let Some(feed) = self.line_feed.as_mut().filter(|_| feed_lines) {
+                feed.push(&text);
+            }
+            self.chunk_handler
+                .process_text(&text, &*formatter, &timestamp)
         } else {
-            self.chunk_handler.process_vt100(
-                chunk,
-                &*formatter,
-                &timestamp,
-                is_filtering,
-                filter_matcher,
-            )
+            if let Some(feed) = self.line_feed.as_mut().filter(|_| feed_lines) {
+                feed.push_bytes(chunk);
+            }
+            let seen = self.chunk_handler.invalid_sequences();
+            let result = self
+                .chunk_handler
+                .process_vt100(chunk, &*formatter, &timestamp);
+            self.invalid_sequences += self.chunk_handler.invalid_sequences() - seen;
+            result
         };
 
         if !batch.is_empty() {
-            self.repository.append_lines(&batch, offsets, filtered)?;
+            self.commit_lines(&batch, offsets)?;
+        }
+        // Checked once the batch is stored, so a continued line sees the level it inherits
+        let repo = &self.repository;
+        Ok(
+            active_line
+                .filter(|(text, _)| !repo.is_filtering() || repo.matches_active_filter(text)),
+        )
+    }
+
+    /// Commits the pending active line and then writes `text` as its own line,
+    /// so markers always land between complete lines.
+    pub fn append_marker(&mut self, text: &str) -> Result<(), LogError> {
+        self.flush_active_line()?;
+        self.commit_text_line(text)
+    }
+
+    /// Writes `text` as a line of its own, leaving the active line open
+    fn commit_text_line(&mut self, text: &str) -> Result<(), LogError> {
+        let formatter = self.formatter.create_strategy(false);
+        let timestamp = if self.show_timestamps {
+            self.formatter.get_timestamp()
+        } else {
+            String::new()
+        };
+
+        let (marker, marker_offsets) =
+            self.chunk_handler
+                .format_line(text, &*formatter, &timestamp);
+        self.commit_lines(&marker, marker_offsets)
+    }
+
+    /// Records bytes sent to the port. Each line sent is stored as its
+    /// own `TX> ` line, with backspaces applied and control keys as tokens;
+    /// a key sending an escape sequence is one token.
+    pub fn record_tx(&mut self, bytes: &[u8]) -> Result<(), LogError> {
+        let key_token = |seq: &[u8]| -> String {
+            seq.iter()
+                .map(|&b| token_mark(TokenKind::Content, b))
+                .collect()
+        };
+        for c in String::from_utf8_lossy(bytes).chars() {
+            if c == '\x1b' || !self.tx_escape.is_empty() {
+                if c.is_ascii() {
+                    self.tx_escape.push(c as u8);
+                    if escape_complete(&self.tx_escape) {
+                        let seq = std::mem::take(&mut self.tx_escape);
+                        self.tx_line.push(key_token(&seq));
+                    }
+                    continue;
+                }
+                // Not a sequence after all
+                let seq = std::mem::take(&mut self.tx_escape);
+                self.tx_line.push(key_token(&seq));
+            }
+            match c {
+                '\r' => self.commit_tx()?,
+                '\n' if !self.tx_line.is_empty() => self.commit_tx()?,
+                '\n' => {}
+                '\x08' | '\x7f' => {
+                    self.tx_line.pop();
+                }
+                // Ctrl+C and Ctrl+D end what was typed
+                '\x03' | '\x04' => {
+                    self.tx_line.push(key_token(&[c as u8]));
+                    self.commit_tx()?;
+                }
+                c if c.is_ascii_control() => self.tx_line.push(key_token(&[c as u8])),
+                c => self
+                    .tx_line
+                    .push(escape_marks(c.encode_utf8(&mut [0; 4])).into_owned()),
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores the typed line without committing the line being received, so
+    /// a prompt and the echo of what was typed stay on one line
+    fn commit_tx(&mut self) -> Result<(), LogError> {
+        let line = format!(
+            "{}{}",
+            TX_LINE_PREFIX,
+            std::mem::take(&mut self.tx_line).concat()
+        );
+        self.commit_text_line(&line)
+    }
+
+    /// Commits whatever has been received of the current line as a complete line.
+    pub fn flush_active_line(&mut self) -> Result<(), LogError> {
+        let formatter = self.formatter.create_strategy(false);
+        let timestamp = if self.show_timestamps {
+            self.formatter.get_timestamp()
+        } else {
+            String::new()
+        };
+
+        let hex_formatter = self.formatter.create_strategy(true);
+        let hex_row = self
+            .chunk_handler
+            .flush_hex_row(&*hex_formatter, &timestamp);
+        let text_line = self
+            .chunk_handler
+            .flush_active_line(&*formatter, &timestamp);
+        for (batch, offsets) in [hex_row, text_line] {
+            if !batch.is_empty() {
+                self.commit_lines(&batch, offsets)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Stores complete lines, remembering the last backtrace among them
+    fn commit_lines(&mut self, batch: &str, offsets: LineEnds) -> Result<(), LogError> {
+        self.repository.append_lines(batch, offsets)?;
+        if let Some(addresses) = batch.lines().rev().find_map(backtrace_addresses) {
+            self.last_backtrace = addresses;
+            self.backtrace_version += 1;
+        }
+        Ok(())
+    }
+
+    /// Replaces the firmware ELF; None unloads it
+    pub fn set_symbolizer(&mut self, symbolizer: Option<Symbolizer>) {
+        self.symbolizer = symbolizer;
+        self.backtrace_version += 1;
+    }
+
+    /// Replaces the defmt table along with the ELF
+    pub fn set_defmt(&mut self, defmt: Option<DefmtDecoder>) {
+        self.defmt = defmt;
+    }
+
+    /// Switches between defmt and character decoding, committing the partial line first
+    pub fn set_defmt_enabled(&mut self, enabled: bool) -> Result<(), LogError> {
+        self.flush_active_line()?;
+        self.defmt_enabled = enabled;
+        if let Some(defmt) = self.defmt.as_mut() {
+            defmt.reset();
+        }
+        Ok(())
+    }
+
+    /// Code address looked up in the ELF, or bare when none is loaded
+    pub fn symbolize(&self, address: u64) -> SymbolInfo {
+        match &self.symbolizer {
+            Some(symbolizer) => symbolizer.lookup(address),
+            None => SymbolInfo {
+                address,
+                function: None,
+                file: None,
+                line: None,
+            },
+        }
+    }
+
+    /// Frames of the latest backtrace, outermost last
+    pub fn backtrace(&self) -> Vec<SymbolInfo> {
+        self.last_backtrace
+            .iter()
+            .map(|&address| self.symbolize(address))
+            .collect()
+    }
+
+    pub fn has_active_line(&self) -> bool {
+        self.chunk_handler.has_active_line()
+    }
+
+    /// Switches how incoming bytes are split into lines. The partial line
+    /// received under the previous framing is committed first.
+    pub fn set_rx_framing(&mut self, framing: &RxFraming) -> Result<(), LogError> {
+        let splitter = LineSplitter::from_framing(framing).map_err(LogError::Regex)?;
+        self.flush_active_line()?;
+        self.chunk_handler.set_splitter(splitter);
+
+        let max_line_bytes = framing
+            .max_line_bytes
+            .clamp(1, crate::config::LINE_BYTES_HARD_LIMIT);
+        self.chunk_handler.set_max_line_bytes(max_line_bytes);
+        self.formatter.max_line_bytes = max_line_bytes;
+        Ok(())
+    }
+
+    /// Changes the hex view row layout, committing the partial row first
+    pub fn set_hex_layout(&mut self, layout: HexLayout) -> Result<(), LogError> {
+        self.flush_active_line()?;
+        self.chunk_handler.set_hex_layout(layout);
+        self.formatter.hex_layout = layout;
+        Ok(())
+    }
+
+    /// Switches the receive encoding. Bytes of a sequence left incomplete under
+    /// the previous encoding are dropped.
+    pub fn set_encoding(&mut self, encoding: TextEncoding) {
+        if encoding != self.encoding {
+            self.encoding = encoding;
+            self.chunk_handler.set_encoding(encoding);
+            if let Some(feed) = self.line_feed.as_mut() {
+                feed.set_encoding(encoding);
+            }
         }
-        Ok(active_line)
+    }
+
+    pub fn set_line_feed(&mut self, enabled: bool) {
+        self.line_feed = enabled.then(|| LineFeed::new(self.encoding));
+    }
+
+    /// Received lines completed since the last call
+    pub fn take_received_lines(&mut self) -> Vec<String> {
+        self.line_feed
+            .as_mut()
+            .map(LineFeed::take_lines)
+            .unwrap_or_default()
+    }
+
+    pub fn invalid_sequences(&self) -> u64 {
+        self.invalid_sequences
     }
 
     pub fn set_timestamp_state(&mut self, enabled: bool) {
@@ -77,7 +380,17 @@ impl LogProcessor {
 
     pub fn clear(&mut self) -> Result<(), LogError> {
         self.repository.clear()?;
+        self.journal.clear()?;
+        self.received_bytes = 0;
+        self.invalid_sequences = 0;
+        self.last_backtrace.clear();
+        self.tx_line.clear();
+        self.tx_escape.clear();
+        self.backtrace_version += 1;
         self.chunk_handler.clear();
+        if let Some(defmt) = self.defmt.as_mut() {
+            defmt.reset();
+        }
         Ok(())
     }
 }
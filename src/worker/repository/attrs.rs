@@ -0,0 +1,125 @@
+use std::collections::BTreeMap;
+
+use crate::types::LineAttrs;
+use crate::worker::error::LogError;
+use crate::worker::repository::index::ByteOffset;
+use crate::worker::repository::storage::{OpfsBackend, StorageBackend};
+use web_sys::FileSystemSyncAccessHandle;
+
+/// Keeps the `LineAttrs` of the lines that have any in a file next to the
+/// text log (one JSON `[line, attrs]` record per line), so they survive a reload.
+pub struct AttrLog {
+    file: OpfsBackend,
+    size: u64,
+}
+
+impl AttrLog {
+    pub fn new() -> Self {
+        Self {
+            file: OpfsBackend { handle: None },
+            size: 0,
+        }
+    }
+
+    /// Attaches the session's attribute file and returns the attributes it holds
+    pub fn initialize(
+        &mut self,
+        handle: Option<FileSystemSyncAccessHandle>,
+    ) -> Result<BTreeMap<usize, LineAttrs>, LogError> {
+        self.file.handle = handle;
+        self.size = 0;
+        if self.file.handle.is_none() {
+            return Ok(BTreeMap::new());
+        }
+
+        let size = self.file.get_file_size()?.0 as usize;
+        let mut buf = vec![0u8; size];
+        if size > 0 {
+            self.file.read_at(ByteOffset(0), &mut buf)?;
+        }
+        let (attrs, valid) = decode_records(&buf);
+        // A torn tail is overwritten by the next append
+        self.size = valid as u64;
+        Ok(attrs)
+    }
+
+    /// Appends the attributes of newly committed lines
+    pub fn append(&mut self, lines: &[(usize, &LineAttrs)]) -> Result<(), LogError> {
+        if self.file.handle.is_none() || lines.is_empty() {
+            return Ok(());
+        }
+        let records = encode_records(lines);
+        self.file.write_at(ByteOffset(self.size), &records)?;
+        self.size += records.len() as u64;
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> Result<(), LogError> {
+        self.size = 0;
+        if self.file.handle.is_some() {
+            self.file.truncate(0)?;
+            self.file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for AttrLog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode_records(lines: &[(usize, &LineAttrs)]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for record in lines {
+        if serde_json::to_writer(&mut out, record).is_ok() {
+            out.push(b'\n');
+        }
+    }
+    out
+}
+
+/// Parses records written by `encode_records`, stopping at the first one that
+/// is cut short. Returns the attributes and the length of the valid prefix.
+fn decode_records(bytes: &[u8]) -> (BTreeMap<usize, LineAttrs>, usize) {
+    let mut attrs = BTreeMap::new();
+    let mut valid = 0;
+    for record in bytes.split_inclusive(|&b| b == b'\n') {
+        let Some(json) = record.strip_suffix(b"\n") else {
+            break;
+        };
+        let Ok((line, a)) = serde_json::from_slice::<(usize, LineAttrs)>(json) else {
+            break;
+        };
+        attrs.insert(line, a);
+        valid += record.len();
+    }
+    (attrs, valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_round_trip() {
+        let continued = LineAttrs {
+            continued: true,
+            ..LineAttrs::default()
+        };
+        let overwritten = LineAttrs {
+            history: vec![("10%".to_string(), Vec::new())],
+            ..LineAttrs::default()
+        };
+        let mut bytes = encode_records(&[(3, &continued), (4, &overwritten)]);
+        let valid = bytes.len();
+        bytes.extend_from_slice(b"[5,{\"contin"); // torn write
+
+        let (attrs, len) = decode_records(&bytes);
+        assert_eq!(len, valid);
+        assert_eq!(attrs.keys().copied().collect::<Vec<_>>(), vec![3, 4]);
+        assert!(attrs[&3].continued);
+        assert_eq!(attrs[&4], overwritten);
+    }
+}
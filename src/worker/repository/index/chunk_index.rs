@@ -0,0 +1,149 @@
+/// Start of a received chunk in the raw journal, tagged with the number of
+/// log lines that had been committed when it arrived
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChunkEntry {
+    pub raw_start: u64,
+    pub lines_before: u64,
+}
+
+impl ChunkEntry {
+    pub const ENCODED_LEN: usize = 16;
+
+    pub fn to_bytes(self) -> [u8; Self::ENCODED_LEN] {
+        let mut out = [0u8; Self::ENCODED_LEN];
+        out[..8].copy_from_slice(&self.raw_start.to_le_bytes());
+        out[8..].copy_from_slice(&self.lines_before.to_le_bytes());
+        out
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        Some(Self {
+            raw_start: u64::from_le_bytes(bytes.get(..8)?.try_into().ok()?),
+            lines_before: u64::from_le_bytes(bytes.get(8..16)?.try_into().ok()?),
+        })
+    }
+}
+
+/// Maps log lines back to the raw chunks they were decoded from.
+///
+/// The mapping is chunk-granular: a line's range starts at the chunk in which
+/// the previous line was committed and ends with the chunk in which it was
+/// committed itself, so it always contains every byte of the line.
+#[derive(Default)]
+pub struct ChunkIndex {
+    entries: Vec<ChunkEntry>,
+}
+
+impl ChunkIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Records a chunk. Chunks that arrive before any new line was committed
+    /// are merged into the previous entry; returns the entry if one was added.
+    pub fn record(&mut self, raw_start: u64, lines_before: u64) -> Option<ChunkEntry> {
+        if self
+            .entries
+            .last()
+            .is_some_and(|e| e.lines_before == lines_before)
+        {
+            return None;
+        }
+        let entry = ChunkEntry {
+            raw_start,
+            lines_before,
+        };
+        self.entries.push(entry);
+        Some(entry)
+    }
+
+    /// Loads entries persisted by `ChunkEntry::to_bytes`, ignoring a torn tail
+    pub fn load(&mut self, bytes: &[u8]) {
+        self.entries = bytes
+            .chunks_exact(ChunkEntry::ENCODED_LEN)
+            .filter_map(ChunkEntry::from_bytes)
+            .collect();
+    }
+
+    /// Raw byte range `[start, end)` covering lines `first_line..end_line`
+    /// (absolute line numbers), given the current journal length.
+    pub fn raw_range(&self, first_line: usize, end_line: usize, raw_len: u64) -> (u64, u64) {
+        if self.entries.is_empty() || end_line <= first_line {
+            return (0, 0);
+        }
+
+        let start = match first_line.checked_sub(1) {
+            None => 0,
+            Some(prev) => {
+                let p = self.position(prev);
+                p.checked_sub(1).map_or(0, |i| self.entries[i].raw_start)
+            }
+        };
+        let p = self.position(end_line - 1);
+        let end = self.entries.get(p).map_or(raw_len, |e| e.raw_start);
+        (start, end.max(start))
+    }
+
+    /// First line that can hold the raw byte at `raw_offset`: the first line
+    /// not yet committed when the byte's chunk arrived
+    pub fn first_line_at(&self, raw_offset: u64) -> Option<usize> {
+        let p = self.entries.partition_point(|e| e.raw_start <= raw_offset);
+        Some(self.entries.get(p.checked_sub(1)?)?.lines_before as usize)
+    }
+
+    /// Number of entries whose chunk arrived at or before `line` was committed
+    fn position(&self, line: usize) -> usize {
+        self.entries
+            .partition_point(|e| e.lines_before <= line as u64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_range_covers_lines() {
+        // chunk 0: "ab\ncd" (0..5), chunk 1: "e\nf\n" (5..9), chunk 2: "gh" (9..11)
+        let mut index = ChunkIndex::new();
+        assert!(index.record(0, 0).is_some());
+        assert!(index.record(5, 1).is_some());
+        assert!(index.record(9, 3).is_some());
+        // A chunk arriving without a committed line merges into the previous one
+        assert!(index.record(11, 3).is_none());
+
+        assert_eq!(index.raw_range(0, 1, 13), (0, 5));
+        assert_eq!(index.raw_range(1, 2, 13), (0, 9));
+        assert_eq!(index.raw_range(2, 3, 13), (5, 9));
+        assert_eq!(index.raw_range(3, 4, 13), (5, 13));
+        assert_eq!(index.raw_range(0, 3, 13), (0, 9));
+
+        assert_eq!(index.first_line_at(3), Some(0));
+        assert_eq!(index.first_line_at(6), Some(1));
+        assert_eq!(index.first_line_at(12), Some(3));
+    }
+
+    #[test]
+    fn test_entries_round_trip() {
+        let mut index = ChunkIndex::new();
+        let mut bytes = Vec::new();
+        for (start, lines) in [(0, 0), (100, 4)] {
+            if let Some(entry) = index.record(start, lines) {
+                bytes.extend_from_slice(&entry.to_bytes());
+            }
+        }
+        bytes.push(0xAA); // torn write
+
+        let mut loaded = ChunkIndex::new();
+        loaded.load(&bytes);
+        assert_eq!(loaded.entries, index.entries);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::utils::ansi_decoder::strip_ansi;
+use crate::worker::continuation::strip_segment_timestamp;
 use regex::Regex;
 
 /// Active filter for log searching
@@ -11,7 +13,11 @@ pub struct ActiveFilter {
 }
 
 impl ActiveFilter {
+    /// Matches against the visible text after the stored timestamp, so
+    /// neither escape sequences nor timestamps break queries and anchors
     pub fn matches(&self, text: &str) -> bool {
+        let visible = strip_ansi(text);
+        let text = strip_segment_timestamp(&visible);
         let matched = if let Some(re) = &self.regex {
             re.is_match(text)
         } else if self.match_case {
@@ -88,3 +94,34 @@ impl ActiveFilterBuilder {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches_visible_text() {
+        let line = "\x1B[0;31mE (1234) wifi:\x1B[0m disconnected";
+        let anchored = ActiveFilterBuilder::new("^E \\(\\d+\\) wifi: disc".to_string())
+            .regex(true)
+            .build()
+            .unwrap();
+        assert!(anchored.matches(line));
+
+        let plain = ActiveFilterBuilder::new("wifi: DISC".to_string())
+            .case_sensitive(false)
+            .build()
+            .unwrap();
+        assert!(plain.matches(line));
+    }
+
+    #[test]
+    fn test_matches_after_timestamp() {
+        let anchored = ActiveFilterBuilder::new("^E \\(".to_string())
+            .regex(true)
+            .build()
+            .unwrap();
+        assert!(anchored.matches("[01:02:03.456] \x1B[0;31mE (1234) wifi: up"));
+        assert!(!anchored.matches("[01:02:03.456] I (1234) wifi: E (5)"));
+    }
+}
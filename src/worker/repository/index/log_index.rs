@@ -1,5 +1,9 @@
+use std::collections::BTreeMap;
+
+use crate::types::{LevelCounts, LevelMask, LineAttrs, TagFilter};
 use crate::worker::repository::index::filter::ActiveFilter;
-use crate::worker::repository::index::types::{ByteOffset, LineIndex, LineRange};
+use crate::worker::repository::index::tags::TagTable;
+use crate::worker::repository::index::types::{ByteOffset, LineIndex, LineMeta, LineRange};
 
 /// Log index that tracks line offsets and filtering state
 pub struct LogIndex {
@@ -8,6 +12,20 @@ pub struct LogIndex {
     pub filtered_lines: Vec<LineRange>,
     pub is_filtering: bool,
     pub active_filter: Option<ActiveFilter>,
+    /// `LogLevel::code` of each line
+    pub line_levels: Vec<u8>,
+    pub level_counts: LevelCounts,
+    /// Levels shown while filtering; applied together with `active_filter`
+    pub level_mask: LevelMask,
+    /// `TagTable` id of each line
+    pub line_tags: Vec<u32>,
+    /// ESP-IDF tick value of each line that has one
+    pub line_ticks: Vec<Option<u64>>,
+    pub tags: TagTable,
+    /// Tags shown while filtering; applied together with `level_mask`
+    pub tag_filter: TagFilter,
+    /// Attributes of the lines that have any, by absolute line
+    pub line_attrs: BTreeMap<usize, LineAttrs>,
 }
 
 impl LogIndex {
@@ -18,6 +36,14 @@ impl LogIndex {
             filtered_lines: Vec::new(),
             is_filtering: false,
             active_filter: None,
+            line_levels: Vec::new(),
+            level_counts: [0; 6],
+            level_mask: LevelMask::ALL,
+            line_tags: Vec::new(),
+            line_ticks: Vec::new(),
+            tags: TagTable::default(),
+            tag_filter: TagFilter::default(),
+            line_attrs: BTreeMap::new(),
         }
     }
 
@@ -25,11 +51,71 @@ impl LogIndex {
         self.line_offsets = vec![ByteOffset(0)];
         self.line_count = 0;
         self.filtered_lines.clear();
+        self.line_levels.clear();
+        self.level_counts = [0; 6];
+        self.line_tags.clear();
+        self.line_ticks.clear();
+        self.tags.clear();
+        self.line_attrs.clear();
     }
 
-    pub fn push_line(&mut self, absolute_end_offset: ByteOffset) {
+    pub fn push_line(&mut self, absolute_end_offset: ByteOffset, meta: LineMeta) {
         self.line_offsets.push(absolute_end_offset);
         self.line_count += 1;
+        self.line_levels.push(meta.level);
+        self.level_counts[meta.level as usize] += 1;
+        self.line_tags.push(meta.tag);
+        self.line_ticks.push(meta.ticks);
+        self.tags.add(meta.tag, meta.ticks);
+    }
+
+    pub fn set_meta(&mut self, line: usize, meta: LineMeta) {
+        if let Some(old) = self.line_levels.get_mut(line) {
+            self.level_counts[*old as usize] -= 1;
+            self.level_counts[meta.level as usize] += 1;
+            *old = meta.level;
+        }
+        if let Some(old) = self.line_tags.get_mut(line) {
+            if *old != meta.tag || meta.ticks.is_some() {
+                self.tags.remove(*old);
+                self.tags.add(meta.tag, meta.ticks);
+                *old = meta.tag;
+            }
+        }
+        if let Some(old) = self.line_ticks.get_mut(line) {
+            *old = meta.ticks;
+        }
+    }
+
+    /// Stores the attributes of an absolute line; default attributes take no space
+    pub fn set_attrs(&mut self, line: usize, attrs: LineAttrs) {
+        if attrs.is_empty() {
+            self.line_attrs.remove(&line);
+        } else {
+            self.line_attrs.insert(line, attrs);
+        }
+    }
+
+    /// Attributes of an absolute line
+    pub fn attrs(&self, line: usize) -> LineAttrs {
+        self.line_attrs.get(&line).cloned().unwrap_or_default()
+    }
+
+    /// True if the absolute line was cut at the line cap and goes on in the next one
+    pub fn is_continued(&self, line: usize) -> bool {
+        self.line_attrs.get(&line).is_some_and(|a| a.continued)
+    }
+
+    /// Metadata of the next line: that of the line it continues, if any
+    pub fn inherited_meta(&self) -> Option<LineMeta> {
+        if !self.is_continued(self.line_count.checked_sub(1)?) {
+            return None;
+        }
+        Some(LineMeta {
+            level: *self.line_levels.last()?,
+            tag: *self.line_tags.last()?,
+            ticks: None,
+        })
     }
 
     pub fn push_filtered(&mut self, range: LineRange) {
@@ -65,6 +151,46 @@ impl LogIndex {
         }
     }
 
+    /// Absolute line number of a displayed line (they differ while filtering)
+    pub fn absolute_line(&self, index: LineIndex) -> Option<usize> {
+        if !self.is_filtering {
+            return (index.0 < self.line_count).then_some(index.0);
+        }
+        let range = self.filtered_lines.get(index.0)?;
+        self.line_offsets[..self.line_count]
+            .binary_search(&range.start)
+            .ok()
+    }
+
+    /// Text byte range of an absolute line, regardless of filtering
+    pub fn get_absolute_range(&self, line: usize) -> Option<LineRange> {
+        (line < self.line_count).then(|| LineRange {
+            start: self.line_offsets[line],
+            end: self.line_offsets[line + 1],
+        })
+    }
+
+    /// Displayed line number of an absolute line; None if the filter hides it
+    pub fn display_line(&self, line: usize) -> Option<usize> {
+        let range = self.get_absolute_range(line)?;
+        if !self.is_filtering {
+            return Some(line);
+        }
+        self.filtered_lines
+            .binary_search_by(|r| r.start.cmp(&range.start))
+            .ok()
+    }
+
+    /// True if a text filter, the level filter or the tag filter hides lines
+    pub fn has_filter(&self) -> bool {
+        self.active_filter.is_some() || !self.level_mask.is_all() || !self.tag_filter.is_empty()
+    }
+
+    /// True if the level and tag filters show a line with this metadata
+    pub fn meta_shown(&self, meta: LineMeta) -> bool {
+        self.level_mask.shows(meta.level) && self.tag_filter.shows(self.tags.name(meta.tag))
+    }
+
     pub fn clear_filter(&mut self) {
         self.is_filtering = false;
         self.active_filter = None;
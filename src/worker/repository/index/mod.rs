@@ -1,8 +1,12 @@
+pub mod chunk_index;
 pub mod filter;
 pub mod log_index;
+pub mod tags;
 pub mod types;
 
 // Re-export commonly used items
+pub use chunk_index::{ChunkEntry, ChunkIndex};
 pub use filter::ActiveFilterBuilder;
 pub use log_index::LogIndex;
-pub use types::{ByteOffset, LineIndex, LineRange};
+pub use tags::TagTable;
+pub use types::{ByteOffset, LineIndex, LineMeta, LineRange};
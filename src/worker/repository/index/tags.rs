@@ -0,0 +1,94 @@
+use crate::types::TagInfo;
+use std::collections::HashMap;
+
+/// ESP-IDF tags seen this session. Lines refer to tags by id; 0 means no tag.
+#[derive(Default)]
+pub struct TagTable {
+    ids: HashMap<String, u32>,
+    tags: Vec<TagInfo>,
+    /// Bumped on every change so updates are only posted when needed
+    pub version: u64,
+}
+
+impl TagTable {
+    pub fn clear(&mut self) {
+        self.ids.clear();
+        self.tags.clear();
+        self.version += 1;
+    }
+
+    /// Id of `name`, adding it if it is new
+    pub fn intern(&mut self, name: &str) -> u32 {
+        if let Some(&id) = self.ids.get(name) {
+            return id;
+        }
+        self.tags.push(TagInfo {
+            name: name.to_string(),
+            count: 0,
+            last_ticks: None,
+        });
+        let id = self.tags.len() as u32;
+        self.ids.insert(name.to_string(), id);
+        id
+    }
+
+    pub fn name(&self, id: u32) -> Option<&str> {
+        let index = (id as usize).checked_sub(1)?;
+        self.tags.get(index).map(|t| t.name.as_str())
+    }
+
+    /// Counts a line under tag `id`, remembering the latest tick value seen
+    pub fn add(&mut self, id: u32, ticks: Option<u64>) {
+        let Some(tag) = (id as usize)
+            .checked_sub(1)
+            .and_then(|i| self.tags.get_mut(i))
+        else {
+            return;
+        };
+        tag.count += 1;
+        if ticks.is_some() {
+            tag.last_ticks = ticks;
+        }
+        self.version += 1;
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        if let Some(tag) = (id as usize)
+            .checked_sub(1)
+            .and_then(|i| self.tags.get_mut(i))
+        {
+            tag.count -= 1;
+            self.version += 1;
+        }
+    }
+
+    /// Tags with at least one line, most frequent first
+    pub fn snapshot(&self) -> Vec<TagInfo> {
+        let mut tags: Vec<TagInfo> = self.tags.iter().filter(|t| t.count > 0).cloned().collect();
+        tags.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+        tags
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_counts() {
+        let mut table = TagTable::default();
+        let wifi = table.intern("wifi");
+        let boot = table.intern("boot");
+        assert_eq!(table.intern("wifi"), wifi);
+        table.add(wifi, Some(10));
+        table.add(wifi, None);
+        table.add(boot, Some(5));
+        table.remove(boot);
+
+        let tags = table.snapshot();
+        assert_eq!(tags.len(), 1);
+        assert_eq!((tags[0].name.as_str(), tags[0].count), ("wifi", 2));
+        assert_eq!(tags[0].last_ticks, Some(10));
+        assert_eq!(table.name(0), None);
+    }
+}
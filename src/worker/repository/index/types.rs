@@ -22,6 +22,16 @@ impl Sub<ByteOffset> for ByteOffset {
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd, Eq, Ord)]
 pub struct LineIndex(pub usize);
 
+/// Metadata detected for a stored line
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LineMeta {
+    /// `LogLevel::code` of the line
+    pub level: u8,
+    /// Id in the `TagTable`; 0 when the line has no tag
+    pub tag: u32,
+    pub ticks: Option<u64>,
+}
+
 /// Range of bytes representing a line
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct LineRange {
@@ -0,0 +1,132 @@
+use crate::worker::error::LogError;
+use crate::worker::repository::index::{ByteOffset, ChunkEntry, ChunkIndex};
+use crate::worker::repository::storage::{OpfsBackend, StorageBackend};
+use web_sys::FileSystemSyncAccessHandle;
+
+/// Lossless copy of every received byte, kept next to the formatted text log
+/// so lines can be re-rendered after the fact.
+pub struct RawJournal {
+    data: OpfsBackend,
+    index_file: OpfsBackend,
+    chunks: ChunkIndex,
+    size: u64,
+}
+
+impl RawJournal {
+    pub fn new() -> Self {
+        Self {
+            data: OpfsBackend { handle: None },
+            index_file: OpfsBackend { handle: None },
+            chunks: ChunkIndex::new(),
+            size: 0,
+        }
+    }
+
+    /// Attaches the session's journal files and loads the chunk index they hold
+    pub fn initialize(
+        &mut self,
+        handles: Option<(FileSystemSyncAccessHandle, FileSystemSyncAccessHandle)>,
+    ) -> Result<(), LogError> {
+        let (data, index) = match handles {
+            Some((data, index)) => (Some(data), Some(index)),
+            None => (None, None),
+        };
+        self.data.handle = data;
+        self.index_file.handle = index;
+        self.chunks.clear();
+        self.size = 0;
+        if !self.is_available() {
+            return Ok(());
+        }
+
+        self.size = self.data.get_file_size()?.0;
+        let index_size = self.index_file.get_file_size()?.0 as usize;
+        if index_size > 0 {
+            let mut buf = vec![0u8; index_size];
+            self.index_file.read_at(ByteOffset(0), &mut buf)?;
+            self.chunks.load(&buf);
+        }
+        Ok(())
+    }
+
+    pub fn is_available(&self) -> bool {
+        self.data.handle.is_some() && self.index_file.handle.is_some()
+    }
+
+    /// Total number of raw bytes captured in this session
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Appends a received chunk; `lines_before` is the number of lines
+    /// committed to the text log before the chunk is processed.
+    pub fn append(&mut self, chunk: &[u8], lines_before: usize) -> Result<(), LogError> {
+        if !self.is_available() || chunk.is_empty() {
+            return Ok(());
+        }
+        let entry = ChunkEntry {
+            raw_start: self.size,
+            lines_before: lines_before as u64,
+        };
+        if self
+            .chunks
+            .record(entry.raw_start, entry.lines_before)
+            .is_some()
+        {
+            let at = ((self.chunks.len() - 1) * ChunkEntry::ENCODED_LEN) as u64;
+            self.index_file
+                .write_at(ByteOffset(at), &entry.to_bytes())?;
+        }
+        self.data.write_at(ByteOffset(self.size), chunk)?;
+        self.size += chunk.len() as u64;
+        Ok(())
+    }
+
+    /// Raw byte range covering absolute lines `first_line..end_line`
+    pub fn line_range(&self, first_line: usize, end_line: usize) -> (u64, u64) {
+        self.chunks.raw_range(first_line, end_line, self.size)
+    }
+
+    /// First absolute line that can hold the raw byte at `offset`
+    pub fn first_line_at(&self, offset: u64) -> Option<usize> {
+        if offset >= self.size {
+            return None;
+        }
+        self.chunks.first_line_at(offset)
+    }
+
+    /// Reads up to `limit` bytes starting at `start`, stopping at `end`
+    pub fn read(&self, start: u64, end: u64, limit: usize) -> Result<Vec<u8>, LogError> {
+        let end = end.min(self.size);
+        if !self.is_available() || start >= end {
+            return Ok(Vec::new());
+        }
+        let len = ((end - start) as usize).min(limit);
+        let mut buf = vec![0u8; len];
+        let read = self.data.read_at(ByteOffset(start), &mut buf)?;
+        buf.truncate(read);
+        Ok(buf)
+    }
+
+    pub fn data_handle(&self) -> Option<FileSystemSyncAccessHandle> {
+        self.data.handle.clone()
+    }
+
+    pub fn clear(&mut self) -> Result<(), LogError> {
+        self.chunks.clear();
+        self.size = 0;
+        if self.is_available() {
+            self.data.truncate(0)?;
+            self.index_file.truncate(0)?;
+            self.data.flush()?;
+            self.index_file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for RawJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,12 +1,17 @@
+pub mod attrs;
 pub mod index;
+pub mod journal;
 pub mod storage;
 
 const NEWLINE: u8 = b'\n';
 
-use self::index::{ByteOffset, LineIndex, LineRange, LogIndex};
+use self::attrs::AttrLog;
+use self::index::{ByteOffset, LineIndex, LineMeta, LineRange, LogIndex};
 use self::storage::{LogStorage, StorageBackend};
 use crate::config::READ_BUFFER_SIZE;
+use crate::worker::chunk_handler::LineEnds;
 use crate::worker::error::LogError;
+use crate::worker::levels::LevelDetector;
 use web_sys::FileSystemSyncAccessHandle;
 
 /// Repository that manages log storage and indexing together
@@ -14,6 +19,8 @@ use web_sys::FileSystemSyncAccessHandle;
 pub struct LogRepository {
     pub storage: LogStorage,
     pub index: LogIndex,
+    pub levels: LevelDetector,
+    pub attrs: AttrLog,
 }
 
 impl LogRepository {
@@ -21,15 +28,19 @@ impl LogRepository {
         Ok(Self {
             storage: LogStorage::new()?,
             index: LogIndex::new(),
+            levels: LevelDetector::default(),
+            attrs: AttrLog::new(),
         })
     }
 
     pub fn initialize_storage(
         &mut self,
         handle: FileSystemSyncAccessHandle,
+        attrs: Option<FileSystemSyncAccessHandle>,
     ) -> Result<(), LogError> {
         self.storage.backend.handle = Some(handle);
         let size = self.storage.backend.get_file_size()?;
+        let line_attrs = self.attrs.initialize(attrs)?;
 
         if size.0 > 0 {
             self.reset_index();
@@ -38,24 +49,28 @@ impl LogRepository {
                 let len = (size.0 - off.0).min(buf.len() as u64) as usize;
                 self.storage.backend.read_at(off, &mut buf[..len])?;
                 for (i, &b) in buf[..len].iter().enumerate() {
+                    // Levels are detected afterwards by a reclassification pass
                     if b == NEWLINE {
-                        self.index.push_line(off + (i as u64 + 1));
+                        self.index
+                            .push_line(off + (i as u64 + 1), LineMeta::default());
                     }
                 }
                 off = off + (len as u64);
             }
+            let line_count = self.index.line_count;
+            self.index.line_attrs = line_attrs;
+            self.index.line_attrs.retain(|&line, _| line < line_count);
+        } else {
+            self.attrs.clear()?;
         }
         Ok(())
     }
 
     /// Appends lines to storage and updates index atomically
-    /// This ensures storage and index remain synchronized
-    pub fn append_lines(
-        &mut self,
-        text: &str,
-        offsets: Vec<ByteOffset>,
-        filtered: Vec<LineRange>,
-    ) -> Result<(), LogError> {
+    /// This ensures storage and index remain synchronized.
+    /// Each line is classified once here, in order, so a line continuing the
+    /// previous one in the same batch inherits its level before filtering.
+    pub fn append_lines(&mut self, text: &str, offsets: LineEnds) -> Result<(), LogError> {
         let start = self.storage.backend.get_file_size()?;
 
         // Write to storage first
@@ -64,15 +79,34 @@ impl LogRepository {
             .write_at(start, self.storage.encoder.encode_with_input(text).as_ref())?;
 
         // Only update index if write succeeded
-        for off in offsets {
-            self.index.push_line(start + off.0);
-        }
-
-        for mut r in filtered {
-            r.start = start + r.start.0;
-            r.end = start + r.end.0;
-            self.index.push_filtered(r);
+        let mut line_start = 0;
+        let first_line = self.index.line_count;
+        for (off, attrs) in offsets {
+            let line = text
+                .get(line_start..off.0 as usize)
+                .unwrap_or_default()
+                .trim_end_matches('\n');
+            let meta = match self.index.inherited_meta() {
+                Some(meta) => meta,
+                None => self.levels.detect(line, &mut self.index.tags),
+            };
+            if self.index.is_filtering && self.shows_line(line, meta) {
+                self.index.push_filtered(LineRange {
+                    start: start + line_start as u64,
+                    end: start + off.0,
+                });
+            }
+            self.index.push_line(start + off.0, meta);
+            self.index.set_attrs(self.index.line_count - 1, attrs);
+            line_start = off.0 as usize;
         }
+        let new_attrs: Vec<_> = self
+            .index
+            .line_attrs
+            .range(first_line..)
+            .map(|(&line, attrs)| (line, attrs))
+            .collect();
+        self.attrs.append(&new_attrs)?;
 
         Ok(())
     }
@@ -98,6 +132,7 @@ impl LogRepository {
     pub fn clear(&mut self) -> Result<(), LogError> {
         self.storage.backend.truncate(0)?;
         self.storage.backend.flush()?;
+        self.attrs.clear()?;
         self.index.reset_base();
         Ok(())
     }
@@ -112,14 +147,32 @@ impl LogRepository {
         self.index.is_filtering
     }
 
-    /// Checks if text matches the active filter
+    /// Checks if the line being received, which is not stored yet, matches the
+    /// active filter and the level and tag filters
     pub fn matches_active_filter(&self, text: &str) -> bool {
         if !self.index.is_filtering {
             return false;
         }
+        let index = &self.index;
+        let meta_shown = (index.level_mask.is_all() && index.tag_filter.is_empty())
+            || match index.inherited_meta() {
+                Some(meta) => index.meta_shown(meta),
+                None => self.levels.inspect(text, |level, esp| {
+                    index.level_mask.shows(level) && index.tag_filter.shows(esp.map(|l| l.tag))
+                }),
+            };
+        meta_shown && self.matches_text_filter(text)
+    }
+
+    /// True if a stored line with `meta` passes the level, tag and text filters
+    fn shows_line(&self, text: &str, meta: LineMeta) -> bool {
+        self.index.meta_shown(meta) && self.matches_text_filter(text)
+    }
+
+    fn matches_text_filter(&self, text: &str) -> bool {
         self.index
             .active_filter
             .as_ref()
-            .is_some_and(|f| f.matches(text))
+            .is_none_or(|f| f.matches(text))
     }
 }
@@ -149,54 +149,116 @@ async fn get_files(
     Ok(files)
 }
 
+/// Sync access handles for the files that make up one capture session
+pub struct SessionHandles {
+    /// Formatted text log (`logs_<ts>.txt`)
+    pub log: web_sys::FileSystemSyncAccessHandle,
+    /// Raw byte journal (`.bin`) and its per-line raw range index (`.idx`)
+    pub raw: Option<(
+        web_sys::FileSystemSyncAccessHandle,
+        web_sys::FileSystemSyncAccessHandle,
+    )>,
+    /// Attributes of the lines that have any (`.attr`)
+    pub attrs: Option<web_sys::FileSystemSyncAccessHandle>,
+}
+
+/// Name of a file stored next to the session's text log, e.g. `logs_1.bin`
+pub fn companion_name(log_name: &str, ext: &str) -> String {
+    format!("{}.{}", log_name.trim_end_matches(".txt"), ext)
+}
+
+async fn open_file(
+    root: &web_sys::FileSystemDirectoryHandle,
+    name: &str,
+) -> Result<web_sys::FileSystemSyncAccessHandle, JsValue> {
+    let opts = web_sys::FileSystemGetFileOptions::new();
+    opts.set_create(true);
+    let file_handle =
+        wasm_bindgen_futures::JsFuture::from(root.get_file_handle_with_options(name, &opts))
+            .await?;
+    get_lock(file_handle.into()).await
+}
+
+/// Opens the raw journal files of a session. Capture keeps working on the
+/// text log alone if they cannot be opened.
+async fn open_raw_files(
+    root: &web_sys::FileSystemDirectoryHandle,
+    log_name: &str,
+) -> Option<(
+    web_sys::FileSystemSyncAccessHandle,
+    web_sys::FileSystemSyncAccessHandle,
+)> {
+    let data = open_file(root, &companion_name(log_name, "bin"))
+        .await
+        .ok()?;
+    match open_file(root, &companion_name(log_name, "idx")).await {
+        Ok(index) => Some((data, index)),
+        Err(_) => {
+            data.close();
+            None
+        }
+    }
+}
+
+async fn remove_session_files(root: &web_sys::FileSystemDirectoryHandle, log_name: &str) {
+    let names = [
+        log_name.to_string(),
+        companion_name(log_name, "bin"),
+        companion_name(log_name, "idx"),
+        companion_name(log_name, "attr"),
+    ];
+    for name in &names {
+        let _ = wasm_bindgen_futures::JsFuture::from(root.remove_entry(name)).await;
+    }
+}
+
 /// Creates a new OPFS session
 pub async fn new_session(
     root: &web_sys::FileSystemDirectoryHandle,
     cleanup_current: bool,
     current_filename: &mut Option<String>,
-) -> Result<web_sys::FileSystemSyncAccessHandle, JsValue> {
+) -> Result<SessionHandles, JsValue> {
     if cleanup_current {
         if let Some(name) = current_filename {
-            let _ = wasm_bindgen_futures::JsFuture::from(root.remove_entry(name)).await;
+            remove_session_files(root, name).await;
         }
     }
 
     let filename = format!("logs_{}.txt", chrono::Utc::now().timestamp_millis());
-    let opts = web_sys::FileSystemGetFileOptions::new();
-    opts.set_create(true);
-    let file_handle =
-        wasm_bindgen_futures::JsFuture::from(root.get_file_handle_with_options(&filename, &opts))
-            .await?;
-    let file_handle: web_sys::FileSystemFileHandle = file_handle.into();
-
-    let lock = get_lock(file_handle).await?;
+    let log = open_file(root, &filename).await?;
+    let raw = open_raw_files(root, &filename).await;
+    let attrs = open_file(root, &companion_name(&filename, "attr"))
+        .await
+        .ok();
     *current_filename = Some(filename);
-    Ok(lock)
+    Ok(SessionHandles { log, raw, attrs })
 }
 
 /// Initializes an OPFS session, reusing existing file if possible
 pub async fn init_opfs_session(
     current_filename: &mut Option<String>,
-) -> Result<web_sys::FileSystemSyncAccessHandle, JsValue> {
+) -> Result<SessionHandles, JsValue> {
     let root = get_opfs_root().await?;
     let files = get_files(&root).await?;
 
     if let Some((name, handle)) = files.first().cloned() {
         match get_lock(handle).await {
-            Ok(lock) => {
+            Ok(log) => {
+                let raw = open_raw_files(&root, &name).await;
+                let attrs = open_file(&root, &companion_name(&name, "attr")).await.ok();
                 *current_filename = Some(name);
                 // Cleanup others
                 for file in files.iter().skip(1) {
-                    let _ = wasm_bindgen_futures::JsFuture::from(root.remove_entry(&file.0)).await;
+                    remove_session_files(&root, &file.0).await;
                 }
-                Ok(lock)
+                Ok(SessionHandles { log, raw, attrs })
             }
             Err(_) => {
                 // If lock fails, start new
                 let res = new_session(&root, false, current_filename).await;
                 // Cleanup all including the failed one
                 for file in &files {
-                    let _ = wasm_bindgen_futures::JsFuture::from(root.remove_entry(&file.0)).await;
+                    remove_session_files(&root, &file.0).await;
                 }
                 res
             }
@@ -1,5 +1,7 @@
+use crate::worker::continuation::strip_segment_timestamp;
 use crate::worker::error::LogError;
-use crate::worker::repository::index::{ActiveFilterBuilder, LineRange};
+use crate::worker::repository::index::filter::ActiveFilter;
+use crate::worker::repository::index::{ActiveFilterBuilder, LineMeta, LineRange};
 use crate::worker::repository::storage::StorageBackend;
 use crate::worker::state::WorkerState;
 use gloo_timers::future::TimeoutFuture;
@@ -8,6 +10,34 @@ use std::rc::Rc;
 
 pub struct LogSearcher;
 
+/// One or more stored lines that form a single logical line
+struct LogicalLine {
+    text: String,
+    ranges: Vec<LineRange>,
+    /// Level and tag of the first segment
+    meta: LineMeta,
+}
+
+impl LogicalLine {
+    fn new(text: &str, range: LineRange, meta: LineMeta) -> Self {
+        Self {
+            text: text.to_string(),
+            ranges: vec![range],
+            meta,
+        }
+    }
+
+    fn push(&mut self, text: &str, range: LineRange) {
+        self.text.push_str(text);
+        self.ranges.push(range);
+    }
+
+    fn append(&mut self, other: LogicalLine) {
+        self.text.push_str(strip_segment_timestamp(&other.text));
+        self.ranges.extend(other.ranges);
+    }
+}
+
 const SEARCH_BATCH_SIZE: usize = 5000;
 
 impl LogSearcher {
@@ -18,23 +48,37 @@ impl LogSearcher {
         use_regex: bool,
         invert: bool,
     ) -> Result<(), LogError> {
-        let (total_lines, search_id) = {
-            let mut state = state_rc.borrow_mut();
-            if query.trim().is_empty() {
-                state.proc.repository.index.clear_filter();
-                return Ok(());
-            }
-
-            state.proc.repository.index.active_filter = Some(
+        let filter = if query.trim().is_empty() {
+            None
+        } else {
+            Some(
                 ActiveFilterBuilder::new(query)
                     .case_sensitive(match_case)
                     .regex(use_regex)
                     .invert(invert)
                     .build()
                     .map_err(LogError::Regex)?,
-            );
-            state.proc.repository.index.is_filtering = true;
-            state.proc.repository.index.filtered_lines.clear();
+            )
+        };
+        Self::filter_async(state_rc, filter).await
+    }
+
+    /// Rebuilds the filtered line list from the text filter and the level
+    /// and tag filters, newest lines first
+    pub async fn filter_async(
+        state_rc: Rc<RefCell<WorkerState>>,
+        filter: Option<ActiveFilter>,
+    ) -> Result<(), LogError> {
+        let (total_lines, search_id) = {
+            let mut state = state_rc.borrow_mut();
+            let index = &mut state.proc.repository.index;
+            index.active_filter = filter;
+            if !index.has_filter() {
+                index.clear_filter();
+                return Ok(());
+            }
+            index.is_filtering = true;
+            index.filtered_lines.clear();
 
             state.current_search_id += 1;
             (
@@ -45,6 +89,8 @@ impl LogSearcher {
 
         let mut idx = total_lines;
         let mut buf = vec![0u8; 512 * 1024];
+        // Logical line whose first segment lies in an earlier (not yet read) batch
+        let mut carry: Option<LogicalLine> = None;
 
         while idx > 0 {
             if state_rc.borrow().current_search_id != search_id {
@@ -81,23 +127,58 @@ impl LogSearcher {
                     .decode_with_u8_array(&buf[..size])
                     .map_err(LogError::Js)?;
 
-                let filter = repo.index.active_filter.as_ref().unwrap().clone();
-                let mut batch_matches = Vec::new();
+                let filter = repo.index.active_filter.clone();
+                let index = &repo.index;
+                let off_ptr = &index.line_offsets;
 
+                // Segments of lines cut at the line cap are matched as one logical line
+                let mut groups = Vec::new();
+                let mut current: Option<LogicalLine> = None;
                 for (j, line) in text.trim_end_matches('\n').split('\n').enumerate() {
-                    if filter.matches(line) {
-                        let off_ptr = &repo.index.line_offsets;
-                        let abs_line_idx = batch_start + j;
-
-                        if abs_line_idx + 1 < off_ptr.len() {
-                            let range = LineRange {
-                                start: off_ptr[abs_line_idx],
-                                end: off_ptr[abs_line_idx + 1],
+                    let abs_line_idx = batch_start + j;
+                    if abs_line_idx + 1 >= off_ptr.len() {
+                        break;
+                    }
+                    let range = LineRange {
+                        start: off_ptr[abs_line_idx],
+                        end: off_ptr[abs_line_idx + 1],
+                    };
+                    let continued = index.is_continued(abs_line_idx);
+                    match current.as_mut() {
+                        Some(group) => group.push(strip_segment_timestamp(line), range),
+                        None => {
+                            let meta = LineMeta {
+                                level: index.line_levels[abs_line_idx],
+                                tag: index.line_tags[abs_line_idx],
+                                ticks: index.line_ticks[abs_line_idx],
                             };
-                            batch_matches.push(range);
+                            current = Some(LogicalLine::new(line, range, meta))
                         }
                     }
+                    if !continued {
+                        groups.extend(current.take());
+                    }
                 }
+                match (current, carry.take()) {
+                    (Some(mut open), Some(tail)) => {
+                        open.append(tail);
+                        groups.push(open);
+                    }
+                    (open, tail) => groups.extend(open.into_iter().chain(tail)),
+                }
+                // The first group may still continue a line from the previous batch
+                if batch_start > 0 && !groups.is_empty() {
+                    carry = Some(groups.remove(0));
+                }
+
+                let batch_matches = groups
+                    .into_iter()
+                    .filter(|group| {
+                        index.meta_shown(group.meta)
+                            && filter.as_ref().is_none_or(|f| f.matches(&group.text))
+                    })
+                    .flat_map(|group| group.ranges)
+                    .collect();
                 state.proc.repository.index.prepend_filtered(batch_matches);
             }
 
@@ -1,3 +1,4 @@
+use crate::types::{LevelCounts, LineAttrs};
 use crate::worker::processor::LogProcessor;
 use crate::worker::repository::storage::{get_opfs_root, init_opfs_session, new_session};
 use crate::worker::types::WorkerMsg;
@@ -14,8 +15,17 @@ pub(crate) struct WorkerState {
     pub(crate) scope: web_sys::DedicatedWorkerGlobalScope,
     pub(crate) last_reported_count: usize,
     pub(crate) current_search_id: u32,
-    pub(crate) last_reported_active_line: Option<String>,
-    pub(crate) current_active_line: Option<String>,
+    pub(crate) current_byte_search_id: u32,
+    pub(crate) current_classify_id: u32,
+    pub(crate) last_reported_level_counts: LevelCounts,
+    pub(crate) last_reported_tags_version: u64,
+    pub(crate) last_reported_backtrace_version: u64,
+    /// Start line and size of the last window sent to the main thread
+    pub(crate) last_window: (usize, usize),
+    pub(crate) last_reported_active_line: Option<(String, LineAttrs)>,
+    pub(crate) current_active_line: Option<(String, LineAttrs)>,
+    pub(crate) idle_flush_ms: Option<u32>,
+    pub(crate) last_chunk_at: f64,
 }
 
 impl WorkerState {
@@ -23,8 +33,8 @@ impl WorkerState {
     pub(crate) async fn new() -> Result<Self, JsValue> {
         let mut proc = LogProcessor::new().map_err(|e| JsValue::from_str(&format!("{:?}", e)))?;
         let mut filename: Option<String> = None;
-        if let Ok(lock) = init_opfs_session(&mut filename).await {
-            let _ = proc.set_sync_handle(lock);
+        if let Ok(handles) = init_opfs_session(&mut filename).await {
+            let _ = proc.set_sync_handle(handles);
         }
 
         let scope = js_sys::global().unchecked_into::<web_sys::DedicatedWorkerGlobalScope>();
@@ -37,8 +47,16 @@ impl WorkerState {
             scope,
             last_reported_count: 0,
             current_search_id: 0,
+            current_byte_search_id: 0,
+            current_classify_id: 0,
+            last_reported_level_counts: [0; 6],
+            last_reported_tags_version: 0,
+            last_reported_backtrace_version: 0,
+            last_window: (0, 0),
             last_reported_active_line: None,
             current_active_line: None,
+            idle_flush_ms: None,
+            last_chunk_at: 0.0,
         })
     }
 
@@ -48,16 +66,19 @@ impl WorkerState {
             loop {
                 gloo_timers::future::TimeoutFuture::new(crate::config::WORKER_UPDATE_INTERVAL_MS)
                     .await; // ~60fps
-                let (count, active_line, scope) = {
+                let (count, active_line, level_counts, scope) = {
                     let state = state_rc.borrow();
                     (
                         state.proc.get_line_count() as usize,
                         state.current_active_line.clone(),
+                        state.proc.repository.index.level_counts,
                         state.scope.clone(),
                     )
                 };
 
                 let mut state = state_rc.borrow_mut();
+                state.flush_if_idle();
+
                 if count != state.last_reported_count {
                     state.last_reported_count = count;
                     if let Ok(msg) = serde_json::to_string(&WorkerMsg::TotalLines(count)) {
@@ -65,6 +86,24 @@ impl WorkerState {
                     }
                 }
 
+                if level_counts != state.last_reported_level_counts {
+                    state.last_reported_level_counts = level_counts;
+                    state.send_msg(WorkerMsg::LevelCounts(level_counts));
+                }
+
+                let tags = &state.proc.repository.index.tags;
+                if tags.version != state.last_reported_tags_version {
+                    let (version, snapshot) = (tags.version, tags.snapshot());
+                    state.last_reported_tags_version = version;
+                    state.send_msg(WorkerMsg::TagStats(snapshot));
+                }
+
+                let backtrace_version = state.proc.backtrace_version;
+                if backtrace_version != state.last_reported_backtrace_version {
+                    state.last_reported_backtrace_version = backtrace_version;
+                    state.send_msg(WorkerMsg::Backtrace(state.proc.backtrace()));
+                }
+
                 if active_line != state.last_reported_active_line {
                     state.last_reported_active_line = active_line.clone();
                     if let Ok(msg) = serde_json::to_string(&WorkerMsg::ActiveLine(active_line)) {
@@ -75,6 +114,24 @@ impl WorkerState {
         });
     }
 
+    /// Commits the partial line once the port has been quiet for the configured idle time
+    fn flush_if_idle(&mut self) {
+        let Some(idle_ms) = self.idle_flush_ms else {
+            return;
+        };
+        if self.last_chunk_at == 0.0 || !self.proc.has_active_line() {
+            return;
+        }
+        if js_sys::Date::now() - self.last_chunk_at < idle_ms as f64 {
+            return;
+        }
+        self.last_chunk_at = 0.0;
+        match self.proc.flush_active_line() {
+            Ok(()) => self.send_msg(WorkerMsg::ActiveLine(None)),
+            Err(e) => self.send_error(e.into()),
+        }
+    }
+
     /// Handles creating a new session asynchronously
     pub(crate) fn handle_new_session(state_rc: Rc<RefCell<Self>>) {
         spawn_local(async move {
@@ -83,10 +140,10 @@ impl WorkerState {
                 (s.root.clone(), s.filename.clone())
             };
             let mut filename = filename_opt;
-            if let Ok(lock) = new_session(&root, true, &mut filename).await {
+            if let Ok(handles) = new_session(&root, true, &mut filename).await {
                 let mut s = state_rc.borrow_mut();
                 s.filename = filename;
-                let _ = s.proc.set_sync_handle(lock);
+                let _ = s.proc.set_sync_handle(handles);
                 let _ = s.proc.clear();
                 s.send_msg(WorkerMsg::TotalLines(0));
             }
@@ -0,0 +1,189 @@
+use crate::types::SymbolInfo;
+use crate::utils::ansi_decoder::strip_ansi;
+use addr2line::Context;
+use gimli::{EndianRcSlice, RunTimeEndian};
+use object::{Architecture, Object, ObjectSection, ObjectSymbol, SymbolKind};
+use regex::Regex;
+use std::borrow::Cow;
+use std::rc::Rc;
+
+type Reader = EndianRcSlice<RunTimeEndian>;
+
+const BACKTRACE_TAG: &str = "Backtrace:";
+
+/// Resolves code addresses to function, file and line using a firmware ELF
+pub struct Symbolizer {
+    context: Context<Reader>,
+    /// Function symbols sorted by address, for code without line info
+    functions: Vec<(u64, u64, String)>,
+    /// Thumb code addresses have bit 0 set
+    thumb: bool,
+    pub has_debug_info: bool,
+}
+
+impl Symbolizer {
+    pub fn from_elf(data: &[u8]) -> Result<Self, String> {
+        let file = object::File::parse(data).map_err(|e| e.to_string())?;
+        let endian = if file.is_little_endian() {
+            RunTimeEndian::Little
+        } else {
+            RunTimeEndian::Big
+        };
+        let load_section = |id: gimli::SectionId| -> Result<Reader, gimli::Error> {
+            let data = file
+                .section_by_name(id.name())
+                .and_then(|s| s.uncompressed_data().ok())
+                .unwrap_or(Cow::Borrowed(&[]));
+            Ok(EndianRcSlice::new(Rc::from(&*data), endian))
+        };
+        let dwarf = gimli::Dwarf::load(load_section).map_err(|e| e.to_string())?;
+        let context = Context::from_dwarf(dwarf).map_err(|e| e.to_string())?;
+
+        let mut functions: Vec<(u64, u64, String)> = file
+            .symbols()
+            .filter(|s| s.kind() == SymbolKind::Text && s.address() != 0)
+            .filter_map(|s| Some((s.address(), s.size(), s.name().ok()?.to_string())))
+            .collect();
+        functions.sort_by_key(|f| f.0);
+        let has_debug_info = file.section_by_name(".debug_line").is_some();
+        if functions.is_empty() && !has_debug_info {
+            return Err("ELF has no symbols or debug info".to_string());
+        }
+
+        Ok(Self {
+            context,
+            functions,
+            thumb: matches!(file.architecture(), Architecture::Arm),
+            has_debug_info,
+        })
+    }
+
+    /// Innermost function and source line at `address`
+    pub fn lookup(&self, address: u64) -> SymbolInfo {
+        let probe = if self.thumb { address & !1 } else { address };
+        let mut info = SymbolInfo {
+            address,
+            function: None,
+            file: None,
+            line: None,
+        };
+        if let Ok(mut frames) = self.context.find_frames(probe).skip_all_loads() {
+            if let Ok(Some(frame)) = frames.next() {
+                info.function = frame
+                    .function
+                    .and_then(|f| f.demangle().ok().map(|name| name.into_owned()));
+                if let Some(location) = frame.location {
+                    info.file = location.file.map(str::to_string);
+                    info.line = location.line;
+                }
+            }
+        }
+        if info.function.is_none() {
+            info.function = self.symbol_at(probe);
+        }
+        info
+    }
+
+    fn symbol_at(&self, address: u64) -> Option<String> {
+        let index = self.functions.partition_point(|f| f.0 <= address);
+        let (start, size, name) = self.functions.get(index.checked_sub(1)?)?;
+        if *size != 0 && address >= start + size {
+            return None;
+        }
+        Some(addr2line::demangle_auto(Cow::Borrowed(name.as_str()), None).into_owned())
+    }
+}
+
+fn parse_hex(digits: &str) -> Option<u64> {
+    u64::from_str_radix(digits, 16).ok()
+}
+
+/// Program counters of a `Backtrace: 0xPC:0xSP ...` line
+pub fn backtrace_addresses(line: &str) -> Option<Vec<u64>> {
+    thread_local! {
+        static FRAME_RE: Regex = Regex::new(r"^\s*0x([0-9a-fA-F]+)(?::0x[0-9a-fA-F]+)?").unwrap();
+    }
+    if !line.contains(BACKTRACE_TAG) {
+        return None;
+    }
+    let visible = strip_ansi(line);
+    let (_, mut rest) = visible.split_once(BACKTRACE_TAG)?;
+    let mut addresses = Vec::new();
+    FRAME_RE.with(|re| {
+        while let Some(caps) = re.captures(rest) {
+            addresses.extend(parse_hex(&caps[1]));
+            rest = &rest[caps.get(0).map_or(0, |m| m.end())..];
+        }
+    });
+    (!addresses.is_empty()).then_some(addresses)
+}
+
+/// Code addresses in a line: backtrace frames and PC/LR/MEPC/RA register values
+pub fn code_addresses(line: &str) -> Vec<u64> {
+    thread_local! {
+        static REGISTER_RE: Regex =
+            Regex::new(r"\b(?:PC|LR|MEPC|RA|pc|lr)\s*[:=]\s*0x([0-9a-fA-F]{1,16})\b").unwrap();
+    }
+    if let Some(addresses) = backtrace_addresses(line) {
+        return addresses;
+    }
+    let visible = strip_ansi(line);
+    REGISTER_RE.with(|re| {
+        re.captures_iter(&visible)
+            .filter_map(|caps| parse_hex(&caps[1]))
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_addresses() {
+        assert_eq!(
+            backtrace_addresses(
+                "Backtrace: 0x400d1234:0x3ffb1230 0x400d5678:0x3ffb1250 |<-CORRUPTED"
+            ),
+            Some(vec![0x400d1234, 0x400d5678])
+        );
+        assert_eq!(
+            code_addresses("\x1b[0;31mBacktrace:0x42000a1c:0x3fc8f3d0\x1b[0m"),
+            vec![0x42000a1c]
+        );
+        assert_eq!(
+            code_addresses("PC      : 0x400d1234  PS      : 0x00060030  A0      : 0x800d5678"),
+            vec![0x400d1234]
+        );
+        assert_eq!(
+            code_addresses("MEPC    : 0x42001234  RA      : 0x42005678  SP : 0x3fc8"),
+            vec![0x42001234, 0x42005678]
+        );
+        assert_eq!(
+            code_addresses("pc = 0x08000f31, lr = 0x08000e01"),
+            vec![0x08000f31, 0x08000e01]
+        );
+        assert!(code_addresses("wrote 0x1000 bytes at 0x08000000").is_empty());
+        assert_eq!(backtrace_addresses("I (10) boot: no backtrace here"), None);
+    }
+
+    #[test]
+    fn test_symbolize_own_binary() {
+        let exe = std::fs::read(std::env::current_exe().unwrap()).unwrap();
+        let symbolizer = Symbolizer::from_elf(&exe).unwrap();
+        let file = object::File::parse(&*exe).unwrap();
+        let symbol = file
+            .symbols()
+            .find(|s| {
+                s.kind() == SymbolKind::Text
+                    && s.name()
+                        .is_ok_and(|n| n.contains("test_symbolize_own_binary"))
+            })
+            .unwrap();
+
+        let info = symbolizer.lookup(symbol.address());
+        assert!(info.function.unwrap().contains("test_symbolize_own_binary"));
+        assert!(info.file.unwrap().ends_with("symbols.rs"));
+        assert!(Symbolizer::from_elf(b"not an elf").is_err());
+    }
+}